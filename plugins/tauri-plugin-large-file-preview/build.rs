@@ -1,4 +1,4 @@
-const COMMANDS: &[&str] = &["open_file", "close_file", "mmap_search", "read_lines", "get_total_lines"];
+const COMMANDS: &[&str] = &["open_file", "close_file", "mmap_search", "read_lines", "get_total_lines", "set_truncation_policy", "get_status", "next_diff", "get_total_lines_exact", "filter_lines", "read_filtered_lines", "find_first", "preview_encodings", "set_encoding", "cancel_open", "context_at_offset", "open_file_by_path", "get_startup_info", "set_reopen_last", "set_io_timeout", "reindex", "preview_file", "get_line_offsets", "read_line_bytes", "activate_file", "get_file_hash", "detect_format", "get_search_history", "process_file", "set_setting", "verify_index", "find_next_blank_line", "find_prev_blank_line", "read_lines_with_encoding", "export_index", "set_tail_window", "get_memory_stats", "get_line", "diff_ranges", "contains", "cancel_task", "get_char_count", "set_follow", "read_lines_reverse", "find_line_by_prefix", "get_longest_lines", "enclosing_block", "scan_invalid_utf8", "unique_lines", "estimate_total_lines", "set_file_setting", "reset_file_settings", "find_prev", "get_match_line", "clear_search_cache", "read_line_ranges", "start_watch_dir", "stop_watch_dir", "line_offsets_in_range"];
 
 fn main() {
   tauri_plugin::Builder::new(COMMANDS)
@@ -1,31 +1,381 @@
 use tauri::{AppHandle, command, Runtime};
 
 #[command]
-pub(crate) async fn get_total_lines<R: Runtime>(_app: AppHandle<R>) -> std::result::Result<usize, String> {
+pub(crate) async fn get_total_lines<R: Runtime>(_app: AppHandle<R>) -> std::result::Result<crate::models::TotalLines, String> {
     crate::models::get_total_lines().await
 }
 
 #[command]
-pub(crate) async fn read_lines<R: Runtime>(_app: AppHandle<R>, start: usize, count: usize) -> std::result::Result<String, String> {
-    crate::models::read_lines(start, count).await
+pub(crate) async fn get_total_lines_exact<R: Runtime>(app: AppHandle<R>) -> std::result::Result<usize, String> {
+    crate::models::get_total_lines_exact(app).await
 }
 
 #[command]
-pub(crate) async fn mmap_search<R: Runtime>(_app: AppHandle<R>, needle: String, ignore_case: bool) -> std::result::Result<serde_json::Value, String> {
-    crate::models::mmap_search(needle, ignore_case).await
+pub(crate) async fn read_lines<R: Runtime>(
+    _app: AppHandle<R>,
+    start: usize,
+    count: usize,
+    handle: Option<u32>,
+    preserve_eol: Option<bool>,
+    row_per_line: Option<bool>,
+) -> std::result::Result<String, String> {
+    crate::models::read_lines(start, count, handle, preserve_eol.unwrap_or(false), row_per_line.unwrap_or(false)).await
 }
 
 #[command]
-pub(crate) async fn close_file<R: Runtime>(_app: AppHandle<R>) -> std::result::Result<(), String> {
-    crate::models::close_file().await
+pub(crate) async fn mmap_search<R: Runtime>(
+    _app: AppHandle<R>,
+    needle: String,
+    ignore_case: bool,
+    line_histogram: Option<bool>,
+    fuzzy: Option<bool>,
+    max_distance: Option<usize>,
+    line_range: Option<(usize, usize)>,
+    region_buckets: Option<usize>,
+    from_line: Option<usize>,
+    include_line_text: Option<bool>,
+    case_sensitivity: Option<crate::models::CaseSensitivity>,
+    max_samples: Option<usize>,
+    checkpoint_regions: Option<bool>,
+    anchor: Option<crate::models::SearchAnchor>,
+) -> std::result::Result<serde_json::Value, String> {
+    let options = crate::models::SearchOptions {
+        line_histogram: line_histogram.unwrap_or(false),
+        fuzzy: fuzzy.unwrap_or(false),
+        max_distance: max_distance.unwrap_or(1),
+        line_range,
+        from_line,
+        region_buckets: region_buckets.unwrap_or(0),
+        checkpoint_regions: checkpoint_regions.unwrap_or(false),
+        anchor: anchor.unwrap_or_default(),
+        include_line_text: include_line_text.unwrap_or(false),
+        max_samples: max_samples.unwrap_or(crate::models::DEFAULT_SEARCH_SAMPLES),
+    };
+    // 给出 `case_sensitivity` 时优先于 `ignore_case`
+    let case = case_sensitivity.unwrap_or(crate::models::CaseSensitivity::from_ignore_case(ignore_case));
+    crate::models::mmap_search(needle, case, options).await
 }
 
 #[command]
-pub(crate) async fn open_file<R: Runtime>(app: AppHandle<R>, extensions: Option<Vec<String>>) -> std::result::Result<serde_json::Value, String> {
-    crate::models::open_file(app, extensions).await
+pub(crate) async fn close_file<R: Runtime>(app: AppHandle<R>, handle: Option<u32>, last_line: Option<usize>) -> std::result::Result<(), String> {
+    crate::models::close_file(app, handle, last_line).await
 }
 
 #[command]
-pub(crate) async fn get_file_size<R: Runtime>(_app: AppHandle<R>) -> std::result::Result<usize, String> {
+pub(crate) async fn open_file<R: Runtime>(
+    app: AppHandle<R>,
+    extensions: Option<Vec<String>>,
+    activate: Option<bool>,
+    force_reload: Option<bool>,
+    no_index: Option<bool>,
+) -> std::result::Result<crate::models::OpenResult, String> {
+    crate::models::open_file(app, extensions, activate.unwrap_or(true), force_reload.unwrap_or(false), no_index.unwrap_or(false)).await
+}
+
+#[command]
+pub(crate) async fn get_file_size<R: Runtime>(_app: AppHandle<R>) -> std::result::Result<u64, String> {
     crate::models::get_file_size().await
 }
+
+#[command]
+pub(crate) async fn set_truncation_policy<R: Runtime>(_app: AppHandle<R>, policy: crate::models::TruncationPolicy) -> std::result::Result<(), String> {
+    crate::models::set_truncation_policy(policy).await
+}
+
+#[command]
+pub(crate) async fn get_status<R: Runtime>(_app: AppHandle<R>) -> std::result::Result<serde_json::Value, String> {
+    crate::models::get_status().await
+}
+
+#[command]
+pub(crate) async fn next_diff<R: Runtime>(_app: AppHandle<R>, from_line: usize, other: u32) -> std::result::Result<Option<serde_json::Value>, String> {
+    crate::models::next_diff(from_line, other).await
+}
+
+#[command]
+pub(crate) async fn filter_lines<R: Runtime>(
+    app: AppHandle<R>,
+    needle: String,
+    ignore_case: Option<bool>,
+    invert: bool,
+    dest_path: String,
+    line_ending: Option<crate::models::LineEnding>,
+    ensure_trailing_newline: Option<bool>,
+) -> std::result::Result<serde_json::Value, String> {
+    let options = crate::models::ExportOptions {
+        line_ending: line_ending.unwrap_or_else(|| crate::models::output_line_ending(&app)),
+        ensure_trailing_newline: ensure_trailing_newline.unwrap_or(false),
+    };
+    crate::models::filter_lines(app, needle, ignore_case.unwrap_or(false), invert, dest_path, options).await
+}
+
+#[command]
+pub(crate) async fn read_filtered_lines<R: Runtime>(
+    _app: AppHandle<R>,
+    needle: String,
+    ignore_case: bool,
+    start_match: usize,
+    count: usize,
+    with_total: Option<bool>,
+) -> std::result::Result<serde_json::Value, String> {
+    crate::models::read_filtered_lines(needle, ignore_case, start_match, count, with_total.unwrap_or(false)).await
+}
+
+#[command]
+pub(crate) async fn find_first<R: Runtime>(_app: AppHandle<R>, needle: String, ignore_case: bool, from_line: usize) -> std::result::Result<Option<serde_json::Value>, String> {
+    crate::models::find_first(needle, ignore_case, from_line).await
+}
+
+#[command]
+pub(crate) async fn preview_encodings<R: Runtime>(_app: AppHandle<R>, sample_lines: Option<usize>) -> std::result::Result<Vec<serde_json::Value>, String> {
+    crate::models::preview_encodings(sample_lines.unwrap_or(5)).await
+}
+
+#[command]
+pub(crate) async fn set_encoding<R: Runtime>(_app: AppHandle<R>, encoding: String) -> std::result::Result<String, String> {
+    crate::models::set_encoding(encoding).await
+}
+
+#[command]
+pub(crate) async fn read_lines_with_encoding<R: Runtime>(_app: AppHandle<R>, start: usize, count: usize, encoding: String) -> std::result::Result<String, String> {
+    crate::models::read_lines_with_encoding(start, count, encoding).await
+}
+
+#[command]
+pub(crate) async fn cancel_open<R: Runtime>(_app: AppHandle<R>) -> std::result::Result<(), String> {
+    crate::models::cancel_open().await
+}
+
+#[command]
+pub(crate) async fn context_at_offset<R: Runtime>(_app: AppHandle<R>, byte_offset: u64, before: usize, after: usize) -> std::result::Result<serde_json::Value, String> {
+    crate::models::context_at_offset(byte_offset, before, after).await
+}
+
+#[command]
+pub(crate) async fn open_file_by_path<R: Runtime>(
+    app: AppHandle<R>,
+    path: String,
+    activate: Option<bool>,
+    force_reload: Option<bool>,
+    byte_offset: Option<u64>,
+    line: Option<usize>,
+    no_index: Option<bool>,
+) -> std::result::Result<crate::models::OpenResult, String> {
+    let target = match (byte_offset, line) {
+        (Some(offset), _) => Some(crate::models::OpenTarget::ByteOffset(offset)),
+        (None, Some(line)) => Some(crate::models::OpenTarget::Line(line)),
+        (None, None) => None,
+    };
+    crate::models::open_file_by_path(app, path, activate.unwrap_or(true), force_reload.unwrap_or(false), target, no_index.unwrap_or(false)).await
+}
+
+#[command]
+pub(crate) async fn get_startup_info<R: Runtime>(app: AppHandle<R>) -> std::result::Result<crate::StartupInfo, String> {
+    crate::models::get_startup_info(app).await
+}
+
+#[command]
+pub(crate) async fn set_reopen_last<R: Runtime>(app: AppHandle<R>, enabled: bool) -> std::result::Result<(), String> {
+    crate::models::set_reopen_last(app, enabled).await
+}
+
+#[command]
+pub(crate) async fn set_io_timeout<R: Runtime>(_app: AppHandle<R>, secs: u64) -> std::result::Result<(), String> {
+    crate::models::set_io_timeout(secs).await
+}
+
+#[command]
+pub(crate) async fn reindex<R: Runtime>(app: AppHandle<R>, new_interval: usize) -> std::result::Result<usize, String> {
+    crate::models::reindex(app, new_interval).await
+}
+
+#[command]
+pub(crate) async fn preview_file<R: Runtime>(_app: AppHandle<R>, path: String, n: usize) -> std::result::Result<serde_json::Value, String> {
+    crate::models::preview_file(path, n).await
+}
+
+#[command]
+pub(crate) async fn get_line_offsets<R: Runtime>(_app: AppHandle<R>, lines: Vec<usize>) -> std::result::Result<Vec<u64>, String> {
+    crate::models::get_line_offsets(lines).await
+}
+
+#[command]
+pub(crate) async fn read_line_bytes<R: Runtime>(_app: AppHandle<R>, line: usize) -> std::result::Result<Vec<u8>, String> {
+    crate::models::read_line_bytes(line).await
+}
+
+#[command]
+pub(crate) async fn activate_file<R: Runtime>(_app: AppHandle<R>, handle: u32) -> std::result::Result<(), String> {
+    crate::models::activate_file(handle).await
+}
+
+#[command]
+pub(crate) async fn get_file_hash<R: Runtime>(app: AppHandle<R>, algorithm: Option<crate::models::HashAlgorithm>) -> std::result::Result<serde_json::Value, String> {
+    crate::models::get_file_hash(app, algorithm.unwrap_or_default()).await
+}
+
+#[command]
+pub(crate) async fn detect_format<R: Runtime>(_app: AppHandle<R>) -> std::result::Result<serde_json::Value, String> {
+    crate::models::detect_format().await
+}
+
+#[command]
+pub(crate) async fn get_search_history<R: Runtime>(app: AppHandle<R>) -> std::result::Result<crate::SearchHistory, String> {
+    crate::models::get_search_history(app).await
+}
+
+#[command]
+pub(crate) async fn process_file<R: Runtime>(app: AppHandle<R>, dest_path: Option<String>) -> std::result::Result<serde_json::Value, String> {
+    crate::models::process_file(app, dest_path).await
+}
+
+#[command]
+pub(crate) async fn set_setting<R: Runtime>(app: AppHandle<R>, key: String, value: serde_json::Value) -> std::result::Result<(), String> {
+    crate::models::set_setting(app, key, value).await
+}
+
+#[command]
+pub(crate) async fn verify_index<R: Runtime>(_app: AppHandle<R>) -> std::result::Result<serde_json::Value, String> {
+    crate::models::verify_index().await
+}
+
+#[command]
+pub(crate) async fn find_next_blank_line<R: Runtime>(_app: AppHandle<R>, from_line: usize, whitespace_is_blank: Option<bool>) -> std::result::Result<Option<usize>, String> {
+    crate::models::find_next_blank_line(from_line, whitespace_is_blank.unwrap_or(true)).await
+}
+
+#[command]
+pub(crate) async fn find_prev_blank_line<R: Runtime>(_app: AppHandle<R>, from_line: usize, whitespace_is_blank: Option<bool>) -> std::result::Result<Option<usize>, String> {
+    crate::models::find_prev_blank_line(from_line, whitespace_is_blank.unwrap_or(true)).await
+}
+
+#[command]
+pub(crate) async fn export_index<R: Runtime>(_app: AppHandle<R>, dest_path: String) -> std::result::Result<serde_json::Value, String> {
+    crate::models::export_index(dest_path).await
+}
+
+#[command]
+pub(crate) async fn set_tail_window<R: Runtime>(_app: AppHandle<R>, max_mb: Option<u64>) -> std::result::Result<serde_json::Value, String> {
+    crate::models::set_tail_window(max_mb).await
+}
+
+#[command]
+pub(crate) async fn get_memory_stats<R: Runtime>(_app: AppHandle<R>) -> std::result::Result<serde_json::Value, String> {
+    crate::models::get_memory_stats().await
+}
+
+#[command]
+pub(crate) async fn get_line<R: Runtime>(_app: AppHandle<R>, line: usize) -> std::result::Result<Option<String>, String> {
+    crate::models::get_line(line).await
+}
+
+#[command]
+pub(crate) async fn diff_ranges<R: Runtime>(
+    _app: AppHandle<R>,
+    start_a: usize,
+    count_a: usize,
+    start_b: usize,
+    count_b: usize,
+) -> std::result::Result<Vec<crate::models::DiffLine>, String> {
+    crate::models::diff_ranges(start_a, count_a, start_b, count_b).await
+}
+
+#[command]
+pub(crate) async fn contains<R: Runtime>(_app: AppHandle<R>, needle: String, ignore_case: Option<bool>) -> std::result::Result<bool, String> {
+    crate::models::contains(needle, ignore_case.unwrap_or(false)).await
+}
+
+#[command]
+pub(crate) async fn cancel_task<R: Runtime>(_app: AppHandle<R>) -> std::result::Result<(), String> {
+    crate::models::cancel_task().await
+}
+
+#[command]
+pub(crate) async fn get_char_count<R: Runtime>(app: AppHandle<R>) -> std::result::Result<u64, String> {
+    crate::models::get_char_count(app).await
+}
+
+#[command]
+pub(crate) async fn set_follow<R: Runtime>(app: AppHandle<R>, enabled: bool) -> std::result::Result<(), String> {
+    crate::models::set_follow(app, enabled).await
+}
+
+#[command]
+pub(crate) async fn read_lines_reverse<R: Runtime>(_app: AppHandle<R>, end_line: usize, count: usize) -> std::result::Result<String, String> {
+    crate::models::read_lines_reverse(end_line, count).await
+}
+
+#[command]
+pub(crate) async fn find_line_by_prefix<R: Runtime>(_app: AppHandle<R>, target_prefix: String) -> std::result::Result<Option<usize>, String> {
+    crate::models::find_line_by_prefix(target_prefix).await
+}
+
+#[command]
+pub(crate) async fn get_longest_lines<R: Runtime>(_app: AppHandle<R>, n: usize) -> std::result::Result<Vec<serde_json::Value>, String> {
+    crate::models::get_longest_lines(n).await
+}
+
+#[command]
+pub(crate) async fn enclosing_block<R: Runtime>(_app: AppHandle<R>, line: usize, braces: Option<String>) -> std::result::Result<Option<serde_json::Value>, String> {
+    crate::models::enclosing_block(line, braces.unwrap_or_else(|| crate::models::DEFAULT_BLOCK_BRACES.to_string())).await
+}
+
+#[command]
+pub(crate) async fn scan_invalid_utf8<R: Runtime>(app: AppHandle<R>, max_results: Option<usize>) -> std::result::Result<Vec<serde_json::Value>, String> {
+    crate::models::scan_invalid_utf8(app, max_results.unwrap_or(crate::models::DEFAULT_INVALID_UTF8_RESULTS)).await
+}
+
+#[command]
+pub(crate) async fn unique_lines<R: Runtime>(app: AppHandle<R>, max_distinct: usize) -> std::result::Result<serde_json::Value, String> {
+    crate::models::unique_lines(app, max_distinct).await
+}
+
+#[command]
+pub(crate) async fn estimate_total_lines<R: Runtime>(_app: AppHandle<R>) -> std::result::Result<crate::models::TotalLines, String> {
+    crate::models::estimate_total_lines().await
+}
+
+#[command]
+pub(crate) async fn set_file_setting<R: Runtime>(_app: AppHandle<R>, key: String, value: serde_json::Value) -> std::result::Result<(), String> {
+    crate::models::set_file_setting(key, value).await
+}
+
+#[command]
+pub(crate) async fn reset_file_settings<R: Runtime>(_app: AppHandle<R>) -> std::result::Result<serde_json::Value, String> {
+    crate::models::reset_file_settings().await
+}
+
+#[command]
+pub(crate) async fn find_prev<R: Runtime>(_app: AppHandle<R>, needle: String, ignore_case: bool, before_line: usize) -> std::result::Result<Option<serde_json::Value>, String> {
+    crate::models::find_prev(needle, ignore_case, before_line).await
+}
+
+#[command]
+pub(crate) async fn get_match_line<R: Runtime>(_app: AppHandle<R>, match_index: usize) -> std::result::Result<serde_json::Value, String> {
+    crate::models::get_match_line(match_index).await
+}
+
+#[command]
+pub(crate) async fn clear_search_cache<R: Runtime>(_app: AppHandle<R>) -> std::result::Result<(), String> {
+    crate::models::clear_search_cache().await
+}
+
+#[command]
+pub(crate) async fn read_line_ranges<R: Runtime>(_app: AppHandle<R>, ranges: Vec<(usize, usize)>) -> std::result::Result<Vec<String>, String> {
+    crate::models::read_line_ranges(ranges).await
+}
+
+#[command]
+pub(crate) async fn start_watch_dir<R: Runtime>(app: AppHandle<R>, dir: String, pattern: String) -> std::result::Result<Option<String>, String> {
+    crate::models::start_watch_dir(app, dir, pattern).await
+}
+
+#[command]
+pub(crate) async fn stop_watch_dir<R: Runtime>(_app: AppHandle<R>) -> std::result::Result<(), String> {
+    crate::models::stop_watch_dir().await
+}
+
+#[command]
+pub(crate) async fn line_offsets_in_range<R: Runtime>(_app: AppHandle<R>, start: usize, count: usize) -> std::result::Result<Vec<u64>, String> {
+    crate::models::line_offsets_in_range(start, count).await
+}
@@ -0,0 +1,135 @@
+//! 压缩文件（gzip / bzip2 / xz）的打开支持。
+//!
+//! 按文件头魔数识别压缩格式，流式解压到临时目录中的文件，再按普通文件打开（`LargeFilePreview::open`），
+//! 这样行索引、mmap 搜索与随机读取都与未压缩的文件相同。临时文件由 `TempFile` 持有，
+//! 最后一个引用它的预览被释放（`close_file`）时删除。
+//!
+//! 目前只在桌面平台的打开流程中使用；Android 上选择压缩文件会直接报错。
+
+use log::{info, warn};
+use serde::Serialize;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// 支持的压缩格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Compression {
+    Gzip,
+    Bzip2,
+    Xz,
+}
+
+/// 打开对话框中压缩文件的扩展名
+pub const COMPRESSED_EXTENSIONS: &[&str] = &["gz", "bz2", "xz"];
+
+impl Compression {
+    /// 按文件头魔数识别压缩格式；不是支持的压缩文件时返回 None
+    pub fn from_magic(head: &[u8]) -> Option<Self> {
+        if head.starts_with(&[0x1f, 0x8b]) {
+            Some(Compression::Gzip)
+        } else if head.starts_with(b"BZh") {
+            Some(Compression::Bzip2)
+        } else if head.starts_with(&[0xfd, b'7', b'z', b'X', b'Z', 0x00]) {
+            Some(Compression::Xz)
+        } else {
+            None
+        }
+    }
+
+    /// 读取 `path` 的文件头并识别压缩格式。扩展名只用于打开对话框的过滤，
+    /// 不作为判断依据：改了扩展名的普通文本文件仍按文本打开
+    pub fn detect(path: &Path) -> std::io::Result<Option<Self>> {
+        let mut head = [0u8; 6];
+        let mut file = std::fs::File::open(path)?;
+        let mut len = 0;
+        while len < head.len() {
+            match file.read(&mut head[len..])? {
+                0 => break,
+                n => len += n,
+            }
+        }
+        Ok(Self::from_magic(&head[..len]))
+    }
+
+    /// 文件名中去掉该格式扩展名后的部分（如 `app.log.gz` → `app.log`）
+    fn strip_extension(self, name: &str) -> &str {
+        let ext = match self {
+            Compression::Gzip => ".gz",
+            Compression::Bzip2 => ".bz2",
+            Compression::Xz => ".xz",
+        };
+        match name.len().checked_sub(ext.len()) {
+            Some(cut) if name.is_char_boundary(cut) && name[cut..].eq_ignore_ascii_case(ext) => &name[..cut],
+            _ => name,
+        }
+    }
+
+    fn decoder(self, file: std::fs::File) -> Box<dyn Read> {
+        let file = std::io::BufReader::new(file);
+        match self {
+            // 多个成员拼接而成的 .gz（如 `cat a.gz b.gz`）也要完整解压
+            Compression::Gzip => Box::new(flate2::bufread::MultiGzDecoder::new(file)),
+            Compression::Bzip2 => Box::new(bzip2::bufread::MultiBzDecoder::new(file)),
+            Compression::Xz => Box::new(xz2::bufread::XzDecoder::new_multi_decoder(file)),
+        }
+    }
+}
+
+/// 解压得到的临时文件，drop 时删除
+#[derive(Debug)]
+pub struct TempFile(PathBuf);
+
+impl TempFile {
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        match std::fs::remove_file(&self.0) {
+            Ok(()) => info!("compressed - removed temp file {:?}", self.0),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => warn!("compressed - failed to remove temp file {:?}: {}", self.0, e),
+        }
+    }
+}
+
+/// 压缩源文件解压后的信息，随预览一起保存（见 `LargeFilePreview::compressed`）
+#[derive(Debug)]
+pub struct Decompressed {
+    pub compression: Compression,
+    /// 压缩文件本身的大小（字节）
+    pub compressed_size: u64,
+    pub temp: TempFile,
+}
+
+/// 把 `path` 按 `compression` 流式解压到临时文件。`copy` 负责实际复制（调用方传入可取消的复制函数），
+/// 出错或被取消时不完整的临时文件随 `TempFile` 一起删除
+pub fn decompress_to_temp(
+    path: &Path,
+    compression: Compression,
+    copy: impl FnOnce(&mut dyn Read, &mut std::fs::File) -> std::io::Result<u64>,
+) -> std::io::Result<Decompressed> {
+    let source = std::fs::File::open(path)?;
+    let compressed_size = source.metadata()?.len();
+    let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos()).unwrap_or(0);
+    let temp = TempFile(std::env::temp_dir().join(format!("tauri_tmp_{}_{}", nanos, compression.strip_extension(&name))));
+    let start = std::time::Instant::now();
+    let mut out = std::fs::File::create(temp.path())?;
+    let mut decoder = compression.decoder(source);
+    let written = copy(&mut *decoder, &mut out)?;
+    info!(
+        "compressed - decompressed {:?} ({:?}, {} bytes) to {:?}: {} bytes in {:?}",
+        path,
+        compression,
+        compressed_size,
+        temp.path(),
+        written,
+        start.elapsed()
+    );
+    Ok(Decompressed { compression, compressed_size, temp })
+}
@@ -1,4 +1,5 @@
 use serde::{ser::Serializer, Serialize};
+use std::path::Path;
 
 pub type Result<T> = std::result::Result<T, Error>;
 
@@ -9,6 +10,37 @@ pub enum Error {
   #[cfg(mobile)]
   #[error(transparent)]
   PluginInvoke(#[from] tauri::plugin::mobile::PluginInvokeError),
+  /// 打开的文件不存在（可能已被移动或删除）
+  #[error("File not found: {0}")]
+  NotFound(String),
+  /// 没有读取权限
+  #[error("Permission denied: {0}. Check the file permissions or choose a copy you can read")]
+  PermissionDenied(String),
+  /// 文件被其他进程独占锁定（Windows 共享/锁定冲突）
+  #[error("File is locked by another program: {0}. Close that program and try again")]
+  Locked(String),
+  /// 选择的是目录或其他非普通文件
+  #[error("Not a regular file: {0}")]
+  NotAFile(String),
+  /// 其他打开失败
+  #[error("Failed to open file preview: {1}")]
+  Open(String, std::io::Error),
+}
+
+impl Error {
+  /// 把打开 `path` 时的 I/O 错误按类型转换为能指导用户操作的错误
+  pub fn from_open(path: &Path, err: std::io::Error) -> Self {
+    // Windows 的 ERROR_SHARING_VIOLATION / ERROR_LOCK_VIOLATION
+    const WINDOWS_LOCK_ERRORS: [i32; 2] = [32, 33];
+    let shown = path.display().to_string();
+    match err.kind() {
+      std::io::ErrorKind::NotFound => Error::NotFound(shown),
+      std::io::ErrorKind::PermissionDenied => Error::PermissionDenied(shown),
+      _ if cfg!(windows) && err.raw_os_error().is_some_and(|c| WINDOWS_LOCK_ERRORS.contains(&c)) => Error::Locked(shown),
+      _ if path.is_dir() => Error::NotAFile(shown),
+      _ => Error::Open(shown, err),
+    }
+  }
 }
 
 impl Serialize for Error {
@@ -19,3 +51,60 @@ impl Serialize for Error {
     serializer.serialize_str(self.to_string().as_ref())
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::io::{Error as IoError, ErrorKind};
+
+  fn open_message(path: &Path, err: IoError) -> String {
+    Error::from_open(path, err).to_string()
+  }
+
+  #[test]
+  fn not_found() {
+    let path = Path::new("/no/such/file.log");
+    assert_eq!(
+      open_message(path, IoError::from(ErrorKind::NotFound)),
+      format!("File not found: {}", path.display())
+    );
+  }
+
+  #[test]
+  fn permission_denied() {
+    let path = Path::new("/root/secret.log");
+    assert_eq!(
+      open_message(path, IoError::from(ErrorKind::PermissionDenied)),
+      format!("Permission denied: {}. Check the file permissions or choose a copy you can read", path.display())
+    );
+  }
+
+  #[cfg(windows)]
+  #[test]
+  fn locked() {
+    let path = Path::new("C:\\logs\\app.log");
+    for code in [32, 33] {
+      assert_eq!(
+        open_message(path, IoError::from_raw_os_error(code)),
+        format!("File is locked by another program: {}. Close that program and try again", path.display())
+      );
+    }
+  }
+
+  #[test]
+  fn directory_is_not_a_file() {
+    let dir = std::env::temp_dir();
+    assert_eq!(
+      open_message(&dir, IoError::from(ErrorKind::Other)),
+      format!("Not a regular file: {}", dir.display())
+    );
+  }
+
+  #[test]
+  fn other_errors_keep_the_io_message() {
+    let path = Path::new("/no/such/file.log");
+    let err = IoError::new(ErrorKind::Other, "disk on fire");
+    assert_eq!(open_message(path, err), "Failed to open file preview: disk on fire");
+    assert!(matches!(Error::from_open(path, IoError::from(ErrorKind::UnexpectedEof)), Error::Open(..)));
+  }
+}
@@ -1,15 +1,23 @@
 use tauri::{
   plugin::{Builder, TauriPlugin},
-  Runtime,
+  RunEvent, Runtime, WindowEvent,
 };
 
 pub use models::*;
 
 mod commands;
+mod compressed;
 mod error;
 mod models;
+mod session;
+#[cfg(not(target_os = "android"))]
+mod watch;
 
+pub use compressed::{Compression, Decompressed, TempFile};
 pub use error::{Error, Result};
+pub use session::{LaunchTarget, SearchHistory, SessionState, StartupInfo};
+#[cfg(not(target_os = "android"))]
+pub use watch::DIR_NEWEST_EVENT;
 
 /// Initializes the plugin.
 pub fn init<R: Runtime>() -> TauriPlugin<R> {
@@ -18,9 +26,72 @@ pub fn init<R: Runtime>() -> TauriPlugin<R> {
                                            commands::read_lines,
                                            commands::mmap_search,
                                            commands::close_file,
-                                           commands::open_file])
+                                           commands::open_file,
+                                           commands::set_truncation_policy,
+                                           commands::get_status,
+                                           commands::next_diff,
+                                           commands::get_total_lines_exact,
+                                           commands::filter_lines,
+                                           commands::read_filtered_lines,
+                                           commands::find_first,
+                                           commands::preview_encodings,
+                                           commands::set_encoding,
+                                           commands::cancel_open,
+                                           commands::context_at_offset,
+                                           commands::open_file_by_path,
+                                           commands::get_startup_info,
+                                           commands::set_reopen_last,
+                                           commands::set_io_timeout,
+                                           commands::reindex,
+                                           commands::preview_file,
+                                           commands::get_line_offsets,
+                                           commands::read_line_bytes,
+                                           commands::activate_file,
+                                           commands::get_file_hash,
+                                           commands::detect_format,
+                                           commands::get_search_history,
+                                           commands::process_file,
+                                           commands::set_setting,
+                                           commands::verify_index,
+                                           commands::find_next_blank_line,
+                                           commands::find_prev_blank_line,
+                                           commands::read_lines_with_encoding,
+                                           commands::export_index,
+                                           commands::set_tail_window,
+                                           commands::get_memory_stats,
+                                           commands::get_line,
+                                           commands::diff_ranges,
+                                           commands::contains,
+                                           commands::cancel_task,
+                                           commands::get_char_count,
+                                           commands::set_follow,
+                                           commands::read_lines_reverse,
+                                           commands::find_line_by_prefix,
+                                           commands::get_longest_lines,
+                                           commands::enclosing_block,
+                                           commands::scan_invalid_utf8,
+                                           commands::unique_lines,
+                                           commands::estimate_total_lines,
+                                           commands::set_file_setting,
+                                           commands::reset_file_settings,
+                                           commands::find_prev,
+                                           commands::get_match_line,
+                                           commands::clear_search_cache,
+                                           commands::read_line_ranges,
+                                           commands::start_watch_dir,
+                                           commands::stop_watch_dir,
+                                           commands::line_offsets_in_range])
     .setup(|app, api| {
       Ok(())
     })
+    .on_event(|app, event| {
+      // 用户未关闭文件就退出时，也要把会话状态写盘
+      match event {
+        RunEvent::WindowEvent { event: WindowEvent::CloseRequested { .. }, .. } | RunEvent::Exit => {
+          session::flush(app);
+        }
+        _ => {}
+      }
+    })
     .build()
 }
\ No newline at end of file
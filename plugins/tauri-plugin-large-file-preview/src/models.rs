@@ -18,11 +18,31 @@ use smol::lock::Mutex as AsyncMutex;
 use anyhow::Result;
 use std::io::Read;
 use std::path::Path;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use crate::session::{self, SessionState};
+use crate::compressed::{self, Compression, Decompressed};
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8};
 // memchr may be useful later for fast byte searches; not required here currently
 
 // 最大单行字节数（6MB）——超过该长度的单行在读取时将被截断
 const MAX_LINE_BYTES: usize = 6 * 1024 * 1024;
 
+// `TruncationPolicy::Split` 下超长行每个显示段的字节数
+const SPLIT_SEGMENT_BYTES: usize = 64 * 1024;
+
+// 模糊搜索允许的最大编辑距离
+const MAX_FUZZY_DISTANCE: usize = 8;
+
+// 搜索直方图最多记录的不同行数，避免匹配分散在海量行时返回过大负载
+const MAX_HISTOGRAM_LINES: usize = 10_000;
+
+// 按文件区域统计匹配数时允许的最大分桶数
+const MAX_REGION_BUCKETS: usize = 1000;
+
+// `include_line_text` 时每个匹配附带的行文本最多的字符数
+const MAX_MATCH_LINE_CHARS: usize = 1000;
+
 #[cfg(not(target_os = "android"))]
 use rfd::AsyncFileDialog;
 
@@ -50,10 +70,710 @@ pub struct LargeFilePreview {
     pub index: Vec<u64>,
     /// 索引间隔（行数）
     pub index_interval: usize,
+    /// 是否已扫描整个文件建立行索引。以 `no_index` 打开（见 `open_unindexed`）时为 false：`index` 为空，
+    /// `total_lines` 只是估算值；`reindex` 之后变为 true
+    pub indexed: bool,
     /// 缓存最近创建的 mmap 窗口：(`aligned_offset`, `len`, `mmap`)
     pub cached_window: Arc<StdMutex<Option<(u64, usize, Mmap)>>>,
     /// 复用的已打开文件句柄（用于 mmap 和 BufReader）
     pub file_handle: Arc<std::fs::File>,
+    /// 超长行（超过 `MAX_LINE_BYTES`）的处理策略
+    pub truncation_policy: TruncationPolicy,
+    /// 打开时分配的句柄（见 `register_preview`），用于在多个已打开文件之间区分
+    pub handle: u32,
+    /// 读取时使用的文本编码（默认 UTF-8，可用 `set_encoding` 修改）
+    pub encoding: &'static Encoding,
+    /// 打开时换行扫描（建立索引）的耗时
+    pub open_duration: std::time::Duration,
+    /// 打开时扫描读取的字节数
+    pub bytes_scanned: u64,
+    /// 打开时 `path` 的 (大小, 修改时间)，用于判断再次打开的是否为未改动的同一文件（见 `reuse_active`）；
+    /// 只由 `open_any` 记录，其它方式打开的预览为 None，不会被复用
+    pub open_stat: Option<(u64, Option<SystemTime>)>,
+    /// `get_char_count` 的缓存：(编码, `base_byte`, 文件长度, 字符数)。三者任一变化后缓存失效；
+    /// 各个克隆共享同一份，后台线程算出的结果对之后的请求可见
+    pub char_count: Arc<StdMutex<Option<(&'static Encoding, u64, u64, u64)>>>,
+    /// 最近一次 `mmap_search` 的匹配位置（见 `LastSearch`），供 `get_match_line` 按序号取匹配所在行；
+    /// 与 `char_count` 一样在各个克隆间共享
+    pub last_search: Arc<StdMutex<Option<LastSearch>>>,
+    /// 尾部模式（`set_tail_window`）下视图起点的字节偏移，第 0 行从这里开始；为 0 时显示整个文件。
+    /// 索引中的偏移仍是文件内的绝对偏移
+    pub base_byte: u64,
+    /// 打开的是压缩文件时，解压得到的临时文件及源文件信息（此时 `path` 仍为压缩文件路径，
+    /// `file_handle` 指向临时文件）。放在 `file_handle` 之后，保证先关闭句柄再删除临时文件
+    pub compressed: Option<Arc<Decompressed>>,
+}
+
+/// 超长行（超过 `MAX_LINE_BYTES`）在 `read_lines` 中的处理策略
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TruncationPolicy {
+    /// 截断到 `MAX_LINE_BYTES`，并停止读取后续行（默认，兼容原有行为）
+    #[default]
+    Truncate,
+    /// 读取失败并返回错误
+    Error,
+    /// 完整返回该行，但按 `SPLIT_SEGMENT_BYTES` 拆分为多个显示段（每段单独成行）。
+    /// 返回的行数因此可能多于请求的行数，假定一行一个逻辑行的读取（倒序视图、`diff_ranges`、
+    /// 前端以 `row_per_line` 读取的视图）按 `Truncate` 处理，见 `row_per_line`
+    Split,
+}
+
+/// 导出（`filter_lines`）时输出的换行符。调用方未指定时使用全局的 `OUTPUT_LINE_ENDING_SETTING` 设置（前端的复制也使用它）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LineEnding {
+    /// 原样保留源文件中每行的换行符（默认）
+    #[default]
+    Preserve,
+    /// 统一为 `\n`
+    Lf,
+    /// 统一为 `\r\n`
+    Crlf,
+}
+
+/// `mmap_search` 的大小写匹配方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CaseSensitivity {
+    /// 区分大小写，直接在文件字节上查找
+    Sensitive,
+    /// 只忽略 ASCII 字母的大小写（默认，即原来的 `ignore_case: true`）。整个文件转小写后查找，
+    /// 需要一份与文件等大的缓冲区，但仍是字节级的快速查找，适合全 ASCII 的日志
+    #[default]
+    AsciiInsensitive,
+    /// 按 Unicode 规则忽略大小写（逐字符 `char::to_lowercase`）。逐行解码并转换，比 ASCII 模式慢得多，
+    /// 匹配不跨行；不是合法 UTF-8 的行及模糊搜索退回 ASCII 规则
+    UnicodeInsensitive,
+}
+
+/// `mmap_search` 的行首/行尾锚定，不需要正则表达式就能只找出现在行边界上的匹配
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchAnchor {
+    /// 不锚定（默认）
+    #[default]
+    None,
+    /// 匹配必须从行首开始（紧跟在换行之后，或位于文件/尾部窗口的开头）
+    Start,
+    /// 匹配必须在行尾结束（紧挨着 `\n` 或 `\r\n` 之前，或在文件末尾）
+    End,
+}
+
+impl CaseSensitivity {
+    /// 兼容旧的 `ignore_case` 参数：true 对应 `AsciiInsensitive`
+    pub fn from_ignore_case(ignore_case: bool) -> Self {
+        if ignore_case {
+            CaseSensitivity::AsciiInsensitive
+        } else {
+            CaseSensitivity::Sensitive
+        }
+    }
+}
+
+/// 把 `text`（位于原始数据的 `base` 偏移处）逐字符转小写后追加到 `out`，
+/// 同时在 `map` 中记录 `out` 每个字节对应的原始字节偏移
+fn fold_unicode_into(text: &str, base: usize, out: &mut String, map: &mut Vec<usize>) {
+    for (i, c) in text.char_indices() {
+        for lc in c.to_lowercase() {
+            map.extend(std::iter::repeat(base + i).take(lc.len_utf8()));
+            out.push(lc);
+        }
+    }
+}
+
+/// Unicode 忽略大小写的查找：逐行转小写后查找 `needle`（已用 `fold_unicode_into` 转换），
+/// 按出现顺序对每个不重叠的匹配调用 `f(start, end)`，偏移为 `hay` 中的原始字节偏移。
+/// 不是合法 UTF-8 的行按 ASCII 规则转换。返回转换用的临时缓冲区大小
+fn for_each_unicode_match(hay: &[u8], needle: &str, mut f: impl FnMut(usize, usize)) -> usize {
+    let finder = memchr::memmem::Finder::new(needle.as_bytes());
+    let mut folded = String::new();
+    let mut map: Vec<usize> = Vec::new();
+    let mut pos = 0usize;
+    while pos < hay.len() {
+        let line_end = memchr::memchr(b'\n', &hay[pos..]).map(|p| pos + p).unwrap_or(hay.len());
+        let line = &hay[pos..line_end];
+        folded.clear();
+        map.clear();
+        match std::str::from_utf8(line) {
+            Ok(text) => fold_unicode_into(text, pos, &mut folded, &mut map),
+            Err(_) => {
+                folded.extend(line.iter().map(|&b| if b.is_ascii() { b.to_ascii_lowercase() as char } else { '\u{fffd}' }));
+                // 替换字符占 3 个字节，逐字节记录对应的原始偏移
+                for (i, &b) in line.iter().enumerate() {
+                    map.extend(std::iter::repeat(pos + i).take(if b.is_ascii() { 1 } else { 3 }));
+                }
+            }
+        }
+        map.push(line_end);
+        let mut from = 0usize;
+        while let Some(p) = finder.find(&folded.as_bytes()[from..]) {
+            let start = from + p;
+            let end = start + needle.len();
+            f(map[start], map[end]);
+            from = end;
+        }
+        pos = line_end + 1;
+    }
+    folded.capacity() + map.capacity() * std::mem::size_of::<usize>()
+}
+
+/// 把字节长度转换为 `usize`。32 位目标（如部分 Android 设备）上超过 `usize::MAX`
+/// 的长度会被钳制并记录警告，而不是像 `as usize` 那样静默截断
+fn clamp_len(len: u64, what: &str) -> usize {
+    clamp_len_to(len, usize::MAX, what)
+}
+
+/// `clamp_len` 的实现：把 `len` 转换为 `T`，放不下时钳制为 `max`（`T` 的最大值）。
+/// 与目标的指针宽度无关，在 64 位机器上也能用 `u32` 验证 32 位目标上的行为
+fn clamp_len_to<T: TryFrom<u64>>(len: u64, max: T, what: &str) -> T {
+    match T::try_from(len) {
+        Ok(v) => v,
+        Err(_) => {
+            warn!("{} - length {} exceeds usize::MAX on this target, clamped", what, len);
+            max
+        }
+    }
+}
+
+/// 把 `LargeFilePreview::open` 的失败转换为返回给前端的错误信息：I/O 错误按类型区分（见 `Error::from_open`），
+/// 取消等其他错误保持原信息
+fn open_error_message(path: &Path, e: anyhow::Error) -> String {
+    match e.downcast::<std::io::Error>() {
+        Ok(io) => crate::Error::from_open(path, io).to_string(),
+        Err(e) => format!("Failed to open file preview: {}", e),
+    }
+}
+
+/// `cancel_open` 设置的取消标志，在打开扫描和临时复制的循环中检查；每次 `open_file` 开始时清除
+static OPEN_CANCELLED: AtomicBool = AtomicBool::new(false);
+
+/// 打开被取消时返回的错误信息（前端据此区分取消与真正的失败）
+pub const OPEN_CANCELLED_MSG: &str = "Open cancelled";
+
+/// `cancel_task` 设置的取消标志，由导出、计算校验值等长时间运行的操作在每块之间检查；
+/// 每个这类操作开始时清除。与 `OPEN_CANCELLED` 分开，取消导出不会打断同时进行的打开
+static TASK_CANCELLED: AtomicBool = AtomicBool::new(false);
+
+/// 长时间运行的操作被 `cancel_task` 取消时返回的错误信息
+pub const TASK_CANCELLED_MSG: &str = "Task cancelled";
+
+/// 长时间运行的操作（`filter_lines` 导出、`get_file_hash`、`get_char_count`、`scan_invalid_utf8`、`unique_lines`）的进度事件，
+/// payload 为 `{"task", "done", "total"}`，`task` 为 `"export"`、`"hash"`、`"char_count"`、`"utf8_scan"` 或 `"unique"`，`done`/`total` 为字节
+pub const TASK_PROGRESS_EVENT: &str = "large-file-preview://task-progress";
+
+/// 一次长时间运行的操作的进度报告：创建时清除取消标志，`report` 发出进度事件并检查取消
+struct TaskProgress<R: Runtime> {
+    app: tauri::AppHandle<R>,
+    task: &'static str,
+    total: u64,
+}
+
+impl<R: Runtime> TaskProgress<R> {
+    fn start(app: tauri::AppHandle<R>, task: &'static str, total: u64) -> Self {
+        TASK_CANCELLED.store(false, Ordering::Relaxed);
+        TaskProgress { app, task, total }
+    }
+
+    /// 报告已处理到 `done` 字节；已请求取消时返回 `Interrupted` 错误
+    fn report(&self, done: u64) -> std::io::Result<()> {
+        use tauri::Emitter;
+        let _ = self.app.emit(TASK_PROGRESS_EVENT, json!({ "task": self.task, "done": done, "total": self.total }));
+        if TASK_CANCELLED.load(Ordering::Relaxed) {
+            return Err(std::io::Error::new(std::io::ErrorKind::Interrupted, TASK_CANCELLED_MSG));
+        }
+        Ok(())
+    }
+}
+
+fn check_open_cancelled() -> Result<()> {
+    if OPEN_CANCELLED.load(Ordering::Relaxed) {
+        return Err(anyhow::anyhow!(OPEN_CANCELLED_MSG));
+    }
+    Ok(())
+}
+
+/// 与 `std::io::copy` 相同，但每块之间检查取消标志，取消时返回 `Interrupted` 错误
+fn copy_cancellable(reader: &mut impl Read, writer: &mut impl std::io::Write) -> std::io::Result<u64> {
+    let mut buf = vec![0u8; 256 * 1024];
+    let mut copied = 0u64;
+    loop {
+        if OPEN_CANCELLED.load(Ordering::Relaxed) {
+            return Err(std::io::Error::new(std::io::ErrorKind::Interrupted, OPEN_CANCELLED_MSG));
+        }
+        let n = match reader.read(&mut buf) {
+            Ok(0) => return Ok(copied),
+            Ok(n) => n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        writer.write_all(&buf[..n])?;
+        copied += n as u64;
+    }
+}
+
+/// 打开文件时探测格式所取的文件头样本大小
+const FORMAT_SAMPLE_BYTES: usize = 64 * 1024;
+
+/// `open_file` 的返回值
+#[derive(Debug, Clone, Serialize)]
+pub struct OpenResult {
+    pub path: String,
+    pub status: String,
+    /// 文件大小（字节）；始终为 `u64`，32 位目标上也能正确表示超过 4GB 的文件
+    pub size: u64,
+    pub total_lines: usize,
+    /// 读取时使用的文本编码（打开时总是 "utf-8"，非法字节以替换字符显示），见 `set_encoding`
+    pub encoding: String,
+    /// 文件头样本是否为合法 UTF-8；为 false 时前端应让用户通过 `preview_encodings` 选择编码
+    pub encoding_confident: bool,
+    /// 根据文件头样本判断的换行风格："lf" / "crlf" / "mixed" / "none"
+    pub line_ending: String,
+    pub truncation_policy: TruncationPolicy,
+    /// 文件头样本中含 NUL 字节时视为二进制文件
+    pub is_binary: bool,
+    /// 文件长度为 0（或尾部窗口中没有任何行）时为 true，前端据此显示“文件为空”而不是空白的视图
+    pub is_empty: bool,
+    /// 是否建立了行索引；以 `no_index` 打开时为 false，此时 `total_lines` 是估算值，见 `LargeFilePreview::open_unindexed`
+    pub indexed: bool,
+    /// 见 `register_preview`
+    pub handle: u32,
+    /// 上次关闭该路径时保存的会话状态（见 `session` 模块）
+    pub session: SessionState,
+    /// 打开时换行扫描的耗时（毫秒），不含文件选择与临时复制
+    pub open_duration_ms: u128,
+    /// 打开时扫描读取的字节数
+    pub bytes_scanned: u64,
+    /// 打开的是压缩文件时为其压缩格式，此时 `size` 为解压后的大小
+    pub compression: Option<Compression>,
+    /// 压缩文件本身的大小（字节）；未压缩时为 None
+    pub compressed_size: Option<u64>,
+}
+
+/// 统计样本中的 `\n` 与 `\r\n`，判断换行风格
+fn detect_line_ending(sample: &[u8]) -> &'static str {
+    let lf = memchr::memchr_iter(b'\n', sample).count();
+    if lf == 0 {
+        return "none";
+    }
+    let crlf = memchr::memmem::find_iter(sample, b"\r\n").count();
+    if crlf == 0 {
+        "lf"
+    } else if crlf == lf {
+        "crlf"
+    } else {
+        "mixed"
+    }
+}
+
+/// `preview_encodings` 尝试的候选编码
+const CANDIDATE_ENCODINGS: &[&str] = &["UTF-8", "GB18030", "Big5", "Shift_JIS", "EUC-KR", "windows-1252", "UTF-16LE", "UTF-16BE"];
+
+/// `preview_encodings` 解码的文件头样本上限
+const ENCODING_PREVIEW_BYTES: usize = 4 * 1024;
+
+/// 按 `encoding` 解码字节，不处理 BOM；UTF-8 时与 `String::from_utf8_lossy` 相同
+fn decode_bytes<'a>(encoding: &'static Encoding, bytes: &'a [u8]) -> std::borrow::Cow<'a, str> {
+    if encoding == UTF_8 {
+        String::from_utf8_lossy(bytes)
+    } else {
+        encoding.decode_without_bom_handling(bytes).0
+    }
+}
+
+/// 文件的 (大小, 修改时间)，无法读取元数据时返回 None
+fn file_stat(path: &Path) -> Option<(u64, Option<SystemTime>)> {
+    std::fs::metadata(path).ok().map(|m| (m.len(), m.modified().ok()))
+}
+
+/// `stream_file` 每次映射的窗口大小
+const STREAM_CHUNK_BYTES: u64 = 64 * 1024 * 1024;
+
+/// 以有界内存顺序读取整个文件：每次 mmap 最多 `chunk_bytes` 字节的窗口并调用 `f(offset, chunk, is_last)`，
+/// `f` 返回 false 时提前停止，返回处理到的位置（文件内偏移）。不移动共享文件句柄的读取位置。
+///
+/// `line_aligned` 为 true 时除最后一块外每块都在换行处结束（窗口中最后一个换行之后的内容留给下一块），
+/// 单行比一个窗口还长时只能按窗口拆开。memmap2 会自行处理未按页对齐的偏移。
+fn stream_file(
+    file: &std::fs::File,
+    start: u64,
+    chunk_bytes: u64,
+    line_aligned: bool,
+    mut f: impl FnMut(u64, &[u8], bool) -> std::io::Result<bool>,
+) -> std::io::Result<u64> {
+    let total = file.metadata()?.len();
+    let mut done = start;
+    while done < total {
+        let len = clamp_len((total - done).min(chunk_bytes), "stream_file");
+        let window = unsafe { MmapOptions::new().offset(done).len(len).map(file)? };
+        let is_last = done + len as u64 >= total;
+        let mut chunk = &window[..];
+        if line_aligned && !is_last {
+            if let Some(i) = memchr::memrchr(b'\n', chunk) {
+                chunk = &chunk[..=i];
+            }
+        }
+        let keep_going = f(done, chunk, is_last)?;
+        done += chunk.len() as u64;
+        if !keep_going {
+            break;
+        }
+    }
+    Ok(done)
+}
+
+/// 在 `hay[line_start..line_end]` 这一行中定位 `needle`（`last` 为 true 时取最后一处），返回 `(字符列, 匹配的字符长度, 绝对字节偏移)`；
+/// 忽略大小写只比较 ASCII 字母，与 `for_each_matching_line` 一致
+fn match_in_line(hay: &[u8], line_start: usize, line_end: usize, needle: &[u8], ignore_case: bool, last: bool) -> (usize, usize, usize) {
+    let line_bytes = &hay[line_start..line_end];
+    let col = match (ignore_case, last) {
+        (true, false) => memchr::memmem::find(&line_bytes.to_ascii_lowercase(), &needle.to_ascii_lowercase()),
+        (true, true) => memchr::memmem::rfind(&line_bytes.to_ascii_lowercase(), &needle.to_ascii_lowercase()),
+        (false, false) => memchr::memmem::find(line_bytes, needle),
+        (false, true) => memchr::memmem::rfind(line_bytes, needle),
+    }
+    .unwrap_or(0);
+    let abs = line_start + col;
+    let col_chars = String::from_utf8_lossy(&line_bytes[..col]).chars().count();
+    let len_chars = String::from_utf8_lossy(&hay[abs..(abs + needle.len()).min(hay.len())]).chars().count();
+    (col_chars, len_chars, abs)
+}
+
+/// 依次对 `hay` 中每个包含 `needle` 的行调用 `f(line_no, line_start, line_end)`，`line_end` 包含行尾 `\n`。
+/// 一行内多次匹配只回调一次；`f` 返回 false 时停止扫描。
+///
+/// 区分大小写时用 memmem 在整个缓冲区上跳跃查找，只对两次匹配之间的区间统计换行；
+/// 忽略大小写（ASCII）时逐行转小写后查找，只需一行大小的临时缓冲区。
+fn for_each_matching_line(hay: &[u8], needle: &[u8], ignore_case: bool, mut f: impl FnMut(usize, usize, usize) -> bool) {
+    if ignore_case {
+        let needle = needle.to_ascii_lowercase();
+        let finder = memchr::memmem::Finder::new(&needle);
+        let mut lowered = Vec::new();
+        let mut line_no = 0usize;
+        let mut pos = 0usize;
+        while pos < hay.len() {
+            let line_end = memchr::memchr(b'\n', &hay[pos..]).map(|p| pos + p + 1).unwrap_or(hay.len());
+            lowered.clear();
+            lowered.extend(hay[pos..line_end].iter().map(|b| b.to_ascii_lowercase()));
+            if finder.find(&lowered).is_some() && !f(line_no, pos, line_end) {
+                return;
+            }
+            line_no += 1;
+            pos = line_end;
+        }
+        return;
+    }
+    let mut line_no = 0usize;
+    // `pos` 为下一个尚未处理的行的起始偏移，`line_no` 为该行的行号
+    let mut pos = 0usize;
+    for m in memchr::memmem::find_iter(hay, needle) {
+        if m < pos {
+            // 同一行内的后续匹配
+            continue;
+        }
+        let line_start = memchr::memrchr(b'\n', &hay[pos..m]).map(|p| pos + p + 1).unwrap_or(pos);
+        line_no += memchr::memchr_iter(b'\n', &hay[pos..line_start]).count();
+        let line_end = memchr::memchr(b'\n', &hay[m..]).map(|p| m + p + 1).unwrap_or(hay.len());
+        if !f(line_no, line_start, line_end) {
+            return;
+        }
+        line_no += 1;
+        pos = line_end;
+    }
+}
+
+/// 从 `reader` 当前位置（第 `first_line` 行的起始处）起最多扫描 `max_lines` 行，对每个空行调用 `f(行号)`，
+/// `f` 返回 false 时停止。空行指没有内容的行（忽略 CRLF 的 `\r`）；`whitespace_is_blank` 为 true 时
+/// 只含空格/制表符的行也算。直接检查 `BufRead` 缓冲区中的字节，不为长行分配内存
+fn scan_blank_lines(
+    reader: &mut impl BufRead,
+    first_line: usize,
+    max_lines: usize,
+    whitespace_is_blank: bool,
+    mut f: impl FnMut(usize) -> bool,
+) -> std::io::Result<()> {
+    let end = first_line.saturating_add(max_lines);
+    let mut line = first_line;
+    let mut blank = true;
+    let mut has_bytes = false;
+    while line < end {
+        let buf = reader.fill_buf()?;
+        if buf.is_empty() {
+            // 文件末尾没有换行的最后一行
+            if has_bytes && blank {
+                f(line);
+            }
+            return Ok(());
+        }
+        let mut used = buf.len();
+        let mut stop = false;
+        for (i, &b) in buf.iter().enumerate() {
+            if b == b'\n' {
+                if blank && !f(line) {
+                    stop = true;
+                }
+                line += 1;
+                blank = true;
+                has_bytes = false;
+                if stop || line >= end {
+                    used = i + 1;
+                    break;
+                }
+            } else {
+                has_bytes = true;
+                let ignorable = b == b'\r' || (whitespace_is_blank && (b == b' ' || b == b'\t'));
+                if !ignorable {
+                    blank = false;
+                }
+            }
+        }
+        reader.consume(used);
+        if stop {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// `enclosing_block` 从目标行向前、向后各最多扫描的字节数
+const MAX_BLOCK_SCAN_BYTES: usize = 4 * 1024 * 1024;
+
+/// 括号配对：从 `from`（不含）往前在 `[low, from)` 中找第一个未配对的左括号，
+/// 再往后在 `(左括号, high)` 中找与之配对的右括号，返回两者的字节偏移。
+/// 多种括号交错不配对时忽略不匹配的那一个
+fn brace_block(hay: &[u8], low: usize, from: usize, high: usize, braces: &[(u8, u8)]) -> Option<(usize, usize)> {
+    let mut stack: Vec<usize> = Vec::new();
+    let mut open = None;
+    for pos in (low..from).rev() {
+        let b = hay[pos];
+        if let Some(i) = braces.iter().position(|p| p.1 == b) {
+            stack.push(i);
+        } else if let Some(i) = braces.iter().position(|p| p.0 == b) {
+            match stack.last() {
+                None => {
+                    open = Some((pos, i));
+                    break;
+                }
+                Some(&top) if top == i => {
+                    stack.pop();
+                }
+                Some(_) => {}
+            }
+        }
+    }
+    let (open_at, pair) = open?;
+    stack.clear();
+    for (pos, &b) in hay.iter().enumerate().take(high).skip(open_at + 1) {
+        if let Some(i) = braces.iter().position(|p| p.0 == b) {
+            stack.push(i);
+        } else if let Some(i) = braces.iter().position(|p| p.1 == b) {
+            match stack.last() {
+                None if i == pair => return Some((open_at, pos)),
+                Some(&top) if top == i => {
+                    stack.pop();
+                }
+                _ => {}
+            }
+        }
+    }
+    None
+}
+
+/// 一行（不含 `\n`）的缩进宽度（行首空格/制表符的字节数），空行或只含空白的行返回 None
+fn line_indent(line: &[u8]) -> Option<usize> {
+    let line = line.strip_suffix(b"\r").unwrap_or(line);
+    let indent = line.iter().take_while(|&&b| b == b' ' || b == b'\t').count();
+    (indent < line.len()).then_some(indent)
+}
+
+/// 按缩进确定代码块：`[low, high)` 由完整的行组成，`line_start` 为目标行的起始偏移。
+/// 返回块首行与末行的起始偏移
+fn indent_block(hay: &[u8], low: usize, line_start: usize, high: usize) -> Option<(usize, usize)> {
+    let line_at = |start: usize| {
+        let end = memchr::memchr(b'\n', &hay[start..high]).map(|p| start + p).unwrap_or(high);
+        (&hay[start..end], end + 1)
+    };
+    // 目标行与其后第一个非空行的缩进；目标行是空行时按其后的行算
+    let (target, mut next) = line_at(line_start);
+    let mut target_indent = line_indent(target);
+    let mut following = None;
+    while next < high {
+        let (text, after) = line_at(next);
+        if let Some(indent) = line_indent(text) {
+            following = Some(indent);
+            break;
+        }
+        next = after;
+    }
+    if target_indent.is_none() {
+        target_indent = following;
+    }
+    let target_indent = target_indent?;
+    // 其后的行缩进更大时目标行本身就是块的首行（如 `def f():`），否则往前找第一个缩进更小的非空行
+    let (block_start, block_indent) = if following.is_some_and(|f| f > target_indent) {
+        (line_start, target_indent)
+    } else {
+        let mut pos = line_start;
+        loop {
+            if pos <= low {
+                return None;
+            }
+            let prev = memchr::memrchr(b'\n', &hay[low..pos - 1]).map(|p| low + p + 1).unwrap_or(low);
+            if let Some(indent) = line_indent(&hay[prev..pos - 1]).filter(|&i| i < target_indent) {
+                break (prev, indent);
+            }
+            pos = prev;
+        }
+    };
+    // 块到其后最后一个缩进比首行大的非空行为止，中间的空行不结束块；超出扫描范围时截止于已扫描的部分
+    let (_, mut pos) = line_at(block_start);
+    let mut block_end = block_start;
+    while pos < high {
+        let (text, after) = line_at(pos);
+        match line_indent(text) {
+            Some(indent) if indent <= block_indent => break,
+            Some(_) => block_end = pos,
+            None => {}
+        }
+        pos = after;
+    }
+    Some((block_start, block_end))
+}
+
+/// 按截断策略把一行（不含 `\n`）追加到 `out`，每段以 `\n` 结尾。
+///
+/// `line_no` 仅用于错误信息。返回 `Ok(false)` 表示调用方应停止读取后续行。
+fn append_line(out: &mut String, line: &[u8], line_no: usize, policy: TruncationPolicy) -> Result<bool> {
+    if line.len() <= MAX_LINE_BYTES {
+        out.push_str(&String::from_utf8_lossy(line));
+        out.push('\n');
+        return Ok(true);
+    }
+    match policy {
+        TruncationPolicy::Truncate => {
+            out.push_str(&String::from_utf8_lossy(&line[..MAX_LINE_BYTES]));
+            out.push('\n');
+            Ok(false)
+        }
+        TruncationPolicy::Error => Err(anyhow::anyhow!(
+            "line {} is longer than {} bytes",
+            line_no + 1,
+            MAX_LINE_BYTES
+        )),
+        TruncationPolicy::Split => {
+            let mut rest = line;
+            while !rest.is_empty() {
+                let mut cut = rest.len().min(SPLIT_SEGMENT_BYTES);
+                // 避免在 UTF-8 多字节字符中间切分（最多回退 3 个续字节）
+                let mut back = 0;
+                while cut < rest.len() && back < 3 && (rest[cut] & 0xC0) == 0x80 {
+                    cut -= 1;
+                    back += 1;
+                }
+                out.push_str(&String::from_utf8_lossy(&rest[..cut]));
+                out.push('\n');
+                rest = &rest[cut..];
+            }
+            Ok(true)
+        }
+    }
+}
+
+/// 估算总行数时抽样的块数与每块大小：共读取约 1 MB，均匀分布在整个文件上
+const LINE_ESTIMATE_SAMPLES: u64 = 8;
+const LINE_ESTIMATE_SAMPLE_BYTES: usize = 128 * 1024;
+
+/// 在 `file` 的 `[start, end)` 中均匀读取 `LINE_ESTIMATE_SAMPLES` 块（第一块从 `start` 开始，最后一块到 `end` 结束），
+/// 按样本的平均行长把这段的字节数换算成估算的行数。这段为空时返回 0，样本中没有换行时按只有一行处理。
+///
+/// 只读几块，耗时与文件大小无关；代价是精度取决于行长是否均匀。行长均匀的日志通常偏差在几个百分点以内，
+/// 前后行长差别很大的文件（如开头是几行很长的 JSON、后面是短日志）可能偏差数倍，因此结果只用于显示与滚动条范围
+fn estimate_lines(file: &std::fs::File, start: u64, end: u64) -> std::io::Result<usize> {
+    let span = end.saturating_sub(start);
+    if span == 0 {
+        return Ok(0);
+    }
+    let sample = LINE_ESTIMATE_SAMPLE_BYTES as u64;
+    let mut f = file.try_clone()?;
+    let mut buf = Vec::with_capacity(LINE_ESTIMATE_SAMPLE_BYTES);
+    let (mut newlines, mut sampled) = (0u128, 0u128);
+    for i in 0..LINE_ESTIMATE_SAMPLES {
+        let offset = start + (span.saturating_sub(sample) as u128 * i as u128 / (LINE_ESTIMATE_SAMPLES - 1) as u128) as u64;
+        f.seek(std::io::SeekFrom::Start(offset))?;
+        buf.clear();
+        (&mut f).take(sample).read_to_end(&mut buf)?;
+        newlines += memchr::memchr_iter(b'\n', &buf).count() as u128;
+        sampled += buf.len() as u128;
+    }
+    if newlines == 0 || sampled == 0 {
+        return Ok(1);
+    }
+    Ok(((span as u128 * newlines / sampled) as usize).max(1))
+}
+
+/// 打开文件时使用的默认索引间隔（行数）
+const DEFAULT_INDEX_INTERVAL: usize = 1000;
+
+/// `reindex` 允许的索引间隔范围
+const MIN_INDEX_INTERVAL: usize = 10;
+const MAX_INDEX_INTERVAL: usize = 1_000_000;
+
+/// 从 `reader` 当前位置顺序扫描换行，返回 `(总行数, 每 index_interval 行的字节偏移, 读取的字节数)`。
+/// 每读一块调用一次 `should_stop`，返回错误时中止扫描
+fn scan_line_index(reader: &mut impl Read, index_interval: usize, should_stop: impl Fn() -> Result<()>) -> Result<(usize, Vec<u64>, u64)> {
+    let mut total = 0usize;
+    let mut index: Vec<u64> = Vec::new();
+    let mut buf = vec![0u8; 64 * 1024]; // 64KB 缓冲
+    let mut rem: Vec<u8> = Vec::new();
+    let mut pos = 0u64;
+    let mut bytes_scanned = 0u64;
+    loop {
+        should_stop()?;
+        let n = reader.read(&mut buf)?;
+        bytes_scanned += n as u64;
+        if n == 0 {
+            if !rem.is_empty() {
+                total += 1;
+                pos += rem.len() as u64;
+                if total % index_interval == 0 {
+                    index.push(pos);
+                }
+            }
+            break;
+        }
+        let mut start = 0usize;
+        for i in 0..n {
+            if buf[i] == b'\n' {
+                // 收集行数据长度
+                let part_len = i + 1 - start;
+                let line_len = rem.len() + part_len;
+                // 如果单行超过 MAX_LINE_BYTES，则按限制计算位置并丢弃多余字节
+                if line_len > MAX_LINE_BYTES {
+                    // 将 pos 增加到截断后的位置（只计算 MAX_LINE_BYTES）
+                    pos += MAX_LINE_BYTES as u64;
+                } else {
+                    pos += line_len as u64;
+                }
+                total += 1;
+                if total % index_interval == 0 {
+                    index.push(pos);
+                }
+                rem.clear();
+                start = i + 1;
+            }
+        }
+        // 处理未结束的行残余
+        if start < n {
+            rem.extend_from_slice(&buf[start..n]);
+            // 防止 rem 无限增长（单行超长），当超过阈值时丢弃超过部分
+            if rem.len() > MAX_LINE_BYTES {
+                // 我们只保留 MAX_LINE_BYTES 的计数信息，不保留全部内容
+                pos += (rem.len() - MAX_LINE_BYTES) as u64;
+                rem.truncate(MAX_LINE_BYTES);
+            }
+        }
+    }
+    Ok((total, index, bytes_scanned))
 }
 
 impl LargeFilePreview {
@@ -70,174 +790,1105 @@ impl LargeFilePreview {
         let file_arc = Arc::new(file);
         // 使用分块读取以避免在遇到极长单行时分配过大缓冲区
         let mut reader = file_arc.as_ref().try_clone()?;
-        let mut total = 0usize;
-        let mut index: Vec<u64> = Vec::new();
         // 默认每 1000 行记录一次索引，减少内存占用并提高随机访问效率
-        let index_interval = 1000usize;
-        let mut buf = vec![0u8; 64 * 1024]; // 64KB 缓冲
-        let mut rem: Vec<u8> = Vec::new();
-        let mut pos = 0u64;
-        loop {
-            let n = reader.read(&mut buf)?;
-            if n == 0 {
-                if !rem.is_empty() {
-                    total += 1;
-                    pos += rem.len() as u64;
-                    if total % index_interval == 0 {
-                        index.push(pos);
-                    }
-                }
-                break;
-            }
-            let mut start = 0usize;
-            for i in 0..n {
-                if buf[i] == b'\n' {
-                    // 收集行数据长度
-                    let part_len = i + 1 - start;
-                    let line_len = rem.len() + part_len;
-                    // 如果单行超过 MAX_LINE_BYTES，则按限制计算位置并丢弃多余字节
-                    if line_len > MAX_LINE_BYTES {
-                        // 将 pos 增加到截断后的位置（只计算 MAX_LINE_BYTES）
-                        pos += MAX_LINE_BYTES as u64;
-                    } else {
-                        pos += line_len as u64;
-                    }
-                    total += 1;
-                    if total % index_interval == 0 {
-                        index.push(pos);
-                    }
-                    rem.clear();
-                    start = i + 1;
-                }
-            }
-            // 处理未结束的行残余
-            if start < n {
-                rem.extend_from_slice(&buf[start..n]);
-                // 防止 rem 无限增长（单行超长），当超过阈值时丢弃超过部分
-                if rem.len() > MAX_LINE_BYTES {
-                    // 我们只保留 MAX_LINE_BYTES 的计数信息，不保留全部内容
-                    pos += (rem.len() - MAX_LINE_BYTES) as u64;
-                    rem.truncate(MAX_LINE_BYTES);
-                }
-            }
-        }
-        info!("LargeFilePreview::open - finished scanning file. total_lines={}, index.len()={} ", total, index.len());
+        let index_interval = DEFAULT_INDEX_INTERVAL;
+        let scan_start = std::time::Instant::now();
+        let (total, index, bytes_scanned) = scan_line_index(&mut reader, index_interval, check_open_cancelled)?;
+        info!(
+            "LargeFilePreview::open - finished scanning file. total_lines={}, index.len()={}, bytes_scanned={}, scan took {:?}",
+            total,
+            index.len(),
+            bytes_scanned,
+            scan_start.elapsed()
+        );
         Ok(Self {
             path,
             total_lines: total,
             index,
             index_interval,
+            indexed: true,
             cached_window: Arc::new(StdMutex::new(None)),
+            char_count: Arc::new(StdMutex::new(None)),
+            last_search: Arc::new(StdMutex::new(None)),
+            open_stat: None,
             file_handle: file_arc,
+            truncation_policy: TruncationPolicy::default(),
+            handle: 0,
+            encoding: UTF_8,
+            open_duration: scan_start.elapsed(),
+            bytes_scanned,
+            base_byte: 0,
+            compressed: None,
         })
     }
 
     #[cfg(unix)]
     /// Create a LargeFilePreview from a native file descriptor (Android case).
+    ///
+    /// 取得 fd 的所有权：fd 保存在 `file_handle` 中，随 preview 一起存活到 `close_file`，
+    /// 因此之后的 `read_lines` / `mmap_search` 都直接基于该 fd。
+    /// 可直接使用 fd 的场景：Android 上由本地存储支持的内容 URI（DocumentsProvider 返回的
+    /// 是普通文件 fd，可 seek、可 mmap），以及桌面 Unix 的普通文件。管道/socket 类型的 fd
+    /// （如部分云盘、流式 provider）不可 seek 或 mmap，调用方应回退到复制到临时文件。
     pub fn open_from_fd(fd: i32, path_hint: PathBuf) -> Result<Self> {
         use std::os::unix::io::FromRawFd;
         // Safety: take ownership of fd; caller must ensure fd was detached and not used elsewhere
         let file = unsafe { std::fs::File::from_raw_fd(fd) };
         let file_arc = Arc::new(file);
         let mut reader = file_arc.as_ref().try_clone()?;
-        let mut total = 0usize;
-        let mut index: Vec<u64> = Vec::new();
-        let index_interval = 1000usize;
-        let mut buf = vec![0u8; 64 * 1024];
-        let mut rem: Vec<u8> = Vec::new();
-        let mut pos = 0u64;
-        loop {
-            let n = reader.read(&mut buf)?;
-            if n == 0 {
-                if !rem.is_empty() {
-                    total += 1;
-                    pos += rem.len() as u64;
-                    if total % index_interval == 0 {
-                        index.push(pos);
-                    }
-                }
-                break;
-            }
-            let mut start = 0usize;
-            for i in 0..n {
-                if buf[i] == b'\n' {
-                    let part_len = i + 1 - start;
-                    let line_len = rem.len() + part_len;
-                    if line_len > MAX_LINE_BYTES {
-                        pos += MAX_LINE_BYTES as u64;
-                    } else {
-                        pos += line_len as u64;
-                    }
-                    total += 1;
-                    if total % index_interval == 0 {
-                        index.push(pos);
-                    }
-                    rem.clear();
-                    start = i + 1;
-                }
-            }
-            if start < n {
-                rem.extend_from_slice(&buf[start..n]);
-                if rem.len() > MAX_LINE_BYTES {
-                    pos += (rem.len() - MAX_LINE_BYTES) as u64;
-                    rem.truncate(MAX_LINE_BYTES);
-                }
-            }
-        }
+        let index_interval = DEFAULT_INDEX_INTERVAL;
+        let scan_start = std::time::Instant::now();
+        let (total, index, bytes_scanned) = scan_line_index(&mut reader, index_interval, check_open_cancelled)?;
         Ok(Self {
             path: path_hint,
             total_lines: total,
             index,
             index_interval,
+            indexed: true,
             cached_window: Arc::new(StdMutex::new(None)),
+            char_count: Arc::new(StdMutex::new(None)),
+            last_search: Arc::new(StdMutex::new(None)),
+            open_stat: None,
             file_handle: file_arc,
+            truncation_policy: TruncationPolicy::default(),
+            handle: 0,
+            encoding: UTF_8,
+            open_duration: scan_start.elapsed(),
+            bytes_scanned,
+            base_byte: 0,
+            compressed: None,
         })
     }
 
-    /// 返回已统计的总行数（open 时计算）
-    pub fn total_lines(&self) -> usize {
-        self.total_lines
+    /// 不扫描换行直接打开 `path`，用于只想看看超大文件开头的场合。
+    ///
+    /// 不建立索引：`index` 为空、`indexed` 为 false，`total_lines` 是抽样估算的行数（见 `estimate_lines`）。
+    /// `read_lines` 因此总是从 `base_byte` 顺序读起，位置越靠后越慢；之后可用 `reindex` 建立完整索引。
+    /// 文件不大于全部样本时完整扫描同样很快，直接按 `open` 打开
+    pub fn open_unindexed(path: PathBuf) -> Result<Self> {
+        let mut opts = OpenOptions::new();
+        opts.read(true);
+        #[cfg(windows)]
+        {
+            opts.share_mode(0x0000_0001 | 0x0000_0002 | 0x0000_0004);
+        }
+        let file = opts.open(&path)?;
+        let file_len = file.metadata()?.len();
+        let sampled = LINE_ESTIMATE_SAMPLES * LINE_ESTIMATE_SAMPLE_BYTES as u64;
+        if file_len <= sampled {
+            drop(file);
+            return Self::open(path);
+        }
+        let scan_start = std::time::Instant::now();
+        let total = estimate_lines(&file, 0, file_len)?;
+        info!(
+            "LargeFilePreview::open_unindexed - {:?}: {} bytes, estimated total_lines={} from {} sampled bytes in {:?}",
+            path,
+            file_len,
+            total,
+            sampled,
+            scan_start.elapsed()
+        );
+        Ok(Self {
+            path,
+            total_lines: total,
+            index: Vec::new(),
+            index_interval: DEFAULT_INDEX_INTERVAL,
+            indexed: false,
+            cached_window: Arc::new(StdMutex::new(None)),
+            char_count: Arc::new(StdMutex::new(None)),
+            last_search: Arc::new(StdMutex::new(None)),
+            open_stat: None,
+            file_handle: Arc::new(file),
+            truncation_policy: TruncationPolicy::default(),
+            handle: 0,
+            encoding: UTF_8,
+            open_duration: scan_start.elapsed(),
+            bytes_scanned: sampled,
+            base_byte: 0,
+            compressed: None,
+        })
     }
 
-    /// 异步读取从 `start` 行开始的 `count` 行文本。
+    /// 打开 `path`：gzip / bzip2 / xz 压缩文件（按文件头识别）先解压到临时文件再打开，
+    /// 其余文件与 `open` 相同。解压同样可被 `cancel_open` 取消。
+    /// `no_index` 为 true 时不建立行索引，见 `open_unindexed`（压缩文件仍需完整解压）
+    pub fn open_any(path: PathBuf, no_index: bool) -> Result<Self> {
+        let open = if no_index { Self::open_unindexed } else { Self::open };
+        // 在扫描前记录，扫描期间文件被修改时下次打开不会误用这次的索引
+        let open_stat = file_stat(&path);
+        let Some(compression) = Compression::detect(&path)? else {
+            let mut preview = open(path)?;
+            preview.open_stat = open_stat;
+            return Ok(preview);
+        };
+        info!("LargeFilePreview::open_any - {:?} is {:?} compressed, decompressing", path, compression);
+        let decompressed = compressed::decompress_to_temp(&path, compression, |mut r, w| copy_cancellable(&mut r, w))?;
+        let mut preview = open(decompressed.temp.path().to_path_buf())?;
+        // 会话状态、最近文件与导出时的防覆盖检查都以压缩文件本身的路径为准
+        preview.path = path;
+        preview.compressed = Some(Arc::new(decompressed));
+        preview.open_stat = open_stat;
+        Ok(preview)
+    }
+
+    /// 生成 `open_file` 的返回值（`handle` 由调用方在注册后填写）
+    fn open_result(&self, path: &Path) -> OpenResult {
+        let size = match self.file_handle.as_ref().metadata() {
+            Ok(meta) => meta.len(),
+            Err(e) => {
+                warn!("open_result - failed to get metadata: {}", e);
+                0u64
+            }
+        };
+        // 用 mmap 读取文件头样本，避免移动共享文件句柄的读取位置
+        let sample_len = size.min(FORMAT_SAMPLE_BYTES as u64) as usize;
+        let sample = if sample_len > 0 {
+            unsafe { MmapOptions::new().len(sample_len).map(self.file_handle.as_ref()) }.ok()
+        } else {
+            None
+        };
+        let sample: &[u8] = sample.as_deref().unwrap_or(&[]);
+        OpenResult {
+            path: path.to_string_lossy().to_string(),
+            status: "success".to_string(),
+            size,
+            total_lines: self.total_lines,
+            encoding: self.encoding.name().to_lowercase(),
+            // 样本末尾可能截断在多字节字符中间，这种不完整的结尾不算错误
+            encoding_confident: match std::str::from_utf8(sample) {
+                Ok(_) => true,
+                Err(e) => e.error_len().is_none(),
+            },
+            line_ending: detect_line_ending(sample).to_string(),
+            truncation_policy: self.truncation_policy,
+            is_binary: memchr::memchr(0, sample).is_some(),
+            is_empty: size == 0 || self.total_lines == 0,
+            indexed: self.indexed,
+            handle: 0,
+            session: SessionState::default(),
+            open_duration_ms: self.open_duration.as_millis(),
+            bytes_scanned: self.bytes_scanned,
+            compression: self.compressed.as_ref().map(|c| c.compression),
+            compressed_size: self.compressed.as_ref().map(|c| c.compressed_size),
+        }
+    }
+
+    /// 以 `new_interval` 重建行索引并清空 mmap 窗口缓存，返回新的索引长度。
     ///
-    /// 实现要点：优先尝试使用 mmap 窗口进行切片读取以提升性能；失败时回退到 `BufReader` 顺序读取。
-    /// - `start`: 起始行（0 基准）
-    /// - `count`: 要读取的行数
-    /// 返回读取到的多行字符串，每行以 `\n` 结尾（如果文件末尾不足则返回实际行数）。
-    pub async fn read_lines(&self, start: usize, count: usize) -> Result<String> {
-        let index = self.index.clone();
-        let index_interval = self.index_interval;
-        let cache = self.cached_window.clone();
-        let file_handle = self.file_handle.clone();
-        smol::unblock(move || -> Result<String> {
-            let file = file_handle.as_ref().try_clone()?;
-            let pos_idx = start / index_interval;
-            let (base_offset, base_line) = if pos_idx == 0 {
-                (0u64, 0usize)
-            } else {
-                let idx = pos_idx.saturating_sub(1);
-                if idx < index.len() {
-                    (index[idx], pos_idx * index_interval)
-                } else {
-                    (0u64, 0usize)
-                }
-            };
+    /// 新间隔是当前间隔的整数倍时直接从已有索引中抽取（不重新扫描）；否则从 `base_byte`（通常为文件开头）重新扫描，
+    /// 同时更新总行数。索引点的含义与打开时一致，`index_base` / `reader_at_line` 的计算保持不变。
+    /// 未建立索引（`open_unindexed`）时总是完整扫描，之后 `indexed` 为 true、总行数为精确值。
+    pub fn reindex(&mut self, new_interval: usize) -> Result<usize> {
+        if self.indexed && new_interval % self.index_interval == 0 {
+            let k = new_interval / self.index_interval;
+            self.index = self.index.iter().copied().skip(k - 1).step_by(k).collect();
+        } else {
+            let mut reader = self.file_handle.as_ref().try_clone()?;
+            reader.seek(std::io::SeekFrom::Start(self.base_byte))?;
+            let (total, index, _) = scan_line_index(&mut reader, new_interval, || Ok(()))?;
+            self.total_lines = total;
+            self.index = index.into_iter().map(|o| o + self.base_byte).collect();
+        }
+        self.index_interval = new_interval;
+        self.indexed = true;
+        self.cached_window = Arc::new(StdMutex::new(None));
+        Ok(self.index.len())
+    }
 
-            // 计算 mmap 映射窗口（以页对齐）以尝试零拷贝读取
-            let page_size = 4096usize;
-            let estimated_line_len = 120usize;
-            let desired_lines = count + index_interval;
-            let desired_bytes = desired_lines.saturating_mul(estimated_line_len);
-            let aligned = (base_offset / page_size as u64) * page_size as u64;
-            let delta = (base_offset.saturating_sub(aligned)) as usize;
-            let mut map_len = delta.saturating_add(desired_bytes);
-            let cap = 8 * 1024 * 1024usize;
-            if map_len > cap {
-                map_len = cap;
+    /// 尾部模式：只显示文件最后 `max_bytes` 字节，`None`（或不小于文件大小）时恢复显示整个文件，返回新的 `base_byte`。
+    ///
+    /// 从 `file_len - max_bytes` 开始，若不在行首则跳过这一不完整的行，从下一个换行之后开始（最多向后找 `MAX_LINE_BYTES`，
+    /// 找不到、或下一行已在文件末尾时就从该偏移开始）。只扫描窗口内的内容重建索引，行号从窗口的第一行起算，
+    /// 之后的 `read_lines` / `mmap_search` / 总行数都只针对这一窗口。
+    pub fn set_tail_window(&mut self, max_bytes: Option<u64>) -> Result<u64> {
+        let len = self.file_handle.as_ref().metadata()?.len();
+        let base = match max_bytes {
+            Some(max) if max < len => {
+                let start = len - max;
+                // 从 `start - 1` 开始映射，正好落在换行上时 `start` 就是行首
+                let map_len = clamp_len((len - (start - 1)).min(MAX_LINE_BYTES as u64 + 1), "set_tail_window");
+                let window = unsafe { MmapOptions::new().offset(start - 1).len(map_len).map(self.file_handle.as_ref())? };
+                match memchr::memchr(b'\n', &window) {
+                    // 找到的换行是文件的最后一个字节时其后没有内容，退回 `start`，保证 `base_byte < len`
+                    Some(p) if start + (p as u64) < len => start + p as u64,
+                    _ => start,
+                }
             }
+            _ => 0,
+        };
+        let mut reader = self.file_handle.as_ref().try_clone()?;
+        reader.seek(std::io::SeekFrom::Start(base))?;
+        let scan_start = std::time::Instant::now();
+        let (total, index, bytes_scanned) = scan_line_index(&mut reader, self.index_interval, || Ok(()))?;
+        info!(
+            "set_tail_window - base_byte={}, total_lines={}, bytes_scanned={}, scan took {:?}",
+            base,
+            total,
+            bytes_scanned,
+            scan_start.elapsed()
+        );
+        self.base_byte = base;
+        self.total_lines = total;
+        self.index = index.into_iter().map(|o| o + base).collect();
+        self.indexed = true;
+        self.cached_window = Arc::new(StdMutex::new(None));
+        Ok(base)
+    }
 
-            // 尝试复用缓存的 mmap 窗口以减少系统调用和重新映射
+    /// 文件末尾追加了内容后（跟随模式，见 `set_follow`）补全索引与行数，不重新扫描整个文件。
+    ///
+    /// 从倒数第二个索引点起重新扫描到文件末尾：最后一个索引点可能位于原来没有结尾换行的最后一行末尾，
+    /// 追加后它落在行中间，不能作为起点。文件变小（被截断或替换）时从 `base_byte` 起完整重建。
+    pub fn extend_index(&mut self) -> Result<()> {
+        // 没有索引时行数本来就是估算值，不为追加的部分补扫
+        if !self.indexed {
+            return Ok(());
+        }
+        let len = self.file_handle.as_ref().metadata()?.len();
+        let keep = self.index.len().saturating_sub(1);
+        let (start_byte, start_line) = if keep == 0 { (self.base_byte, 0) } else { (self.index[keep - 1], keep * self.index_interval) };
+        let (start_byte, start_line, keep) = if start_byte > len { (self.base_byte, 0, 0) } else { (start_byte, start_line, keep) };
+        let mut reader = self.file_handle.as_ref().try_clone()?;
+        reader.seek(std::io::SeekFrom::Start(start_byte))?;
+        let (total, index, _) = scan_line_index(&mut reader, self.index_interval, || Ok(()))?;
+        self.index.truncate(keep);
+        self.index.extend(index.into_iter().map(|o| o + start_byte));
+        self.total_lines = start_line + total;
+        self.cached_window = Arc::new(StdMutex::new(None));
+        Ok(())
+    }
+
+    /// 返回不晚于 `line` 的最近索引点 `(byte_offset, line_number)`；`line` 超出索引范围时使用最后一个索引点。
+    /// 第 0 行位于 `base_byte`
+    fn index_base(&self, line: usize) -> (u64, usize) {
+        let pos_idx = (line / self.index_interval).min(self.index.len());
+        if pos_idx == 0 {
+            (self.base_byte, 0usize)
+        } else {
+            (self.index[pos_idx - 1], pos_idx * self.index_interval)
+        }
+    }
+
+    /// 打开一个新的 `BufReader` 并定位到第 `line` 行（0 基准）的起始处：先跳到最近的索引点，再顺序跳过剩余行。
+    /// 若文件不足 `line` 行，读取器停在文件末尾。
+    fn reader_at_line(&self, line: usize) -> std::io::Result<BufReader<std::fs::File>> {
+        let (base_offset, base_line) = self.index_base(line);
+        let mut reader = BufReader::new(self.file_handle.as_ref().try_clone()?);
+        reader.seek(std::io::SeekFrom::Start(base_offset))?;
+        let mut cur = base_line;
+        let mut tmp: Vec<u8> = Vec::new();
+        while cur < line {
+            tmp.clear();
+            if reader.read_until(b'\n', &mut tmp)? == 0 {
+                break;
+            }
+            cur += 1;
+        }
+        Ok(reader)
+    }
+
+    /// 返回第 `line` 行（0 基准）未经解码的原始字节，不含结尾的 `\n`（`\r` 保留）。
+    /// 最多返回 `MAX_LINE_BYTES` 字节，更长的行只返回开头部分；行号超出文件末尾时返回 `UnexpectedEof`。
+    pub fn read_line_bytes(&self, line: usize) -> std::io::Result<Vec<u8>> {
+        let mut reader = self.reader_at_line(line)?;
+        let mut buf: Vec<u8> = Vec::new();
+        let read = (&mut reader).take(MAX_LINE_BYTES as u64 + 1).read_until(b'\n', &mut buf)?;
+        if read == 0 {
+            return Err(std::io::Error::new(std::io::ErrorKind::UnexpectedEof, format!("line {} is past the end of the file", line + 1)));
+        }
+        if buf.last() == Some(&b'\n') {
+            buf.pop();
+        }
+        buf.truncate(MAX_LINE_BYTES);
+        Ok(buf)
+    }
+
+    /// 返回最长的 `n` 行 `(行号, 字节数, 字符数)`，按字节数从大到小排列，长度相同时行号小的在前。
+    /// 长度不含行尾的 `\n` / `\r\n`。
+    ///
+    /// 经 `stream_file` 顺序扫描一遍，只维护一个大小为 `n` 的最小堆，内存占用与文件大小无关；
+    /// 只为可能进入前 `n` 的行统计字符数（UTF-8 统计非续字节，其它编码解码后计数）。
+    /// 长度超过 `MAX_LINE_BYTES` 的行在 `read_lines` 中会被截断，这里给出的是完整长度。
+    pub fn longest_lines(&self, n: usize) -> std::io::Result<Vec<(usize, u64, u64)>> {
+        use std::cmp::Reverse;
+        use std::collections::BinaryHeap;
+
+        // 堆顶是当前前 n 行中最短（长度相同时行号最大）的一行
+        type Heap = BinaryHeap<Reverse<(u64, Reverse<usize>, u64)>>;
+        fn consider(heap: &mut Heap, n: usize, line: usize, bytes: u64, chars: impl FnOnce() -> u64) {
+            if heap.len() < n {
+                heap.push(Reverse((bytes, Reverse(line), chars())));
+            } else if heap.peek().is_some_and(|Reverse(min)| min.0 < bytes) {
+                heap.pop();
+                heap.push(Reverse((bytes, Reverse(line), chars())));
+            }
+        }
+        let encoding = self.encoding;
+        let count_chars = |bytes: &[u8]| -> u64 {
+            if encoding == UTF_8 {
+                bytes.iter().filter(|&&b| b & 0xC0 != 0x80).count() as u64
+            } else {
+                decode_bytes(encoding, bytes).chars().count() as u64
+            }
+        };
+
+        if n == 0 {
+            return Ok(Vec::new());
+        }
+        let mut heap = Heap::new();
+        let mut line = 0usize;
+        // 比一个窗口还长、被拆到多块中的行：前面几块中已读到的 (字节数, 字符数)
+        let mut carry = (0u64, 0u64);
+        stream_file(self.file_handle.as_ref(), self.base_byte, STREAM_CHUNK_BYTES, true, |_, hay, is_last| {
+            let mut pos = 0usize;
+            for nl in memchr::memchr_iter(b'\n', hay) {
+                let body = &hay[pos..nl];
+                let body = body.strip_suffix(b"\r").unwrap_or(body);
+                let (carried_bytes, carried_chars) = std::mem::take(&mut carry);
+                consider(&mut heap, n, line, carried_bytes + body.len() as u64, || carried_chars + count_chars(body));
+                line += 1;
+                pos = nl + 1;
+            }
+            if pos < hay.len() {
+                let rest = &hay[pos..];
+                if is_last {
+                    let (carried_bytes, carried_chars) = std::mem::take(&mut carry);
+                    consider(&mut heap, n, line, carried_bytes + rest.len() as u64, || carried_chars + count_chars(rest));
+                } else {
+                    carry.0 += rest.len() as u64;
+                    carry.1 += count_chars(rest);
+                }
+            }
+            Ok(true)
+        })?;
+        Ok(heap.into_sorted_vec().into_iter().map(|Reverse((bytes, Reverse(line), chars))| (line, bytes, chars)).collect())
+    }
+
+    /// 统计每种不同的行（去掉行尾的 `\n` / `\r\n` 后按字节比较）出现的次数，返回出现最多的 `max_distinct` 种
+    /// `(次数, 第一次出现的行号)`（次数相同时先出现的在前），以及 `(总行数, 跟踪的不同行数, 未跟踪的行数)`。
+    ///
+    /// 行按 64 位 xxh3 哈希归类，每种只记下次数和第一次出现的行号（作为样本，由调用方按行号读取文本），
+    /// 哈希碰撞的不同行会被合并，对实际文件可以忽略。内存随不同行的种数增长，每种约几十字节：
+    /// 最多跟踪 `MAX_UNIQUE_TRACKED` 种，之后第一次出现的行不再计入（计入“未跟踪的行数”），已跟踪的行仍照常计数，
+    /// 因此基数极大的文件中排名只反映先出现的那些行。
+    /// 每处理完一块调用一次 `progress(已处理到的绝对字节偏移)`，它返回错误时停止并返回该错误。
+    pub fn unique_lines(
+        &self,
+        max_distinct: usize,
+        mut progress: impl FnMut(u64) -> std::io::Result<()>,
+    ) -> std::io::Result<(Vec<(u64, usize)>, (usize, usize, u64))> {
+        use xxhash_rust::xxh3::{xxh3_64, Xxh3};
+
+        let mut counts: HashMap<u64, (u64, usize)> = HashMap::new();
+        let mut untracked = 0u64;
+        let mut line = 0usize;
+        let mut count_line = |hash: u64, line: usize| {
+            if let Some(entry) = counts.get_mut(&hash) {
+                entry.0 += 1;
+            } else if counts.len() < MAX_UNIQUE_TRACKED {
+                counts.insert(hash, (1, line));
+            } else {
+                untracked += 1;
+            }
+        };
+        // 比一个窗口还长、被拆到多块中的行：前面几块的内容已经送入这个哈希
+        let mut carry: Option<Xxh3> = None;
+        stream_file(self.file_handle.as_ref(), self.base_byte, STREAM_CHUNK_BYTES, true, |offset, hay, is_last| {
+            let mut pos = 0usize;
+            let hash_of = |carry: &mut Option<Xxh3>, body: &[u8]| match carry.take() {
+                Some(mut h) => {
+                    h.update(body);
+                    h.digest()
+                }
+                None => xxh3_64(body),
+            };
+            for nl in memchr::memchr_iter(b'\n', hay) {
+                let body = &hay[pos..nl];
+                let body = body.strip_suffix(b"\r").unwrap_or(body);
+                count_line(hash_of(&mut carry, body), line);
+                line += 1;
+                pos = nl + 1;
+            }
+            if pos < hay.len() {
+                let rest = &hay[pos..];
+                if is_last {
+                    count_line(hash_of(&mut carry, rest), line);
+                    line += 1;
+                } else {
+                    carry.get_or_insert_with(Xxh3::new).update(rest);
+                }
+            }
+            progress(offset + hay.len() as u64)?;
+            Ok(true)
+        })?;
+        let distinct = counts.len();
+        let mut top: Vec<(u64, usize)> = counts.into_values().collect();
+        top.sort_unstable_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+        top.truncate(max_distinct);
+        Ok((top, (line, distinct, untracked)))
+    }
+
+    /// 二分查找第一个行首不小于 `target` 的行（按字节比较行首 `target.len()` 个字节），所有行都更小时返回 None。
+    ///
+    /// 用于按行首的时间戳跳转（如 “跳到 14:32:00”），前提是可比较的行首按时间单调递增；不满足时结果没有意义。
+    /// 行首第一个字节与 `target` 的第一个字节不是同一类（数字、字母、其它）的行视为不可比较（如堆栈的续行），
+    /// 探测时跳过，最多向后看 `PREFIX_SEARCH_SKIP_LINES` 行。每次探测经索引定位，共约 log2(总行数) 次。
+    pub fn find_line_by_prefix(&self, target: &[u8]) -> std::io::Result<Option<usize>> {
+        if target.is_empty() {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "prefix must not be empty"));
+        }
+        let class = |b: u8| if b.is_ascii_digit() { 0 } else if b.is_ascii_alphabetic() { 1 } else { 2 };
+        let target_class = class(target[0]);
+        let (mut lo, mut hi) = (0usize, self.total_lines);
+        let mut buf: Vec<u8> = Vec::new();
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            // 从 `mid` 起找第一个可比较的行
+            let mut reader = self.reader_at_line(mid)?;
+            let limit = hi.min(mid + PREFIX_SEARCH_SKIP_LINES);
+            let mut probe = None;
+            for line in mid..limit {
+                buf.clear();
+                if reader.read_until(b'\n', &mut buf)? == 0 {
+                    break;
+                }
+                if buf.first().is_some_and(|&b| class(b) == target_class) {
+                    probe = Some(line);
+                    break;
+                }
+            }
+            match probe {
+                Some(line) if buf[..buf.len().min(target.len())] < *target => lo = line + 1,
+                // 不小于目标，或这一段都不可比较：答案不在 `mid` 之后更远处
+                _ => hi = mid,
+            }
+        }
+        Ok((lo < self.total_lines).then_some(lo))
+    }
+
+    /// 读取从 `start` 行起的 `count` 行，原样保留每行的换行符（`\n` 或 `\r\n`），最后一行没有换行时也不补上，
+    /// 拼接后按当前编码解码。与 `read_lines` 不同，不经过截断策略：任一行超过 `MAX_LINE_BYTES` 时返回
+    /// `InvalidData` 错误，而不是返回截断后的内容。
+    pub fn read_lines_exact(&self, start: usize, count: usize) -> std::io::Result<String> {
+        let mut reader = self.reader_at_line(start)?;
+        let mut buf: Vec<u8> = Vec::new();
+        for i in 0..count {
+            let before = buf.len();
+            let read = (&mut reader).take(MAX_LINE_BYTES as u64 + 2).read_until(b'\n', &mut buf)?;
+            if read == 0 {
+                break;
+            }
+            let line = &buf[before..];
+            let body = line.strip_suffix(b"\n").unwrap_or(line);
+            if body.strip_suffix(b"\r").unwrap_or(body).len() > MAX_LINE_BYTES {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("line {} exceeds {} bytes", start + i + 1, MAX_LINE_BYTES),
+                ));
+            }
+        }
+        Ok(decode_bytes(self.encoding, &buf).into_owned())
+    }
+
+    /// 把包含 `needle` 的行（`invert` 为 true 时为不包含的行）写入 `dest`，返回写出的行数。
+    ///
+    /// 经 `stream_file` 按行对齐的块读取，在每块上用 `for_each_matching_line` 查找匹配行：匹配所在行整行写出，
+    /// 反选时写出两个匹配行之间的所有行。输出经 `BufWriter` 直接流式写入文件，内存占用与文件大小无关。
+    /// `ignore_case` 为 true 时忽略 ASCII 大小写，与筛选视图（`read_filtered_lines`）的匹配结果一致。
+    ///
+    /// 换行符与文件末尾换行的处理见 [`ExportOptions`]：默认原样保留源文件的风格，
+    /// 即源文件最后一行没有换行时输出的最后一行也没有。
+    ///
+    /// 每处理完一块调用一次 `progress(已处理到的绝对字节偏移)`，它返回错误时（如被取消）停止并返回该错误，
+    /// 已写出的部分留给调用方处理。
+    pub fn filter_lines_to(
+        &self,
+        needle: &[u8],
+        ignore_case: bool,
+        invert: bool,
+        dest: &Path,
+        options: &ExportOptions,
+        mut progress: impl FnMut(u64) -> std::io::Result<()>,
+    ) -> std::io::Result<usize> {
+        use std::io::Write;
+
+        if needle.is_empty() {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "needle must not be empty"));
+        }
+        // 目标不能是正在读取的文件本身，否则 `File::create` 会把它截断
+        if std::fs::canonicalize(dest).ok() == std::fs::canonicalize(&self.path).ok() && dest.exists() {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "destination is the file being filtered"));
+        }
+        let mut out = std::io::BufWriter::new(std::fs::File::create(dest)?);
+
+        // 统一换行符时使用的换行；`None` 表示原样写出
+        let eol: Option<&[u8]> = match options.line_ending {
+            LineEnding::Preserve => None,
+            LineEnding::Lf => Some(b"\n"),
+            LineEnding::Crlf => Some(b"\r\n"),
+        };
+        // 保留原样时补结尾换行所用的换行符，按源文件第一个换行判断
+        let mut source_eol: &[u8] = b"\n";
+
+        let mut written = 0usize;
+        // 写出由若干完整行组成的 `lines` 并统计行数。只有文件的最后一行可能缺少结尾换行（`at_eof`），
+        // 是否补上由 `ensure_trailing_newline` 决定
+        let mut emit = |out: &mut std::io::BufWriter<std::fs::File>, lines: &[u8], at_eof: bool, source_eol: &[u8]| -> std::io::Result<()> {
+            if lines.is_empty() {
+                return Ok(());
+            }
+            match eol {
+                None => out.write_all(lines)?,
+                Some(eol) => {
+                    for line in lines.split_inclusive(|&b| b == b'\n') {
+                        match line.strip_suffix(b"\n") {
+                            Some(body) => {
+                                out.write_all(body.strip_suffix(b"\r").unwrap_or(body))?;
+                                out.write_all(eol)?;
+                            }
+                            None => out.write_all(line)?,
+                        }
+                    }
+                }
+            }
+            written += memchr::memchr_iter(b'\n', lines).count();
+            if at_eof && lines.last() != Some(&b'\n') {
+                written += 1;
+                if options.ensure_trailing_newline {
+                    out.write_all(eol.unwrap_or(source_eol))?;
+                }
+            }
+            Ok(())
+        };
+        // 按行对齐的块处理，匹配不会跨块（needle 不含换行时）
+        stream_file(self.file_handle.as_ref(), self.base_byte, STREAM_CHUNK_BYTES, true, |offset, hay, is_last| {
+            if offset == self.base_byte {
+                if let Some(i) = memchr::memchr(b'\n', hay) {
+                    if i > 0 && hay[i - 1] == b'\r' {
+                        source_eol = b"\r\n";
+                    }
+                }
+            }
+            // `pos` 为本块中下一个尚未处理的行的起始偏移
+            let mut pos = 0usize;
+            let mut result = Ok(());
+            for_each_matching_line(hay, needle, ignore_case, |_, line_start, line_end| {
+                result = if invert {
+                    emit(&mut out, &hay[pos..line_start], false, source_eol)
+                } else {
+                    emit(&mut out, &hay[line_start..line_end], is_last && line_end == hay.len(), source_eol)
+                };
+                pos = line_end;
+                result.is_ok()
+            });
+            result?;
+            if invert {
+                emit(&mut out, &hay[pos..], is_last, source_eol)?;
+            }
+            progress(offset + hay.len() as u64)?;
+            Ok(true)
+        })?;
+        out.flush()?;
+        Ok(written)
+    }
+
+    /// 筛选视图的分页读取：返回第 `start_match` 个匹配行（从 0 计，每行无论匹配几次只算一个）起的
+    /// 最多 `count` 个匹配行 `(行号, 文本)`，找够即停止扫描。`with_total` 为 true 时继续扫描到文件末尾，
+    /// 额外返回匹配行总数（只建议在进入筛选模式时请求一次）。
+    pub fn read_filtered_lines(
+        &self,
+        needle: &[u8],
+        ignore_case: bool,
+        start_match: usize,
+        count: usize,
+        with_total: bool,
+    ) -> std::io::Result<(Vec<(usize, String)>, Option<usize>)> {
+        if needle.is_empty() {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "needle must not be empty"));
+        }
+        let f = self.file_handle.as_ref().try_clone()?;
+        if f.metadata()?.len() == 0 {
+            return Ok((Vec::new(), with_total.then_some(0)));
+        }
+        let mmap = unsafe { Mmap::map(&f)? };
+        let hay = &mmap[clamp_len(self.base_byte, "read_filtered_lines")..];
+
+        let mut lines = Vec::new();
+        let mut seen = 0usize;
+        for_each_matching_line(hay, needle, ignore_case, |line_no, line_start, line_end| {
+            if seen >= start_match && lines.len() < count {
+                let mut text = &hay[line_start..line_end];
+                text = text.strip_suffix(b"\n").unwrap_or(text);
+                text = text.strip_suffix(b"\r").unwrap_or(text);
+                let text = &text[..text.len().min(MAX_LINE_BYTES)];
+                lines.push((line_no, String::from_utf8_lossy(text).into_owned()));
+            }
+            seen += 1;
+            with_total || lines.len() < count
+        });
+        Ok((lines, with_total.then_some(seen)))
+    }
+
+    /// 从 `from_line` 开始逐行比较本文件与 `other`，返回第一处不同的行 `(line, in_self, in_other)`。
+    ///
+    /// 流式读取，不做完整 diff，适用于按行对齐的日志。行尾的 `\n` / `\r\n` 不参与比较；
+    /// 一侧已到达末尾而另一侧仍有内容时，该行也视为差异（对应的 `in_*` 为 false）。两侧同时结束返回 `None`。
+    pub fn next_diff_line(&self, other: &LargeFilePreview, from_line: usize) -> std::io::Result<Option<(usize, bool, bool)>> {
+        fn trim_eol(l: &[u8]) -> &[u8] {
+            let l = l.strip_suffix(b"\n").unwrap_or(l);
+            l.strip_suffix(b"\r").unwrap_or(l)
+        }
+        let mut ra = self.reader_at_line(from_line)?;
+        let mut rb = other.reader_at_line(from_line)?;
+        let mut la: Vec<u8> = Vec::new();
+        let mut lb: Vec<u8> = Vec::new();
+        let mut line = from_line;
+        loop {
+            la.clear();
+            lb.clear();
+            let has_a = ra.read_until(b'\n', &mut la)? > 0;
+            let has_b = rb.read_until(b'\n', &mut lb)? > 0;
+            match (has_a, has_b) {
+                (false, false) => return Ok(None),
+                (true, true) if trim_eol(&la) == trim_eol(&lb) => line += 1,
+                (a, b) => return Ok(Some((line, a, b))),
+            }
+        }
+    }
+
+    /// 借助稀疏索引把行号解析为 `hay`（整个文件内容）中的字节偏移；超出末尾时返回 `hay.len()`
+    fn line_offset_in(&self, hay: &[u8], line: usize) -> usize {
+        let (base_offset, base_line) = self.index_base(line);
+        let mut off = (base_offset as usize).min(hay.len());
+        let mut cur = base_line;
+        while cur < line {
+            match memchr::memchr(b'\n', &hay[off..]) {
+                Some(p) => {
+                    off += p + 1;
+                    cur += 1;
+                }
+                None => return hay.len(),
+            }
+        }
+        off
+    }
+
+    /// 查找 `from_line` 之后（`forward` 为 false 时为之前）最近的空行，到达文件边界仍未找到时返回 None。
+    ///
+    /// 向后查找从 `from_line` 所在位置顺序读取；向前查找按索引分段，从 `from_line` 所在的段开始
+    /// 逐段往文件开头扫描，每次只读一个索引间隔的行。空行的定义见 `scan_blank_lines`
+    pub fn find_blank_line(&self, from_line: usize, forward: bool, whitespace_is_blank: bool) -> std::io::Result<Option<usize>> {
+        let mut found = None;
+        if forward {
+            let start = from_line.saturating_add(1);
+            let mut reader = self.reader_at_line(start)?;
+            scan_blank_lines(&mut reader, start, usize::MAX, whitespace_is_blank, |l| {
+                found = Some(l);
+                false
+            })?;
+            return Ok(found);
+        }
+        // 只查找 `[.., end)` 范围内的行
+        let mut end = from_line.min(self.total_lines);
+        while end > 0 {
+            let (offset, base) = self.index_base(end - 1);
+            let mut reader = BufReader::new(self.file_handle.as_ref().try_clone()?);
+            reader.seek(std::io::SeekFrom::Start(offset))?;
+            scan_blank_lines(&mut reader, base, end - base, whitespace_is_blank, |l| {
+                found = Some(l);
+                true
+            })?;
+            if found.is_some() {
+                break;
+            }
+            end = base;
+        }
+        Ok(found)
+    }
+
+    /// 第 `line` 行所在代码块的 `(首行, 末行)`（都包含在内），找不到时返回 None。
+    ///
+    /// `braces` 非空时按括号配对：从该行末尾往前找第一个未配对的左括号，再往后找与之配对的右括号；
+    /// 该行以右括号开头时（如 `} else {`）算作它所结束的块。不识别字符串与注释中的括号。
+    /// `braces` 为空时按缩进（适合 Python、YAML 等）：其后的行缩进更大时该行就是块的首行，
+    /// 否则块从之前第一个缩进更小的非空行开始，到其后最后一个缩进比首行大的非空行结束；
+    /// 缩进按行首空白的字节数计，不展开制表符。
+    /// 两个方向最多各扫描 `MAX_BLOCK_SCAN_BYTES` 字节，括号不配对的文件不会一直扫到文件边界
+    pub fn enclosing_block(&self, line: usize, braces: &[(u8, u8)]) -> std::io::Result<Option<(usize, usize)>> {
+        let f = self.file_handle.as_ref().try_clone()?;
+        if line >= self.total_lines || f.metadata()?.len() == 0 {
+            return Ok(None);
+        }
+        let mmap = unsafe { Mmap::map(&f)? };
+        let hay = &mmap[..];
+        let base_byte = clamp_len(self.base_byte, "enclosing_block");
+        let line_start = self.line_offset_in(hay, line);
+        let line_end = memchr::memchr(b'\n', &hay[line_start..]).map(|p| line_start + p).unwrap_or(hay.len());
+        // 扫描范围对齐到完整的行，避免把被截断的行当作一行
+        let mut low = line_start.saturating_sub(MAX_BLOCK_SCAN_BYTES).max(base_byte);
+        if low > base_byte {
+            low = memchr::memchr(b'\n', &hay[low..line_start]).map(|p| low + p + 1).unwrap_or(line_start);
+        }
+        let mut high = line_end.saturating_add(MAX_BLOCK_SCAN_BYTES).min(hay.len());
+        if high < hay.len() {
+            high = memchr::memrchr(b'\n', &hay[line_end..high]).map(|p| line_end + p).unwrap_or(line_end);
+        }
+
+        let found = if braces.is_empty() {
+            indent_block(hay, low, line_start, high)
+        } else {
+            let text = &hay[line_start..line_end];
+            let lead = text.iter().position(|&b| b != b' ' && b != b'\t');
+            let from = match lead {
+                Some(i) if braces.iter().any(|p| p.1 == text[i]) => line_start + i,
+                _ => line_end,
+            };
+            brace_block(hay, low, from, high, braces)
+        };
+        let Some((open_at, close_at)) = found else { return Ok(None) };
+        // 偏移换算成行号：从目标行起往两侧数换行
+        let start = if open_at >= line_start { line } else { line - memchr::memchr_iter(b'\n', &hay[open_at..line_start]).count() };
+        let end = line + memchr::memchr_iter(b'\n', &hay[line_start..close_at]).count();
+        Ok(Some((start, end)))
+    }
+
+    /// 重新扫描整个文件（尾部模式下为 `base_byte` 之后的部分），检查每个索引点 `index[k]` 是否正好位于第 `(k + 1) * index_interval` 行的起始处。
+    ///
+    /// 返回 `(实际总行数, 不一致的索引点)`，后者为 `(k, 行号, 保存的偏移, 实际偏移)`，最多 `MAX_INDEX_MISMATCHES` 个；
+    /// 保存的偏移为 None 表示索引缺少该点，实际偏移为 None 表示文件中没有这一行（索引点多了）。
+    /// 只用于诊断，需要读完整个文件。
+    pub fn verify_index(&self) -> std::io::Result<(usize, Vec<(usize, usize, Option<u64>, Option<u64>)>)> {
+        let interval = self.index_interval;
+        let mut mismatches = Vec::new();
+        let mut check = |k: usize, actual: Option<u64>| {
+            let stored = self.index.get(k).copied();
+            if stored != actual && mismatches.len() < MAX_INDEX_MISMATCHES {
+                mismatches.push((k, (k + 1) * interval, stored, actual));
+            }
+        };
+        let mut lines = 0usize;
+        let mut ends_with_newline = true;
+        let len = stream_file(self.file_handle.as_ref(), self.base_byte, STREAM_CHUNK_BYTES, false, |offset, chunk, _| {
+            for i in memchr::memchr_iter(b'\n', chunk) {
+                lines += 1;
+                if lines % interval == 0 {
+                    check(lines / interval - 1, Some(offset + i as u64 + 1));
+                }
+            }
+            ends_with_newline = chunk.last() == Some(&b'\n');
+            Ok(true)
+        })?;
+        // 没有以换行结尾的最后一行也算一行，其“结束位置”为文件末尾（与 `scan_line_index` 一致）
+        if len > self.base_byte && !ends_with_newline {
+            lines += 1;
+            if lines % interval == 0 {
+                check(lines / interval - 1, Some(len));
+            }
+        }
+        for k in lines / interval..self.index.len() {
+            check(k, None);
+        }
+        Ok((lines, mismatches))
+    }
+
+    /// 把内存中的行索引写成独立的索引文件（格式见 `INDEX_FILE_MAGIC`），返回写入的字节数。
+    /// 只写出已有的索引，不重新扫描文件
+    pub fn export_index(&self, dest: &Path) -> std::io::Result<u64> {
+        use std::io::Write;
+        // 索引文件约定第 0 行从偏移 0 开始，尾部模式下的窗口索引不符合这一格式
+        if self.base_byte > 0 {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "cannot export the index of a tail window"));
+        }
+        let file_size = self.file_handle.as_ref().metadata()?.len();
+        let mut out = std::io::BufWriter::new(std::fs::File::create(dest)?);
+        out.write_all(INDEX_FILE_MAGIC)?;
+        for v in [self.index_interval as u64, self.total_lines as u64, file_size, self.index.len() as u64] {
+            out.write_all(&v.to_le_bytes())?;
+        }
+        for &off in &self.index {
+            out.write_all(&off.to_le_bytes())?;
+        }
+        out.flush()?;
+        Ok((INDEX_FILE_MAGIC.len() + 8 * (4 + self.index.len())) as u64)
+    }
+
+    /// 返回 `lines` 中每一行的起始字节偏移（超出末尾的行返回文件长度）。
+    /// 连续的行从上一行接着扫描，不必每行都从索引点重新开始
+    pub fn line_offsets(&self, lines: &[usize]) -> std::io::Result<Vec<u64>> {
+        let f = self.file_handle.as_ref().try_clone()?;
+        let len = f.metadata()?.len();
+        if len == 0 {
+            return Ok(vec![0; lines.len()]);
+        }
+        let mmap = unsafe { Mmap::map(&f)? };
+        let hay = &mmap[..];
+        let mut prev: Option<(usize, usize)> = None;
+        let mut out = Vec::with_capacity(lines.len());
+        for &line in lines {
+            let off = match prev {
+                Some((l, o)) if line == l + 1 => match memchr::memchr(b'\n', &hay[o..]) {
+                    Some(p) => o + p + 1,
+                    None => hay.len(),
+                },
+                _ => self.line_offset_in(hay, line),
+            };
+            prev = Some((line, off));
+            out.push(off as u64);
+        }
+        Ok(out)
+    }
+
+    /// 文件中（尾部模式下为窗口内）是否包含 `needle`，找到第一处即返回 true，否则扫描到文件末尾返回 false。
+    ///
+    /// 经 `stream_file` 按行对齐的块读取，内存占用与文件大小无关；不统计行号，也不跟踪行边界
+    /// （忽略大小写时除外，见 `for_each_matching_line`），因此比 `find_first` 与 `mmap_search` 都快。
+    /// needle 中含换行时可能漏掉跨块的匹配。
+    pub fn contains(&self, needle: &[u8], ignore_case: bool) -> std::io::Result<bool> {
+        if needle.is_empty() {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "needle must not be empty"));
+        }
+        let finder = memchr::memmem::Finder::new(needle);
+        let mut found = false;
+        stream_file(self.file_handle.as_ref(), self.base_byte, STREAM_CHUNK_BYTES, true, |_, hay, _| {
+            found = if ignore_case {
+                let mut hit = false;
+                for_each_matching_line(hay, needle, true, |_, _, _| {
+                    hit = true;
+                    false
+                });
+                hit
+            } else {
+                finder.find(hay).is_some()
+            };
+            Ok(!found)
+        })?;
+        Ok(found)
+    }
+
+    /// 按 UTF-8 校验 `base_byte` 起的内容，返回前 `max_results` 处非法字节序列的 `(行号, 列, 绝对字节偏移, 首字节)`。
+    ///
+    /// 列按有损解码后的字符计（之前的每段非法序列算一个替换字符），与显示的文本及搜索报告的列一致。
+    /// 经 `stream_file` 按行对齐的块读取，内存占用与文件大小无关；一行比一块还长时块边界上被拆开的字符序列不报告，
+    /// 该行中之后位置的列从块起点算起。每处理完一块调用一次 `progress(已处理到的绝对字节偏移)`，它返回错误时停止并返回该错误。
+    pub fn scan_invalid_utf8(&self, max_results: usize, mut progress: impl FnMut(u64) -> std::io::Result<()>) -> std::io::Result<Vec<(usize, usize, u64, u8)>> {
+        let mut found = Vec::new();
+        let mut line_no = 0usize;
+        stream_file(self.file_handle.as_ref(), self.base_byte, STREAM_CHUNK_BYTES, true, |offset, chunk, is_last| {
+            let mut pos = 0usize;
+            let mut counted = 0usize;
+            while found.len() < max_results {
+                let Err(e) = std::str::from_utf8(&chunk[pos..]) else { break };
+                let bad = pos + e.valid_up_to();
+                // 块末尾不完整的序列：文件最后一块中是真正的截断，其它块中是块边界拆开了长行
+                let len = match e.error_len() {
+                    Some(n) => n,
+                    None if is_last => chunk.len() - bad,
+                    None => break,
+                };
+                line_no += memchr::memchr_iter(b'\n', &chunk[counted..bad]).count();
+                counted = bad;
+                let line_start = memchr::memrchr(b'\n', &chunk[..bad]).map(|p| p + 1).unwrap_or(0);
+                let column = String::from_utf8_lossy(&chunk[line_start..bad]).chars().count();
+                found.push((line_no, column, offset + bad as u64, chunk[bad]));
+                pos = bad + len;
+            }
+            line_no += memchr::memchr_iter(b'\n', &chunk[counted..]).count();
+            progress(offset + chunk.len() as u64)?;
+            Ok(found.len() < max_results)
+        })?;
+        Ok(found)
+    }
+
+    /// 按 `encoding` 解码 `base_byte` 起的全部内容并统计字符（Unicode 标量值）数。
+    ///
+    /// UTF-8 不解码，直接统计非续字节（`10xxxxxx` 以外的字节），无效字节各算一个字符，
+    /// 与有损解码时每段无效序列替换为一个 U+FFFD 的结果可能略有出入，因此只是近似值。
+    /// 其它编码经 `encoding_rs` 流式解码后计数。与 `read_lines` 一样不处理 BOM。
+    /// 每处理完一块调用一次 `progress(已处理到的绝对字节偏移)`，它返回错误时停止并返回该错误。
+    pub fn count_chars(&self, mut progress: impl FnMut(u64) -> std::io::Result<()>) -> std::io::Result<u64> {
+        let mut count = 0u64;
+        let mut decoder = (self.encoding != UTF_8).then(|| self.encoding.new_decoder_without_bom_handling());
+        let mut buf = String::new();
+        stream_file(self.file_handle.as_ref(), self.base_byte, STREAM_CHUNK_BYTES, false, |offset, chunk, is_last| {
+            match decoder.as_mut() {
+                None => count += chunk.iter().filter(|&&b| b & 0xC0 != 0x80).count() as u64,
+                Some(decoder) => {
+                    // 分段解码，`buf` 只保留一段的输出，内存占用与块大小无关
+                    for (i, piece) in chunk.chunks(1024 * 1024).enumerate() {
+                        let last = is_last && (i + 1) * 1024 * 1024 >= chunk.len();
+                        buf.clear();
+                        buf.reserve(decoder.max_utf8_buffer_length(piece.len()).unwrap_or(piece.len() * 3));
+                        let _ = decoder.decode_to_string(piece, &mut buf, last);
+                        count += buf.chars().count() as u64;
+                    }
+                }
+            }
+            progress(offset + chunk.len() as u64)?;
+            Ok(true)
+        })?;
+        Ok(count)
+    }
+
+    /// 从 `from_line` 起查找第一个匹配，返回 `(line, column_chars, length_chars, byte_offset)`。
+    ///
+    /// 借助稀疏索引直接定位到 `from_line` 附近开始扫描，找到第一处即停止，因此比完整的
+    /// `mmap_search` 快得多，适合 “跳到下一个 ERROR” 这类导航。
+    pub fn find_first(&self, needle: &[u8], ignore_case: bool, from_line: usize) -> std::io::Result<Option<(usize, usize, usize, u64)>> {
+        if needle.is_empty() {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "needle must not be empty"));
+        }
+        let f = self.file_handle.as_ref().try_clone()?;
+        if f.metadata()?.len() == 0 {
+            return Ok(None);
+        }
+        let mmap = unsafe { Mmap::map(&f)? };
+        let hay = &mmap[..];
+        let start = self.line_offset_in(hay, from_line);
+
+        let mut hit = None;
+        for_each_matching_line(&hay[start..], needle, ignore_case, |line_no, line_start, line_end| {
+            hit = Some((from_line + line_no, start + line_start, start + line_end));
+            false
+        });
+        let Some((line, line_start, line_end)) = hit else { return Ok(None) };
+        let (col_chars, len_chars, abs) = match_in_line(hay, line_start, line_end, needle, ignore_case, false);
+        Ok(Some((line, col_chars, len_chars, abs as u64)))
+    }
+
+    /// 查找 `before_line` 之前（不含该行）的最后一个匹配，返回值同 `find_first`；行内有多处匹配时取最后一处，
+    /// 之前没有匹配时返回 None。
+    ///
+    /// 借助稀疏索引从 `before_line` 所在的索引段开始，一段（`index_interval` 行）一段往前扫描，
+    /// 在最近的一段中找到即停止，不需要收集全部匹配位置，与 `find_first` 配合实现双向的“上一处/下一处”导航
+    pub fn find_prev(&self, needle: &[u8], ignore_case: bool, before_line: usize) -> std::io::Result<Option<(usize, usize, usize, u64)>> {
+        if needle.is_empty() {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "needle must not be empty"));
+        }
+        let f = self.file_handle.as_ref().try_clone()?;
+        if f.metadata()?.len() == 0 || before_line == 0 {
+            return Ok(None);
+        }
+        let mmap = unsafe { Mmap::map(&f)? };
+        let hay = &mmap[..];
+        let mut end = self.line_offset_in(hay, before_line);
+        let mut last_line = before_line - 1;
+        loop {
+            let (seg_offset, seg_line) = self.index_base(last_line);
+            let seg_start = (seg_offset as usize).min(end);
+            let mut hit = None;
+            for_each_matching_line(&hay[seg_start..end], needle, ignore_case, |line_no, line_start, line_end| {
+                hit = Some((seg_line + line_no, seg_start + line_start, seg_start + line_end));
+                true
+            });
+            if let Some((line, line_start, line_end)) = hit {
+                let (col_chars, len_chars, abs) = match_in_line(hay, line_start, line_end, needle, ignore_case, true);
+                return Ok(Some((line, col_chars, len_chars, abs as u64)));
+            }
+            if seg_line == 0 {
+                return Ok(None);
+            }
+            end = seg_start;
+            last_line = seg_line - 1;
+        }
+    }
+
+    /// 返回字节偏移 `byte_offset` 所在行及其前 `before` 行、后 `after` 行，结果为
+    /// `(所在行, 返回的第一行的行号, 各行文本, 偏移在所在行内的字节位置)`。
+    ///
+    /// 先在稀疏索引中二分找到偏移之前最近的索引点，再从那里数换行确定行号。超出文件末尾的偏移按最后一个字节处理；
+    /// 每行文本与 `read_lines` 一样最多取 `MAX_LINE_BYTES`，偏移落在超长行被截掉的部分时仍解析到该行，
+    /// 调用方可用返回的行内位置判断是否超出了显示的文本。
+    pub fn context_at_offset(&self, byte_offset: u64, before: usize, after: usize) -> std::io::Result<(usize, usize, Vec<String>, usize)> {
+        let f = self.file_handle.as_ref().try_clone()?;
+        if f.metadata()?.len() == 0 {
+            return Ok((0, 0, Vec::new(), 0));
+        }
+        let mmap = unsafe { Mmap::map(&f)? };
+        let hay = &mmap[..];
+        let base_byte = clamp_len(self.base_byte, "context_at_offset");
+        // 文件在设置尾部窗口后被截断时窗口已不存在
+        if base_byte >= hay.len() {
+            return Ok((0, 0, Vec::new(), 0));
+        }
+        // 尾部模式下窗口之前的偏移按窗口的第一个字节处理
+        let off = clamp_len(byte_offset, "context_at_offset").max(base_byte).min(hay.len() - 1);
+
+        let k = self.index.partition_point(|&o| o as usize <= off);
+        let (base_offset, base_line) = if k == 0 { (base_byte, 0usize) } else { (self.index[k - 1] as usize, k * self.index_interval) };
+        let line = base_line + memchr::memchr_iter(b'\n', &hay[base_offset..off]).count();
+        let line_start = memchr::memrchr(b'\n', &hay[base_byte..off]).map(|p| base_byte + p + 1).unwrap_or(base_byte);
+
+        let first = line.saturating_sub(before);
+        let mut pos = self.line_offset_in(hay, first);
+        let wanted = line - first + 1 + after;
+        let mut text_lines = Vec::with_capacity(wanted);
+        while text_lines.len() < wanted && pos < hay.len() {
+            let end = memchr::memchr(b'\n', &hay[pos..]).map(|p| pos + p + 1).unwrap_or(hay.len());
+            let mut text = &hay[pos..end];
+            text = text.strip_suffix(b"\n").unwrap_or(text);
+            text = text.strip_suffix(b"\r").unwrap_or(text);
+            text_lines.push(decode_bytes(self.encoding, &text[..text.len().min(MAX_LINE_BYTES)]).into_owned());
+            pos = end;
+        }
+        Ok((line, first, text_lines, off - line_start))
+    }
+
+    /// 把搜索词转换为文件编码下的字节序列，使非 UTF-8 文件也能按字节搜索
+    pub fn encode_needle(&self, needle: &str) -> Vec<u8> {
+        if self.encoding == UTF_8 {
+            needle.as_bytes().to_vec()
+        } else {
+            self.encoding.encode(needle).0.into_owned()
+        }
+    }
+
+    /// 不扫描整个文件估算当前视图（尾部模式下为窗口）的总行数，返回 `(行数, 是否精确)`。
+    /// 已建立索引时直接返回 `total_lines`；否则抽样估算，精度见 `estimate_lines`
+    pub fn estimate_total_lines(&self) -> std::io::Result<(usize, bool)> {
+        if self.indexed {
+            return Ok((self.total_lines, true));
+        }
+        let len = self.file_handle.as_ref().metadata()?.len();
+        Ok((estimate_lines(self.file_handle.as_ref(), self.base_byte, len)?, false))
+    }
+
+    /// 返回已统计的总行数（open 时计算）
+    pub fn total_lines(&self) -> usize {
+        self.total_lines
+    }
+
+    /// 异步读取从 `start` 行开始的 `count` 行文本。
+    ///
+    /// 实现要点：优先尝试使用 mmap 窗口进行切片读取以提升性能；失败时回退到 `BufReader` 顺序读取。
+    /// - `start`: 起始行（0 基准）
+    /// - `count`: 要读取的行数
+    /// 返回读取到的多行字符串，每行以 `\n` 结尾（如果文件末尾不足则返回实际行数）。
+    /// 超过 `MAX_LINE_BYTES` 的单行按 `truncation_policy` 处理，见 [`TruncationPolicy`]。
+    pub async fn read_lines(&self, start: usize, count: usize) -> Result<String> {
+        let index_interval = self.index_interval;
+        let cache = self.cached_window.clone();
+        let file_handle = self.file_handle.clone();
+        let policy = self.truncation_policy;
+        let encoding = self.encoding;
+        let (base_offset, base_line) = self.index_base(start);
+        smol::unblock(move || -> Result<String> {
+            let file = file_handle.as_ref().try_clone()?;
+
+            // 计算 mmap 映射窗口（以页对齐）以尝试零拷贝读取
+            let page_size = 4096usize;
+            let estimated_line_len = 120usize;
+            let desired_lines = count + index_interval;
+            let desired_bytes = desired_lines.saturating_mul(estimated_line_len);
+            let aligned = (base_offset / page_size as u64) * page_size as u64;
+            let delta = (base_offset.saturating_sub(aligned)) as usize;
+            let mut map_len = delta.saturating_add(desired_bytes);
+            let cap = 8 * 1024 * 1024usize;
+            if map_len > cap {
+                map_len = cap;
+            }
+
+            // 尝试复用缓存的 mmap 窗口以减少系统调用和重新映射
             if map_len > 0 {
                 if let Ok(guard) = cache.lock() {
                     if let Some((cached_aligned, cached_len, mmap)) = &*guard {
@@ -245,34 +1896,31 @@ impl LargeFilePreview {
                         let cached_end = cached_start + (*cached_len as u64);
                         if base_offset >= cached_start && (base_offset + map_len as u64) <= cached_end {
                             let delta2 = (base_offset - cached_start) as usize;
-                            let slice = &mmap[delta2..];
-                            let text = String::from_utf8_lossy(slice);
-                            let mut iter = text.lines();
-                            let skip = start.saturating_sub(base_line);
-                            let mut ok = true;
-                            for _ in 0..skip {
-                                if iter.next().is_none() {
-                                    ok = false;
-                                    break;
+                            // 偏移计算出错时不要对 mmap 越界切片导致 panic，放弃缓存窗口走下面的路径
+                            if let Some(slice) = mmap.get(delta2..) {
+                                let text = decode_bytes(encoding, slice);
+                                let mut iter = text.lines();
+                                let skip = start.saturating_sub(base_line);
+                                let mut ok = true;
+                                for _ in 0..skip {
+                                    if iter.next().is_none() {
+                                        ok = false;
+                                        break;
+                                    }
                                 }
-                            }
-                            if ok {
-                                let mut out = String::new();
-                                for _ in 0..count {
-                                    if let Some(l) = iter.next() {
-                                        if l.as_bytes().len() > MAX_LINE_BYTES {
-                                            out.push_str(&String::from_utf8_lossy(&l.as_bytes()[..MAX_LINE_BYTES]));
-                                            out.push('\n');
-                                            break;
+                                if ok {
+                                    let mut out = String::new();
+                                    for i in 0..count {
+                                        if let Some(l) = iter.next() {
+                                            if !append_line(&mut out, l.as_bytes(), start + i, policy)? {
+                                                break;
+                                            }
                                         } else {
-                                            out.push_str(l);
-                                            out.push('\n');
+                                            break;
                                         }
-                                    } else {
-                                        break;
                                     }
+                                    return Ok(out);
                                 }
-                                return Ok(out);
                             }
                         }
                     }
@@ -291,7 +1939,7 @@ impl LargeFilePreview {
 
                 if aligned >= file_len {
                 } else {
-                    let max_map = (file_len - aligned) as usize;
+                    let max_map = clamp_len(file_len - aligned, "read_lines");
                     if map_len > max_map {
                         map_len = max_map;
                     }
@@ -306,34 +1954,32 @@ impl LargeFilePreview {
                                 if let Ok(guard2) = cache.lock() {
                                     if let Some((cached_aligned, _cached_len, mmap2)) = &*guard2 {
                                         let delta2 = (base_offset.saturating_sub(*cached_aligned)) as usize;
-                                        let slice = &mmap2[delta2..];
-                                        let text = String::from_utf8_lossy(slice);
-                                        let mut iter = text.lines();
-                                        let skip = start.saturating_sub(base_line);
-                                        let mut ok = true;
-                                        for _ in 0..skip {
-                                            if iter.next().is_none() {
-                                                ok = false;
-                                                break;
+                                        if let Some(slice) = mmap2.get(delta2..) {
+                                            let text = decode_bytes(encoding, slice);
+                                            let mut iter = text.lines();
+                                            let skip = start.saturating_sub(base_line);
+                                            let mut ok = true;
+                                            for _ in 0..skip {
+                                                if iter.next().is_none() {
+                                                    ok = false;
+                                                    break;
+                                                }
                                             }
-                                        }
-                                        if ok {
-                                            let mut out = String::new();
-                                            for _ in 0..count {
-                                                if let Some(l) = iter.next() {
-                                                    if l.as_bytes().len() > MAX_LINE_BYTES {
-                                                        out.push_str(&String::from_utf8_lossy(&l.as_bytes()[..MAX_LINE_BYTES]));
-                                                        out.push('\n');
-                                                        break;
+                                            if ok {
+                                                let mut out = String::new();
+                                                for i in 0..count {
+                                                    if let Some(l) = iter.next() {
+                                                        if !append_line(&mut out, l.as_bytes(), start + i, policy)? {
+                                                            break;
+                                                        }
                                                     } else {
-                                                        out.push_str(l);
-                                                        out.push('\n');
+                                                        break;
                                                     }
-                                                } else {
-                                                    break;
                                                 }
+                                                return Ok(out);
                                             }
-                                            return Ok(out);
+                                        } else {
+                                            warn!("read_lines - window offset out of range: base_offset={}, window_start={}, window_len={}; falling back to BufReader", base_offset, cached_aligned, mmap2.len());
                                         }
                                     }
                                 }
@@ -360,22 +2006,16 @@ impl LargeFilePreview {
                 cur += 1;
             }
             let mut out = String::new();
-            for _ in 0..count {
+            for i in 0..count {
                 let mut tmp: Vec<u8> = Vec::new();
                 if reader.read_until(b'\n', &mut tmp)? == 0 {
                     break;
                 }
-                // 截断过长的单行，防止内存溢出
-                if tmp.len() > MAX_LINE_BYTES {
-                    out.push_str(&String::from_utf8_lossy(&tmp[..MAX_LINE_BYTES]));
-                    out.push('\n');
+                // 按截断策略处理过长的单行，防止内存溢出
+                let line = tmp.strip_suffix(b"\n").unwrap_or(&tmp[..]);
+                let line = decode_bytes(encoding, line);
+                if !append_line(&mut out, line.as_bytes(), start + i, policy)? {
                     break;
-                } else {
-                    let s = String::from_utf8_lossy(&tmp);
-                    out.push_str(&s);
-                    if !out.ends_with('\n') {
-                        out.push('\n');
-                    }
                 }
             }
             Ok(out)
@@ -386,20 +2026,15 @@ impl LargeFilePreview {
     /// 在整个文件上使用 mmap 执行字节级别的快速搜索。
     ///
     /// - `needle`: 要搜索的字节序列（通常为 UTF-8 字符串的 `.as_bytes()`）。
-    /// - `ignore_case`: 是否忽略大小写（会为整个文件分配额外缓冲区）。
-    /// 返回 `(match_count, samples, duration, extra_alloc_bytes, first_match)`，其中 `first_match` 为可选的 `(line, col_chars, match_len_chars)`。
+    /// - `case`: 大小写匹配方式，见 [`CaseSensitivity`]（ASCII 模式会为整个文件分配额外缓冲区）。
+    /// - `options`: 额外的 opt-in 选项，见 [`SearchOptions`]。
+    /// 返回 [`SearchResult`]，其中 `first_match` 为可选的 `(line, col_chars, match_len_chars)`。
     pub fn mmap_search(
         &self,
         needle: &[u8],
-        ignore_case: bool,
-    ) -> std::io::Result<(
-        usize,
-        Vec<String>,
-        std::time::Duration,
-        usize,
-        Option<(usize, usize, usize)>,
-        Vec<serde_json::Value>,
-    )> {
+        case: CaseSensitivity,
+        options: &SearchOptions,
+    ) -> std::io::Result<SearchResult> {
         use memchr::memmem;
         use memmap2::Mmap;
         use std::time::Instant;
@@ -417,176 +2052,1802 @@ impl LargeFilePreview {
             }
         };
         let start_time = Instant::now();
-        info!("mmap_search - needle_len={}, ignore_case={}, file_len={}", needle.len(), ignore_case, file_len);
+        // Unicode 规则只支持字面搜索，且需要 UTF-8 的搜索词（非 UTF-8 编码的文件）
+        let unicode_needle = match case {
+            CaseSensitivity::UnicodeInsensitive if !options.fuzzy => std::str::from_utf8(needle).ok().map(|n| {
+                let mut folded = String::new();
+                fold_unicode_into(n, 0, &mut folded, &mut Vec::new());
+                folded
+            }),
+            _ => None,
+        };
+        let ascii_fold = case == CaseSensitivity::AsciiInsensitive || (case == CaseSensitivity::UnicodeInsensitive && unicode_needle.is_none());
+        info!("mmap_search - needle_len={}, case={:?}, file_len={}", needle.len(), case, file_len);
 
         if file_len == 0 {
-            return Ok((0usize, Vec::new(), start_time.elapsed(), 0usize, None, Vec::new()));
+            return Ok(SearchResult {
+                duration: start_time.elapsed(),
+                line_histogram: options.line_histogram.then(BTreeMap::new),
+                regions: (options.region_buckets > 0).then(|| vec![0; options.region_buckets.min(MAX_REGION_BUCKETS)]),
+                checkpoint_regions: options.checkpoint_regions.then(|| vec![0; self.index.len() + 1]),
+                ..Default::default()
+            });
         }
 
+        // 整个文件需要映射进地址空间；32 位目标上超过 `usize::MAX` 的文件无法映射
+        if usize::try_from(file_len).is_err() {
+            warn!("mmap_search - file_len={} exceeds usize::MAX, cannot map on this target", file_len);
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                format!("file of {} bytes is too large to search on this platform", file_len),
+            ));
+        }
         let mmap = unsafe { Mmap::map(&f)? };
         let hay_orig = &mmap[..];
 
-        let mut extra_alloc = 0usize;
-        let (hay, needle_used): (std::borrow::Cow<[u8]>, Vec<u8>) = if ignore_case {
-            let lowered: Vec<u8> = hay_orig.iter().map(|b| b.to_ascii_lowercase()).collect();
-            extra_alloc = lowered.len();
-            let n = needle
-                .iter()
-                .map(|b| b.to_ascii_lowercase())
-                .collect::<Vec<u8>>();
-            (std::borrow::Cow::Owned(lowered), n)
-        } else {
-            (std::borrow::Cow::Borrowed(hay_orig), needle.to_vec())
-        };
+        let mut extra_alloc = 0usize;
+        let (hay, needle_used): (std::borrow::Cow<[u8]>, Vec<u8>) = if ascii_fold {
+            let lowered: Vec<u8> = hay_orig.iter().map(|b| b.to_ascii_lowercase()).collect();
+            extra_alloc = lowered.len();
+            let n = needle
+                .iter()
+                .map(|b| b.to_ascii_lowercase())
+                .collect::<Vec<u8>>();
+            (std::borrow::Cow::Owned(lowered), n)
+        } else {
+            (std::borrow::Cow::Borrowed(hay_orig), needle.to_vec())
+        };
+
+        let mut count = 0usize;
+        let mut samples = Vec::new();
+        let mut matches_pos: Vec<serde_json::Value> = Vec::new();
+        let max_matches_return = 1000usize;
+        let mut first_match: Option<(usize, usize, usize)> = None;
+        // 行号采用增量统计：记录上次统计到的位置，只对新增区间计数换行
+        let mut line_no = 0usize;
+        let base_byte = clamp_len(self.base_byte, "mmap_search");
+        let mut line_scan_pos = base_byte;
+        let mut histogram: BTreeMap<usize, usize> = BTreeMap::new();
+        let mut histogram_truncated = false;
+        let region_buckets = options.region_buckets.min(MAX_REGION_BUCKETS);
+        let mut regions = vec![0usize; region_buckets];
+        let mut checkpoint_regions = vec![0usize; if options.checkpoint_regions { self.index.len() + 1 } else { 0 }];
+        let max_samples = options.max_samples.min(MAX_SEARCH_SAMPLES);
+
+        // 搜索窗口：默认整个文件，指定 `line_range` 时只搜索 `[start_line, end_line)`，指定 `from_line` 时从该行开始
+        let (mut win_start, win_end) = match options.line_range {
+            Some((first, last)) => {
+                let ws = self.line_offset_in(&hay, first);
+                let we = self.line_offset_in(&hay, last).max(ws);
+                (ws, we)
+            }
+            None => (base_byte, hay.len()),
+        };
+        if let Some(from) = options.from_line {
+            win_start = win_start.max(self.line_offset_in(&hay, from)).min(win_end);
+        }
+
+        // 记录一个匹配 `[abs, end)`：行/列信息、样例行与直方图。匹配必须按 `abs` 递增的顺序传入。
+        // 不满足 `anchor` 的匹配不记录，返回 false
+        let mut record = |abs: usize, end: usize, distance: Option<u32>| -> bool {
+            let anchored = match options.anchor {
+                SearchAnchor::None => true,
+                SearchAnchor::Start => abs == base_byte || hay[abs - 1] == b'\n',
+                SearchAnchor::End => matches!(&hay[end..], [] | [b'\n', ..] | [b'\r', b'\n', ..]),
+            };
+            if !anchored {
+                return false;
+            }
+            if region_buckets > 0 {
+                let bucket = (((abs - base_byte) as u128 * region_buckets as u128) / (hay.len() - base_byte) as u128) as usize;
+                regions[bucket.min(region_buckets - 1)] += 1;
+            }
+            if options.checkpoint_regions {
+                // 第 k 个索引点是第 (k + 1) * index_interval 行的起点，二分即可得到所在区域，不需要数换行
+                checkpoint_regions[self.index.partition_point(|&o| o as usize <= abs)] += 1;
+            }
+            let need_line = first_match.is_none()
+                || matches_pos.len() < max_matches_return
+                || options.line_histogram;
+            if need_line {
+                line_no += memchr::memchr_iter(b'\n', &hay[line_scan_pos..abs]).count();
+                line_scan_pos = abs;
+            }
+            let ln = line_no;
+            if options.line_histogram {
+                if let Some(c) = histogram.get_mut(&ln) {
+                    *c += 1;
+                } else if histogram.len() < MAX_HISTOGRAM_LINES {
+                    histogram.insert(ln, 1);
+                } else {
+                    histogram_truncated = true;
+                }
+            }
+            let line_start = hay[..abs]
+                .iter()
+                .rposition(|&b| b == b'\n')
+                .map(|p| p + 1)
+                .unwrap_or(0);
+            let line_end = || hay[abs..]
+                .iter()
+                .position(|&b| b == b'\n')
+                .map(|p| abs + p)
+                .unwrap_or(hay.len());
+            // record this match's position (line, column, length) up to the configured cap
+            if first_match.is_none() || matches_pos.len() < max_matches_return {
+                let col_chars = std::str::from_utf8(&hay_orig[line_start..abs])
+                    .map(|s| s.chars().count())
+                    .unwrap_or(0usize);
+                let match_len_chars = std::str::from_utf8(&hay_orig[abs..end])
+                    .map(|s| s.chars().count())
+                    .unwrap_or(end - abs);
+                if first_match.is_none() {
+                    first_match = Some((ln, col_chars, match_len_chars));
+                }
+                if matches_pos.len() < max_matches_return {
+                    let mut m = json!({"line": ln, "column": col_chars, "length": match_len_chars});
+                    if let Some(d) = distance {
+                        m["distance"] = json!(d);
+                    }
+                    if options.include_line_text {
+                        let raw = &hay_orig[line_start..line_end()];
+                        let raw = raw.strip_suffix(b"\r").unwrap_or(raw);
+                        let text: String = String::from_utf8_lossy(raw).chars().take(MAX_MATCH_LINE_CHARS).collect();
+                        m["text"] = json!(text);
+                    }
+                    matches_pos.push(m);
+                }
+            }
+            if samples.len() < max_samples {
+                if let Ok(s) = std::str::from_utf8(&hay_orig[line_start..line_end()]) {
+                    samples.push(s.to_string());
+                }
+            }
+            count += 1;
+            true
+        };
+
+        let window = &hay[..win_end];
+        if options.fuzzy {
+            use triple_accel::levenshtein::{levenshtein_search_simd_with_opts, LEVENSHTEIN_COSTS};
+            use triple_accel::SearchType;
+
+            // 模糊搜索明显慢于字面搜索：距离必须小于模式长度（否则处处匹配）且不超过上限
+            if options.max_distance >= needle_used.len() || options.max_distance > MAX_FUZZY_DISTANCE {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    format!(
+                        "max_distance must be < needle length ({}) and <= {}",
+                        needle_used.len(),
+                        MAX_FUZZY_DISTANCE
+                    ),
+                ));
+            }
+            info!(
+                "mmap_search - fuzzy search, max_distance={}, window={}..{}",
+                options.max_distance, win_start, win_end
+            );
+            // `SearchType::All` 会为同一处近似匹配报告多个重叠的结束位置；
+            // 将重叠的候选合并为一组，保留编辑距离最小（并列时取最先出现）的那个。
+            let mut pending: Option<(usize, usize, u32)> = None;
+            for m in levenshtein_search_simd_with_opts(
+                &needle_used,
+                &window[win_start..],
+                options.max_distance as u32,
+                SearchType::All,
+                LEVENSHTEIN_COSTS,
+                false,
+            ) {
+                let cand = (win_start + m.start, win_start + m.end, m.k);
+                match pending {
+                    Some(p) if cand.0 < p.1 => {
+                        if cand.2 < p.2 {
+                            pending = Some(cand);
+                        }
+                    }
+                    Some(p) => {
+                        record(p.0, p.1, Some(p.2));
+                        pending = Some(cand);
+                    }
+                    None => pending = Some(cand),
+                }
+            }
+            if let Some(p) = pending {
+                record(p.0, p.1, Some(p.2));
+            }
+        } else if let Some(folded_needle) = unicode_needle.as_deref().filter(|n| !n.is_empty()) {
+            extra_alloc = for_each_unicode_match(&window[win_start..], folded_needle, |s, e| {
+                record(win_start + s, win_start + e, None);
+            });
+        } else {
+            let mut start = win_start;
+            while let Some(pos) = memmem::find(&window[start..], &needle_used) {
+                let abs = start + pos;
+                // 被锚定排除的匹配只前进一个字节，与它重叠的下一处可能正好落在行边界上
+                start = if record(abs, abs + needle_used.len(), None) { abs + needle_used.len() } else { abs + 1 };
+            }
+        }
+
+        let dur = start_time.elapsed();
+        Ok(SearchResult {
+            count,
+            samples,
+            duration: dur,
+            file_len,
+            extra_alloc,
+            first_match,
+            matches: matches_pos,
+            line_histogram: options.line_histogram.then_some(histogram),
+            histogram_truncated,
+            regions: (region_buckets > 0).then_some(regions),
+            checkpoint_regions: options.checkpoint_regions.then_some(checkpoint_regions),
+        })
+    }
+}
+
+/// `mmap_search` 默认返回的样例行数
+pub const DEFAULT_SEARCH_SAMPLES: usize = 5;
+
+/// `SearchOptions::max_samples` 的上限，样例行随结果整体返回，过多会拖慢 IPC
+const MAX_SEARCH_SAMPLES: usize = 1000;
+
+/// `mmap_search` 的可选参数，默认值保持原有行为（不增加返回负载）。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchOptions {
+    /// 是否额外返回 “行号 → 匹配次数” 直方图（最多 `MAX_HISTOGRAM_LINES` 行）
+    pub line_histogram: bool,
+    /// 近似（模糊）匹配：报告编辑距离不超过 `max_distance` 的子串。
+    /// 比字面搜索慢得多，建议配合 `line_range` 限定范围。
+    pub fuzzy: bool,
+    /// 模糊匹配允许的最大 Levenshtein 距离（须小于模式长度，且不超过 `MAX_FUZZY_DISTANCE`）
+    pub max_distance: usize,
+    /// 只在 `[start_line, end_line)` 行范围内搜索（0 基准）；行号仍为文件内的绝对行号
+    pub line_range: Option<(usize, usize)>,
+    /// 从该行（0 基准）开始向后搜索，跳过之前的内容，用于从当前视图处继续查找；
+    /// 与 `line_range` 同时给出时取两者的交集。行号仍为文件内的绝对行号
+    pub from_line: Option<usize>,
+    /// 把文件按字节等分为若干区域并统计每个区域的匹配数（0 表示不统计，最多 `MAX_REGION_BUCKETS`）。
+    /// 只是计数器，不受返回匹配位置数量上限的影响
+    pub region_buckets: usize,
+    /// 按行索引的索引点把文件分成每 `index_interval` 行一段的区域，统计每个区域的匹配数，
+    /// 用作“匹配频率随文件位置变化”的粗略近似（如日志中某类错误的出现速率）。
+    /// 这只在行与时间大致成正比时才近似于按时间的速率：不解析时间戳，行长或写入速率不均匀时区域对应的时间跨度也不同。
+    /// 与 `region_buckets` 一样只是计数器，不受返回匹配位置数量上限的影响
+    pub checkpoint_regions: bool,
+    /// 在每个返回的匹配位置中附带所在行的文本（`"text"`，最多 `MAX_MATCH_LINE_CHARS` 个字符），
+    /// 供结果列表显示与复制
+    pub include_line_text: bool,
+    /// 最多返回多少条包含匹配的样例行（默认 `DEFAULT_SEARCH_SAMPLES`，0 表示不返回，最多 `MAX_SEARCH_SAMPLES`）。
+    /// 只影响 `samples`，匹配计数与位置不变
+    pub max_samples: usize,
+    /// 只保留从行首开始或在行尾结束的匹配，见 [`SearchAnchor`]；计数、位置与直方图都只统计保留的匹配
+    pub anchor: SearchAnchor,
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        SearchOptions {
+            line_histogram: false,
+            fuzzy: false,
+            max_distance: 0,
+            line_range: None,
+            from_line: None,
+            region_buckets: 0,
+            checkpoint_regions: false,
+            include_line_text: false,
+            max_samples: DEFAULT_SEARCH_SAMPLES,
+            anchor: SearchAnchor::None,
+        }
+    }
+}
+
+/// 导出类命令（`filter_lines`）的可选参数，默认原样保留源文件的换行风格。
+#[derive(Debug, Clone, Default)]
+pub struct ExportOptions {
+    /// 输出的换行符，见 [`LineEnding`]
+    pub line_ending: LineEnding,
+    /// 源文件最后一行没有换行时是否在输出末尾补上一个（换行符按 `line_ending`，
+    /// `Preserve` 时与源文件第一个换行相同）
+    pub ensure_trailing_newline: bool,
+}
+
+/// `mmap_search` 的搜索结果
+#[derive(Debug, Default)]
+pub struct SearchResult {
+    /// 匹配总数
+    pub count: usize,
+    /// 包含匹配的样例行，最多 `SearchOptions::max_samples` 条
+    pub samples: Vec<String>,
+    /// 搜索耗时
+    pub duration: std::time::Duration,
+    /// 被搜索文件的大小（字节），与 `duration` 一起可算出吞吐量
+    pub file_len: u64,
+    /// 忽略大小写时额外分配的字节数
+    pub extra_alloc: usize,
+    /// 第一个匹配的 `(line, col_chars, match_len_chars)`
+    pub first_match: Option<(usize, usize, usize)>,
+    /// 每个匹配的位置（最多 1000 条），形如 `{"line", "column", "length"}`；开启 `include_line_text` 时还有 `"text"`
+    pub matches: Vec<serde_json::Value>,
+    /// 行号 → 该行匹配次数，仅在 `SearchOptions::line_histogram` 开启时为 `Some`
+    pub line_histogram: Option<BTreeMap<usize, usize>>,
+    /// 直方图的行数是否达到上限而被截断（计数仍覆盖已记录的行）
+    pub histogram_truncated: bool,
+    /// 每个字节区域的匹配数，仅在 `SearchOptions::region_buckets > 0` 时为 `Some`
+    pub regions: Option<Vec<usize>>,
+    /// 每个索引点区域（第 k 个区域为 `[k * index_interval, (k + 1) * index_interval)` 行）的匹配数，
+    /// 仅在 `SearchOptions::checkpoint_regions` 开启时为 `Some`
+    pub checkpoint_regions: Option<Vec<usize>>,
+}
+
+/// 搜索缓存的键：(编码, `base_byte`, 索引间隔, 文件长度, 修改时间)，见 `search_cache_key`
+pub type SearchCacheKey = (&'static Encoding, u64, usize, u64, Option<SystemTime>);
+
+/// 当前的搜索缓存键。索引间隔决定 `checkpoint_regions` 的区域划分；只比较长度会漏掉等长改写，因此也比较修改时间
+fn search_cache_key(preview: &LargeFilePreview) -> SearchCacheKey {
+    let meta = preview.file_handle.as_ref().metadata().ok();
+    (
+        preview.encoding,
+        preview.base_byte,
+        preview.index_interval,
+        meta.as_ref().map_or(0, |m| m.len()),
+        meta.and_then(|m| m.modified().ok()),
+    )
+}
+
+/// 缓存的最近一次搜索结果。编码、尾部窗口、索引间隔或文件内容变化后行号与区域可能不再对应，缓存失效
+#[derive(Debug, Clone)]
+pub struct LastSearch {
+    /// 搜索时的缓存键
+    pub key: SearchCacheKey,
+    /// 搜索的关键字、大小写方式与选项，三者都相同时 `mmap_search` 直接返回 `result`
+    pub query: (String, CaseSensitivity, SearchOptions),
+    /// 每个返回的匹配所在的行号，与 `SearchResult::matches` 一一对应
+    pub match_lines: Vec<usize>,
+    /// `mmap_search` 返回给前端的完整结果
+    pub result: serde_json::Value,
+}
+
+// 定义返回给前端的结果结构体
+#[derive(Serialize)]
+pub struct FileInfo {
+    pub uri: String,
+    // 或者其他元数据，如文件大小等
+}
+
+// 全局缓存用于存储打开的 LargeFilePreview
+static LARGE_FILE_PREVIEW: Lazy<Arc<AsyncMutex<Option<LargeFilePreview>>>> = 
+    Lazy::new(|| Arc::new(AsyncMutex::new(None)));
+
+// 其余已打开但不处于活动状态的文件（句柄 → 预览），例如对比模式下的第二个文件。
+// 活动文件只保存在 LARGE_FILE_PREVIEW 中；需要同时加锁时先锁 LARGE_FILE_PREVIEW 再锁 OPEN_FILES。
+static OPEN_FILES: Lazy<AsyncMutex<HashMap<u32, LargeFilePreview>>> =
+    Lazy::new(|| AsyncMutex::new(HashMap::new()));
+
+static NEXT_HANDLE: AtomicU32 = AtomicU32::new(1);
+
+/// 为新打开的预览分配句柄。`activate` 为 true 时替换当前活动文件（原行为），
+/// 否则只登记为后台文件，活动文件保持不变。
+async fn register_preview(mut preview: LargeFilePreview, activate: bool) -> u32 {
+    let handle = NEXT_HANDLE.fetch_add(1, Ordering::Relaxed);
+    preview.handle = handle;
+    if activate {
+        let mut preview_guard = LARGE_FILE_PREVIEW.lock().await;
+        *preview_guard = Some(preview);
+    } else {
+        OPEN_FILES.lock().await.insert(handle, preview);
+    }
+    handle
+}
+
+/// 读取/搜索命令的超时（秒），0 表示不限制；见 `set_io_timeout`
+static IO_TIMEOUT_SECS: AtomicU64 = AtomicU64::new(30);
+
+/// 为读取/搜索加上超时：网络盘或移动存储卡住时，命令在超时后返回错误，前端可以恢复并提示用户。
+///
+/// 超时只是不再等待：`smol::unblock` 中已开始的阻塞读取无法中断，会在后台继续直到系统调用返回，其结果被丢弃。
+/// `cached_window` 只在持锁时整体替换为完整建立的窗口，因此后台任务晚些完成也不会留下不一致的缓存。
+async fn with_timeout<T>(what: &str, fut: impl std::future::Future<Output = T>) -> Result<T, String> {
+    let secs = IO_TIMEOUT_SECS.load(Ordering::Relaxed);
+    if secs == 0 {
+        return Ok(fut.await);
+    }
+    let timeout = async {
+        smol::Timer::after(std::time::Duration::from_secs(secs)).await;
+        warn!("{} timed out after {} s", what, secs);
+        Err(format!("{} timed out after {} s", what, secs))
+    };
+    smol::future::or(async { Ok(fut.await) }, timeout).await
+}
+
+/// 设置读取/搜索命令的超时秒数（0 表示不限制）
+pub async fn set_io_timeout(secs: u64) -> Result<(), String> {
+    IO_TIMEOUT_SECS.store(secs, Ordering::Relaxed);
+    info!("set_io_timeout - {} s", secs);
+    Ok(())
+}
+
+/// 取得指定句柄（`None` 表示活动文件）对应预览的克隆
+async fn preview_for(handle: Option<u32>) -> Result<LargeFilePreview, String> {
+    {
+        let preview_guard = LARGE_FILE_PREVIEW.lock().await;
+        if let Some(preview) = preview_guard.as_ref() {
+            if handle.map_or(true, |h| h == preview.handle) {
+                return Ok(preview.clone());
+            }
+        }
+    }
+    match handle {
+        Some(h) => OPEN_FILES.lock().await.get(&h).cloned()
+            .ok_or_else(|| format!("No open file with handle {}", h)),
+        None => Err("No file is currently opened".to_string()),
+    }
+}
+
+/// 需要每个返回的行对应一个逻辑行（按行号换算位置）的读取使用：`Split` 会把一行拆成多行，
+/// 导致之后的行号整体错位，此时返回按 `Truncate` 读取的副本，其它策略保持不变
+fn row_per_line(mut preview: LargeFilePreview) -> LargeFilePreview {
+    if preview.truncation_policy == TruncationPolicy::Split {
+        preview.truncation_policy = TruncationPolicy::Truncate;
+    }
+    preview
+}
+
+/// 依赖精确行数或完整索引的命令先调用：文件以 `no_index` 打开且尚未 `reindex` 时返回错误
+fn require_index(preview: &LargeFilePreview) -> Result<(), String> {
+    if preview.indexed {
+        Ok(())
+    } else {
+        Err("The file was opened without a line index; build it with reindex first".to_string())
+    }
+}
+
+// 插件状态管理结构（如果需要）
+// PluginState removed — not currently used
+
+/// `get_total_lines` 的返回值
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct TotalLines {
+    pub total_lines: usize,
+    /// 行数是否为最终值。索引在 `open` 时同步建立完成，通常为 `true`；
+    /// 以 `no_index` 打开且尚未 `reindex` 时为 `false`，此时 `total_lines` 是按文件头估算的行数
+    pub exact: bool,
+}
+
+/// 立即返回当前已知的总行数，不等待索引完成
+pub async fn get_total_lines() -> Result<TotalLines, String> {
+    // debug!("get_total_lines command invoked");
+    let preview_guard = LARGE_FILE_PREVIEW.lock()
+        .await;
+    let preview = preview_guard.as_ref()
+        .ok_or("No file is currently opened")?;
+    let lines = preview.total_lines();
+    info!("Total lines: {}", lines);
+    Ok(TotalLines { total_lines: lines, exact: preview.indexed })
+}
+
+/// 返回精确的总行数。索引通常在打开时即已完成，直接返回；以 `no_index` 打开且尚未 `reindex` 时
+/// 先按当前间隔建立完整索引（扫描整个文件，完成时同样发出 `INDEX_COMPLETE_EVENT`），等它完成后再返回
+pub async fn get_total_lines_exact<R: Runtime>(app: tauri::AppHandle<R>) -> Result<usize, String> {
+    let t = get_total_lines().await?;
+    if t.exact {
+        return Ok(t.total_lines);
+    }
+    let interval = preview_for(None).await?.index_interval;
+    reindex(app, interval).await?;
+    Ok(preview_for(None).await?.total_lines)
+}
+
+/// 抽样估算活动文件的总行数（见 `LargeFilePreview::estimate_total_lines`），只读几块、不触发完整扫描。
+/// 未建立索引时 `exact` 为 false；文件在打开后增长时，结果也反映增长后的大小
+pub async fn estimate_total_lines() -> Result<TotalLines, String> {
+    let preview = preview_for(None).await?;
+    let (total_lines, exact) = with_timeout("Read", smol::unblock(move || preview.estimate_total_lines()))
+        .await?
+        .map_err(|e| format!("Failed to estimate total lines: {}", e))?;
+    Ok(TotalLines { total_lines, exact })
+}
+
+/// 返回当前打开文件的字节大小（若没有打开文件，返回 0）
+pub async fn get_file_size() -> Result<u64, String> {
+    // debug!("get_file_size command invoked");
+    let preview_guard = LARGE_FILE_PREVIEW.lock().await;
+    if let Some(preview) = preview_guard.as_ref() {
+        // 尝试通过 file handle 获取元数据
+        match preview.file_handle.as_ref().metadata() {
+            Ok(meta) => Ok(meta.len()),
+            Err(e) => Err(format!("Failed to read file metadata: {}", e)),
+        }
+    } else {
+        // 如果没有打开文件，按要求返回 0（作为 Ok）
+        Ok(0u64)
+    }
+}
+
+/// 读取 `start` 行起的 `count` 行。`preserve_eol` 为 true 时原样返回各行的换行符且不在最后一行补换行
+/// （见 `LargeFilePreview::read_lines_exact`），供需要精确字节的复制使用；默认每行以 `\n` 结尾，供文本框显示。
+/// `row_per_line` 为 true 时保证返回的每行对应一个逻辑行（`Split` 按 `Truncate` 处理），供按行号对齐行号区与选区的视图使用
+pub async fn read_lines(start: usize, count: usize, handle: Option<u32>, preserve_eol: bool, row_per_line: bool) -> Result<String, String> {
+    let mut preview = preview_for(handle).await?;
+    if row_per_line {
+        preview = self::row_per_line(preview);
+    }
+    if handle.is_none() {
+        session::note_line(&preview.path.to_string_lossy(), start);
+    }
+    if preserve_eol {
+        return with_timeout("Read", smol::unblock(move || preview.read_lines_exact(start, count))).await?
+            .map_err(|e| format!("Failed to read lines: {}", e));
+    }
+    with_timeout("Read", preview.read_lines(start, count)).await?
+        .map_err(|e| format!("Failed to read lines: {}", e))
+}
+
+/// `read_line_ranges` 所有范围合计最多读取的行数
+const MAX_LINE_RANGES_LINES: usize = 10_000;
+
+/// 一次读取多个不相邻的行范围 `(start, count)`，省去逐段调用 `read_lines` 的 IPC 往返，
+/// 供搜索结果预览、书签列表等需要分散片段的界面使用。每段的格式与 `read_lines` 相同；
+/// 按起始行排序后依次读取（相邻的段可以复用 mmap 窗口缓存），但按请求的顺序返回。
+/// 所有范围的行数合计不能超过 `MAX_LINE_RANGES_LINES`
+pub async fn read_line_ranges(ranges: Vec<(usize, usize)>) -> Result<Vec<String>, String> {
+    let total = ranges.iter().fold(0usize, |acc, &(_, count)| acc.saturating_add(count));
+    if total > MAX_LINE_RANGES_LINES {
+        return Err(format!("The ranges may contain at most {} lines in total", MAX_LINE_RANGES_LINES));
+    }
+    let preview = preview_for(None).await?;
+    let mut order: Vec<usize> = (0..ranges.len()).collect();
+    order.sort_by_key(|&i| ranges[i].0);
+    with_timeout("Read", async move {
+        let mut out = vec![String::new(); ranges.len()];
+        for i in order {
+            let (start, count) = ranges[i];
+            out[i] = preview.read_lines(start, count).await.map_err(|e| format!("Failed to read lines: {}", e))?;
+        }
+        Ok::<_, String>(out)
+    })
+    .await?
+}
+
+/// 倒序读取：返回以 `end_line` 结束（含该行）的最多 `count` 行，按从后往前的顺序排列（`end_line` 在最前），
+/// 供前端从新到旧浏览日志。格式与 `read_lines` 相同，每行以 `\n` 结尾。
+///
+/// `end_line` 超出文件末尾时按最后一行处理；靠近文件开头时返回的行数少于 `count`，文件为空时返回空字符串。
+pub async fn read_lines_reverse(end_line: usize, count: usize) -> Result<String, String> {
+    // 倒序按行反转，拆分出的段会被颠倒顺序
+    let preview = row_per_line(preview_for(None).await?);
+    // 从末尾倒数需要精确的总行数
+    require_index(&preview)?;
+    if preview.total_lines == 0 || count == 0 {
+        return Ok(String::new());
+    }
+    let end = end_line.min(preview.total_lines - 1);
+    let start = (end + 1).saturating_sub(count);
+    let text = with_timeout("Read", preview.read_lines(start, end + 1 - start)).await?
+        .map_err(|e| format!("Failed to read lines: {}", e))?;
+    let mut reversed = String::with_capacity(text.len() + 1);
+    for line in text.split_inclusive('\n').rev() {
+        reversed.push_str(line);
+        if !line.ends_with('\n') {
+            reversed.push('\n');
+        }
+    }
+    Ok(reversed)
+}
+
+/// 对比活动文件与句柄为 `other` 的文件，从 `from_line` 起查找第一处不同的行。
+///
+/// 返回 `{"line", "line_a", "line_b"}`，某一侧已超出文件末尾时对应字段为 `null`；没有差异返回 `null`。
+pub async fn next_diff(from_line: usize, other: u32) -> Result<Option<serde_json::Value>, String> {
+    let a = preview_for(None).await?;
+    let b = preview_for(Some(other)).await?;
+    let diff = smol::unblock(move || a.next_diff_line(&b, from_line))
+        .await
+        .map_err(|e| format!("Compare failed: {}", e))?;
+    Ok(diff.map(|(line, in_a, in_b)| json!({
+        "line": line,
+        "line_a": in_a.then_some(line),
+        "line_b": in_b.then_some(line)
+    })))
+}
+
+/// `diff_ranges` 每个范围最多比较的行数，避免逐行 diff 的开销与返回负载失控
+const MAX_DIFF_RANGE_LINES: usize = 10_000;
+
+/// `diff_ranges` 结果中的一行
+#[derive(Debug, Clone, Serialize)]
+pub struct DiffLine {
+    /// "equal"：两段都有；"delete"：只在 A 段；"insert"：只在 B 段
+    pub tag: &'static str,
+    /// 在文件中的行号（0 基准），该行不在 A 段时为 None
+    pub line_a: Option<usize>,
+    /// 同上，对应 B 段
+    pub line_b: Option<usize>,
+    pub text: String,
+}
+
+/// 对活动文件中的两段行 `[start_a, start_a + count_a)` 与 `[start_b, start_b + count_b)` 做逐行 diff（Myers 算法），
+/// 比如比较追加到同一个日志中的两次运行。两段都按 `read_lines` 读取，每段最多 `MAX_DIFF_RANGE_LINES` 行
+pub async fn diff_ranges(start_a: usize, count_a: usize, start_b: usize, count_b: usize) -> Result<Vec<DiffLine>, String> {
+    if count_a > MAX_DIFF_RANGE_LINES || count_b > MAX_DIFF_RANGE_LINES {
+        return Err(format!("Each range may contain at most {} lines", MAX_DIFF_RANGE_LINES));
+    }
+    // 结果中的行号按返回的行序号换算
+    let preview = row_per_line(preview_for(None).await?);
+    let a = with_timeout("Read", preview.read_lines(start_a, count_a)).await?
+        .map_err(|e| format!("Failed to read lines: {}", e))?;
+    let b = with_timeout("Read", preview.read_lines(start_b, count_b)).await?
+        .map_err(|e| format!("Failed to read lines: {}", e))?;
+    smol::unblock(move || -> Result<Vec<DiffLine>, String> {
+        use similar::{Algorithm, ChangeTag, TextDiff};
+        let a_lines: Vec<&str> = a.lines().collect();
+        let b_lines: Vec<&str> = b.lines().collect();
+        let diff = TextDiff::configure().algorithm(Algorithm::Myers).diff_slices(&a_lines, &b_lines);
+        let out = diff
+            .iter_all_changes()
+            .map(|change| DiffLine {
+                tag: match change.tag() {
+                    ChangeTag::Equal => "equal",
+                    ChangeTag::Delete => "delete",
+                    ChangeTag::Insert => "insert",
+                },
+                line_a: change.old_index().map(|i| start_a + i),
+                line_b: change.new_index().map(|i| start_b + i),
+                text: change.value().to_string(),
+            })
+            .collect();
+        Ok(out)
+    })
+    .await
+}
+
+/// 把活动文件中包含（`invert` 为 true 时为不包含）`needle` 的行（`ignore_case` 时忽略 ASCII 大小写）流式写入 `dest_path`，
+/// 结果文件可以再用 `open_file` 打开。返回 `{"dest_path", "lines", "duration_ms"}`。
+///
+/// 默认原样保留源文件的换行符，源文件最后一行没有换行时输出也没有；`options` 可以统一为
+/// LF / CRLF，或保证输出以换行结尾，便于在不同平台之间来回传递日志。
+pub async fn filter_lines<R: Runtime>(
+    app: tauri::AppHandle<R>,
+    needle: String,
+    ignore_case: bool,
+    invert: bool,
+    dest_path: String,
+    options: ExportOptions,
+) -> Result<serde_json::Value, String> {
+    let preview = preview_for(None).await?;
+    let needle = preview.encode_needle(&needle);
+    let start_time = std::time::Instant::now();
+    let dest = PathBuf::from(&dest_path);
+    let lines = smol::unblock(move || {
+        let progress = TaskProgress::start(app, "export", preview.file_handle.metadata()?.len());
+        let result = preview.filter_lines_to(&needle, ignore_case, invert, &dest, &options, |done| progress.report(done));
+        // 被取消时删除写了一半的目标文件（其它错误发生在创建目标之前或写入途中，保留现场便于排查）
+        if matches!(&result, Err(e) if e.kind() == std::io::ErrorKind::Interrupted) {
+            if let Err(e) = std::fs::remove_file(&dest) {
+                warn!("filter_lines - failed to remove partial output {:?}: {}", dest, e);
+            }
+        }
+        result
+    })
+    .await
+    .map_err(|e| format!("Filter failed: {}", e))?;
+    info!("filter_lines - wrote {} lines to {}", lines, dest_path);
+    Ok(json!({
+        "dest_path": dest_path,
+        "lines": lines,
+        "duration_ms": start_time.elapsed().as_millis()
+    }))
+}
+
+/// 筛选视图分页：返回 `{"lines": [{"line", "text"}], "next_match", "total"}`，`total` 仅在 `with_total` 时给出
+pub async fn read_filtered_lines(
+    needle: String,
+    ignore_case: bool,
+    start_match: usize,
+    count: usize,
+    with_total: bool,
+) -> Result<serde_json::Value, String> {
+    let preview = preview_for(None).await?;
+    let needle = preview.encode_needle(&needle);
+    let (lines, total) = with_timeout("Filter", smol::unblock(move || {
+        preview.read_filtered_lines(&needle, ignore_case, start_match, count, with_total)
+    }))
+    .await?
+    .map_err(|e| format!("Failed to read filtered lines: {}", e))?;
+    let next_match = start_match + lines.len();
+    Ok(json!({
+        "lines": lines.into_iter().map(|(line, text)| json!({"line": line, "text": text})).collect::<Vec<_>>(),
+        "next_match": next_match,
+        "total": total
+    }))
+}
+
+/// 从 `from_line` 起查找活动文件中的第一个匹配，返回 `{"line", "column", "length", "byte_offset"}`；没有匹配返回 `null`
+pub async fn find_first(needle: String, ignore_case: bool, from_line: usize) -> Result<Option<serde_json::Value>, String> {
+    let preview = preview_for(None).await?;
+    let needle = preview.encode_needle(&needle);
+    let hit = with_timeout("Search", smol::unblock(move || preview.find_first(&needle, ignore_case, from_line)))
+        .await?
+        .map_err(|e| format!("Search failed: {}", e))?;
+    Ok(hit.map(|(line, column, length, byte_offset)| json!({
+        "line": line,
+        "column": column,
+        "length": length,
+        "byte_offset": byte_offset
+    })))
+}
+
+/// 查找 `before_line` 之前的最后一个匹配（见 `LargeFilePreview::find_prev`），返回格式同 `find_first`
+pub async fn find_prev(needle: String, ignore_case: bool, before_line: usize) -> Result<Option<serde_json::Value>, String> {
+    let preview = preview_for(None).await?;
+    let needle = preview.encode_needle(&needle);
+    let hit = with_timeout("Search", smol::unblock(move || preview.find_prev(&needle, ignore_case, before_line)))
+        .await?
+        .map_err(|e| format!("Search failed: {}", e))?;
+    Ok(hit.map(|(line, column, length, byte_offset)| json!({
+        "line": line,
+        "column": column,
+        "length": length,
+        "byte_offset": byte_offset
+    })))
+}
+
+/// 活动文件中是否包含 `needle`（见 `LargeFilePreview::contains`），找到第一处即停止扫描
+pub async fn contains(needle: String, ignore_case: bool) -> Result<bool, String> {
+    let preview = preview_for(None).await?;
+    let needle = preview.encode_needle(&needle);
+    with_timeout("Search", smol::unblock(move || preview.contains(&needle, ignore_case)))
+        .await?
+        .map_err(|e| format!("Search failed: {}", e))
+}
+
+/// 活动文件（尾部模式下为视图窗口内）按当前编码解码后的字符数，见 `LargeFilePreview::count_chars`，
+/// 对无效字节是近似值。
+///
+/// 第一次需要读完整个文件，期间发出 `TASK_PROGRESS_EVENT`（`task` 为 `"char_count"`），可用 `cancel_task` 取消；
+/// 结果按编码、尾部窗口与文件长度缓存，之后直接返回。不受读取超时限制。
+pub async fn get_char_count<R: Runtime>(app: tauri::AppHandle<R>) -> Result<u64, String> {
+    let preview = preview_for(None).await?;
+    let file_len = preview.file_handle.as_ref().metadata().map(|m| m.len()).unwrap_or(0);
+    let key = (preview.encoding, preview.base_byte, file_len);
+    if let Ok(guard) = preview.char_count.lock() {
+        if let Some((encoding, base_byte, len, count)) = *guard {
+            if (encoding, base_byte, len) == key {
+                return Ok(count);
+            }
+        }
+    }
+    let start_time = std::time::Instant::now();
+    let count = smol::unblock(move || -> std::io::Result<u64> {
+        let progress = TaskProgress::start(app, "char_count", file_len);
+        let count = preview.count_chars(|done| progress.report(done))?;
+        if let Ok(mut guard) = preview.char_count.lock() {
+            *guard = Some((key.0, key.1, key.2, count));
+        }
+        Ok(count)
+    })
+    .await
+    .map_err(|e| format!("Failed to count characters: {}", e))?;
+    info!("get_char_count - {} chars in {:?}", count, start_time.elapsed());
+    Ok(count)
+}
+
+/// 查找 `enclosing_block` 默认使用的括号
+pub const DEFAULT_BLOCK_BRACES: &str = "{}";
+
+/// 活动文件中第 `line` 行所在的代码块，返回 `{"start", "end"}`（都包含在内），找不到时返回 `null`，
+/// 见 `LargeFilePreview::enclosing_block`。`braces` 为成对的 ASCII 括号（如 `"{}()[]"`），为空字符串时按缩进查找；
+/// UTF-16 文件不支持
+pub async fn enclosing_block(line: usize, braces: String) -> Result<Option<serde_json::Value>, String> {
+    if !braces.is_ascii() || braces.len() % 2 != 0 {
+        return Err(format!("Invalid brace pairs: {:?}", braces));
+    }
+    let pairs: Vec<(u8, u8)> = braces.as_bytes().chunks(2).map(|p| (p[0], p[1])).collect();
+    let preview = preview_for(None).await?;
+    if preview.encoding == UTF_16LE || preview.encoding == UTF_16BE {
+        return Err("Finding blocks is not supported for UTF-16 files".to_string());
+    }
+    let block = with_timeout("Search", smol::unblock(move || preview.enclosing_block(line, &pairs)))
+        .await?
+        .map_err(|e| format!("Search failed: {}", e))?;
+    Ok(block.map(|(start, end)| json!({ "start": start, "end": end })))
+}
+
+/// `scan_invalid_utf8` 默认报告的位置数
+pub const DEFAULT_INVALID_UTF8_RESULTS: usize = 1000;
+
+/// `scan_invalid_utf8` 最多报告的位置数
+const MAX_INVALID_UTF8_RESULTS: usize = 10_000;
+
+/// 查找活动文件（尾部模式下为视图窗口内）中前 `max_results` 处（最多 `MAX_INVALID_UTF8_RESULTS`）非法的 UTF-8 字节序列，
+/// 返回 `[{"line", "column", "offset", "byte"}]`：`line` / `column` 与 `mmap_search` 的匹配位置相同（0 基准，列按字符计），
+/// `offset` 为文件内的字节偏移，`byte` 为序列的第一个字节。有损解码显示为替换字符的内容可由此定位。
+///
+/// 只用于 UTF-8 编码（其它编码没有“非法 UTF-8”的概念）。需要读完整个文件时较慢，期间发出 `TASK_PROGRESS_EVENT`
+/// （`task` 为 `"utf8_scan"`），可用 `cancel_task` 取消；不受读取超时限制
+pub async fn scan_invalid_utf8<R: Runtime>(app: tauri::AppHandle<R>, max_results: usize) -> Result<Vec<serde_json::Value>, String> {
+    let preview = preview_for(None).await?;
+    if preview.encoding != UTF_8 {
+        return Err(format!("The file is read as {}, not UTF-8", preview.encoding.name()));
+    }
+    let max_results = max_results.clamp(1, MAX_INVALID_UTF8_RESULTS);
+    let start_time = std::time::Instant::now();
+    let found = smol::unblock(move || -> std::io::Result<Vec<(usize, usize, u64, u8)>> {
+        let file_len = preview.file_handle.metadata()?.len();
+        let progress = TaskProgress::start(app, "utf8_scan", file_len);
+        preview.scan_invalid_utf8(max_results, |done| progress.report(done))
+    })
+    .await
+    .map_err(|e| format!("Failed to scan for invalid UTF-8: {}", e))?;
+    info!("scan_invalid_utf8 - {} positions in {:?}", found.len(), start_time.elapsed());
+    Ok(found
+        .into_iter()
+        .map(|(line, column, offset, byte)| json!({ "line": line, "column": column, "offset": offset, "byte": byte }))
+        .collect())
+}
+
+/// 从 `from_line` 之后查找活动文件中的下一个空行（`whitespace_is_blank` 为 true 时只含空白字符的行也算），
+/// 到达文件末尾仍未找到时返回 `null`
+pub async fn find_next_blank_line(from_line: usize, whitespace_is_blank: bool) -> Result<Option<usize>, String> {
+    let preview = preview_for(None).await?;
+    with_timeout("Search", smol::unblock(move || preview.find_blank_line(from_line, true, whitespace_is_blank)))
+        .await?
+        .map_err(|e| format!("Search failed: {}", e))
+}
+
+/// 从 `from_line` 之前查找活动文件中的上一个空行，到达文件开头仍未找到时返回 `null`
+pub async fn find_prev_blank_line(from_line: usize, whitespace_is_blank: bool) -> Result<Option<usize>, String> {
+    let preview = preview_for(None).await?;
+    with_timeout("Search", smol::unblock(move || preview.find_blank_line(from_line, false, whitespace_is_blank)))
+        .await?
+        .map_err(|e| format!("Search failed: {}", e))
+}
+
+/// 返回活动文件第 `line` 行的原始字节（不解码，最多 `MAX_LINE_BYTES`），用于排查编码问题
+pub async fn read_line_bytes(line: usize) -> Result<Vec<u8>, String> {
+    let preview = preview_for(None).await?;
+    with_timeout("Read", smol::unblock(move || preview.read_line_bytes(line)))
+        .await?
+        .map_err(|e| format!("Failed to read line bytes: {}", e))
+}
+
+/// `get_longest_lines` 最多返回的行数
+const MAX_LONGEST_LINES: usize = 1000;
+
+/// 活动文件中最长的 `n` 行（最多 `MAX_LONGEST_LINES`），见 `LargeFilePreview::longest_lines`。
+/// 返回 `[{"line", "bytes", "chars", "truncated"}]`，`truncated` 表示该行超过 `MAX_LINE_BYTES`，
+/// 在 `read_lines` 中会按截断策略处理。需要读完整个文件，因此不受读取超时限制。
+pub async fn get_longest_lines(n: usize) -> Result<Vec<serde_json::Value>, String> {
+    let preview = preview_for(None).await?;
+    let n = n.min(MAX_LONGEST_LINES);
+    let start_time = std::time::Instant::now();
+    let lines = smol::unblock(move || preview.longest_lines(n))
+        .await
+        .map_err(|e| format!("Failed to scan lines: {}", e))?;
+    info!("get_longest_lines - top {} in {:?}", lines.len(), start_time.elapsed());
+    Ok(lines
+        .into_iter()
+        .map(|(line, bytes, chars)| json!({ "line": line, "bytes": bytes, "chars": chars, "truncated": bytes > MAX_LINE_BYTES as u64 }))
+        .collect())
+}
+
+/// `unique_lines` 最多跟踪的不同行种数，超出后新出现的行不再计入（见 `LargeFilePreview::unique_lines`）
+const MAX_UNIQUE_TRACKED: usize = 2_000_000;
+
+/// `unique_lines` 最多返回的行数
+const MAX_UNIQUE_LINES: usize = 1000;
+
+/// 活动文件（尾部模式下为视图窗口内）中出现最多的 `max_distinct` 种行（最多 `MAX_UNIQUE_LINES`），类似 `sort | uniq -c | sort -rn`。
+/// 返回 `{"lines": [{"text", "count", "first_line"}], "total_lines", "distinct", "untracked_lines", "truncated"}`：
+/// `text` 为该行的样本（最多 `MAX_MATCH_LINE_CHARS` 个字符），`distinct` 为跟踪到的不同行种数，
+/// `truncated` 表示达到了 `MAX_UNIQUE_TRACKED` 种、之后新出现的 `untracked_lines` 行没有计入。
+///
+/// 需要读完整个文件，期间发出 `TASK_PROGRESS_EVENT`（`task` 为 `"unique"`），可用 `cancel_task` 取消；不受读取超时限制
+pub async fn unique_lines<R: Runtime>(app: tauri::AppHandle<R>, max_distinct: usize) -> Result<serde_json::Value, String> {
+    let preview = preview_for(None).await?;
+    let max_distinct = max_distinct.clamp(1, MAX_UNIQUE_LINES);
+    let start_time = std::time::Instant::now();
+    let (top, (total_lines, distinct, untracked)) = smol::unblock(move || -> std::io::Result<_> {
+        let file_len = preview.file_handle.metadata()?.len();
+        let progress = TaskProgress::start(app, "unique", file_len);
+        let (top, stats) = preview.unique_lines(max_distinct, |done| progress.report(done))?;
+        // 按第一次出现的行号读出样本文本
+        let mut lines = Vec::with_capacity(top.len());
+        for (count, first_line) in top {
+            let raw = preview.read_line_bytes(first_line)?;
+            let raw = raw.strip_suffix(b"\r").unwrap_or(&raw[..]);
+            let text: String = decode_bytes(preview.encoding, raw).chars().take(MAX_MATCH_LINE_CHARS).collect();
+            lines.push(json!({ "text": text, "count": count, "first_line": first_line }));
+        }
+        Ok((lines, stats))
+    })
+    .await
+    .map_err(|e| format!("Failed to count distinct lines: {}", e))?;
+    info!("unique_lines - {} distinct of {} lines in {:?}", distinct, total_lines, start_time.elapsed());
+    Ok(json!({
+        "lines": top,
+        "total_lines": total_lines,
+        "distinct": distinct,
+        "untracked_lines": untracked,
+        "truncated": distinct >= MAX_UNIQUE_TRACKED,
+    }))
+}
+
+/// `find_line_by_prefix` 每次探测时为找到可比较的行最多向后看的行数
+const PREFIX_SEARCH_SKIP_LINES: usize = 64;
+
+/// 在活动文件中二分查找第一个行首不小于 `target_prefix` 的行（0 基准），见 `LargeFilePreview::find_line_by_prefix`。
+/// 要求行首的时间戳/前缀单调递增；所有行都更小时返回 `null`
+pub async fn find_line_by_prefix(target_prefix: String) -> Result<Option<usize>, String> {
+    let preview = preview_for(None).await?;
+    require_index(&preview)?;
+    let target = preview.encode_needle(&target_prefix);
+    with_timeout("Search", smol::unblock(move || preview.find_line_by_prefix(&target)))
+        .await?
+        .map_err(|e| format!("Search failed: {}", e))
+}
+
+/// 返回活动文件第 `line` 行（0 基准）按当前编码解码的文本，不含换行符，最多 `MAX_LINE_BYTES` 字节；
+/// 行号超出文件末尾时返回 None。用于跳转前预览目标行
+pub async fn get_line(line: usize) -> Result<Option<String>, String> {
+    let preview = preview_for(None).await?;
+    let encoding = preview.encoding;
+    let bytes = match with_timeout("Read", smol::unblock(move || preview.read_line_bytes(line))).await? {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(format!("Failed to read line: {}", e)),
+    };
+    let text = bytes.strip_suffix(b"\r").unwrap_or(&bytes);
+    Ok(Some(decode_bytes(encoding, text).into_owned()))
+}
+
+/// `get_file_hash` 支持的算法
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HashAlgorithm {
+    /// XXH3-64，非加密哈希，速度接近内存带宽（默认）
+    #[default]
+    Xxh3,
+    /// SHA-256，用于与其它工具给出的校验值比对
+    Sha256,
+}
+
+/// 计算活动文件内容的哈希，返回 `{"algorithm", "digest", "duration_ms"}`（`digest` 为小写十六进制）。
+///
+/// 打开的是压缩文件时，`digest` 为压缩文件本身的哈希（与 `sha256sum app.log.gz` 等工具的结果一致，
+/// 用来确认看的是预期的文件），另外返回解压后内容的哈希 `decompressed_digest`。
+///
+/// 在后台线程经 `stream_file` 按块读取整个文件，每处理一块发出一次 `TASK_PROGRESS_EVENT`（`task` 为 `"hash"`，
+/// 压缩文件的 `total` 为两个文件的大小之和），可用 `cancel_task` 取消。需要读完整个文件，因此不受读取超时限制。
+pub async fn get_file_hash<R: Runtime>(app: tauri::AppHandle<R>, algorithm: HashAlgorithm) -> Result<serde_json::Value, String> {
+    use sha2::Digest;
+
+    let preview = preview_for(None).await?;
+    let start_time = std::time::Instant::now();
+    let (digest, decompressed_digest) = smol::unblock(move || -> std::io::Result<(String, Option<String>)> {
+        let content = preview.file_handle.as_ref();
+        let source = match &preview.compressed {
+            Some(_) => Some(std::fs::File::open(&preview.path)?),
+            None => None,
+        };
+        let source_len = match &source {
+            Some(f) => f.metadata()?.len(),
+            None => 0,
+        };
+        let progress = TaskProgress::start(app, "hash", source_len + content.metadata()?.len());
+        // 依次哈希 `file`，进度从 `done_before` 字节起算
+        let hash = |file: &std::fs::File, done_before: u64| -> std::io::Result<String> {
+            let mut xxh3 = xxhash_rust::xxh3::Xxh3::new();
+            let mut sha256 = sha2::Sha256::new();
+            stream_file(file, 0, STREAM_CHUNK_BYTES, false, |offset, chunk, _| {
+                match algorithm {
+                    HashAlgorithm::Xxh3 => xxh3.update(chunk),
+                    HashAlgorithm::Sha256 => sha256.update(chunk),
+                }
+                progress.report(done_before + offset + chunk.len() as u64)?;
+                Ok(true)
+            })?;
+            Ok(match algorithm {
+                HashAlgorithm::Xxh3 => format!("{:016x}", xxh3.digest()),
+                HashAlgorithm::Sha256 => sha256.finalize().iter().map(|b| format!("{:02x}", b)).collect(),
+            })
+        };
+        match &source {
+            Some(source) => Ok((hash(source, 0)?, Some(hash(content, source_len)?))),
+            None => Ok((hash(content, 0)?, None)),
+        }
+    })
+    .await
+    .map_err(|e| format!("Failed to hash file: {}", e))?;
+    let mut out = json!({
+        "algorithm": algorithm,
+        "digest": digest,
+        "duration_ms": start_time.elapsed().as_millis()
+    });
+    if let Some(d) = decompressed_digest {
+        out["decompressed_digest"] = json!(d);
+    }
+    Ok(out)
+}
+
+/// `process_file` 不写目标文件时发出的分块事件，payload 为 `{"offset", "text"}`，`offset` 是该块在文件内的字节偏移
+pub const PROCESS_CHUNK_EVENT: &str = "large-file-preview://process-chunk";
+
+/// `process_file` 通过事件发出的每块大小（事件经 IPC 传给前端，不宜过大）
+const PROCESS_CHUNK_BYTES: u64 = 1024 * 1024;
+
+/// 以有界内存把活动文件从 `base_byte`（与 `read_lines` / `mmap_search` 相同，尾部模式下是显示的第一行）到末尾流式处理一遍，供自定义转换使用：
+/// 给出 `dest_path` 时原样写入该文件；否则按行对齐的块（约 `PROCESS_CHUNK_BYTES`）依次发出
+/// `PROCESS_CHUNK_EVENT`，文本按当前编码解码。返回 `{"bytes", "chunks", "duration_ms"}`。
+pub async fn process_file<R: Runtime>(app: tauri::AppHandle<R>, dest_path: Option<String>) -> Result<serde_json::Value, String> {
+    use std::io::Write;
+    use tauri::Emitter;
+
+    let preview = preview_for(None).await?;
+    let start_time = std::time::Instant::now();
+    let (bytes, chunks) = smol::unblock(move || -> std::io::Result<(u64, usize)> {
+        let file = preview.file_handle.as_ref();
+        let mut chunks = 0usize;
+        let end = match dest_path {
+            Some(dest) => {
+                let dest = PathBuf::from(dest);
+                // 目标不能是正在读取的文件本身，否则 `File::create` 会把它截断
+                if dest.exists() && std::fs::canonicalize(&dest).ok() == std::fs::canonicalize(&preview.path).ok() {
+                    return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "destination is the file being processed"));
+                }
+                let mut out = std::io::BufWriter::new(std::fs::File::create(&dest)?);
+                let end = stream_file(file, preview.base_byte, STREAM_CHUNK_BYTES, false, |_, chunk, _| {
+                    out.write_all(chunk)?;
+                    chunks += 1;
+                    Ok(true)
+                })?;
+                out.flush()?;
+                end
+            }
+            None => stream_file(file, preview.base_byte, PROCESS_CHUNK_BYTES, true, |offset, chunk, _| {
+                let text = decode_bytes(preview.encoding, chunk);
+                chunks += 1;
+                // 前端已经关闭监听时没有必要继续读下去
+                Ok(app.emit(PROCESS_CHUNK_EVENT, json!({ "offset": offset, "text": text })).is_ok())
+            })?,
+        };
+        // `stream_file` 返回处理到的文件内位置
+        Ok((end - preview.base_byte, chunks))
+    })
+    .await
+    .map_err(|e| format!("Failed to process file: {}", e))?;
+    Ok(json!({
+        "bytes": bytes,
+        "chunks": chunks,
+        "duration_ms": start_time.elapsed().as_millis()
+    }))
+}
+
+/// 单次 `get_line_offsets` 最多查询的行数
+const MAX_LINE_OFFSET_QUERY: usize = 10_000;
+
+/// 一次返回活动文件中多行（行号 0 基准）的起始字节偏移，供行号区按偏移显示时批量获取
+pub async fn get_line_offsets(lines: Vec<usize>) -> Result<Vec<u64>, String> {
+    if lines.len() > MAX_LINE_OFFSET_QUERY {
+        return Err(format!("At most {} lines per request", MAX_LINE_OFFSET_QUERY));
+    }
+    let preview = preview_for(None).await?;
+    with_timeout("Read", smol::unblock(move || preview.line_offsets(&lines)))
+        .await?
+        .map_err(|e| format!("Failed to get line offsets: {}", e))
+}
+
+/// 返回活动文件中 `[start, start + count)` 各行的起始字节偏移，供宿主把屏幕上的行对应到文件中的精确位置。
+/// 从最近的索引点定位到 `start` 后顺序扫描换行（见 `LargeFilePreview::line_offsets`）；
+/// 超出文件末尾的行不返回，`count` 最多 `MAX_LINE_OFFSET_QUERY`
+pub async fn line_offsets_in_range(start: usize, count: usize) -> Result<Vec<u64>, String> {
+    if count > MAX_LINE_OFFSET_QUERY {
+        return Err(format!("At most {} lines per request", MAX_LINE_OFFSET_QUERY));
+    }
+    let preview = preview_for(None).await?;
+    // 未建立索引时总行数只是估计值，按它截断会漏掉或多出行
+    require_index(&preview)?;
+    let end = start.saturating_add(count).min(preview.total_lines);
+    let lines: Vec<usize> = (start..end).collect();
+    with_timeout("Read", smol::unblock(move || preview.line_offsets(&lines)))
+        .await?
+        .map_err(|e| format!("Failed to get line offsets: {}", e))
+}
+
+/// 返回活动文件中字节偏移所在行及上下文：`{"line", "first_line", "text_lines", "offset_in_line"}`，
+/// 供按字节偏移跳转的外部工具一次取得定位结果与周围内容
+pub async fn context_at_offset(byte_offset: u64, before: usize, after: usize) -> Result<serde_json::Value, String> {
+    let preview = preview_for(None).await?;
+    let (line, first_line, text_lines, offset_in_line) = with_timeout("Read", smol::unblock(move || preview.context_at_offset(byte_offset, before, after)))
+        .await?
+        .map_err(|e| format!("Failed to read context: {}", e))?;
+    Ok(json!({
+        "line": line,
+        "first_line": first_line,
+        "text_lines": text_lines,
+        "offset_in_line": offset_in_line
+    }))
+}
+
+/// 搜索活动文件，见 `LargeFilePreview::mmap_search`。
+///
+/// 结果缓存在 `LargeFilePreview::last_search` 中：文件未变化时再次以相同的关键字、大小写方式与选项搜索，
+/// 直接返回缓存的结果（带 `"cached": true`），不重新扫描。`clear_search_cache` 可手动清除
+pub async fn mmap_search(needle: String, case: CaseSensitivity, options: SearchOptions) -> Result<serde_json::Value, String> {
+    // 在后台线程中搜索，超时返回时不会一直占用 `LARGE_FILE_PREVIEW` 的锁
+    let preview = preview_for(None).await?;
+    let path = preview.path.to_string_lossy().to_string();
+    let key = search_cache_key(&preview);
+    let query = (needle.clone(), case, options.clone());
+    let last_search = preview.last_search.clone();
+    if let Ok(guard) = last_search.lock() {
+        if let Some(cached) = guard.as_ref().filter(|c| c.key == key && c.query == query) {
+            info!("mmap_search - returning cached result for needle_len={}", needle.len());
+            session::note_search(&path, &needle);
+            let mut out = cached.result.clone();
+            out["cached"] = json!(true);
+            return Ok(out);
+        }
+    }
+    let needle_bytes = preview.encode_needle(&needle);
+    let interval = preview.index_interval;
+    let result = with_timeout("Search", smol::unblock(move || preview.mmap_search(&needle_bytes, case, &options)))
+        .await?
+        .map_err(|e| format!("Search failed: {}", e))?;
+    session::note_search(&path, &needle);
+
+    let duration_ms = result.duration.as_millis();
+    let first_match_json = if let Some((line, col, len)) = result.first_match {
+        Some(json!({"line": line, "column": col, "length": len}))
+    } else {
+        None
+    };
+
+    let mut out = json!({
+        "count": result.count,
+        "samples": result.samples,
+        "matches": result.matches,
+        "duration_ms": duration_ms,
+        "file_len": result.file_len,
+        "extra_alloc_bytes": result.extra_alloc,
+        "first_match": first_match_json
+    });
+    // 直方图为 opt-in 字段，未请求时不出现在返回值中
+    if let Some(histogram) = result.line_histogram {
+        out["line_histogram"] = json!(histogram);
+        out["histogram_truncated"] = json!(result.histogram_truncated);
+    }
+    if let Some(regions) = result.regions {
+        let n = regions.len() as f64;
+        out["regions"] = regions
+            .iter()
+            .enumerate()
+            .map(|(i, &count)| json!({"range_start_pct": i as f64 * 100.0 / n, "count": count}))
+            .collect::<serde_json::Value>();
+    }
+    // 索引点区域可能很多，只返回有匹配的区域
+    if let Some(checkpoints) = result.checkpoint_regions {
+        out["index_interval"] = json!(interval);
+        out["checkpoint_regions"] = checkpoints
+            .iter()
+            .enumerate()
+            .filter(|(_, &count)| count > 0)
+            .map(|(k, &count)| json!({"start_line": k * interval, "count": count}))
+            .collect::<serde_json::Value>();
+    }
+    if let Ok(mut guard) = last_search.lock() {
+        *guard = Some(LastSearch {
+            key,
+            query,
+            match_lines: result.matches.iter().filter_map(|m| m["line"].as_u64().map(|l| l as usize)).collect(),
+            result: out.clone(),
+        });
+    }
+    Ok(out)
+}
+
+/// 清除活动文件缓存的搜索结果（见 `mmap_search`），之后的搜索总是重新扫描，`get_match_line` 需要重新搜索。
+/// 没有打开文件时什么也不做
+pub async fn clear_search_cache() -> Result<(), String> {
+    if let Some(preview) = LARGE_FILE_PREVIEW.lock().await.as_ref() {
+        if let Ok(mut guard) = preview.last_search.lock() {
+            *guard = None;
+        }
+    }
+    Ok(())
+}
+
+/// 最近一次 `mmap_search` 返回的第 `match_index` 个匹配（0 基准，对应返回值 `matches` 中的序号）所在的行，
+/// 返回 `{"line", "text"}`，`text` 按当前编码解码、去掉换行符。
+/// 还没有搜索过、或之后编码/尾部窗口/文件内容变化导致缓存失效时返回错误，需要重新搜索
+pub async fn get_match_line(match_index: usize) -> Result<serde_json::Value, String> {
+    let preview = preview_for(None).await?;
+    let key = search_cache_key(&preview);
+    let line = {
+        let guard = preview.last_search.lock().map_err(|_| "Search cache is poisoned".to_string())?;
+        let cached = match guard.as_ref() {
+            Some(cached) if cached.key == key => cached,
+            Some(_) => return Err("The file changed since the last search, search again".to_string()),
+            None => return Err("No search results".to_string()),
+        };
+        *cached.match_lines.get(match_index).ok_or_else(|| {
+            format!("Match index {} out of range ({} matches)", match_index, cached.match_lines.len())
+        })?
+    };
+    let encoding = preview.encoding;
+    let bytes = with_timeout("Read", smol::unblock(move || preview.read_line_bytes(line)))
+        .await?
+        .map_err(|e| format!("Failed to read line: {}", e))?;
+    let raw = bytes.strip_suffix(b"\r").unwrap_or(&bytes);
+    Ok(json!({"line": line, "text": decode_bytes(encoding, raw)}))
+}
+
+/// `verify_index` 最多报告的不一致索引点个数
+const MAX_INDEX_MISMATCHES: usize = 100;
+
+/// 诊断用：重新扫描活动文件并核对行索引，返回
+/// `{"ok", "total_lines", "actual_total_lines", "index_len", "mismatches": [{"index", "line", "stored", "actual"}], "duration_ms"}`。
+/// 需要读完整个文件，较慢，不受读取超时限制
+pub async fn verify_index() -> Result<serde_json::Value, String> {
+    let preview = preview_for(None).await?;
+    require_index(&preview)?;
+    let total_lines = preview.total_lines;
+    let index_len = preview.index.len();
+    let start_time = std::time::Instant::now();
+    let (actual_total, mismatches) = smol::unblock(move || preview.verify_index())
+        .await
+        .map_err(|e| format!("Failed to verify index: {}", e))?;
+    if !mismatches.is_empty() {
+        warn!("verify_index - {} mismatching index points, first: {:?}", mismatches.len(), mismatches[0]);
+    }
+    Ok(json!({
+        "ok": mismatches.is_empty() && actual_total == total_lines,
+        "total_lines": total_lines,
+        "actual_total_lines": actual_total,
+        "index_len": index_len,
+        "mismatches": mismatches.iter().map(|(k, line, stored, actual)| json!({
+            "index": k,
+            "line": line,
+            "stored": stored,
+            "actual": actual
+        })).collect::<Vec<_>>(),
+        "duration_ms": start_time.elapsed().as_millis()
+    }))
+}
+
+/// `export_index` 写出的索引文件开头的 8 字节标识，最后一个字节为格式版本。
+///
+/// 文件格式（所有整数均为小端 u64）：
+///
+/// | 偏移 | 内容 |
+/// |------|------|
+/// | 0    | `INDEX_FILE_MAGIC` |
+/// | 8    | `index_interval`：索引间隔（行数） |
+/// | 16   | `total_lines`：总行数 |
+/// | 24   | `file_size`：建立索引时的文件大小，读取方可用来判断索引是否过期 |
+/// | 32   | `count`：索引点个数 |
+/// | 40   | `count` 个偏移：第 k 个为第 `(k + 1) * index_interval` 行（0 基准）起始处的字节偏移 |
+///
+/// 第 0 行总是从偏移 0 开始，因此不写出
+pub const INDEX_FILE_MAGIC: &[u8; 8] = b"LFPIDX\0\x01";
+
+/// 把活动文件的行索引导出到 `dest_path`，供其他工具复用打开时扫描的结果。
+///
+/// 返回 `{"path", "bytes", "index_interval", "total_lines", "index_len"}`
+pub async fn export_index(dest_path: String) -> Result<serde_json::Value, String> {
+    let preview = preview_for(None).await?;
+    require_index(&preview)?;
+    let dest = PathBuf::from(&dest_path);
+    let (interval, total_lines, index_len) = (preview.index_interval, preview.total_lines, preview.index.len());
+    let bytes = smol::unblock(move || preview.export_index(&dest))
+        .await
+        .map_err(|e| format!("Failed to export index: {}", e))?;
+    info!("export_index - wrote {} points ({} bytes) to {}", index_len, bytes, dest_path);
+    Ok(json!({
+        "path": dest_path,
+        "bytes": bytes,
+        "index_interval": interval,
+        "total_lines": total_lines,
+        "index_len": index_len
+    }))
+}
+
+/// 索引建立完成（`reindex`，包括 `get_total_lines_exact` 触发的）时发出，payload 为 `{"handle", "total_lines", "exact"}`，
+/// 前端据此把快速打开时显示的“约 N 行”换成精确值
+pub const INDEX_COMPLETE_EVENT: &str = "large-file-preview://index-complete";
+
+/// 以新的间隔重建活动文件的行索引（间隔越小跳转越快、内存占用越大），返回新的索引长度；完成时发出 `INDEX_COMPLETE_EVENT`
+pub async fn reindex<R: Runtime>(app: tauri::AppHandle<R>, new_interval: usize) -> Result<usize, String> {
+    use tauri::Emitter;
+
+    if !(MIN_INDEX_INTERVAL..=MAX_INDEX_INTERVAL).contains(&new_interval) {
+        return Err(format!("Index interval must be between {} and {}", MIN_INDEX_INTERVAL, MAX_INDEX_INTERVAL));
+    }
+    let mut preview = preview_for(None).await?;
+    let handle = preview.handle;
+    let preview = smol::unblock(move || preview.reindex(new_interval).map(|_| preview))
+        .await
+        .map_err(|e| format!("Reindex failed: {}", e))?;
+    let mut preview_guard = LARGE_FILE_PREVIEW.lock().await;
+    // 只替换索引相关字段，期间修改的编码/截断策略等设置保持不变
+    match preview_guard.as_mut() {
+        Some(active) if active.handle == handle => {
+            active.total_lines = preview.total_lines;
+            active.index = preview.index;
+            active.index_interval = preview.index_interval;
+            active.indexed = preview.indexed;
+            active.cached_window = preview.cached_window;
+            // 缓存的 `checkpoint_regions` 按旧的间隔划分
+            if let Ok(mut guard) = active.last_search.lock() {
+                *guard = None;
+            }
+            info!("reindex - interval={}, index.len()={}", active.index_interval, active.index.len());
+            let _ = app.emit(INDEX_COMPLETE_EVENT, json!({ "handle": handle, "total_lines": active.total_lines, "exact": active.indexed }));
+            Ok(active.index.len())
+        }
+        _ => Err("The active file changed during reindex".to_string()),
+    }
+}
+
+/// 跟随模式下检查活动文件是否增长的间隔
+const FOLLOW_POLL_MS: u64 = 1000;
+
+/// 每次 `set_follow` 加一；轮询任务发现代数变化后退出，保证同时只有一个在运行
+static FOLLOW_GENERATION: AtomicU32 = AtomicU32::new(0);
+
+/// 跟随模式下活动文件追加了内容时发出，payload 为 `{"handle", "total_lines", "size"}`
+pub const APPENDED_EVENT: &str = "large-file-preview://appended";
+
+/// 开启或关闭跟随模式（类似 `tail -f`）：开启后每 `FOLLOW_POLL_MS` 检查一次活动文件的大小，
+/// 增长时补全索引（见 `LargeFilePreview::extend_index`）并发出 `APPENDED_EVENT`，前端据此更新总行数并滚到末尾。
+/// 压缩文件打开的是解压后的临时文件，不会增长，跳过
+pub async fn set_follow<R: Runtime>(app: tauri::AppHandle<R>, enabled: bool) -> Result<(), String> {
+    let generation = FOLLOW_GENERATION.fetch_add(1, Ordering::Relaxed) + 1;
+    info!("set_follow - enabled={}", enabled);
+    if !enabled {
+        return Ok(());
+    }
+    tauri::async_runtime::spawn(async move {
+        // 上一次检查时的 (句柄, 文件大小)；切换活动文件后重新记录
+        let mut last: Option<(u32, u64)> = None;
+        loop {
+            smol::Timer::after(std::time::Duration::from_millis(FOLLOW_POLL_MS)).await;
+            if FOLLOW_GENERATION.load(Ordering::Relaxed) != generation {
+                break;
+            }
+            if let Err(e) = follow_tick(&app, &mut last).await {
+                warn!("set_follow - {}", e);
+            }
+        }
+    });
+    Ok(())
+}
+
+async fn follow_tick<R: Runtime>(app: &tauri::AppHandle<R>, last: &mut Option<(u32, u64)>) -> Result<(), String> {
+    use tauri::Emitter;
+
+    let Ok(mut preview) = preview_for(None).await else { return Ok(()) };
+    if preview.compressed.is_some() {
+        return Ok(());
+    }
+    let handle = preview.handle;
+    let size = preview.file_handle.as_ref().metadata().map_err(|e| e.to_string())?.len();
+    let changed = match *last {
+        Some((h, s)) => h != handle || s != size,
+        None => true,
+    };
+    if !changed {
+        return Ok(());
+    }
+    let old_total = preview.total_lines;
+    let preview = smol::unblock(move || preview.extend_index().map(|_| preview))
+        .await
+        .map_err(|e| format!("Failed to extend index: {}", e))?;
+    let mut preview_guard = LARGE_FILE_PREVIEW.lock().await;
+    match preview_guard.as_mut() {
+        Some(active) if active.handle == handle => {
+            active.total_lines = preview.total_lines;
+            active.index = preview.index;
+            active.cached_window = preview.cached_window;
+            // 第一次检查只记录大小；之后大小变化（包括最后一行变长但行数不变）都通知前端
+            let notify = last.map_or(active.total_lines != old_total, |(h, _)| h == handle);
+            *last = Some((handle, size));
+            if notify {
+                let _ = app.emit(APPENDED_EVENT, json!({ "handle": handle, "total_lines": active.total_lines, "size": size }));
+            }
+        }
+        _ => *last = None,
+    }
+    Ok(())
+}
+
+/// 开始监视目录 `dir`，其中最新的匹配 `pattern` 的文件变化时发出 `watch::DIR_NEWEST_EVENT`，
+/// 前端据此打开轮转后的新日志。返回当前最新的匹配文件，详见 `watch::start_watch_dir`
+pub async fn start_watch_dir<R: Runtime>(app: tauri::AppHandle<R>, dir: String, pattern: String) -> Result<Option<String>, String> {
+    #[cfg(target_os = "android")]
+    {
+        let _ = (app, dir, pattern);
+        Err("Watching directories is not supported on Android".to_string())
+    }
+    #[cfg(not(target_os = "android"))]
+    {
+        smol::unblock(move || crate::watch::start_watch_dir(app, dir, pattern)).await
+    }
+}
+
+/// 停止 `start_watch_dir` 开始的监视
+pub async fn stop_watch_dir() -> Result<(), String> {
+    #[cfg(not(target_os = "android"))]
+    crate::watch::stop_watch_dir();
+    Ok(())
+}
+
+/// 尾部模式下 `max_mb` 的单位
+const TAIL_WINDOW_UNIT: u64 = 1024 * 1024;
+
+/// 只显示活动文件最后 `max_mb` MB（`None` 恢复显示整个文件），见 `LargeFilePreview::set_tail_window`。
+///
+/// 只扫描窗口内的内容，对只关心最近日志的超大文件比完整建立索引快得多。返回
+/// `{"base_byte", "total_lines", "size"}`，行号从窗口的第一行起算，前端应重新加载视图
+pub async fn set_tail_window(max_mb: Option<u64>) -> Result<serde_json::Value, String> {
+    if max_mb == Some(0) {
+        return Err("Tail window must be at least 1 MB".to_string());
+    }
+    let mut preview = preview_for(None).await?;
+    let handle = preview.handle;
+    let preview = smol::unblock(move || preview.set_tail_window(max_mb.map(|mb| mb.saturating_mul(TAIL_WINDOW_UNIT))).map(|_| preview))
+        .await
+        .map_err(|e| format!("Failed to set tail window: {}", e))?;
+    let size = preview.file_handle.as_ref().metadata().map(|m| m.len()).unwrap_or(0);
+    let mut preview_guard = LARGE_FILE_PREVIEW.lock().await;
+    // 与 `reindex` 相同，只替换索引相关字段
+    match preview_guard.as_mut() {
+        Some(active) if active.handle == handle => {
+            active.base_byte = preview.base_byte;
+            active.total_lines = preview.total_lines;
+            active.index = preview.index;
+            active.indexed = preview.indexed;
+            active.cached_window = preview.cached_window;
+            Ok(json!({
+                "base_byte": active.base_byte,
+                "total_lines": active.total_lines,
+                "size": size
+            }))
+        }
+        _ => Err("The active file changed while setting the tail window".to_string()),
+    }
+}
+
+/// 设置当前打开文件的超长行处理策略
+pub async fn set_truncation_policy(policy: TruncationPolicy) -> Result<(), String> {
+    let mut preview_guard = LARGE_FILE_PREVIEW.lock().await;
+    let preview = preview_guard.as_mut()
+        .ok_or("No file is currently opened")?;
+    preview.truncation_policy = policy;
+    session::note_file_setting(&preview.path.to_string_lossy(), FILE_SETTING_TRUNCATION, json!(policy));
+    info!("Truncation policy set to {:?}", policy);
+    Ok(())
+}
+
+/// 用各候选编码解码活动文件开头（最多 `ENCODING_PREVIEW_BYTES` 字节）的前 `sample_lines` 行，
+/// 供用户在编码无法确定时挑选。返回 `[{"encoding", "sample_text", "had_errors"}]`
+pub async fn preview_encodings(sample_lines: usize) -> Result<Vec<serde_json::Value>, String> {
+    let preview = preview_for(None).await?;
+    let size = preview.file_handle.as_ref().metadata().map(|m| m.len()).unwrap_or(0);
+    let sample_len = size.min(ENCODING_PREVIEW_BYTES as u64) as usize;
+    if sample_len == 0 {
+        return Ok(Vec::new());
+    }
+    let sample = unsafe { MmapOptions::new().len(sample_len).map(preview.file_handle.as_ref()) }
+        .map_err(|e| format!("Failed to read sample: {}", e))?;
+    let sample_lines = sample_lines.max(1);
+    Ok(CANDIDATE_ENCODINGS
+        .iter()
+        .filter_map(|label| Encoding::for_label(label.as_bytes()))
+        .map(|enc| {
+            // 带 BOM 的编码需要识别 BOM，其余按原样解码
+            let (text, had_errors) = enc.decode_with_bom_removal(&sample);
+            let sample_text: Vec<&str> = text.lines().take(sample_lines).collect();
+            json!({
+                "encoding": enc.name().to_lowercase(),
+                "sample_text": sample_text.join("\n"),
+                "had_errors": had_errors
+            })
+        })
+        .collect())
+}
+
+/// `detect_format` 识别出的文件格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileFormat {
+    Log,
+    Csv,
+    Jsonl,
+    Plain,
+}
+
+/// `detect_format` 最多采样的非空行数（字节数与打开时相同，为 `FORMAT_SAMPLE_BYTES`，只看文件开头，不扫描整个文件）
+const FORMAT_SAMPLE_LINES: usize = 200;
+
+// 行首附近出现这些词时视为日志级别
+const LOG_LEVEL_TOKENS: [&str; 8] = ["TRACE", "DEBUG", "INFO", "WARN", "WARNING", "ERROR", "FATAL", "CRITICAL"];
+
+/// 行首（可带 `[`）是否为 `YYYY-MM-DD` / `YYYY/MM/DD` 日期或 `HH:MM:SS` 时间
+fn starts_with_timestamp(line: &str) -> bool {
+    let b = line.trim_start_matches('[').as_bytes();
+    let matches = |pattern: &[u8]| {
+        b.len() >= pattern.len()
+            && pattern.iter().zip(b).all(|(p, c)| match p {
+                b'd' => c.is_ascii_digit(),
+                b'-' => *c == b'-' || *c == b'/',
+                _ => p == c,
+            })
+    };
+    matches(b"dddd-dd-dd") || matches(b"dd:dd:dd")
+}
+
+/// 行首 64 字节内是否有独立的日志级别词（如 `INFO`、`[ERROR]`）
+fn has_level_token(line: &str) -> bool {
+    let mut end = line.len().min(64);
+    while !line.is_char_boundary(end) {
+        end -= 1;
+    }
+    line[..end]
+        .split(|c: char| !c.is_ascii_alphabetic())
+        .any(|w| LOG_LEVEL_TOKENS.contains(&w))
+}
+
+/// 引号外的逗号个数（CSV 字段中可以用双引号包含逗号）
+fn csv_field_separators(line: &str) -> usize {
+    let mut in_quotes = false;
+    let mut count = 0;
+    for c in line.bytes() {
+        match c {
+            b'"' => in_quotes = !in_quotes,
+            b',' if !in_quotes => count += 1,
+            _ => {}
+        }
+    }
+    count
+}
+
+/// 按采样行判断格式，返回 `(格式, 置信度 0..=1)`。依次检查：
+/// 每行都能解析为 JSON 对象/数组 → jsonl；多数行带日志级别 → log；
+/// 各行逗号数一致 → csv；多数行以时间戳开头 → log；否则 plain。
+fn classify_format(lines: &[&str]) -> (FileFormat, f64) {
+    if lines.is_empty() {
+        return (FileFormat::Plain, 0.0);
+    }
+    let n = lines.len() as f64;
+    let fraction = |f: &dyn Fn(&str) -> bool| lines.iter().filter(|&&l| f(l)).count() as f64 / n;
 
-        let mut count = 0usize;
-        let mut samples = Vec::new();
-        let mut matches_pos: Vec<serde_json::Value> = Vec::new();
-        let max_matches_return = 1000usize;
-        let mut start = 0usize;
-        let mut first_match: Option<(usize, usize, usize)> = None;
-        // 遍历所有匹配位置，收集样例行并记录第一次匹配的行/列信息
-        while let Some(pos) = memmem::find(&hay[start..], &needle_used) {
-            let abs = start + pos;
-            if first_match.is_none() {
-                let ln = hay[..abs].iter().filter(|&&b| b == b'\n').count();
-                let line_start = hay[..abs]
-                    .iter()
-                    .rposition(|&b| b == b'\n')
-                    .map(|p| p + 1)
-                    .unwrap_or(0);
-                let col_chars = std::str::from_utf8(&hay_orig[line_start..abs])
-                    .map(|s| s.chars().count())
-                    .unwrap_or(0usize);
-                let match_len_chars = std::str::from_utf8(&needle_used)
-                    .map(|s| s.chars().count())
-                    .unwrap_or(needle_used.len());
-                first_match = Some((ln, col_chars, match_len_chars));
-            }
-            // record this match's position (line, column, length) up to the configured cap
-            if matches_pos.len() < max_matches_return {
-                let ln = hay[..abs].iter().filter(|&&b| b == b'\n').count();
-                let line_start = hay[..abs]
-                    .iter()
-                    .rposition(|&b| b == b'\n')
-                    .map(|p| p + 1)
-                    .unwrap_or(0);
-                let col_chars = std::str::from_utf8(&hay_orig[line_start..abs])
-                    .map(|s| s.chars().count())
-                    .unwrap_or(0usize);
-                let match_len_chars = std::str::from_utf8(&needle_used)
-                    .map(|s| s.chars().count())
-                    .unwrap_or(needle_used.len());
-                matches_pos.push(json!({"line": ln, "column": col_chars, "length": match_len_chars}));
+    let json = fraction(&|l: &str| {
+        let t = l.trim();
+        (t.starts_with('{') || t.starts_with('[')) && serde_json::from_str::<serde_json::Value>(t).is_ok()
+    });
+    if json >= 0.9 {
+        return (FileFormat::Jsonl, json);
+    }
+    let level = fraction(&has_level_token);
+    let timestamp = fraction(&starts_with_timestamp);
+    if level >= 0.5 {
+        return (FileFormat::Log, level.max(timestamp));
+    }
+    if lines.len() >= 2 {
+        let mut counts: HashMap<usize, usize> = HashMap::new();
+        for l in lines {
+            *counts.entry(csv_field_separators(l)).or_default() += 1;
+        }
+        if let Some((&commas, &same)) = counts.iter().max_by_key(|&(_, &c)| c) {
+            let csv = same as f64 / n;
+            if commas > 0 && csv >= 0.8 {
+                return (FileFormat::Csv, csv);
             }
-            let line_start = hay[..abs]
-                .iter()
-                .rposition(|&b| b == b'\n')
-                .map(|p| p + 1)
-                .unwrap_or(0);
-            let line_end = hay[abs..]
-                .iter()
-                .position(|&b| b == b'\n')
-                .map(|p| abs + p)
-                .unwrap_or(hay.len());
-            if let Ok(s) = std::str::from_utf8(&hay_orig[line_start..line_end]) {
-                if samples.len() < 5 {
-                    samples.push(s.to_string());
-                }
+        }
+    }
+    if timestamp >= 0.6 {
+        return (FileFormat::Log, timestamp);
+    }
+    (FileFormat::Plain, 1.0 - json.max(level).max(timestamp))
+}
+
+/// 采样活动文件开头（最多 `FORMAT_SAMPLE_BYTES` 字节、`FORMAT_SAMPLE_LINES` 个非空行）猜测文件格式，
+/// 返回 `{"kind": "log"|"csv"|"jsonl"|"plain", "confidence"}`，供前端建议合适的显示方式
+pub async fn detect_format() -> Result<serde_json::Value, String> {
+    let preview = preview_for(None).await?;
+    let size = preview.file_handle.as_ref().metadata().map(|m| m.len()).unwrap_or(0);
+    let sample_len = size.min(FORMAT_SAMPLE_BYTES as u64) as usize;
+    let (kind, confidence) = if sample_len == 0 {
+        (FileFormat::Plain, 0.0)
+    } else {
+        let sample = unsafe { MmapOptions::new().len(sample_len).map(preview.file_handle.as_ref()) }
+            .map_err(|e| format!("Failed to read sample: {}", e))?;
+        let mut bytes = &sample[..];
+        // 采样截在文件中间时丢掉最后一个不完整的行
+        if (sample_len as u64) < size {
+            if let Some(i) = memchr::memrchr(b'\n', bytes) {
+                bytes = &bytes[..i];
             }
-            count += 1;
-            start = abs + needle_used.len();
         }
+        let text = decode_bytes(preview.encoding, bytes);
+        let lines: Vec<&str> = text.lines().filter(|l| !l.trim().is_empty()).take(FORMAT_SAMPLE_LINES).collect();
+        classify_format(&lines)
+    };
+    Ok(json!({
+        "kind": kind,
+        "confidence": (confidence * 100.0).round() / 100.0
+    }))
+}
 
-        let dur = start_time.elapsed();
-        Ok((count, samples, dur, extra_alloc, first_match, matches_pos))
+/// 解析可用于读取的编码标签，未知编码与 UTF-16 返回错误
+fn reading_encoding(label: &str) -> Result<&'static Encoding, String> {
+    let enc = Encoding::for_label(label.trim().as_bytes())
+        .ok_or_else(|| format!("Unknown encoding: {}", label))?;
+    if enc == UTF_16LE || enc == UTF_16BE {
+        return Err(format!("{} is not supported for reading yet", enc.name()));
     }
+    Ok(enc)
 }
 
-// 定义返回给前端的结果结构体
-#[derive(Serialize)]
-pub struct FileInfo {
-    pub uri: String,
-    // 或者其他元数据，如文件大小等
+/// 按 `encoding` 解码活动文件从 `start` 起的 `count` 行，不改变活动文件的读取编码。
+///
+/// 用于编码选择对话框中预览当前位置的内容；编码限制与 `set_encoding` 相同。
+pub async fn read_lines_with_encoding(start: usize, count: usize, encoding: String) -> Result<String, String> {
+    let enc = reading_encoding(&encoding)?;
+    // preview_for 返回的是副本，只修改副本的编码
+    let mut preview = preview_for(None).await?;
+    preview.encoding = enc;
+    with_timeout("Read", preview.read_lines(start, count)).await?
+        .map_err(|e| format!("Failed to read lines: {}", e))
 }
 
-// 全局缓存用于存储打开的 LargeFilePreview
-static LARGE_FILE_PREVIEW: Lazy<Arc<AsyncMutex<Option<LargeFilePreview>>>> = 
-    Lazy::new(|| Arc::new(AsyncMutex::new(None)));
+/// 设置活动文件的读取编码（标签按 WHATWG 规范解析，如 "gbk"、"shift_jis"）。
+///
+/// 行索引按 `\n` 字节建立，UTF-16 中换行占两个字节，因此暂不支持 UTF-16（仅用于预览判断字节序）。
+pub async fn set_encoding(encoding: String) -> Result<String, String> {
+    let enc = reading_encoding(&encoding)?;
+    let mut preview_guard = LARGE_FILE_PREVIEW.lock().await;
+    let preview = preview_guard.as_mut()
+        .ok_or("No file is currently opened")?;
+    preview.encoding = enc;
+    session::note_file_setting(&preview.path.to_string_lossy(), FILE_SETTING_ENCODING, json!(enc.name().to_lowercase()));
+    info!("Encoding set to {}", enc.name());
+    Ok(enc.name().to_lowercase())
+}
 
-// 插件状态管理结构（如果需要）
-// PluginState removed — not currently used
+/// 复制与导出共用的“输出换行符”设置的键，值为 `LineEnding` 的序列化形式（"preserve" / "lf" / "crlf"），由前端经 `set_setting` 保存
+pub const OUTPUT_LINE_ENDING_SETTING: &str = "output_line_ending";
 
-pub async fn get_total_lines() -> Result<usize, String> {
-    // debug!("get_total_lines command invoked");
-    let preview_guard = LARGE_FILE_PREVIEW.lock()
-        .await;
-    let preview = preview_guard.as_ref()
-        .ok_or("No file is currently opened")?;
-    let lines = preview.total_lines();
-    info!("Total lines: {}", lines);
-    Ok(lines)
+/// 读取全局的输出换行符设置；未设置或无法识别时为 `Preserve`，即保持源文件的换行
+pub fn output_line_ending<R: Runtime>(app: &tauri::AppHandle<R>) -> LineEnding {
+    session::setting(app, OUTPUT_LINE_ENDING_SETTING)
+        .and_then(|v| serde_json::from_value(v).ok())
+        .unwrap_or_default()
 }
 
-/// 返回当前打开文件的字节大小（若没有打开文件，返回 0）
-pub async fn get_file_size() -> Result<usize, String> {
-    // debug!("get_file_size command invoked");
-    let preview_guard = LARGE_FILE_PREVIEW.lock().await;
-    if let Some(preview) = preview_guard.as_ref() {
-        // 尝试通过 file handle 获取元数据
-        match preview.file_handle.as_ref().metadata() {
-            Ok(meta) => Ok(meta.len() as usize),
-            Err(e) => Err(format!("Failed to read file metadata: {}", e)),
+/// 按文件设置中由插件应用的两项：读取编码与超长行策略（见 `session::SessionState::settings`）
+pub const FILE_SETTING_ENCODING: &str = "encoding";
+pub const FILE_SETTING_TRUNCATION: &str = "truncation_policy";
+
+/// 打开文件时应用该文件保存的编码与超长行策略；无法识别的值记日志后忽略，保持默认
+fn apply_file_settings(preview: &mut LargeFilePreview, settings: &std::collections::BTreeMap<String, serde_json::Value>) {
+    if let Some(label) = settings.get(FILE_SETTING_ENCODING).and_then(|v| v.as_str()) {
+        match reading_encoding(label) {
+            Ok(enc) => preview.encoding = enc,
+            Err(e) => warn!("open - ignoring saved encoding for {:?}: {}", preview.path, e),
+        }
+    }
+    if let Some(v) = settings.get(FILE_SETTING_TRUNCATION) {
+        match serde_json::from_value::<TruncationPolicy>(v.clone()) {
+            Ok(policy) => preview.truncation_policy = policy,
+            Err(e) => warn!("open - ignoring saved truncation policy for {:?}: {}", preview.path, e),
         }
-    } else {
-        // 如果没有打开文件，按要求返回 0（作为 Ok）
-        Ok(0usize)
     }
 }
 
-pub async fn read_lines(start: usize, count: usize) -> Result<String, String> {
-    let preview = {
-        let preview_guard = LARGE_FILE_PREVIEW.lock().await;
-        preview_guard.as_ref()
-            .ok_or("No file is currently opened")?
-            .clone()
-    };
-    preview.read_lines(start, count).await
-        .map_err(|e| format!("Failed to read lines: {}", e))
+/// 生成打开结果并附上 `path` 的会话状态；会话中保存的按文件设置先应用到 `preview`，返回值中的编码与策略即为应用后的值
+fn open_result_with_session<R: Runtime>(app: &tauri::AppHandle<R>, preview: &mut LargeFilePreview, path: &Path) -> OpenResult {
+    let session = session::load(app, &path.to_string_lossy());
+    apply_file_settings(preview, &session.settings);
+    let mut result = preview.open_result(path);
+    result.session = session;
+    result
 }
 
-pub async fn mmap_search(needle: String, ignore_case: bool) -> Result<serde_json::Value, String> {
-    let preview_guard = LARGE_FILE_PREVIEW.lock().await;
-    let preview = preview_guard.as_ref()
+/// 记录活动文件的一项按文件设置（`value` 为 null 时删除），下次打开该文件时随 `OpenResult::session` 返回。
+/// 编码与超长行策略由 `set_encoding` / `set_truncation_policy` 自动记录，这里用于前端自己解释的设置（如折行列数）
+pub async fn set_file_setting(key: String, value: serde_json::Value) -> Result<(), String> {
+    if key.is_empty() {
+        return Err("Setting key must not be empty".to_string());
+    }
+    let preview = preview_for(None).await?;
+    session::note_file_setting(&preview.path.to_string_lossy(), &key, value);
+    Ok(())
+}
+
+/// 清除活动文件的全部按文件设置并把编码与超长行策略恢复为默认值，返回 `{"encoding", "truncation_policy"}`；
+/// 前端据此重新加载当前页，并把自己保存的按文件设置恢复为全局默认值
+pub async fn reset_file_settings() -> Result<serde_json::Value, String> {
+    let mut preview_guard = LARGE_FILE_PREVIEW.lock().await;
+    let preview = preview_guard.as_mut()
         .ok_or("No file is currently opened")?;
-    
-    let (count, samples, duration, extra_alloc, first_match, matches_pos) = preview
-        .mmap_search(needle.as_bytes(), ignore_case)
-        .map_err(|e| format!("Search failed: {}", e))?;
-    
-    let duration_ms = duration.as_millis();
-    let first_match_json = if let Some((line, col, len)) = first_match {
-        Some(json!({"line": line, "column": col, "length": len}))
-    } else {
-        None
-    };
+    session::reset_file_settings(&preview.path.to_string_lossy());
+    preview.encoding = UTF_8;
+    preview.truncation_policy = TruncationPolicy::default();
+    info!("reset_file_settings - {:?}", preview.path);
+    Ok(json!({
+        "encoding": preview.encoding.name().to_lowercase(),
+        "truncation_policy": preview.truncation_policy
+    }))
+}
+
+/// 返回当前预览状态：是否打开文件、路径、总行数、大小与截断策略
+pub async fn get_status() -> Result<serde_json::Value, String> {
+    let preview_guard = LARGE_FILE_PREVIEW.lock().await;
+    match preview_guard.as_ref() {
+        Some(preview) => {
+            let size = preview.file_handle.as_ref().metadata().map(|m| m.len()).unwrap_or(0);
+            Ok(json!({
+                "open": true,
+                "path": preview.path.to_string_lossy(),
+                "total_lines": preview.total_lines,
+                "size": size,
+                "truncation_policy": preview.truncation_policy,
+                "encoding": preview.encoding.name().to_lowercase(),
+                "base_byte": preview.base_byte
+            }))
+        }
+        None => Ok(json!({"open": false})),
+    }
+}
 
+/// 活动文件占用的内存：`{"index_bytes", "index_len", "index_interval", "cached_window_bytes", "file_len"}`。
+///
+/// `index_bytes` 为稀疏行索引的大小（每个索引点 8 字节），`cached_window_bytes` 为缓存的 mmap 窗口长度
+/// （由系统按需换入，不一定全部驻留内存）。只读取已有的状态，不访问文件内容
+pub async fn get_memory_stats() -> Result<serde_json::Value, String> {
+    let preview = preview_for(None).await?;
+    let cached_window_bytes = match preview.cached_window.lock() {
+        Ok(guard) => guard.as_ref().map_or(0, |(_, len, _)| *len),
+        Err(_) => 0,
+    };
+    let file_len = preview.file_handle.as_ref().metadata().map(|m| m.len()).unwrap_or(0);
     Ok(json!({
-        "count": count,
-        "samples": samples,
-        "matches": matches_pos,
-        "duration_ms": duration_ms,
-        "extra_alloc_bytes": extra_alloc,
-        "first_match": first_match_json
+        "index_bytes": preview.index.len() * std::mem::size_of::<u64>(),
+        "index_len": preview.index.len(),
+        "index_interval": preview.index_interval,
+        "cached_window_bytes": cached_window_bytes,
+        "file_len": file_len
     }))
 }
 
-pub async fn close_file() -> Result<(), String> {
+/// 关闭文件：`handle` 为 `None` 或等于活动文件句柄时关闭活动文件，否则关闭对应的后台文件
+///
+/// 关闭活动文件前会记录 `last_line`（前端视图顶部所在行），并把所有会话状态写盘。
+pub async fn close_file<R: Runtime>(app: tauri::AppHandle<R>, handle: Option<u32>, last_line: Option<usize>) -> Result<(), String> {
     // debug!("close_file command invoked");
     let mut preview_guard = LARGE_FILE_PREVIEW.lock().await;
+    if let Some(h) = handle {
+        if preview_guard.as_ref().map_or(true, |p| p.handle != h) {
+            drop(preview_guard);
+            return match OPEN_FILES.lock().await.remove(&h) {
+                Some(_) => {
+                    info!("Background file {} closed", h);
+                    Ok(())
+                }
+                None => Err(format!("No open file with handle {}", h)),
+            };
+        }
+    }
     
-    if preview_guard.is_some() {
+    if let Some(preview) = preview_guard.as_ref() {
+        if let Some(line) = last_line {
+            session::note_line(&preview.path.to_string_lossy(), line);
+        }
+        session::flush(&app);
         *preview_guard = None;
+        #[cfg(not(target_os = "android"))]
+        crate::watch::stop_watch_dir();
         info!("File closed successfully");
         Ok(())
     } else {
@@ -595,13 +3856,292 @@ pub async fn close_file() -> Result<(), String> {
     }
 }
 
-pub async fn open_file<R: Runtime>(app: tauri::AppHandle<R>, extensions: Option<Vec<String>>) -> Result<serde_json::Value, String> {
+/// 把句柄为 `handle` 的后台文件设为活动文件，原活动文件转为后台文件（句柄不变），用于多标签切换。
+/// `handle` 已是活动文件时什么都不做
+pub async fn activate_file(handle: u32) -> Result<(), String> {
+    let mut preview_guard = LARGE_FILE_PREVIEW.lock().await;
+    if preview_guard.as_ref().is_some_and(|p| p.handle == handle) {
+        return Ok(());
+    }
+    let mut open_files = OPEN_FILES.lock().await;
+    let target = open_files.remove(&handle)
+        .ok_or_else(|| format!("No open file with handle {}", handle))?;
+    if let Some(prev) = preview_guard.replace(target) {
+        open_files.insert(prev.handle, prev);
+    }
+    info!("activate_file - handle {} is now active", handle);
+    Ok(())
+}
+
+/// 取消正在进行的 `open_file`：打开扫描或 Android 临时复制会在下一块数据前中止，
+/// 预览不会被登记（之前的活动文件保持不变，没有活动文件时 `LARGE_FILE_PREVIEW` 仍为 `None`），
+/// 已打开的文件句柄随未完成的 preview 一起释放，临时文件被删除。
+pub async fn cancel_open() -> Result<(), String> {
+    OPEN_CANCELLED.store(true, Ordering::Relaxed);
+    info!("cancel_open - cancellation requested");
+    Ok(())
+}
+
+/// 取消正在进行的长时间运行操作（`filter_lines` 导出、`get_file_hash`、`get_char_count`、`scan_invalid_utf8`、`unique_lines`）：它们在处理下一块前返回
+/// 包含 `TASK_CANCELLED_MSG` 的错误，被取消的导出会删除写了一半的目标文件。没有这类操作时不做任何事
+pub async fn cancel_task() -> Result<(), String> {
+    TASK_CANCELLED.store(true, Ordering::Relaxed);
+    info!("cancel_task - cancellation requested");
+    Ok(())
+}
+
+/// 要打开的 `path` 就是活动文件、且打开后大小与修改时间都没有变化时，直接返回活动文件的打开结果（句柄不变），
+/// 不再重新扫描。尾部模式下的活动文件不复用（重新打开应显示整个文件）；未建立索引的活动文件只在这次也是
+/// `no_index` 打开时复用
+#[cfg(not(target_os = "android"))]
+async fn reuse_active<R: Runtime>(app: &tauri::AppHandle<R>, path: &Path, no_index: bool) -> Option<OpenResult> {
+    let canonical = std::fs::canonicalize(path).ok()?;
+    let preview_guard = LARGE_FILE_PREVIEW.lock().await;
+    let active = preview_guard.as_ref()?;
+    if active.base_byte != 0 || active.open_stat.is_none() || active.open_stat != file_stat(path) || (!active.indexed && !no_index) {
+        return None;
+    }
+    if std::fs::canonicalize(&active.path).ok()? != canonical {
+        return None;
+    }
+    let mut result = active.open_result(path);
+    result.handle = active.handle;
+    drop(preview_guard);
+    result.session = session::load(app, &result.path);
+    session::note_opened(app, &result.path);
+    info!("open - {:?} is already open and unchanged, reusing handle {}", path, result.handle);
+    Some(result)
+}
+
+/// `open_file_by_path` 指定了落点时，在索引建好后发出，payload 为 `{"handle", "line"}`（从 0 开始的行号）
+pub const OPEN_TARGET_EVENT: &str = "large-file-preview://open-target";
+
+/// 落点目标：字节偏移或行号
+#[derive(Debug, Clone, Copy)]
+pub enum OpenTarget {
+    ByteOffset(u64),
+    Line(usize),
+}
+
+/// 把落点换算成 `handle` 对应文件中的行号（偏移超出末尾时落在最后一行，行号超出时取最后一行）并发出 `OPEN_TARGET_EVENT`
+async fn emit_open_target<R: Runtime>(app: &tauri::AppHandle<R>, handle: u32, target: OpenTarget) -> Result<(), String> {
+    use tauri::Emitter;
+    let preview = preview_for(Some(handle)).await?;
+    let last = preview.total_lines.saturating_sub(1);
+    let line = match target {
+        OpenTarget::Line(line) => line.min(last),
+        OpenTarget::ByteOffset(offset) => {
+            let fut = smol::unblock(move || preview.context_at_offset(offset, 0, 0));
+            with_timeout("Read", fut).await?.map_err(|e| format!("Failed to resolve offset: {}", e))?.0
+        }
+    };
+    info!("open - landing at line {} for handle {} ({:?})", line, handle, target);
+    let _ = app.emit(OPEN_TARGET_EVENT, json!({ "handle": handle, "line": line }));
+    Ok(())
+}
+
+/// 不弹出文件选择框，直接打开 `path`（用于启动时重新打开上次的文件，以及命令行参数指定的文件）。
+///
+/// 打开扫描在 `smol::unblock` 中进行，不阻塞调用方。Android 上最近文件记录的是内容 URI 的文件名或临时副本，
+/// 无法按路径重新打开，因此只支持桌面平台。`path` 就是未改动的活动文件时直接复用（见 `reuse_active`），
+/// `force_reload` 为 true 时总是重新打开。给出 `target` 时，索引建好后把它换算成行号并发出 `OPEN_TARGET_EVENT`，
+/// 前端收到后滚到该行，而不是恢复上次的阅读位置。`no_index` 为 true 时不建立行索引，见 `LargeFilePreview::open_unindexed`。
+pub async fn open_file_by_path<R: Runtime>(
+    app: tauri::AppHandle<R>,
+    path: String,
+    activate: bool,
+    force_reload: bool,
+    target: Option<OpenTarget>,
+    no_index: bool,
+) -> Result<OpenResult, String> {
+    #[cfg(target_os = "android")]
+    {
+        let _ = (app, path, activate, force_reload, target, no_index);
+        Err("Opening by path is not supported on Android".to_string())
+    }
+    #[cfg(not(target_os = "android"))]
+    {
+        OPEN_CANCELLED.store(false, Ordering::Relaxed);
+        let path = PathBuf::from(path);
+        let reused = if activate && !force_reload { reuse_active(&app, &path, no_index).await } else { None };
+        let result = match reused {
+            Some(result) => result,
+            None => {
+                let open_path = path.clone();
+                let mut preview = smol::unblock(move || LargeFilePreview::open_any(open_path, no_index))
+                    .await
+                    .map_err(|e| open_error_message(&path, e))?;
+                let mut result = open_result_with_session(&app, &mut preview, &path);
+                session::note_opened(&app, &result.path);
+                result.handle = register_preview(preview, activate).await;
+                result
+            }
+        };
+        if let Some(target) = target {
+            // 落点换算失败不影响打开本身，前端按上次的阅读位置显示
+            if let Err(e) = emit_open_target(&app, result.handle, target).await {
+                warn!("open - failed to resolve landing target {:?}: {}", target, e);
+            }
+        }
+        Ok(result)
+    }
+}
+
+/// `preview_file` 最多返回的行数
+const MAX_PREVIEW_FILE_LINES: usize = 1000;
+
+/// 不建立索引、不影响当前打开的文件，只读取 `path` 的前 `n` 行（最多 `MAX_PREVIEW_FILE_LINES`），
+/// 返回 `{"lines", "size"}`，供打开超大文件前预览确认。每行最多读取 `MAX_LINE_BYTES`，超出部分跳过。
+pub async fn preview_file(path: String, n: usize) -> Result<serde_json::Value, String> {
+    let n = n.min(MAX_PREVIEW_FILE_LINES);
+    let (lines, size) = smol::unblock(move || -> std::io::Result<(Vec<String>, u64)> {
+        let file = std::fs::File::open(&path)?;
+        let size = file.metadata()?.len();
+        let mut reader = BufReader::new(file);
+        let mut lines = Vec::with_capacity(n);
+        let mut buf: Vec<u8> = Vec::new();
+        while lines.len() < n {
+            buf.clear();
+            let read = (&mut reader).take(MAX_LINE_BYTES as u64).read_until(b'\n', &mut buf)?;
+            if read == 0 {
+                break;
+            }
+            if buf.last() != Some(&b'\n') && read == MAX_LINE_BYTES {
+                // 超长行：不缓存剩余部分，直接跳到行尾
+                loop {
+                    let chunk = reader.fill_buf()?;
+                    if chunk.is_empty() {
+                        break;
+                    }
+                    match memchr::memchr(b'\n', chunk) {
+                        Some(p) => {
+                            reader.consume(p + 1);
+                            break;
+                        }
+                        None => {
+                            let len = chunk.len();
+                            reader.consume(len);
+                        }
+                    }
+                }
+            }
+            let mut text = &buf[..];
+            text = text.strip_suffix(b"\n").unwrap_or(text);
+            text = text.strip_suffix(b"\r").unwrap_or(text);
+            lines.push(String::from_utf8_lossy(text).into_owned());
+        }
+        Ok((lines, size))
+    })
+    .await
+    .map_err(|e| format!("Failed to preview file: {}", e))?;
+    Ok(json!({ "lines": lines, "size": size }))
+}
+
+/// 启动设置与最近打开的文件列表，以及命令行参数指定的要打开的文件（见 `launch_target`）
+pub async fn get_startup_info<R: Runtime>(app: tauri::AppHandle<R>) -> Result<session::StartupInfo, String> {
+    let mut info = session::startup_info(&app);
+    if !LAUNCH_TAKEN.swap(true, Ordering::Relaxed) {
+        info.launch = launch_target(std::env::args().skip(1));
+    }
+    Ok(info)
+}
+
+/// 启动参数只交给前端一次，刷新页面后不再重复跳转
+static LAUNCH_TAKEN: AtomicBool = AtomicBool::new(false);
+
+/// 解析启动参数：第一个不以 `--` 开头的参数是要打开的文件，`--offset N` / `--line N`（也可写成 `--offset=N`）
+/// 指定落点。外部链接只能传一个参数时，可以在路径后追加查询参数（`app.log?offset=123456` 或 `?line=100`）；
+/// 整个参数本身就是存在的文件时不做拆分
+fn launch_target(args: impl Iterator<Item = String>) -> Option<session::LaunchTarget> {
+    let mut target = session::LaunchTarget { path: String::new(), byte_offset: None, line: None };
+    let mut args = args;
+    while let Some(arg) = args.next() {
+        let (key, inline) = match arg.split_once('=') {
+            Some((k, v)) if k.starts_with("--") => (k.to_string(), Some(v.to_string())),
+            _ => (arg.clone(), None),
+        };
+        match key.as_str() {
+            "--offset" | "--line" => {
+                let value = inline.or_else(|| args.next()).and_then(|v| v.trim().parse::<u64>().ok());
+                if key == "--offset" {
+                    target.byte_offset = value.or(target.byte_offset);
+                } else {
+                    target.line = value.map(|v| v as usize).or(target.line);
+                }
+            }
+            _ if arg.starts_with("--") => {}
+            _ if target.path.is_empty() => target.path = arg,
+            _ => {}
+        }
+    }
+    if target.path.is_empty() {
+        return None;
+    }
+    let full = target.path.clone();
+    if !Path::new(&full).exists() {
+        if let Some((path, query)) = full.split_once('?') {
+            for (k, v) in query.split('&').filter_map(|kv| kv.split_once('=')) {
+                match k {
+                    "offset" => target.byte_offset = v.parse().ok().or(target.byte_offset),
+                    "line" => target.line = v.parse().ok().or(target.line),
+                    _ => {}
+                }
+            }
+            target.path = path.to_string();
+        }
+    }
+    info!("startup - launch target {:?}", target);
+    Some(target)
+}
+
+/// 保存前端的一项界面设置（在 `get_startup_info` 的 `settings` 中读回），`value` 为 null 时删除
+pub async fn set_setting<R: Runtime>(app: tauri::AppHandle<R>, key: String, value: serde_json::Value) -> Result<(), String> {
+    if key.is_empty() {
+        return Err("Setting key must not be empty".to_string());
+    }
+    session::set_setting(&app, &key, value);
+    Ok(())
+}
+
+/// 活动文件的搜索历史与所有文件共用的全局搜索历史（均为最近的在前）
+pub async fn get_search_history<R: Runtime>(app: tauri::AppHandle<R>) -> Result<session::SearchHistory, String> {
+    let preview = preview_for(None).await?;
+    Ok(session::search_history(&app, &preview.path.to_string_lossy()))
+}
+
+/// 设置启动时是否重新打开最近的文件（立即持久化）
+pub async fn set_reopen_last<R: Runtime>(app: tauri::AppHandle<R>, enabled: bool) -> Result<(), String> {
+    session::set_reopen_last(&app, enabled);
+    Ok(())
+}
+
+/// Android 文件选择器出错时的最多尝试次数与首次重试前的等待（之后每次翻倍）
+#[cfg(target_os = "android")]
+const ANDROID_PICKER_ATTEMPTS: u32 = 3;
+#[cfg(target_os = "android")]
+const ANDROID_PICKER_BACKOFF_MS: u64 = 200;
+
+/// 弹出文件选择器并打开所选文件。`activate` 为 false 时文件只作为后台文件登记（例如对比模式），
+/// 不替换当前活动文件；返回值中的 `handle` 可用于 `read_lines` / `close_file` / `next_diff`。
+/// 桌面平台上选中的就是未改动的活动文件时直接复用，不重新扫描（见 `reuse_active`），`force_reload` 为 true 时总是重新打开。
+/// `no_index` 为 true 时不建立行索引（见 `LargeFilePreview::open_unindexed`），目前只在桌面平台生效。
+pub async fn open_file<R: Runtime>(
+    app: tauri::AppHandle<R>,
+    extensions: Option<Vec<String>>,
+    activate: bool,
+    force_reload: bool,
+    no_index: bool,
+) -> Result<OpenResult, String> {
     // debug!("open_file command invoked");
     info!("Opening file via large-file-preview plugin");
+    OPEN_CANCELLED.store(false, Ordering::Relaxed);
     // Android: use tauri_plugin_android_fs
     #[cfg(target_os = "android")]
     {
         info!("open_file (Android) - using file picker");
+        // Android 上打开的是内容 URI 的临时副本，无法判断是否为同一文件，总是重新打开；
+        // 直接使用的 fd 与临时副本都按原方式建立索引
+        let _ = (force_reload, no_index);
 
         let api = app.android_fs_async();
 
@@ -611,18 +4151,29 @@ pub async fn open_file<R: Runtime>(app: tauri::AppHandle<R>, extensions: Option<
         info!("open_file (Android) - computed mime_types: {:?}", mime_types);
 
         // For Android, force a broad picker filter to increase chance of seeing .log files
-        let mut selected_files: Vec<FileUri> = Vec::new();
         let broad = vec!["*/*"];
         info!("open_file (Android) - forcing pick_files with broad filter {:?}", broad);
-        match api.file_picker().pick_files(None, &broad, false).await {
-            Ok(v) => {
-                info!("open_file (Android) - pick_files returned {} entries for broad filter", v.len());
-                selected_files = v;
-            }
-            Err(e) => {
-                warn!("open_file (Android) - pick_files error for broad filter: {}", e);
+        // 部分设备上选择器会因 activity result 的偶发问题直接返回错误，按指数退避重试几次；
+        // 选择器正常返回但结果为空表示用户取消，不重试
+        let mut attempt = 1u32;
+        let selected_files: Vec<FileUri> = loop {
+            match api.file_picker().pick_files(None, &broad, false).await {
+                Ok(v) => {
+                    info!("open_file (Android) - pick_files attempt {} returned {} entries", attempt, v.len());
+                    break v;
+                }
+                Err(e) if attempt < ANDROID_PICKER_ATTEMPTS => {
+                    let delay = ANDROID_PICKER_BACKOFF_MS << (attempt - 1);
+                    warn!("open_file (Android) - pick_files attempt {} failed: {}; retrying in {} ms", attempt, e, delay);
+                    smol::Timer::after(std::time::Duration::from_millis(delay)).await;
+                    attempt += 1;
+                }
+                Err(e) => {
+                    error!("open_file (Android) - pick_files attempt {} failed, giving up: {}", attempt, e);
+                    return Err(format!("Failed to open the file picker: {}", e));
+                }
             }
-        }
+        };
 
         if selected_files.is_empty() {
             return Err("No file selected".to_string());
@@ -700,6 +4251,50 @@ pub async fn open_file<R: Runtime>(app: tauri::AppHandle<R>, extensions: Option<
             }
         }
 
+        // 压缩文件需要解压到临时文件，Android 上尚未支持
+        if selected_ext_opt.as_deref().is_some_and(|ext| compressed::COMPRESSED_EXTENSIONS.contains(&ext)) {
+            return Err(format!("Compressed files ({:?}) are not supported on Android yet", selected_ext_opt));
+        }
+
+        // 优先直接使用内容 URI 的 fd，避免把整个文件复制到临时目录（存储占用翻倍、大文件耗时）。
+        // 只有 fd 指向普通文件时才可 seek/mmap；否则回退到下面的复制流程
+        match api.open_file_readable(uri).await {
+            Ok(file) => {
+                let direct = match file.metadata() {
+                    Ok(md) => md.is_file() && md.len() > 0,
+                    Err(e) => {
+                        warn!("open_file (Android) - metadata for direct fd failed: {}", e);
+                        false
+                    }
+                };
+                if direct {
+                    use std::os::unix::io::IntoRawFd;
+                    let hint = PathBuf::from(filename_suspect.clone().unwrap_or_else(|| uri_str.clone()));
+                    // fd 的所有权转交给 LargeFilePreview，由其 `file_handle` 负责关闭
+                    let fd = file.into_raw_fd();
+                    match LargeFilePreview::open_from_fd(fd, hint.clone()) {
+                        Ok(mut preview) => {
+                            let mut result = open_result_with_session(&app, &mut preview, &hint);
+                            result.handle = register_preview(preview, activate).await;
+                            info!("open_file (Android) - opened directly from fd (size={} bytes, handle={})", result.size, result.handle);
+                            return Ok(result);
+                        }
+                        Err(e) => {
+                            if OPEN_CANCELLED.load(Ordering::Relaxed) {
+                                return Err(format!("Failed to open file preview: {}", e));
+                            }
+                            warn!("open_file (Android) - open_from_fd failed, falling back to temp copy: {}", e);
+                        }
+                    }
+                } else {
+                    info!("open_file (Android) - fd is not a regular file, falling back to temp copy");
+                }
+            }
+            Err(e) => {
+                warn!("open_file (Android) - open_file_readable for direct fd failed: {}", e);
+            }
+        }
+
         match api.open_file_readable(uri).await {
             Ok(mut reader) => {
                 match reader.metadata() {
@@ -714,35 +4309,31 @@ pub async fn open_file<R: Runtime>(app: tauri::AppHandle<R>, extensions: Option<
 
                 match std::fs::File::create(&tmp) {
                     Ok(mut out) => {
-                        use std::io::copy;
-                        match copy(&mut reader, &mut out) {
+                        let copy_start = std::time::Instant::now();
+                        match copy_cancellable(&mut reader, &mut out) {
                             Ok(bytes_copied) => {
-                                info!("open_file (Android) - copied {} bytes to temp file", bytes_copied);
+                                info!("open_file (Android) - copied {} bytes to temp file in {:?}", bytes_copied, copy_start.elapsed());
                                 // 使用 LargeFilePreview 打开并缓存
                                 match LargeFilePreview::open(tmp.clone()) {
-                                    Ok(preview) => {
+                                    Ok(mut preview) => {
                                         info!("open_file (Android) - LargeFilePreview::open succeeded");
-                                        // 尝试读取文件大小（字节）
-                                        let size = match preview.file_handle.as_ref().metadata() {
-                                            Ok(meta) => meta.len() as usize,
-                                            Err(e) => {
-                                                warn!("open_file (Android) - failed to get metadata from preview.file_handle: {}", e);
-                                                0usize
-                                            }
-                                        };
-                                        let mut preview_guard = LARGE_FILE_PREVIEW.lock().await;
-                                        *preview_guard = Some(preview);
-                                        info!("open_file (Android) - preview cached (size={} bytes)", size);
-                                        Ok(json!({"path": tmp.to_string_lossy(), "status": "success", "size": size, "truncation_policy": "lines_longer_than_6MB_are_truncated"}))
+                                        let mut result = open_result_with_session(&app, &mut preview, &tmp);
+                                        result.handle = register_preview(preview, activate).await;
+                                        info!("open_file (Android) - preview cached (size={} bytes, handle={})", result.size, result.handle);
+                                        Ok(result)
                                     }
                                     Err(e) => {
                                         error!("open_file (Android) - LargeFilePreview::open failed: {}", e);
-                                        Err(format!("Failed to open file preview: {}", e))
+                                        let _ = std::fs::remove_file(&tmp);
+                                        Err(open_error_message(&tmp, e))
                                     }
                                 }
                             }
                             Err(e) => {
+                                // 复制失败或被取消：删除不完整的临时文件
                                 error!("open_file (Android) - copy to temp file failed: {}", e);
+                                drop(out);
+                                let _ = std::fs::remove_file(&tmp);
                                 Err(format!("Failed to copy file: {}", e))
                             }
                         }
@@ -765,11 +4356,11 @@ pub async fn open_file<R: Runtime>(app: tauri::AppHandle<R>, extensions: Option<
     {
         info!("open_file (PC) - using rfd AsyncFileDialog");
 
-        // Prepare extension filters for rfd if provided, otherwise default to txt/log
+        // Prepare extension filters for rfd if provided, otherwise default to txt/log and the compressed formats
         let filters: Vec<String> = if let Some(exts) = &extensions {
             exts.iter().map(|s| s.trim_start_matches('.').to_string()).collect()
         } else {
-            vec!["txt".to_string(), "log".to_string()]
+            ["txt", "log"].iter().chain(compressed::COMPRESSED_EXTENSIONS).map(|s| s.to_string()).collect()
         };
 
         if let Some(file_handle) = AsyncFileDialog::new()
@@ -778,24 +4369,145 @@ pub async fn open_file<R: Runtime>(app: tauri::AppHandle<R>, extensions: Option<
             .await
         {
             let path = file_handle.path().to_path_buf();
+            if activate && !force_reload {
+                if let Some(result) = reuse_active(&app, &path, no_index).await {
+                    return Ok(result);
+                }
+            }
 
-            // 使用 LargeFilePreview 打开并缓存
-                match LargeFilePreview::open(path.clone()) {
-                Ok(preview) => {
-                    let size = match preview.file_handle.as_ref().metadata() {
-                        Ok(meta) => meta.len() as usize,
-                        Err(_) => 0usize,
-                    };
-                    let mut preview_guard = LARGE_FILE_PREVIEW.lock().await;
-                    *preview_guard = Some(preview);
-                    Ok(json!({"path": path.to_string_lossy(), "status": "success", "size": size, "truncation_policy": "lines_longer_than_6MB_are_truncated"}))
+            // 使用 LargeFilePreview 打开并缓存；解压与换行扫描都是阻塞操作，放到后台线程
+            let open_path = path.clone();
+            match smol::unblock(move || LargeFilePreview::open_any(open_path, no_index)).await {
+                Ok(mut preview) => {
+                    let mut result = open_result_with_session(&app, &mut preview, &path);
+                    session::note_opened(&app, &result.path);
+                    result.handle = register_preview(preview, activate).await;
+                    Ok(result)
                 }
                 Err(e) => {
-                    Err(format!("Failed to open file preview: {}", e))
+                    Err(open_error_message(&path, e))
                 }
             }
         } else {
             Err("No file selected".to_string())
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 测试用的临时文件，drop 时删除
+    struct TestFile(PathBuf);
+
+    impl TestFile {
+        fn new(content: &[u8]) -> Self {
+            static NEXT: AtomicU32 = AtomicU32::new(0);
+            let name = format!("lfp_test_{}_{}.txt", std::process::id(), NEXT.fetch_add(1, Ordering::Relaxed));
+            let path = std::env::temp_dir().join(name);
+            std::fs::write(&path, content).unwrap();
+            TestFile(path)
+        }
+
+        fn open(&self) -> LargeFilePreview {
+            LargeFilePreview::open(self.0.clone()).unwrap()
+        }
+    }
+
+    impl Drop for TestFile {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    /// 按 `anchor` 搜索 `needle`，返回每个匹配所在的行号
+    fn anchored_lines(content: &[u8], needle: &str, anchor: SearchAnchor) -> Vec<usize> {
+        let file = TestFile::new(content);
+        let preview = file.open();
+        let options = SearchOptions { anchor, ..SearchOptions::default() };
+        let result = preview.mmap_search(needle.as_bytes(), CaseSensitivity::Sensitive, &options).unwrap();
+        assert_eq!(result.count, result.matches.len());
+        result.matches.iter().map(|m| m["line"].as_u64().unwrap() as usize).collect()
+    }
+
+    #[test]
+    fn anchor_start_matches_at_beginning_of_file() {
+        assert_eq!(anchored_lines(b"foo bar\nbar foo\nfoo\n", "foo", SearchAnchor::Start), vec![0, 2]);
+    }
+
+    #[test]
+    fn anchor_end_matches_at_end_of_file_without_newline() {
+        assert_eq!(anchored_lines(b"foo bar\nbar foo", "foo", SearchAnchor::End), vec![1]);
+    }
+
+    #[test]
+    fn anchor_end_matches_at_end_of_file_with_newline() {
+        assert_eq!(anchored_lines(b"foo bar\nbar foo\n", "foo", SearchAnchor::End), vec![1]);
+    }
+
+    #[test]
+    fn anchor_end_matches_before_crlf() {
+        assert_eq!(anchored_lines(b"a foo\r\nfoo b\r\nc foo", "foo", SearchAnchor::End), vec![0, 2]);
+    }
+
+    #[test]
+    fn no_anchor_keeps_every_match() {
+        assert_eq!(anchored_lines(b"foo bar\nbar foo\n", "foo", SearchAnchor::None), vec![0, 1]);
+    }
+
+    /// 每行 10 字节的 `line 0000` … 文件，第 n 行从 `n * 10` 处开始
+    fn numbered_lines(n: usize) -> Vec<u8> {
+        (0..n).flat_map(|i| format!("line {:04}\n", i).into_bytes()).collect()
+    }
+
+    #[test]
+    fn read_lines_ignores_cached_window_shorter_than_recorded() {
+        let file = TestFile::new(&numbered_lines(3000));
+        let preview = file.open();
+        // 第 1500 行从索引点（第 1000 行，偏移 10000）开始读。缓存中记录的窗口长度覆盖该偏移，
+        // 实际映射却只有 16 字节，切片越界时必须放弃缓存而不是 panic
+        let short = unsafe { MmapOptions::new().len(16).map(preview.file_handle.as_ref()) }.unwrap();
+        *preview.cached_window.lock().unwrap() = Some((0, 1 << 20, short));
+        let text = smol::block_on(preview.read_lines(1500, 3)).unwrap();
+        assert_eq!(text, "line 1500\nline 1501\nline 1502\n");
+    }
+
+    #[test]
+    fn clamp_len_clamps_lengths_above_4gb_on_32_bit() {
+        let five_gb = 5 * 1024 * 1024 * 1024u64;
+        assert!(five_gb > u32::MAX as u64);
+        assert_eq!(clamp_len_to(five_gb, u32::MAX, "test"), u32::MAX);
+        assert_eq!(clamp_len_to(u32::MAX as u64 + 1, u32::MAX, "test"), u32::MAX);
+        assert_eq!(clamp_len_to(u32::MAX as u64, u32::MAX, "test"), u32::MAX);
+        assert_eq!(clamp_len_to(4096, u32::MAX, "test"), 4096u32);
+    }
+
+    #[cfg(target_pointer_width = "64")]
+    #[test]
+    fn clamp_len_keeps_lengths_above_4gb_on_64_bit() {
+        let five_gb = 5 * 1024 * 1024 * 1024u64;
+        assert_eq!(clamp_len(five_gb, "test") as u64, five_gb);
+    }
+
+    #[test]
+    fn tail_window_holding_only_final_newline() {
+        let file = TestFile::new(b"aaa\nbbb\n");
+        let mut preview = file.open();
+        // 窗口起点前一个字节是最后一行的内容，它之后的换行就是文件末尾：不能把 `base_byte` 推到文件长度
+        let base = preview.set_tail_window(Some(1)).unwrap();
+        assert_eq!(base, 7);
+        let (line, first, text_lines, offset_in_line) = preview.context_at_offset(7, 1, 1).unwrap();
+        assert_eq!((line, first, offset_in_line), (0, 0, 0));
+        assert_eq!(text_lines, vec![String::new()]);
+    }
+
+    #[test]
+    fn context_at_offset_past_tail_window_is_empty() {
+        let file = TestFile::new(b"aaa\nbbb\n");
+        let mut preview = file.open();
+        preview.base_byte = 8;
+        let (line, first, text_lines, offset_in_line) = preview.context_at_offset(3, 1, 1).unwrap();
+        assert_eq!((line, first, offset_in_line), (0, 0, 0));
+        assert!(text_lines.is_empty());
+    }
+}
@@ -0,0 +1,302 @@
+//! 按文件路径持久化的会话状态：上次阅读位置、搜索历史、书签与覆盖全局默认值的按文件设置。
+//!
+//! 状态先记录在内存中（`read_lines` / `mmap_search` 时更新），在 `close_file` 与应用退出时
+//! 写入应用数据目录下的 `session.json`，再次打开同一路径时通过 `OpenResult::session` 恢复。
+//! 注意 Android 上回退到临时文件复制时，每次打开的路径都不同，因此无法恢复。
+//!
+//! 同一文件中还保存最近打开的文件列表与“启动时重新打开上次的文件”设置（见 `get_startup_info`），
+//! 以及不区分文件的全局搜索历史（见 `get_search_history`）和前端的界面设置（见 `set_setting`）。
+
+use log::{info, warn};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::Mutex as StdMutex;
+use tauri::{AppHandle, Manager, Runtime};
+
+const SESSION_FILE: &str = "session.json";
+
+// 每个文件保留的搜索历史条数
+const MAX_SEARCH_HISTORY: usize = 20;
+
+// 全局搜索历史条数
+const MAX_GLOBAL_SEARCH_HISTORY: usize = 50;
+
+// 最近打开的文件列表长度
+const MAX_RECENT_FILES: usize = 10;
+
+/// 单个文件的会话状态
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SessionState {
+    /// 上次视图顶部所在的行（0 基准）
+    pub last_line: usize,
+    /// 搜索历史，最近的在前
+    pub search_history: Vec<String>,
+    /// 书签行号（0 基准）
+    pub bookmarks: Vec<usize>,
+    /// 查看该文件时所做的选择（编码、超长行策略、折行列数等），再次打开时覆盖全局默认值。
+    /// `encoding` 与 `truncation_policy` 由插件在打开时应用（见 `models::apply_file_settings`），其余项由前端解释
+    pub settings: BTreeMap<String, serde_json::Value>,
+}
+
+/// `session.json` 的内容
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct SessionData {
+    files: HashMap<String, SessionState>,
+    /// 最近打开的文件路径，最近的在前
+    recent_files: Vec<String>,
+    /// 启动时是否重新打开 `recent_files` 中的第一个文件
+    reopen_last: bool,
+    /// 所有文件共用的搜索历史，最近的在前
+    search_history: Vec<String>,
+    /// 前端的界面设置（如每页行数），插件只负责保存，不解释其含义
+    settings: BTreeMap<String, serde_json::Value>,
+}
+
+/// 某个文件的搜索历史与全局搜索历史，见 `get_search_history`
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchHistory {
+    pub file: Vec<String>,
+    pub global: Vec<String>,
+}
+
+/// 启动时前端需要的信息，见 `get_startup_info`
+#[derive(Debug, Clone, Serialize)]
+pub struct StartupInfo {
+    pub reopen_last: bool,
+    pub recent_files: Vec<String>,
+    pub settings: BTreeMap<String, serde_json::Value>,
+    /// 命令行参数指定的要打开的文件，只在第一次调用 `get_startup_info` 时返回
+    pub launch: Option<LaunchTarget>,
+}
+
+/// 启动参数中要打开的文件及落点（字节偏移或行号，都从 0 开始），见 `models::launch_target`
+#[derive(Debug, Clone, Serialize)]
+pub struct LaunchTarget {
+    pub path: String,
+    pub byte_offset: Option<u64>,
+    pub line: Option<usize>,
+}
+
+#[derive(Default)]
+struct SessionStore {
+    /// 是否已从磁盘读取过（需要 `AppHandle` 才能定位数据目录，因此延迟到第一次使用）
+    loaded: bool,
+    /// 内存中的状态是否有尚未写盘的修改
+    dirty: bool,
+    data: SessionData,
+}
+
+static SESSIONS: Lazy<StdMutex<SessionStore>> = Lazy::new(|| StdMutex::new(SessionStore::default()));
+
+fn session_path<R: Runtime>(app: &AppHandle<R>) -> Option<PathBuf> {
+    match app.path().app_data_dir() {
+        Ok(dir) => Some(dir.join(SESSION_FILE)),
+        Err(e) => {
+            warn!("session - failed to resolve app data dir: {}", e);
+            None
+        }
+    }
+}
+
+fn ensure_loaded<R: Runtime>(app: &AppHandle<R>, store: &mut SessionStore) {
+    if store.loaded {
+        return;
+    }
+    store.loaded = true;
+    let Some(path) = session_path(app) else { return };
+    match std::fs::read(&path) {
+        Ok(bytes) => match serde_json::from_slice::<SessionData>(&bytes) {
+            Ok(data) => {
+                // 读取前已在内存中更新过的条目优先
+                for (k, v) in data.files {
+                    store.data.files.entry(k).or_insert(v);
+                }
+                for p in data.recent_files {
+                    if !store.data.recent_files.contains(&p) {
+                        store.data.recent_files.push(p);
+                    }
+                }
+                store.data.recent_files.truncate(MAX_RECENT_FILES);
+                store.data.reopen_last = data.reopen_last;
+                for q in data.search_history {
+                    if !store.data.search_history.contains(&q) {
+                        store.data.search_history.push(q);
+                    }
+                }
+                store.data.search_history.truncate(MAX_GLOBAL_SEARCH_HISTORY);
+                for (k, v) in data.settings {
+                    store.data.settings.entry(k).or_insert(v);
+                }
+            }
+            Err(e) => warn!("session - ignoring malformed {:?}: {}", path, e),
+        },
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => warn!("session - failed to read {:?}: {}", path, e),
+    }
+}
+
+/// 读取 `path` 的会话状态（没有记录时返回默认值）
+pub(crate) fn load<R: Runtime>(app: &AppHandle<R>, path: &str) -> SessionState {
+    let mut store = SESSIONS.lock().unwrap_or_else(|e| e.into_inner());
+    ensure_loaded(app, &mut store);
+    store.data.files.get(path).cloned().unwrap_or_default()
+}
+
+/// 把 `path` 记为最近打开的文件（放到列表最前面）
+pub(crate) fn note_opened<R: Runtime>(app: &AppHandle<R>, path: &str) {
+    let mut store = SESSIONS.lock().unwrap_or_else(|e| e.into_inner());
+    ensure_loaded(app, &mut store);
+    let recent = &mut store.data.recent_files;
+    if recent.first().map(String::as_str) == Some(path) {
+        return;
+    }
+    recent.retain(|p| p != path);
+    recent.insert(0, path.to_string());
+    recent.truncate(MAX_RECENT_FILES);
+    store.dirty = true;
+}
+
+/// 读取启动设置与最近打开的文件列表
+pub(crate) fn startup_info<R: Runtime>(app: &AppHandle<R>) -> StartupInfo {
+    let mut store = SESSIONS.lock().unwrap_or_else(|e| e.into_inner());
+    ensure_loaded(app, &mut store);
+    StartupInfo {
+        reopen_last: store.data.reopen_last,
+        recent_files: store.data.recent_files.clone(),
+        settings: store.data.settings.clone(),
+        launch: None,
+    }
+}
+
+/// 修改“启动时重新打开上次的文件”设置并立即写盘
+pub(crate) fn set_reopen_last<R: Runtime>(app: &AppHandle<R>, enabled: bool) {
+    {
+        let mut store = SESSIONS.lock().unwrap_or_else(|e| e.into_inner());
+        ensure_loaded(app, &mut store);
+        if store.data.reopen_last != enabled {
+            store.data.reopen_last = enabled;
+            store.dirty = true;
+        }
+    }
+    flush(app);
+}
+
+/// 读取一项界面设置（没有保存时为 None）
+pub(crate) fn setting<R: Runtime>(app: &AppHandle<R>, key: &str) -> Option<serde_json::Value> {
+    let mut store = SESSIONS.lock().unwrap_or_else(|e| e.into_inner());
+    ensure_loaded(app, &mut store);
+    store.data.settings.get(key).cloned()
+}
+
+/// 保存一项界面设置并立即写盘；`value` 为 null 时删除该项
+pub(crate) fn set_setting<R: Runtime>(app: &AppHandle<R>, key: &str, value: serde_json::Value) {
+    {
+        let mut store = SESSIONS.lock().unwrap_or_else(|e| e.into_inner());
+        ensure_loaded(app, &mut store);
+        let changed = if value.is_null() {
+            store.data.settings.remove(key).is_some()
+        } else {
+            store.data.settings.insert(key.to_string(), value.clone()) != Some(value)
+        };
+        store.dirty |= changed;
+    }
+    flush(app);
+}
+
+fn update(path: &str, f: impl FnOnce(&mut SessionState)) {
+    let mut store = SESSIONS.lock().unwrap_or_else(|e| e.into_inner());
+    let state = store.data.files.entry(path.to_string()).or_default();
+    let before = state.clone();
+    f(state);
+    if *state != before {
+        store.dirty = true;
+    }
+}
+
+/// 记录 `path` 当前视图顶部所在的行
+pub(crate) fn note_line(path: &str, line: usize) {
+    update(path, |s| s.last_line = line);
+}
+
+/// 记录 `path` 的一项按文件设置；`value` 为 null 时删除该项（恢复为全局默认值）。与阅读位置一样在 `close_file` 时写盘
+pub(crate) fn note_file_setting(path: &str, key: &str, value: serde_json::Value) {
+    update(path, |s| {
+        if value.is_null() {
+            s.settings.remove(key);
+        } else {
+            s.settings.insert(key.to_string(), value);
+        }
+    });
+}
+
+/// 清除 `path` 的全部按文件设置，之后再打开时使用全局默认值
+pub(crate) fn reset_file_settings(path: &str) {
+    update(path, |s| s.settings.clear());
+}
+
+/// 把 item 放到 list 最前面（去重并截断到 max 条），返回列表是否有变化
+fn push_front_dedup(list: &mut Vec<String>, item: &str, max: usize) -> bool {
+    if list.first().map(String::as_str) == Some(item) {
+        return false;
+    }
+    list.retain(|h| h != item);
+    list.insert(0, item.to_string());
+    list.truncate(max);
+    true
+}
+
+/// 把 `needle` 放到 `path` 搜索历史（最多 `MAX_SEARCH_HISTORY` 条）与全局搜索历史
+/// （最多 `MAX_GLOBAL_SEARCH_HISTORY` 条）的最前面，均去重
+pub(crate) fn note_search(path: &str, needle: &str) {
+    if needle.is_empty() {
+        return;
+    }
+    update(path, |s| {
+        push_front_dedup(&mut s.search_history, needle, MAX_SEARCH_HISTORY);
+    });
+    let mut store = SESSIONS.lock().unwrap_or_else(|e| e.into_inner());
+    if push_front_dedup(&mut store.data.search_history, needle, MAX_GLOBAL_SEARCH_HISTORY) {
+        store.dirty = true;
+    }
+}
+
+/// 读取 `path` 的搜索历史与全局搜索历史
+pub(crate) fn search_history<R: Runtime>(app: &AppHandle<R>, path: &str) -> SearchHistory {
+    let mut store = SESSIONS.lock().unwrap_or_else(|e| e.into_inner());
+    ensure_loaded(app, &mut store);
+    SearchHistory {
+        file: store.data.files.get(path).map(|s| s.search_history.clone()).unwrap_or_default(),
+        global: store.data.search_history.clone(),
+    }
+}
+
+/// 把内存中的会话状态写入磁盘；没有修改时什么都不做
+pub(crate) fn flush<R: Runtime>(app: &AppHandle<R>) {
+    let mut store = SESSIONS.lock().unwrap_or_else(|e| e.into_inner());
+    if !store.dirty {
+        return;
+    }
+    ensure_loaded(app, &mut store);
+    let Some(path) = session_path(app) else { return };
+    let write = || -> std::io::Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let json = serde_json::to_vec_pretty(&store.data)?;
+        // 先写临时文件再重命名，避免退出时写到一半留下损坏的文件
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, json)?;
+        std::fs::rename(&tmp, &path)
+    };
+    match write() {
+        Ok(()) => {
+            store.dirty = false;
+            info!("session - saved {} entries to {:?}", store.data.files.len(), path);
+        }
+        Err(e) => warn!("session - failed to write {:?}: {}", path, e),
+    }
+}
@@ -0,0 +1,157 @@
+//! 监视目录，在其中出现更新的匹配文件时通知前端（“总是显示这个文件夹里最新的日志”）。
+//!
+//! 日志轮转后正在写入的文件名会变化（如 `app-2024-01-02.log`），跟随模式（`set_follow`）只能跟随已打开的文件。
+//! 这里用 `notify` 监视目录（不递归），文件系统事件按 `DEBOUNCE_MS` 去抖后，
+//! 在目录中找出修改时间最新的匹配文件；它与上次通知的不同时发出 `DIR_NEWEST_EVENT`，由前端决定是否打开。
+//!
+//! 同时只监视一个目录，`stop_watch_dir` 或关闭活动文件（`close_file`）时停止。只在桌面平台可用。
+
+use log::{info, warn};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use once_cell::sync::Lazy;
+use serde_json::json;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Mutex as StdMutex;
+use std::time::{Duration, SystemTime};
+use tauri::{Emitter, Runtime};
+
+/// 监视的目录中最新的匹配文件变化时发出，payload 为 `{"dir", "path"}`
+pub const DIR_NEWEST_EVENT: &str = "large-file-preview://dir-newest";
+
+/// 文件系统事件的去抖间隔：写日志时事件非常密集，安静这么久之后才重新查找最新的文件
+const DEBOUNCE_MS: u64 = 500;
+
+/// 正在进行的监视。drop 时 `watcher` 停止，事件通道随之断开，去抖线程退出
+struct DirWatch {
+    dir: PathBuf,
+    _watcher: RecommendedWatcher,
+}
+
+static DIR_WATCH: Lazy<StdMutex<Option<DirWatch>>> = Lazy::new(|| StdMutex::new(None));
+
+/// 文件名是否匹配 `pattern`（不区分 ASCII 大小写）。含 `*` / `?` 时按通配符匹配整个文件名，
+/// 否则当作扩展名（`log` 与 `.log` 相同）；为空时匹配所有文件
+pub fn name_matches(pattern: &str, name: &str) -> bool {
+    if pattern.is_empty() {
+        return true;
+    }
+    if !pattern.contains(['*', '?']) {
+        let ext = pattern.trim_start_matches('.');
+        return Path::new(name)
+            .extension()
+            .is_some_and(|e| e.to_string_lossy().eq_ignore_ascii_case(ext));
+    }
+    let p: Vec<char> = pattern.chars().map(|c| c.to_ascii_lowercase()).collect();
+    let n: Vec<char> = name.chars().map(|c| c.to_ascii_lowercase()).collect();
+    // 经典的贪心回溯：记录最近一个 `*` 的位置，失配时让它多吞一个字符
+    let (mut pi, mut ni) = (0usize, 0usize);
+    let mut star: Option<(usize, usize)> = None;
+    while ni < n.len() {
+        if pi < p.len() && (p[pi] == '?' || p[pi] == n[ni]) {
+            pi += 1;
+            ni += 1;
+        } else if pi < p.len() && p[pi] == '*' {
+            star = Some((pi, ni));
+            pi += 1;
+        } else if let Some((sp, sn)) = star {
+            pi = sp + 1;
+            ni = sn + 1;
+            star = Some((sp, sn + 1));
+        } else {
+            return false;
+        }
+    }
+    p[pi..].iter().all(|&c| c == '*')
+}
+
+/// `dir` 中修改时间最新的匹配文件（不含子目录）；读不到元数据的条目跳过
+fn newest_matching(dir: &Path, pattern: &str) -> std::io::Result<Option<PathBuf>> {
+    let mut newest: Option<(SystemTime, PathBuf)> = None;
+    for entry in std::fs::read_dir(dir)? {
+        let Ok(entry) = entry else { continue };
+        if !name_matches(pattern, &entry.file_name().to_string_lossy()) {
+            continue;
+        }
+        let Ok(meta) = entry.metadata() else { continue };
+        if !meta.is_file() {
+            continue;
+        }
+        let modified = meta.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+        if newest.as_ref().map_or(true, |(t, _)| modified > *t) {
+            newest = Some((modified, entry.path()));
+        }
+    }
+    Ok(newest.map(|(_, p)| p))
+}
+
+/// 开始监视 `dir` 中匹配 `pattern`（见 `name_matches`）的文件，替换之前的监视。
+/// 返回当前最新的匹配文件（没有时为 None），之后它变化时发出 `DIR_NEWEST_EVENT`。
+/// 目录不存在或没有权限时返回错误，不会开始监视
+pub fn start_watch_dir<R: Runtime>(app: tauri::AppHandle<R>, dir: String, pattern: String) -> Result<Option<String>, String> {
+    let dir = PathBuf::from(dir);
+    let newest = newest_matching(&dir, &pattern).map_err(|e| match e.kind() {
+        std::io::ErrorKind::PermissionDenied => format!("Permission denied: {}", dir.display()),
+        _ => format!("Failed to read directory {}: {}", dir.display(), e),
+    })?;
+
+    let (tx, rx) = mpsc::channel::<()>();
+    let event_pattern = pattern.clone();
+    let mut watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| match res {
+        Ok(event) => {
+            let relevant = event.paths.iter().any(|p| {
+                p.file_name().is_some_and(|n| name_matches(&event_pattern, &n.to_string_lossy()))
+            });
+            if relevant {
+                let _ = tx.send(());
+            }
+        }
+        // 监视过程中的错误（如目录被删除、权限变化）只记录，不中断监视
+        Err(e) => warn!("watch_dir - {}", e),
+    })
+    .map_err(|e| format!("Failed to watch directory: {}", e))?;
+    watcher
+        .watch(&dir, RecursiveMode::NonRecursive)
+        .map_err(|e| format!("Failed to watch directory {}: {}", dir.display(), e))?;
+
+    let thread_dir = dir.clone();
+    let mut last = newest.clone();
+    std::thread::spawn(move || {
+        while rx.recv().is_ok() {
+            loop {
+                match rx.recv_timeout(Duration::from_millis(DEBOUNCE_MS)) {
+                    Ok(()) => continue,
+                    Err(RecvTimeoutError::Timeout) => break,
+                    Err(RecvTimeoutError::Disconnected) => return,
+                }
+            }
+            match newest_matching(&thread_dir, &pattern) {
+                Ok(Some(path)) if last.as_ref() != Some(&path) => {
+                    info!("watch_dir - newest file is now {:?}", path);
+                    let _ = app.emit(
+                        DIR_NEWEST_EVENT,
+                        json!({ "dir": thread_dir.to_string_lossy(), "path": path.to_string_lossy() }),
+                    );
+                    last = Some(path);
+                }
+                Ok(_) => {}
+                Err(e) => warn!("watch_dir - failed to read {:?}: {}", thread_dir, e),
+            }
+        }
+    });
+
+    info!("watch_dir - watching {:?}, newest={:?}", dir, newest);
+    if let Ok(mut guard) = DIR_WATCH.lock() {
+        *guard = Some(DirWatch { dir, _watcher: watcher });
+    }
+    Ok(newest.map(|p| p.to_string_lossy().to_string()))
+}
+
+/// 停止监视目录；没有在监视时什么也不做
+pub fn stop_watch_dir() {
+    if let Ok(mut guard) = DIR_WATCH.lock() {
+        if let Some(watch) = guard.take() {
+            info!("watch_dir - stopped watching {:?}", watch.dir);
+        }
+    }
+}
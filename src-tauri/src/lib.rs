@@ -1,5 +1,8 @@
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
 
+#[cfg(desktop)]
+mod window_state;
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     // initialize logging for android (no-op on other platforms)
@@ -18,6 +21,16 @@ pub fn run() {
         let builder = builder.plugin(tauri_plugin_android_fs::init());
         
         let builder = builder.plugin(tauri_plugin_large_file_preview::init());
+
+        // 记住主窗口的大小与位置
+        #[cfg(desktop)]
+        let builder = builder.setup(|app| {
+            use tauri::Manager;
+            if let Some(window) = app.get_webview_window("main") {
+                window_state::restore_and_track(&window);
+            }
+            Ok(())
+        });
         
         builder
         .run(tauri::generate_context!())
@@ -0,0 +1,105 @@
+//! 主窗口大小与位置的保存与恢复（仅桌面平台）。
+//!
+//! 关闭窗口时把外框位置与内容区大小写入应用配置目录下的 `window-state.json`，下次启动时恢复。
+//! 上次所在的显示器已经断开或分辨率变小时，窗口可能落在所有显示器之外，
+//! 这时把它移回主显示器并把大小钳制到显示器范围内。
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use tauri::{AppHandle, Manager, Monitor, PhysicalPosition, PhysicalSize, Runtime, WebviewWindow, WindowEvent};
+
+const STATE_FILE: &str = "window-state.json";
+
+/// 恢复的窗口至少要有这么大的区域（宽、高，物理像素）落在某个显示器内才算可见
+const MIN_VISIBLE_PX: i64 = 100;
+
+/// 过小的窗口没有意义（通常是异常保存的结果），不恢复大小
+const MIN_SIZE_PX: u32 = 200;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct WindowState {
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+    maximized: bool,
+}
+
+fn state_path<R: Runtime>(app: &AppHandle<R>) -> Option<PathBuf> {
+    app.path().app_config_dir().ok().map(|dir| dir.join(STATE_FILE))
+}
+
+fn load<R: Runtime>(app: &AppHandle<R>) -> Option<WindowState> {
+    let data = std::fs::read(state_path(app)?).ok()?;
+    serde_json::from_slice(&data).ok()
+}
+
+fn save<R: Runtime>(window: &WebviewWindow<R>) {
+    let app = window.app_handle();
+    let Some(path) = state_path(app) else { return };
+    // 最小化时的位置没有意义（Windows 上为 -32000）；最大化时保留之前的普通大小，只记录最大化状态，
+    // 这样下次取消最大化时仍回到用户调整过的大小
+    if window.is_minimized().unwrap_or(false) {
+        return;
+    }
+    let maximized = window.is_maximized().unwrap_or(false);
+    let state = match (maximized, load(app), window.outer_position(), window.inner_size()) {
+        (true, Some(prev), _, _) => WindowState { maximized: true, ..prev },
+        (_, _, Ok(pos), Ok(size)) => WindowState { x: pos.x, y: pos.y, width: size.width, height: size.height, maximized },
+        _ => return,
+    };
+    let result = path
+        .parent()
+        .map_or(Ok(()), std::fs::create_dir_all)
+        .and_then(|_| std::fs::write(&path, serde_json::to_vec(&state).unwrap_or_default()));
+    match result {
+        Ok(()) => info!("window_state - saved {:?}", state),
+        Err(e) => warn!("window_state - failed to save {:?}: {}", path, e),
+    }
+}
+
+/// `state` 描述的窗口与 `monitor` 重叠的区域是否足够大
+fn is_visible_on(state: &WindowState, monitor: &Monitor) -> bool {
+    let (mx, my) = (monitor.position().x as i64, monitor.position().y as i64);
+    let (mw, mh) = (monitor.size().width as i64, monitor.size().height as i64);
+    let overlap_w = (state.x as i64 + state.width as i64).min(mx + mw) - (state.x as i64).max(mx);
+    let overlap_h = (state.y as i64 + state.height as i64).min(my + mh) - (state.y as i64).max(my);
+    overlap_w >= MIN_VISIBLE_PX && overlap_h >= MIN_VISIBLE_PX
+}
+
+/// 窗口不在任何显示器上时移到 `fallback` 显示器的左上角，大小不超过该显示器
+fn clamp_to_monitors(mut state: WindowState, monitors: &[Monitor], fallback: Option<&Monitor>) -> WindowState {
+    if monitors.iter().any(|m| is_visible_on(&state, m)) {
+        return state;
+    }
+    let Some(monitor) = fallback.or(monitors.first()) else { return state };
+    state.width = state.width.min(monitor.size().width);
+    state.height = state.height.min(monitor.size().height);
+    state.x = monitor.position().x;
+    state.y = monitor.position().y;
+    state
+}
+
+/// 恢复上次保存的窗口大小与位置，并在窗口关闭时保存
+pub fn restore_and_track<R: Runtime>(window: &WebviewWindow<R>) {
+    if let Some(state) = load(window.app_handle()) {
+        let monitors = window.available_monitors().unwrap_or_default();
+        let primary = window.primary_monitor().ok().flatten();
+        let state = clamp_to_monitors(state, &monitors, primary.as_ref());
+        if state.width >= MIN_SIZE_PX && state.height >= MIN_SIZE_PX {
+            let _ = window.set_size(PhysicalSize::new(state.width, state.height));
+        }
+        let _ = window.set_position(PhysicalPosition::new(state.x, state.y));
+        if state.maximized {
+            let _ = window.maximize();
+        }
+        info!("window_state - restored {:?}", state);
+    }
+    let w = window.clone();
+    window.on_window_event(move |event| {
+        if let WindowEvent::CloseRequested { .. } = event {
+            save(&w);
+        }
+    });
+}
@@ -7,11 +7,24 @@ use web_sys::console;
 use crate::dialog;
 use wasm_bindgen_futures::JsFuture;
 use js_sys::Promise;
+use std::collections::HashMap;
 
 #[wasm_bindgen]
 extern "C" {
     #[wasm_bindgen(js_namespace = ["window", "__TAURI__", "core"], js_name = invoke)]
     fn invoke_promise(cmd: &str, args: JsValue) -> Promise;
+
+    // 监听插件发出的事件（如 get_file_hash 的进度）
+    #[wasm_bindgen(js_namespace = ["window", "__TAURI__", "event"], js_name = listen)]
+    fn event_listen(event: &str, handler: &Closure<dyn Fn(JsValue)>) -> Promise;
+
+    // 剪贴板写入；在不支持 Clipboard API 的环境下会抛出异常，因此使用 catch
+    #[wasm_bindgen(catch, js_namespace = ["navigator", "clipboard"], js_name = writeText)]
+    fn clipboard_write_text(text: &str) -> Result<Promise, JsValue>;
+
+    // 向宿主应用（Rust 端与其他 webview）发出事件，见 `VIEW_CHANGED_EVENT`
+    #[wasm_bindgen(catch, js_namespace = ["window", "__TAURI__", "event"], js_name = emit)]
+    fn event_emit(event: &str, payload: JsValue) -> Result<Promise, JsValue>;
 }
 
 // 状态管理
@@ -20,20 +33,573 @@ extern "C" {
 struct SearchArgs {
     needle: String,
     ignore_case: bool,
+    // 从该行开始向后搜索；None 表示搜索整个文件
+    from_line: Option<usize>,
+    // 每个匹配附带所在行的文本，供结果列表显示与复制
+    include_line_text: bool,
+    // 样例行数；结果列表使用每个匹配附带的行文本，不需要样例
+    max_samples: usize,
+    // 只保留行首/行尾的匹配："none" / "start" / "end"
+    anchor: &'static str,
 }
 
+// 打开对话框中可选的扩展名；压缩文件由插件解压后按文本打开
+const OPEN_EXTENSIONS: &[&str] = &[".txt", ".log", ".gz", ".bz2", ".xz"];
+
+// 插件在打开被 cancel_open 取消时返回的错误信息（与插件的 `OPEN_CANCELLED_MSG` 一致）
+const OPEN_CANCELLED_MSG: &str = "Open cancelled";
+
 #[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 struct ReadLinesArgs {
     start: usize,
     count: usize,
+    // 目标文件句柄；None 表示当前活动文件
+    handle: Option<u32>,
+    // 行号区、匹配选区都按“每行一个逻辑行”对齐，要求插件不拆分超长行
+    row_per_line: bool,
+}
+
+// 日志级别着色规则：行首附近出现 `token`（不区分大小写）的行以 `color` 作为背景色
+#[derive(Clone, Debug, PartialEq)]
+struct LogLevelRule {
+    token: String,
+    color: String,
+}
+
+// 默认规则：ERROR 红、WARN 黄、DEBUG 灰（变暗），INFO 保持默认不着色。
+// 颜色使用半透明色，浅色与深色主题下都能看清文字
+fn default_log_rules() -> Vec<LogLevelRule> {
+    [
+        ("ERROR", "rgba(229,57,53,0.18)"),
+        ("WARN", "rgba(251,192,45,0.22)"),
+        ("WARNING", "rgba(251,192,45,0.22)"),
+        ("DEBUG", "rgba(128,128,128,0.14)"),
+    ]
+    .into_iter()
+    .map(|(token, color)| LogLevelRule { token: token.to_string(), color: color.to_string() })
+    .collect()
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct OpenByPathArgs {
+    path: String,
+    // 命令行参数指定的落点，插件换算成行号后发出 `OPEN_TARGET_EVENT`
+    byte_offset: Option<u64>,
+    line: Option<usize>,
+    // false 时只登记为后台文件，之后再 `activate_file`，原活动文件保留为另一个标签
+    activate: bool,
+}
+
+#[derive(Serialize)]
+struct SetReopenLastArgs {
+    enabled: bool,
+}
+
+// 一个已打开文件（标签）的视图状态。活动标签的状态就是 App 中的顶层 signal，
+// 只在切换标签时写回这里；切回时再从这里恢复
+#[derive(Clone, Debug, Default)]
+struct TabState {
+    handle: u32,
+    path: String,
+    total_lines: usize,
+    file_size: u64,
+    file_format: String,
+    open_stats: String,
+    current_line: usize,
+    search_query: String,
+    matches_list: Vec<String>,
+    matches_lines: Vec<usize>,
+    current_match_idx: usize,
+    search_info: String,
+    detected_format: String,
+    tail_mb: Option<u64>,
+    line_ending: String,
+    indexed: bool,
+    wrap_col: Option<usize>,
+}
+
+#[derive(Serialize)]
+struct FileHashArgs {
+    algorithm: String,
+}
+
+// 插件中的 `TASK_PROGRESS_EVENT`：导出与计算校验值的进度，payload 为 `{task, done, total}`
+const TASK_PROGRESS_EVENT: &str = "large-file-preview://task-progress";
+
+// 插件在长时间运行的操作被 cancel_task 取消时返回的错误信息（与插件的 `TASK_CANCELLED_MSG` 一致）
+const TASK_CANCELLED_MSG: &str = "Task cancelled";
+
+// 搜索、筛选与导出匹配行统一忽略 ASCII 大小写，导出的行与屏幕上看到的一致
+const SEARCH_IGNORE_CASE: bool = true;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct FilterLinesArgs {
+    needle: String,
+    ignore_case: bool,
+    invert: bool,
+    dest_path: String,
+}
+
+// 视图变化时发出的事件，供嵌入本查看器的宿主应用同步相关视图；payload 为 `ViewChanged`
+const VIEW_CHANGED_EVENT: &str = "large-file-preview://view-changed";
+// 连续变化（快速滚动）时合并发送的间隔
+const VIEW_CHANGED_THROTTLE_MS: i32 = 100;
+
+// 跳转到行对话框中输入停止多久后读取预览（毫秒）
+const GOTO_PREVIEW_DEBOUNCE_MS: i32 = 150;
+
+// `VIEW_CHANGED_EVENT` 的 payload；行号均为 0 基准，筛选模式下为当前页第一/最后一个匹配行
+#[derive(Serialize, Clone, Copy, Default, PartialEq)]
+struct ViewChanged {
+    top_line: usize,
+    bottom_line: usize,
+    total_lines: usize,
+}
+
+#[derive(Serialize)]
+struct HandleArgs {
+    handle: u32,
+}
+
+// 行号区显示内容：行号、相对当前行的行数，或每行起始的字节偏移（十六进制/十进制）
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+enum GutterMode {
+    #[default]
+    LineNumber,
+    // 与当前行（光标行，未开启高亮当前行时为视图第一行）相距的行数，当前行为 0
+    Relative,
+    // 同 Relative，但当前行显示绝对行号（类似 Vim 的 `number` + `relativenumber`）
+    Hybrid,
+    OffsetHex,
+    OffsetDec,
+}
+
+impl GutterMode {
+    // 是否需要向插件获取每行的字节偏移
+    fn shows_offsets(self) -> bool {
+        matches!(self, GutterMode::OffsetHex | GutterMode::OffsetDec)
+    }
+}
+
+// 已加载内容中 ANSI 转义序列（终端颜色）的显示方式
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+enum AnsiMode {
+    // 原样显示
+    #[default]
+    Off,
+    // 去除转义序列
+    Strip,
+    // 去除转义序列，并在背景层按 SGR 前景色给文字加底色
+    Color,
+}
+
+// 复制与导出时输出的换行符：源文件的换行（默认），或统一为 LF / CRLF
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+enum OutputEol {
+    #[default]
+    Source,
+    Lf,
+    Crlf,
+}
+
+impl OutputEol {
+    // 保存在插件 settings 中的取值，与插件的 `LineEnding` 相同
+    fn as_setting(self) -> &'static str {
+        match self {
+            OutputEol::Source => "preserve",
+            OutputEol::Lf => "lf",
+            OutputEol::Crlf => "crlf",
+        }
+    }
+
+    fn from_setting(v: &str) -> Self {
+        match v {
+            "lf" => OutputEol::Lf,
+            "crlf" => OutputEol::Crlf,
+            _ => OutputEol::Source,
+        }
+    }
+
+    // 复制多行时使用的换行符；`source` 为打开时检测到的换行风格（"lf" / "crlf" / "mixed" / "none"），混合或未知时用 LF
+    fn newline(self, source: &str) -> &'static str {
+        match self {
+            OutputEol::Lf => "\n",
+            OutputEol::Crlf => "\r\n",
+            OutputEol::Source if source == "crlf" => "\r\n",
+            OutputEol::Source => "\n",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            OutputEol::Source => "与源文件相同",
+            OutputEol::Lf => "LF",
+            OutputEol::Crlf => "CRLF",
+        }
+    }
+}
+
+// 跳转到匹配时匹配行在视图中的位置：靠上（上方留几行上下文）或垂直居中
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+enum MatchPosition {
+    #[default]
+    Top,
+    Center,
+}
+
+impl MatchPosition {
+    // 靠上时匹配行上方保留的行数
+    const CONTEXT_BEFORE: usize = 3;
+
+    // 一页显示 rows 行时，匹配行上方应显示的行数
+    fn lead_lines(self, rows: usize) -> usize {
+        match self {
+            MatchPosition::Top => Self::CONTEXT_BEFORE,
+            MatchPosition::Center => rows / 2,
+        }
+    }
+
+    // 保存在插件 settings 中的取值
+    fn as_setting(self) -> &'static str {
+        match self {
+            MatchPosition::Top => "top",
+            MatchPosition::Center => "center",
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ExportIndexArgs {
+    dest_path: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TailWindowArgs {
+    max_mb: Option<u64>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ReindexArgs {
+    new_interval: usize,
+}
+
+// 为“快速打开”的文件建立索引时使用的索引间隔，与插件打开文件时的默认值相同
+const INDEX_INTERVAL: usize = 1000;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ReadLinesReverseArgs {
+    end_line: usize,
+    count: usize,
+}
+
+#[derive(Serialize)]
+struct LongestLinesArgs {
+    n: usize,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct UniqueLinesArgs {
+    max_distinct: usize,
+}
+
+// `unique_lines` 的返回值：出现最多的几种行及统计
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+struct UniqueLines {
+    lines: Vec<UniqueLine>,
+    total_lines: usize,
+    distinct: usize,
+    untracked_lines: u64,
+    truncated: bool,
+}
+
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+struct UniqueLine {
+    text: String,
+    count: u64,
+    first_line: usize,
+}
+
+// 重复行统计最多列出的行数
+const UNIQUE_LINES_LIMIT: usize = 200;
+
+// `get_longest_lines` 返回的一项
+#[derive(Deserialize)]
+struct LongestLine {
+    line: usize,
+    bytes: u64,
+    chars: u64,
+    truncated: bool,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PrefixArgs {
+    target_prefix: String,
+}
+
+#[derive(Serialize)]
+struct FollowArgs {
+    enabled: bool,
+}
+
+// 插件中的 `APPENDED_EVENT`：跟随模式下活动文件追加了内容，payload 为 `{handle, total_lines, size}`
+const APPENDED_EVENT: &str = "large-file-preview://appended";
+
+// 插件中的 `OPEN_TARGET_EVENT`：按路径打开时指定的落点已换算成行号，payload 为 `{handle, line}`
+const OPEN_TARGET_EVENT: &str = "large-file-preview://open-target";
+
+// 插件中的 `INDEX_COMPLETE_EVENT`：活动文件的完整索引已建立，payload 为 `{handle, total_lines, exact}`
+const INDEX_COMPLETE_EVENT: &str = "large-file-preview://index-complete";
+
+// 插件中的 `DIR_NEWEST_EVENT`：监视的文件夹中最新的匹配文件变了，payload 为 `{dir, path}`
+const DIR_NEWEST_EVENT: &str = "large-file-preview://dir-newest";
+
+#[derive(Serialize)]
+struct WatchDirArgs {
+    dir: String,
+    pattern: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ContextAtOffsetArgs {
+    byte_offset: u64,
+    before: usize,
+    after: usize,
+}
+
+// 按字节比例的滚动条的刻度数：滑块值 / BYTE_SLIDER_STEPS 即文件中的字节比例
+const BYTE_SLIDER_STEPS: u64 = 10000;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct InvalidUtf8Args {
+    max_results: usize,
+}
+
+// 插件 `scan_invalid_utf8` 报告的一处非法 UTF-8 序列；行、列为 0 基准，列按字符计
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+struct InvalidUtf8 {
+    line: usize,
+    column: usize,
+    offset: u64,
+    byte: u8,
+}
+
+// 查找无效 UTF-8 时最多列出的位置数
+const INVALID_UTF8_LIMIT: usize = 1000;
+
+#[derive(Serialize)]
+struct BlockArgs {
+    line: usize,
+    braces: String,
+}
+
+#[derive(Serialize)]
+struct LineOffsetsArgs {
+    lines: Vec<usize>,
+}
+
+// open_file 的返回值，对应插件中的 `OpenResult`（这里只取前端用到的字段）
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+struct OpenResult {
+    path: String,
+    size: u64,
+    total_lines: usize,
+    encoding: String,
+    encoding_confident: bool,
+    line_ending: String,
+    is_binary: bool,
+    // 文件长度为 0 或没有任何行
+    is_empty: bool,
+    // 快速打开（不建立索引）时为 false，此时 total_lines 是按文件头估算的行数
+    indexed: bool,
+    handle: u32,
+    session: SessionState,
+    // 打开时换行扫描的耗时与读取字节数，用于诊断打开缓慢
+    open_duration_ms: u64,
+    bytes_scanned: u64,
+    // 压缩文件（gzip / bzip2 / xz）的格式与压缩前大小；此时 `size` 为解压后的大小
+    compression: Option<String>,
+    compressed_size: Option<u64>,
+}
+
+// 活动文件与全局的搜索历史（插件的 `SearchHistory`），最近的在前
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+struct SearchHistory {
+    file: Vec<String>,
+    global: Vec<String>,
+}
+
+// 上次关闭该文件时保存的会话状态（插件的 `SessionState`）
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+struct SessionState {
+    last_line: usize,
+    search_history: Vec<String>,
+    settings: FileSettings,
+}
+
+// 查看该文件时所做、覆盖全局默认值的设置（插件的 `SessionState::settings`）。编码与超长行策略由插件在打开时应用，
+// 已反映在 `OpenResult` 中，这里只取前端自己解释的项
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+struct FileSettings {
+    wrap_col: Option<usize>,
+}
+
+// 按文件设置中折行列数的键
+const WRAP_COL_FILE_SETTING: &str = "wrap_col";
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CloseFileArgs {
+    // 视图顶部所在行，随会话状态一起保存
+    last_line: usize,
+}
+
+// preview_encodings 返回的一项：某个候选编码下的文件开头几行
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+struct EncodingPreview {
+    encoding: String,
+    sample_text: String,
+    had_errors: bool,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DiffRangesArgs {
+    start_a: usize,
+    count_a: usize,
+    start_b: usize,
+    count_b: usize,
+}
+
+// diff_ranges 返回的一行（插件的 `DiffLine`）
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+struct DiffLine {
+    tag: String,
+    line_a: Option<usize>,
+    line_b: Option<usize>,
+    text: String,
+}
+
+#[derive(Serialize)]
+struct LineArgs {
+    line: usize,
+}
+
+#[derive(Serialize)]
+struct ReadLinesWithEncodingArgs {
+    start: usize,
+    count: usize,
+    encoding: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BlankLineArgs {
+    from_line: usize,
+    whitespace_is_blank: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FindFirstArgs {
+    needle: String,
+    ignore_case: bool,
+    from_line: usize,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct FindPrevArgs {
+    needle: String,
+    ignore_case: bool,
+    before_line: usize,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FilteredLinesArgs {
+    needle: String,
+    ignore_case: bool,
+    // 从第几个匹配行开始（匹配行序号，而非文件行号）
+    start_match: usize,
+    count: usize,
+    // 是否同时返回匹配行总数（需要扫描整个文件）
+    with_total: bool,
+}
+
+// read_filtered_lines 返回的一行：原始行号与文本
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+struct FilteredLine {
+    line: usize,
+    text: String,
+}
+
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+struct FilteredPage {
+    lines: Vec<FilteredLine>,
+    total: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct NextDiffArgs {
+    from_line: usize,
+    other: u32,
 }
 
 #[component]
 pub fn App() -> impl IntoView {
     // 文件状态
     let (file_path, set_file_path) = signal(String::new());
-    let (file_size, set_file_size) = signal(0usize);
+    // 文件名过长时的滚动速度（px/s）与是否在鼠标悬停时暂停滚动，均保存在插件的 settings 中
+    const DEFAULT_MARQUEE_SPEED: f64 = 30.0;
+    const MIN_MARQUEE_SPEED: f64 = 5.0;
+    const MAX_MARQUEE_SPEED: f64 = 200.0;
+    const MARQUEE_SPEED_SETTING: &str = "marquee_speed";
+    const MARQUEE_PAUSE_SETTING: &str = "marquee_pause_on_hover";
+    let (marquee_speed, set_marquee_speed) = signal(DEFAULT_MARQUEE_SPEED);
+    let (marquee_pause, set_marquee_pause) = signal(true);
+    // 字节大小使用 u64：wasm32 上 usize 只有 32 位，无法表示超过 4GB 的文件
+    let (file_size, set_file_size) = signal(0u64);
+    // 编码 / 换行风格等格式说明，显示在文件大小旁
+    let (file_format, set_file_format) = signal(String::new());
+    // 编码选择对话框：各候选编码下的样例，为空时不显示对话框
+    let (encoding_previews, set_encoding_previews) = signal(Vec::<EncodingPreview>::new());
+    // 编码选择对话框中按某编码预览的当前页：(编码, 起始行, 文本)
+    let (encoding_page_samples, set_encoding_page_samples) = signal(Vec::<(String, usize, String)>::new());
+    // 候选列表变化（打开或关闭对话框）时丢弃旧的当前页预览
+    Effect::new(move |_| {
+        encoding_previews.track();
+        set_encoding_page_samples.set(Vec::new());
+    });
+    // 打开耗时说明，作为文件大小的悬停提示
+    let (open_stats, set_open_stats) = signal(String::new());
+    // 尾部模式下只显示文件最后多少 MB，None 为显示整个文件
+    let (tail_mb, set_tail_mb) = signal(None::<u64>);
     let (total_lines, set_total_lines) = signal(0usize);
+    // 活动文件是否建立了行索引；快速打开的文件为 false，总行数只是估算，滚动条与倒序不可用，直到建立索引
+    let (indexed, set_indexed) = signal(true);
     let (file_content, set_file_content) = signal(String::new());
     let (current_line, set_current_line) = signal(0usize);
     // 当前可视内容起始行（用于行号显示）
@@ -47,8 +613,41 @@ pub fn App() -> impl IntoView {
     let (matches_lines, set_matches_lines) = signal(Vec::<usize>::new());
     let (current_match_idx, set_current_match_idx) = signal(0usize);
     let (search_info, set_search_info) = signal(String::new());
+    // 关闭时搜索从当前视图顶部开始（不丢失阅读位置），匹配数也只统计其后的部分；开启时总是搜索整个文件
+    let (search_whole_file, set_search_whole_file) = signal(false);
+    // 搜索的行首/行尾锚定，取值同插件的 `SearchAnchor`："none" / "start" / "end"
+    let (search_anchor, set_search_anchor) = signal("none");
+    // 跳转到匹配时匹配行在视图中的位置，保存在插件的 settings 中
+    const MATCH_POSITION_SETTING: &str = "match_position";
+    let (match_position, set_match_position) = signal(MatchPosition::default());
+    // 滚动条按字节比例而不是行号定位（行长差别很大时“拖到中间”更直观），保存在插件的 settings 中
+    const SCROLLBAR_BYTES_SETTING: &str = "scrollbar_by_bytes";
+    let (scrollbar_bytes, set_scrollbar_bytes) = signal(false);
+    // 复制与导出的换行符，保存在插件的 settings 中；插件导出时也读取同一项设置
+    const OUTPUT_EOL_SETTING: &str = "output_line_ending";
+    let (output_eol, set_output_eol) = signal(OutputEol::default());
+    // 活动文件打开时检测到的换行风格，“与源文件相同”时复制使用
+    let (line_ending, set_line_ending) = signal(String::new());
+    // 让 line 按 match_position 出现在一页 rows 行的视图中时，这一页的起始行
+    let match_view_start = move |line: usize, rows: usize| -> usize {
+        let start = line.saturating_sub(match_position.get_untracked().lead_lines(rows));
+        match total_lines.get_untracked() {
+            0 => start,
+            total => start.min(total - 1),
+        }
+    };
     let (show_dropdown, set_show_dropdown) = signal(false);
 
+    // 倒序视图：从最后一行往前显示（最新的日志在最上面）。此时 visible_start/current_line 表示倒数第几行，
+    // 第 r 个显示位置对应文件第 total_lines - 1 - r 行；搜索与筛选只在正序视图中进行
+    let (reversed, set_reversed) = signal(false);
+
+    // 按列硬换行：Some(N) 时显示内容在第 N 个字符处折行（只影响显示，不改变文件与行号）
+    let (wrap_col, set_wrap_col) = signal(Option::<usize>::None);
+    // CSV/TSV 列对齐：只解析已加载的这一页，按列宽补空格显示（行号不变）；分隔符为 None 时按内容自动识别
+    let (align_columns, set_align_columns) = signal(false);
+    let (column_delimiter, set_column_delimiter) = signal(Option::<char>::None);
+
     // Helper: construct a selection callback that will run after content is loaded.
     // Returns `Some(Closure)` when matches_list[idx] contains column/length, otherwise None.
     let make_select_cb = move |matches_snapshot: Vec<String>, idx: usize, start_local: usize, target_line: usize| {
@@ -62,26 +661,41 @@ pub fn App() -> impl IntoView {
                             if let Some(doc) = window.document() {
                                 if let Some(el) = doc.get_element_by_id("editor-textarea") {
                                     if let Some(textarea) = el.dyn_ref::<web_sys::HtmlTextAreaElement>() {
+                                        let _ = textarea.focus();
+                                        // 硬换行时 textarea 中插入了额外的换行（列对齐时插入了补齐的空格），
+                                        // 匹配的列偏移无法直接对应，此时不做选区，只停在包含匹配行的这一页顶部
+                                        if wrap_col.get_untracked().is_some() || align_columns.get_untracked() {
+                                            let he: web_sys::HtmlElement = textarea.clone().unchecked_into();
+                                            he.set_scroll_top(0);
+                                            return;
+                                        }
                                         let content = textarea.value();
                                         let rel_line = if target_line >= start_local { target_line - start_local } else { 0 };
                                         let mut off = 0usize;
                                         let mut cur_line = 0usize;
+                                        let mut line_len = usize::MAX;
                                         for l in content.lines() {
                                             if cur_line < rel_line {
                                                 off = off.saturating_add(l.chars().count()).saturating_add(1);
                                             } else {
+                                                line_len = l.chars().count();
                                                 break;
                                             }
                                             cur_line += 1;
                                         }
-                                        off = off.saturating_add(column);
-                                        let start_sel = off;
-                                        let end_sel = off.saturating_add(length);
+                                        // 偏移基于 textarea 中实际显示的内容计算；去除行尾空白后，
+                                        // 落在被去掉部分的匹配会被截到行尾
+                                        let start_sel = off.saturating_add(column.min(line_len));
+                                        let end_sel = off.saturating_add(column.saturating_add(length).min(line_len));
                                         let _ = textarea.set_selection_start(Some(start_sel as u32));
                                         let _ = textarea.set_selection_end(Some(end_sel as u32));
                                         let _ = textarea.focus();
                                         let line_px = compute_line_pixel("editor-textarea").unwrap_or(18.0);
-                                        let scroll_top = (rel_line.saturating_sub(0) as f64 * line_px) as i32;
+                                        // 页面起始行已按 match_position 让匹配行上方留出 lead 行，滚动时保留同样的行数
+                                        let rows = compute_visible_lines("editor-textarea").unwrap_or(DEFAULT_VISIBLE_LINES)
+                                            .saturating_sub(VISIBLE_SAFETY_MARGIN).max(1);
+                                        let lead = match_position.get_untracked().lead_lines(rows);
+                                        let scroll_top = (rel_line.saturating_sub(lead) as f64 * line_px) as i32;
                                         let he: web_sys::HtmlElement = textarea.clone().unchecked_into();
                                         he.set_scroll_top(scroll_top);
                                         console::log_1(&wasm_bindgen::JsValue::from_str(&format!("select_cb applied (factory): rel_line={}, start={}, end={}", rel_line, start_sel, end_sel)));
@@ -93,183 +707,2088 @@ pub fn App() -> impl IntoView {
                     return Some(cb);
                 }
             }
-        }
-        None
+        }
+        None
+    };
+    
+    // 对比模式：第二个文件的句柄/路径/与主视图同步的当前页内容，以及最近定位到的差异行
+    let (compare_handle, set_compare_handle) = signal(Option::<u32>::None);
+    let (compare_path, set_compare_path) = signal(String::new());
+    let (compare_content, set_compare_content) = signal(String::new());
+    let (diff_line, set_diff_line) = signal(Option::<usize>::None);
+    let (diff_info, set_diff_info) = signal(String::new());
+
+    // 显示时去除每行末尾的空格/制表符（仅影响显示，不改变文件内容与行号计算）
+    let (trim_trailing, set_trim_trailing) = signal(false);
+    // ANSI 转义：只处理已加载的这一页，文件内容与行号不变。开启时逐行给出 (去除后的文本,
+    // 原文字符位置 → 去除后字符位置, 着色区间)，用于显示与把搜索结果的列换算到去除后的位置
+    let (ansi_mode, set_ansi_mode) = signal(AnsiMode::default());
+    let ansi_lines = Memo::new(move |_| {
+        if ansi_mode.get() == AnsiMode::Off {
+            return Vec::new();
+        }
+        file_content.with(|content| content.lines().map(parse_ansi_line).collect::<Vec<_>>())
+    });
+    // 去除转义序列后的内容（未开启时即 file_content）
+    let plain_content = Memo::new(move |_| {
+        if ansi_mode.get() == AnsiMode::Off {
+            return file_content.get();
+        }
+        ansi_lines.with(|lines| {
+            let mut out = String::new();
+            for (text, _, _) in lines {
+                out.push_str(text);
+                out.push('\n');
+            }
+            out
+        })
+    });
+    // 实际显示的内容：(文本, 每个显示行对应的逻辑行相对序号（换行续行为 None）, 本页实际显示的逻辑行数)。
+    // 硬换行后一页放不下所有已加载的行时，只显示能放下的部分，剩下的留给下一页
+    let wrapped = Memo::new(move |_| {
+        let mut base = if trim_trailing.get() { trim_trailing_ws(&plain_content.get()) } else { plain_content.get() };
+        if align_columns.get() {
+            let delimiter = column_delimiter.get().unwrap_or_else(|| guess_delimiter(&base));
+            base = align_delimited(&base, delimiter);
+        }
+        match wrap_col.get() {
+            Some(col) => {
+                let visible = compute_visible_lines("editor-textarea").unwrap_or(DEFAULT_VISIBLE_LINES);
+                wrap_at_column(&base, col, visible.saturating_sub(VISIBLE_SAFETY_MARGIN).max(1))
+            }
+            None => {
+                let n = base.lines().count();
+                (base, (0..n).map(Some).collect(), n)
+            }
+        }
+    });
+    let display_content = Memo::new(move |_| wrapped.with(|w| w.0.clone()));
+    // 内容更新后（DOM 已更新时）按 textarea 的滚动位置重新对齐行号区与背景层；
+    // 赋值不一定触发 scroll 事件，例如新内容更短、滚动位置被浏览器钳制时
+    Effect::new(move |_| {
+        display_content.track();
+        if let Some(w) = web_sys::window() {
+            let cb = Closure::once_into_js(sync_editor_layers);
+            let _ = w.set_timeout_with_callback_and_timeout_and_arguments_0(cb.unchecked_ref(), 0);
+        }
+    });
+
+    // 在当前页查找：只在已加载的 file_content 中查找（忽略 ASCII 大小写），不访问插件。
+    // 匹配为 (逻辑行相对序号, 起始字符, 结束字符)；查找栏打开且查找内容非空时代替搜索结果的高亮
+    let (show_page_find, set_show_page_find) = signal(false);
+    let (page_find_query, set_page_find_query) = signal(String::new());
+    let (page_find_idx, set_page_find_idx) = signal(0usize);
+    let page_find_active = move || show_page_find.get() && !page_find_query.get().is_empty();
+    let page_hits = Memo::new(move |_| {
+        if !page_find_active() {
+            return Vec::new();
+        }
+        let query = page_find_query.get().to_ascii_lowercase();
+        let shown = wrapped.with(|w| w.2);
+        plain_content.with(|content| {
+            let mut hits = Vec::new();
+            for (rel, line) in content.lines().take(shown).enumerate() {
+                // ASCII 转小写不改变字节位置，匹配的字节偏移可直接用于原文
+                for (at, m) in line.to_ascii_lowercase().match_indices(&query) {
+                    let a = line[..at].chars().count();
+                    hits.push((rel, a, a + m.chars().count()));
+                }
+            }
+            hits
+        })
+    });
+    // 查找内容或页面变化后回到第一个匹配
+    Effect::new(move |_| {
+        page_hits.track();
+        set_page_find_idx.set(0);
+    });
+
+    // 日志级别着色：textarea 无法按行设置样式，因此在其下方放一层逐行的背景（editor-backdrop），
+    // 按 log_rules 给每个显示行上色；换行产生的续行沿用所属逻辑行的颜色
+    let (log_coloring, set_log_coloring) = signal(true);
+    let (log_rules, _set_log_rules) = signal(default_log_rules());
+    // detect_format 对活动文件的猜测（"log" / "csv" / "jsonl" / "plain"，未检测时为空），用于提示合适的显示方式
+    let (detected_format, set_detected_format) = signal(String::new());
+    let line_colors = Memo::new(move |_| {
+        if !log_coloring.get() {
+            return Vec::new();
+        }
+        let rules = log_rules.get();
+        wrapped.with(|(text, rows, _)| {
+            let mut current = None;
+            text.lines()
+                .zip(rows.iter())
+                .map(|(line, row)| {
+                    if row.is_some() {
+                        current = classify_log_line(line, &rules).map(|r| r.color.clone());
+                    }
+                    current.clone()
+                })
+                .collect::<Vec<_>>()
+        })
+    });
+    // 筛选模式：只显示包含 filter_needle 的行，行号区显示原始行号。
+    // 筛选模式下 current_line / 滚动条表示匹配行序号，visible_start 保持进入筛选前的位置
+    let (filter_needle, set_filter_needle) = signal(Option::<String>::None);
+    let (filter_total, set_filter_total) = signal(0usize);
+    let (filter_line_nos, set_filter_line_nos) = signal(Vec::<usize>::new());
+
+    // 上一次搜索落在当前页内的匹配：逐显示行给出 (文本片段, 是否为匹配, 是否为当前匹配)，没有匹配的行为空列表。
+    // 翻页（滚轮/滚动条/键盘）后同样生效，而不只是 `<` / `>` 定位到的那一个匹配；
+    // `current_match_idx` 指向的匹配单独标记，`<` / `>` 切换时随之移动
+    let row_highlights = Memo::new(move |_| {
+        let start = visible_start.get();
+        let active_idx = current_match_idx.get();
+        let filtered = filter_needle.get().map(|_| filter_line_nos.get());
+        wrapped.with(|(text, rows, shown)| {
+            // 每个逻辑行（相对序号）内的匹配字符区间
+            let mut ranges: Vec<Vec<(usize, usize, bool)>> = vec![Vec::new(); *shown];
+            // 列对齐后字符位置已经改变，不再标出匹配
+            if !align_columns.get() && page_find_active() {
+                let active_idx = page_find_idx.get();
+                page_hits.with(|hits| {
+                    for (k, &(rel, a, b)) in hits.iter().enumerate() {
+                        if let Some(slot) = ranges.get_mut(rel) {
+                            slot.push((a, b, k == active_idx));
+                        }
+                    }
+                });
+            } else if !align_columns.get() && !reversed.get() {
+                matches_lines.with(|lines| matches_list.with(|list| {
+                    for (k, &abs) in lines.iter().enumerate() {
+                        let rel = match &filtered {
+                            Some(nos) => nos.binary_search(&abs).ok(),
+                            None => abs.checked_sub(start),
+                        };
+                        let Some(slot) = rel.and_then(|rel| ranges.get_mut(rel)) else { continue };
+                        let Some(jv) = list.get(k).and_then(|m| js_sys::JSON::parse(m).ok()) else { continue };
+                        let field = |name: &str| js_sys::Reflect::get(&jv, &JsValue::from_str(name)).ok().and_then(|v| v.as_f64()).unwrap_or(0.0) as usize;
+                        let (mut column, length) = (field("column"), field("length"));
+                        if length == 0 {
+                            continue;
+                        }
+                        let mut end = column + length;
+                        // 插件给出的是原文中的列，去除转义序列后换算到显示的位置
+                        if let Some(map) = ansi_lines.with(|l| rel.and_then(|rel| l.get(rel)).map(|(_, map, _)| map.clone())) {
+                            let at = |c: usize| map.get(c).or(map.last()).copied().unwrap_or(0);
+                            (column, end) = (at(column), at(end));
+                            if column >= end {
+                                continue;
+                            }
+                        }
+                        let length = end - column;
+                        let active = k == active_idx;
+                        match slot.iter_mut().find(|r| r.0 == column && r.1 == column + length) {
+                            Some(r) => r.2 |= active,
+                            None => slot.push((column, column + length, active)),
+                        }
+                    }
+                }));
+            }
+            for v in &mut ranges {
+                v.sort_unstable();
+            }
+            // 按 ANSI 颜色着色的区间，列对齐后位置已经改变，不再着色
+            let colors = if ansi_mode.get() == AnsiMode::Color && !align_columns.get() {
+                ansi_lines.with(|l| l.iter().map(|(_, _, spans)| spans.clone()).collect::<Vec<_>>())
+            } else {
+                Vec::new()
+            };
+            let mut rel = 0usize;
+            let mut row_start = 0usize;
+            text.lines()
+                .zip(rows.iter())
+                .map(|(row_text, row)| {
+                    if let Some(i) = row {
+                        rel = *i;
+                        row_start = 0;
+                    }
+                    let chars: Vec<char> = row_text.chars().collect();
+                    let row_end = row_start + chars.len();
+                    // 逐字符标出 (是否为匹配, 是否为当前匹配, 颜色)，再把属性相同的相邻字符合并为一段；
+                    // 硬换行时一个匹配或着色区间可能跨越多行，只取落在本显示行内的部分
+                    let mut attrs: Vec<(bool, bool, Option<&str>)> = vec![(false, false, None); chars.len()];
+                    for (a, b, color) in colors.get(rel).map(|v| v.as_slice()).unwrap_or(&[]) {
+                        for attr in &mut attrs[(*a).clamp(row_start, row_end) - row_start..(*b).clamp(row_start, row_end) - row_start] {
+                            attr.2 = Some(color.as_str());
+                        }
+                    }
+                    for &(a, b, active) in ranges.get(rel).map(|v| v.as_slice()).unwrap_or(&[]) {
+                        for attr in &mut attrs[a.clamp(row_start, row_end) - row_start..b.clamp(row_start, row_end).max(a.clamp(row_start, row_end)) - row_start] {
+                            attr.0 = true;
+                            attr.1 |= active;
+                        }
+                    }
+                    let mut segments: Vec<(String, bool, bool, Option<String>)> = Vec::new();
+                    let mut at = 0usize;
+                    while at < chars.len() {
+                        let attr = attrs[at];
+                        let end = (at..chars.len()).find(|&k| attrs[k] != attr).unwrap_or(chars.len());
+                        segments.push((chars[at..end].iter().collect::<String>(), attr.0, attr.1, attr.2.map(str::to_string)));
+                        at = end;
+                    }
+                    // 没有匹配与着色的行为空列表
+                    if segments.iter().all(|s| !s.1 && s.3.is_none()) {
+                        segments.clear();
+                    }
+                    row_start = row_end;
+                    segments
+                })
+                .collect::<Vec<_>>()
+        })
+    });
+    // 高亮当前行：在背景层中给光标行加底色，光标行可用 ↑ / ↓ 逐行移动，到达页面边缘时视图随之滚动一行。
+    // 翻页后光标不在视图内时回到视图的第一行/最后一行；筛选视图中不显示
+    let (highlight_current, set_highlight_current) = signal(false);
+    let (cursor_line, set_cursor_line) = signal(0usize);
+    Effect::new(move |_| {
+        let top = visible_start.get();
+        let shown = wrapped.with(|w| w.2).max(1);
+        let cur = cursor_line.get_untracked();
+        if cur < top {
+            set_cursor_line.set(top);
+        } else if cur >= top + shown {
+            set_cursor_line.set(top + shown - 1);
+        }
+    });
+    // 光标行在当前页中的逻辑行相对序号
+    let cursor_rel = Memo::new(move |_| {
+        if !highlight_current.get() || filter_needle.get().is_some() || reversed.get() {
+            return None;
+        }
+        let rel = cursor_line.get().checked_sub(visible_start.get())?;
+        (rel < wrapped.with(|w| w.2)).then_some(rel)
+    });
+    // 智能 Home：把 textarea 的光标移到光标行（未开启高亮当前行时为视图第一行）的第一个非空白字符处，
+    // 已经在那里时再移到行首。硬换行时只处理该逻辑行的第一个显示行
+    let smart_home = move |textarea: &web_sys::HtmlTextAreaElement| {
+        let rel = cursor_rel.get_untracked().unwrap_or(0);
+        let Some(row) = wrapped.with_untracked(|w| w.1.iter().position(|r| *r == Some(rel))) else { return };
+        let content = textarea.value();
+        let mut row_start = 0usize;
+        let mut indent = 0usize;
+        for (i, l) in content.lines().enumerate() {
+            if i == row {
+                indent = l.chars().take_while(|c| c.is_whitespace()).count();
+                break;
+            }
+            row_start = row_start.saturating_add(l.chars().count()).saturating_add(1);
+        }
+        let caret = textarea.selection_start().ok().flatten().unwrap_or(0) as usize;
+        let target = if caret == row_start + indent { row_start } else { row_start + indent };
+        let _ = textarea.set_selection_start(Some(target as u32));
+        let _ = textarea.set_selection_end(Some(target as u32));
+        if target == row_start {
+            let he: web_sys::HtmlElement = textarea.clone().unchecked_into();
+            he.set_scroll_left(0);
+        }
+    };
+    // “所在代码块”的行范围 (首行, 末行)，在背景层中标出；换文件或进入筛选/倒序视图时清除
+    let (block_range, set_block_range) = signal(Option::<(usize, usize)>::None);
+    Effect::new(move |_| {
+        file_path.track();
+        filter_needle.track();
+        reversed.track();
+        set_block_range.set(None);
+    });
+
+    // 开启时询问折行列数（默认 120），已开启时再次点击则关闭；作为按文件设置保存，再次打开该文件时恢复
+    let toggle_wrap = move || {
+        if wrap_col.get_untracked().is_some() {
+            set_wrap_col.set(None);
+            save_file_setting(WRAP_COL_FILE_SETTING, JsValue::NULL);
+            return;
+        }
+        let input = web_sys::window().and_then(|w| w.prompt_with_message_and_default("在第几列换行？", "120").ok().flatten());
+        if let Some(n) = input.and_then(|v| v.trim().parse::<usize>().ok()).filter(|n| *n > 0) {
+            set_wrap_col.set(Some(n));
+            save_file_setting(WRAP_COL_FILE_SETTING, JsValue::from(n as u32));
+        }
+    };
+    // 指定列分隔符：留空为自动识别，`\t` 表示制表符；取消时保持不变
+    let choose_delimiter = move || {
+        let current = match column_delimiter.get_untracked() {
+            Some('\t') => "\\t".to_string(),
+            Some(c) => c.to_string(),
+            None => String::new(),
+        };
+        let input = web_sys::window().and_then(|w| w.prompt_with_message_and_default("列分隔符（留空为自动识别，\\t 表示制表符）", &current).ok().flatten());
+        let Some(v) = input else { return };
+        let v = v.trim();
+        let delimiter = if v.is_empty() {
+            None
+        } else if v == "\\t" {
+            Some('\t')
+        } else {
+            v.chars().next()
+        };
+        set_column_delimiter.set(delimiter);
+        set_align_columns.set(true);
+    };
+    let display_compare_content = Memo::new(move |_| {
+        if trim_trailing.get() { trim_trailing_ws(&compare_content.get()) } else { compare_content.get() }
+    });
+
+    // 当前视图显示的绝对行范围 `[start, end)`，由 visible_start 与已加载的行数得出
+    let view_range = Memo::new(move |_| {
+        let start = visible_start.get();
+        let loaded = wrapped.with(|w| w.2);
+        (start, (start + loaded).min(total_lines.get().max(start)))
+    });
+
+    // 最近一次通过行号复制的行（绝对行号），用于短暂的 “已复制” 视觉反馈
+    let (copied_line, set_copied_line) = signal(Option::<usize>::None);
+
+    // UI 状态
+    let (loading, set_loading) = signal(false);
+    // 正在进行的翻页读取（load_content）数，与打开/搜索的 loading 分开，只用于编辑框上的加载提示
+    let (page_loads, set_page_loads) = signal(0u32);
+    // 正在打开文件（文件选择、临时复制与换行扫描期间），此时显示 “取消打开” 按钮
+    let (opening, set_opening) = signal(false);
+    // 搜索专用 loading 状态：区分 “打开文件” 与 “正在搜索” 两种不同的 loading 文案
+    let (searching, set_searching) = signal(false);
+    // 每次 read_lines 最多加载的行数：可在菜单中设置（MIN..=MAX），保存在插件的 settings 中。
+    // 实际加载量还会按编辑框可见行数裁剪；调大可以在大窗口中减少翻页时的 IPC 往返，代价是单次返回更大
+    const DEFAULT_LINES_PER_PAGE: usize = 30;
+    const MIN_LINES_PER_PAGE: usize = 10;
+    const MAX_LINES_PER_PAGE: usize = 500;
+    const LINES_PER_PAGE_SETTING: &str = "lines_per_page";
+    let (lines_per_page, set_lines_per_page) = signal(DEFAULT_LINES_PER_PAGE);
+
+    // 如果无法测量，可回退到这个值
+    const DEFAULT_VISIBLE_LINES: usize = 20;
+    // 为避免边界处出现竖向滚动条，保留一个安全行数的余量
+    const VISIBLE_SAFETY_MARGIN: usize = 2;
+
+    // 弹窗错误提示的辅助函数
+    async fn show_error(message: &str) {
+        let _ = dialog::message(message, dialog::MessageOptions { title: Some("错误"), kind: None }).await;
+        console::error_1(&wasm_bindgen::JsValue::from_str(message));
+    }
+
+    // 格式化字节为 KB/MB 字符串
+    fn format_bytes(bytes: u64) -> String {
+        const KB: f64 = 1024.0;
+        const MB: f64 = KB * 1024.0;
+        const GB: f64 = MB * 1024.0;
+        let b = bytes as f64;
+        if b >= GB {
+            format!("{:.2} GB", b / GB)
+        } else if b >= MB {
+            format!("{:.2} MB", b / MB)
+        } else if b >= KB {
+            format!("{:.2} KB", b / KB)
+        } else {
+            format!("{} B", bytes)
+        }
+    }
+
+    // 生成文件格式说明，例如 "UTF-8 · CRLF"
+    fn format_label(info: &OpenResult) -> String {
+        let mut parts = vec![info.encoding.to_uppercase()];
+        match info.line_ending.as_str() {
+            "lf" => parts.push("LF".to_string()),
+            "crlf" => parts.push("CRLF".to_string()),
+            "mixed" => parts.push("混合换行".to_string()),
+            _ => {}
+        }
+        if info.is_binary {
+            parts.push("二进制".to_string());
+        }
+        if let Some(c) = &info.compression {
+            parts.push(format!("{} 压缩（{}）", c.to_uppercase(), format_bytes(info.compressed_size.unwrap_or(0))));
+        }
+        parts.retain(|p| !p.is_empty());
+        parts.join(" · ")
+    }
+
+    // 安全调用 invoke 的辅助函数：返回 Result 而不是直接 panic
+    async fn call_invoke(cmd: &str, args: JsValue) -> Result<wasm_bindgen::JsValue, wasm_bindgen::JsValue> {
+        let p = invoke_promise(cmd, args);
+        match JsFuture::from(p).await {
+            Ok(v) => Ok(v),
+            Err(e) => Err(e),
+        }
+    }
+
+    // 按句柄关闭一个后台文件（对比文件）；失败只记录日志
+    async fn close_handle(handle: u32) {
+        let args = js_sys::Object::new();
+        let _ = js_sys::Reflect::set(&args, &wasm_bindgen::JsValue::from_str("handle"), &wasm_bindgen::JsValue::from(handle));
+        if let Err(e) = call_invoke("plugin:large-file-preview|close_file", wasm_bindgen::JsValue::from(args)).await {
+            console::warn_1(&e);
+        }
+    }
+
+    // 多标签：每个打开的文件是一个标签，对应插件中的一个句柄；active_handle 为当前活动标签
+    let (tabs, set_tabs) = signal(Vec::<TabState>::new());
+    let (active_handle, set_active_handle) = signal(Option::<u32>::None);
+
+    // 把当前视图状态写回活动标签
+    let snapshot_active_tab = move || {
+        let Some(h) = active_handle.get_untracked() else { return };
+        let state = TabState {
+            handle: h,
+            path: file_path.get_untracked(),
+            total_lines: total_lines.get_untracked(),
+            file_size: file_size.get_untracked(),
+            file_format: file_format.get_untracked(),
+            open_stats: open_stats.get_untracked(),
+            current_line: visible_start.get_untracked(),
+            search_query: search_query.get_untracked(),
+            matches_list: matches_list.get_untracked(),
+            matches_lines: matches_lines.get_untracked(),
+            current_match_idx: current_match_idx.get_untracked(),
+            search_info: search_info.get_untracked(),
+            detected_format: detected_format.get_untracked(),
+            tail_mb: tail_mb.get_untracked(),
+            line_ending: line_ending.get_untracked(),
+            indexed: indexed.get_untracked(),
+            wrap_col: wrap_col.get_untracked(),
+        };
+        set_tabs.update(|tabs| {
+            if let Some(t) = tabs.iter_mut().find(|t| t.handle == h) {
+                *t = state;
+            }
+        });
+    };
+
+    // 用标签保存的状态恢复视图并加载其当前页（调用前插件中的活动文件应已切换到该标签）
+    let restore_tab = move |t: TabState| {
+        set_filter_needle.set(None);
+        set_filter_line_nos.set(Vec::new());
+        set_encoding_previews.set(Vec::new());
+        set_file_path.set(t.path);
+        set_file_size.set(t.file_size);
+        set_file_format.set(t.file_format);
+        set_open_stats.set(t.open_stats);
+        set_total_lines.set(t.total_lines);
+        set_current_line.set(t.current_line);
+        set_visible_start.set(t.current_line);
+        set_search_query.set(t.search_query);
+        set_matches_list.set(t.matches_list);
+        set_matches_lines.set(t.matches_lines);
+        set_current_match_idx.set(t.current_match_idx);
+        set_search_info.set(t.search_info);
+        set_detected_format.set(t.detected_format);
+        set_tail_mb.set(t.tail_mb);
+        set_indexed.set(t.indexed);
+        set_line_ending.set(t.line_ending);
+        set_wrap_col.set(t.wrap_col);
+        set_active_handle.set(Some(t.handle));
+        schedule_auto_scroll("file-path", marquee_speed.get_untracked());
+        let visible = compute_visible_lines("editor-textarea").unwrap_or(DEFAULT_VISIBLE_LINES);
+        let safe = visible.saturating_sub(VISIBLE_SAFETY_MARGIN).max(1);
+        load_content(t.current_line, safe.min(lines_per_page.get_untracked()), set_file_content, set_page_loads, None);
+    };
+
+    // 切换到句柄为 `h` 的标签
+    let switch_tab = move |h: u32| {
+        if active_handle.get_untracked() == Some(h) {
+            return;
+        }
+        spawn_local(async move {
+            let args = serde_wasm_bindgen::to_value(&HandleArgs { handle: h }).unwrap();
+            if let Err(e) = call_invoke("plugin:large-file-preview|activate_file", args).await {
+                let em = e.as_string().unwrap_or_else(|| format!("{:?}", e));
+                show_error(&format!("切换标签失败：{}", em)).await;
+                return;
+            }
+            snapshot_active_tab();
+            if let Some(t) = tabs.get_untracked().into_iter().find(|t| t.handle == h) {
+                restore_tab(t);
+            }
+        });
+    };
+
+    // 应用 open_file / open_file_by_path 的结果：新建并切换到该文件的标签，更新文件信息、恢复阅读位置并加载第一页
+    // 活动文件为空（长度为 0 或没有任何行）时在编辑区显示“文件为空”，而不是一片空白
+    let file_empty = Memo::new(move |_| !file_path.get().is_empty() && (file_size.get() == 0 || total_lines.get() == 0));
+    // 在打开结果到达前先收到的落点（句柄, 行号），由 apply_open_result 代替上次的阅读位置使用
+    let pending_target = StoredValue::new(Option::<(u32, usize)>::None);
+    let apply_open_result = move |info: OpenResult| {
+        // 重新选择了未改动的活动文件：插件复用了原来的句柄，保持当前视图不变
+        if active_handle.get_untracked() == Some(info.handle) {
+            return;
+        }
+        snapshot_active_tab();
+        set_tabs.update(|tabs| tabs.push(TabState { handle: info.handle, path: info.path.clone(), ..Default::default() }));
+        set_active_handle.set(Some(info.handle));
+        set_filter_needle.set(None);
+        set_filter_line_nos.set(Vec::new());
+        set_matches_list.set(Vec::new());
+        set_matches_lines.set(Vec::new());
+        set_current_match_idx.set(0);
+        set_search_info.set(String::new());
+        set_file_format.set(format_label(&info));
+        set_open_stats.set(if info.indexed {
+            format!("打开扫描 {}，用时 {} ms", format_bytes(info.bytes_scanned), info.open_duration_ms)
+        } else {
+            format!("快速打开：未建立索引，总行数按文件头 {} 估算", format_bytes(info.bytes_scanned))
+        });
+        set_tail_mb.set(None);
+        set_indexed.set(info.indexed);
+        set_line_ending.set(info.line_ending.clone());
+        set_detected_format.set(String::new());
+        if !info.is_binary {
+            let handle = info.handle;
+            spawn_local(async move {
+                // 只是提示，失败时不打扰用户；检测完成前已切换标签时丢弃结果
+                if let Ok(v) = call_invoke("plugin:large-file-preview|detect_format", JsValue::NULL).await {
+                    let kind = js_sys::Reflect::get(&v, &wasm_bindgen::JsValue::from_str("kind")).ok().and_then(|k| k.as_string()).unwrap_or_default();
+                    if active_handle.get_untracked() == Some(handle) {
+                        set_detected_format.set(kind);
+                    }
+                }
+            });
+        }
+        // 文件头不是合法 UTF-8 时，让用户从候选编码中挑选；已为该文件记住了其它编码时不再询问
+        if !info.encoding_confident && !info.is_binary && info.encoding == "utf-8" {
+            spawn_local(async move { load_encoding_previews(set_encoding_previews).await; });
+        }
+        let is_empty = info.is_empty;
+        set_file_path.set(info.path);
+        set_file_size.set(info.size);
+        // 恢复上次关闭时的阅读位置（超出范围时回到开头），并预填最近一次搜索
+        let restore_line = match pending_target.get_value() {
+            Some((handle, line)) if handle == info.handle => line.min(info.total_lines.saturating_sub(1)),
+            _ if info.session.last_line < info.total_lines => info.session.last_line,
+            _ => 0,
+        };
+        pending_target.set_value(None);
+        if let Some(q) = info.session.search_history.first() {
+            set_search_query.set(q.clone());
+        }
+        // 该文件保存的设置覆盖全局默认值（没有保存时不折行）
+        set_wrap_col.set(info.session.settings.wrap_col);
+        set_visible_start.set(restore_line);
+        // schedule auto-scroll for filename display after DOM updates
+        schedule_auto_scroll("file-path", marquee_speed.get_untracked());
+        set_total_lines.set(info.total_lines);
+        set_current_line.set(restore_line);
+
+        // 在 DOM 更新后测量编辑框可见行数并加载对应行数，避免出现垂直滚动
+        // 延迟一点时间以等待 textarea 渲染并计算高度；空文件没有可加载的行，直接清空视图
+        if is_empty {
+            set_file_content.set(String::new());
+        } else {
+            let set_file_content = set_file_content.clone();
+            let set_page_loads = set_page_loads.clone();
+            let _ = web_sys::window().map(|w| {
+                let closure = Closure::wrap(Box::new(move || {
+                    let visible = compute_visible_lines("editor-textarea").unwrap_or(DEFAULT_VISIBLE_LINES);
+                    // 留出安全边距，避免载入过满导致竖向滚动
+                    let safe = visible.saturating_sub(VISIBLE_SAFETY_MARGIN).max(1);
+                    let to_load = safe.min(lines_per_page.get_untracked());
+                    // 等待期间可能已收到落点并滚动过，按当前的视图起点加载
+                    load_content(visible_start.get_untracked(), to_load, set_file_content.clone(), set_page_loads.clone(), None);
+                }) as Box<dyn Fn()>);
+                let _ = w.set_timeout_with_callback_and_timeout_and_arguments_0(closure.as_ref().unchecked_ref(), 120);
+                closure.forget();
+            });
+        }
+
+        // 注册窗口 resize 的防抖处理：在 resize 结束后重新测量并加载可见行数
+        {
+            if let Some(win) = web_sys::window() {
+                // 创建防抖 closure（存放在 window.__txt_reader_resize_closure）
+                let set_file_content = set_file_content.clone();
+                let set_page_loads = set_page_loads.clone();
+                let resize_closure = Closure::wrap(Box::new(move || {
+                    // 在 resize 事件被触发后延迟 180ms 再测量
+                    if let Some(w2) = web_sys::window() {
+                        let inner = Closure::wrap(Box::new(move || {
+                            let visible = compute_visible_lines("editor-textarea").unwrap_or(DEFAULT_VISIBLE_LINES);
+                            let safe = visible.saturating_sub(VISIBLE_SAFETY_MARGIN).max(1);
+                            let to_load = safe.min(lines_per_page.get_untracked());
+                            load_content(0, to_load, set_file_content.clone(), set_page_loads.clone(), None);
+                        }) as Box<dyn Fn()>);
+                        let _ = w2.set_timeout_with_callback_and_timeout_and_arguments_0(inner.as_ref().unchecked_ref(), 180);
+                        inner.forget();
+                    }
+                }) as Box<dyn Fn()>);
+
+                // 将该 closure 赋给 window.__txt_reader_resize_closure 以便 later removal
+                let _ = js_sys::Reflect::set(&win, &wasm_bindgen::JsValue::from_str("__txt_reader_resize_closure"), resize_closure.as_ref());
+                // attach to onresize
+                let _ = win.set_onresize(Some(resize_closure.as_ref().unchecked_ref()));
+                // leak the closure intentionally (we will remove it reference on close)
+                resize_closure.forget();
+            }
+        }
+    };
+
+    // 打开文件（作为新标签；之前的活动文件留在后台，可切换回去）。`no_index` 为 true 时为快速打开：
+    // 插件不扫描整个文件、不建立行索引，适合只看超大文件的开头
+    let open_file_with = move |no_index: bool| {
+        spawn_local(async move {
+            // removed perf log
+            set_loading.set(true);
+            set_opening.set(true);
+            // pass extension filters to plugin (text files and compressed text)
+            let args = js_sys::Object::new();
+            let ex = serde_wasm_bindgen::to_value(OPEN_EXTENSIONS).unwrap();
+            let _ = js_sys::Reflect::set(&args, &wasm_bindgen::JsValue::from_str("extensions"), &ex);
+            // 先登记为后台文件再激活，使原活动文件保留为另一个标签而不是被替换
+            let _ = js_sys::Reflect::set(&args, &wasm_bindgen::JsValue::from_str("activate"), &wasm_bindgen::JsValue::FALSE);
+            let _ = js_sys::Reflect::set(&args, &wasm_bindgen::JsValue::from_str("noIndex"), &wasm_bindgen::JsValue::from_bool(no_index));
+            let res = call_invoke("plugin:large-file-preview|open_file", wasm_bindgen::JsValue::from(args)).await;
+            set_opening.set(false);
+            let res = match res {
+                Ok(v) => v,
+                Err(e) => {
+                    let em = e.as_string().unwrap_or_else(|| format!("{:?}", e));
+                    // 用户主动取消不算错误
+                    if !em.contains(OPEN_CANCELLED_MSG) {
+                        show_error(&format!("打开文件调用失败：{}", em)).await;
+                    }
+                    set_loading.set(false);
+                    return;
+                }
+            };
+                        // removed perf log
+
+            let info: OpenResult = match serde_wasm_bindgen::from_value(res) {
+                Ok(v) => v,
+                Err(e) => {
+                    show_error(&format!("打开文件失败：无法解析返回值 {}", e)).await;
+                    set_loading.set(false);
+                    return;
+                }
+            };
+            if info.path.is_empty() {
+                show_error("打开文件失败：返回的 path 字段为空").await;
+                set_loading.set(false);
+                return;
+            }
+            let args = serde_wasm_bindgen::to_value(&HandleArgs { handle: info.handle }).unwrap();
+            if let Err(e) = call_invoke("plugin:large-file-preview|activate_file", args).await {
+                let em = e.as_string().unwrap_or_else(|| format!("{:?}", e));
+                show_error(&format!("打开文件失败：{}", em)).await;
+                close_handle(info.handle).await;
+                set_loading.set(false);
+                return;
+            }
+            apply_open_result(info);
+            set_loading.set(false);
+        });
+    };
+    let open_file = move |ev: leptos::ev::MouseEvent| {
+        let _ = ev; // keep signature compatible
+        open_file_with(false);
+    };
+
+    // 启动时按设置重新打开最近的文件；文件已不存在或打开失败时保持空白状态，只记日志
+    let (reopen_last, set_reopen_last) = signal(false);
+    spawn_local(async move {
+        let Ok(v) = call_invoke("plugin:large-file-preview|get_startup_info", JsValue::NULL).await else { return };
+        let enabled = js_sys::Reflect::get(&v, &JsValue::from_str("reopen_last")).ok().and_then(|b| b.as_bool()).unwrap_or(false);
+        set_reopen_last.set(enabled);
+        let settings = js_sys::Reflect::get(&v, &JsValue::from_str("settings")).unwrap_or(JsValue::UNDEFINED);
+        if let Some(n) = js_sys::Reflect::get(&settings, &JsValue::from_str(LINES_PER_PAGE_SETTING)).ok().and_then(|n| n.as_f64()) {
+            set_lines_per_page.set((n as usize).clamp(MIN_LINES_PER_PAGE, MAX_LINES_PER_PAGE));
+        }
+        if let Some(v) = js_sys::Reflect::get(&settings, &JsValue::from_str(MARQUEE_SPEED_SETTING)).ok().and_then(|v| v.as_f64()) {
+            set_marquee_speed.set(v.clamp(MIN_MARQUEE_SPEED, MAX_MARQUEE_SPEED));
+        }
+        if let Some(v) = js_sys::Reflect::get(&settings, &JsValue::from_str(MARQUEE_PAUSE_SETTING)).ok().and_then(|v| v.as_bool()) {
+            set_marquee_pause.set(v);
+        }
+        if let Some(v) = js_sys::Reflect::get(&settings, &JsValue::from_str(SCROLLBAR_BYTES_SETTING)).ok().and_then(|v| v.as_bool()) {
+            set_scrollbar_bytes.set(v);
+        }
+        if let Some(v) = js_sys::Reflect::get(&settings, &JsValue::from_str(OUTPUT_EOL_SETTING)).ok().and_then(|v| v.as_string()) {
+            set_output_eol.set(OutputEol::from_setting(&v));
+        }
+        if js_sys::Reflect::get(&settings, &JsValue::from_str(MATCH_POSITION_SETTING)).ok().and_then(|p| p.as_string()).as_deref() == Some("center") {
+            set_match_position.set(MatchPosition::Center);
+        }
+        let last = js_sys::Reflect::get(&v, &JsValue::from_str("recent_files")).ok()
+            .and_then(|a| a.dyn_into::<js_sys::Array>().ok())
+            .and_then(|a| a.get(0).as_string());
+        // 命令行参数指定了文件时优先打开它（不受“重新打开上次的文件”设置影响），并跳到指定的偏移或行
+        let launch = js_sys::Reflect::get(&v, &JsValue::from_str("launch")).unwrap_or(JsValue::NULL);
+        let launch_path = js_sys::Reflect::get(&launch, &JsValue::from_str("path")).ok().and_then(|p| p.as_string());
+        let from_launch = launch_path.is_some();
+        let args = match (launch_path, enabled, last) {
+            (Some(path), _, _) => OpenByPathArgs {
+                path,
+                byte_offset: js_sys::Reflect::get(&launch, &JsValue::from_str("byte_offset")).ok().and_then(|n| n.as_f64()).map(|n| n as u64),
+                line: js_sys::Reflect::get(&launch, &JsValue::from_str("line")).ok().and_then(|n| n.as_f64()).map(|n| n as usize),
+                activate: true,
+            },
+            (None, true, Some(path)) => OpenByPathArgs { path, byte_offset: None, line: None, activate: true },
+            _ => return,
+        };
+        let path = args.path.clone();
+        set_loading.set(true);
+        set_opening.set(true);
+        let res = call_invoke("plugin:large-file-preview|open_file_by_path", serde_wasm_bindgen::to_value(&args).unwrap()).await;
+        set_opening.set(false);
+        match res {
+            Ok(v) => match serde_wasm_bindgen::from_value::<OpenResult>(v) {
+                Ok(info) if !info.path.is_empty() => apply_open_result(info),
+                _ => console::log_1(&JsValue::from_str(&format!("reopen last file failed: {}", path))),
+            },
+            Err(e) if from_launch => {
+                let em = e.as_string().unwrap_or_else(|| format!("{:?}", e));
+                show_error(&format!("打开 {} 失败：{}", path, em)).await;
+            }
+            Err(_) => console::log_1(&JsValue::from_str(&format!("reopen last file failed: {}", path))),
+        }
+        set_loading.set(false);
+    });
+    // 询问每页行数并保存；超出范围时钳制到 MIN..=MAX
+    let choose_lines_per_page = move || {
+        let current = lines_per_page.get_untracked().to_string();
+        let message = format!("每次最多加载的行数（{}–{}）", MIN_LINES_PER_PAGE, MAX_LINES_PER_PAGE);
+        let input = web_sys::window().and_then(|w| w.prompt_with_message_and_default(&message, &current).ok().flatten());
+        let Some(n) = input.and_then(|v| v.trim().parse::<usize>().ok()) else { return };
+        let n = n.clamp(MIN_LINES_PER_PAGE, MAX_LINES_PER_PAGE);
+        set_lines_per_page.set(n);
+        let args = js_sys::Object::new();
+        let _ = js_sys::Reflect::set(&args, &JsValue::from_str("key"), &JsValue::from_str(LINES_PER_PAGE_SETTING));
+        let _ = js_sys::Reflect::set(&args, &JsValue::from_str("value"), &JsValue::from(n as u32));
+        spawn_local(async move {
+            if let Err(e) = call_invoke("plugin:large-file-preview|set_setting", JsValue::from(args)).await {
+                let em = e.as_string().unwrap_or_else(|| format!("{:?}", e));
+                show_error(&format!("保存设置失败：{}", em)).await;
+            }
+        });
+        // 按新的行数重新加载当前页
+        if !file_path.get_untracked().is_empty() {
+            let visible = compute_visible_lines("editor-textarea").unwrap_or(DEFAULT_VISIBLE_LINES);
+            let safe = visible.saturating_sub(VISIBLE_SAFETY_MARGIN).max(1);
+            load_content(visible_start.get_untracked(), safe.min(n), set_file_content, set_page_loads, None);
+        }
+    };
+
+    // 保存一项界面设置，失败时提示
+    fn save_setting(key: &'static str, value: JsValue) {
+        let args = js_sys::Object::new();
+        let _ = js_sys::Reflect::set(&args, &JsValue::from_str("key"), &JsValue::from_str(key));
+        let _ = js_sys::Reflect::set(&args, &JsValue::from_str("value"), &value);
+        spawn_local(async move {
+            if let Err(e) = call_invoke("plugin:large-file-preview|set_setting", JsValue::from(args)).await {
+                let em = e.as_string().unwrap_or_else(|| format!("{:?}", e));
+                show_error(&format!("保存设置失败：{}", em)).await;
+            }
+        });
+    }
+
+    // 保存活动文件的一项按文件设置（null 为删除，恢复全局默认值），插件在关闭文件时写盘
+    fn save_file_setting(key: &'static str, value: JsValue) {
+        let args = js_sys::Object::new();
+        let _ = js_sys::Reflect::set(&args, &JsValue::from_str("key"), &JsValue::from_str(key));
+        let _ = js_sys::Reflect::set(&args, &JsValue::from_str("value"), &value);
+        spawn_local(async move {
+            if let Err(e) = call_invoke("plugin:large-file-preview|set_file_setting", JsValue::from(args)).await {
+                let em = e.as_string().unwrap_or_else(|| format!("{:?}", e));
+                show_error(&format!("保存设置失败：{}", em)).await;
+            }
+        });
+    }
+
+    // 询问文件名滚动速度并保存，超出范围时钳制；立即按新速度重新计算滚动时长
+    let choose_marquee_speed = move || {
+        let current = marquee_speed.get_untracked().to_string();
+        let message = format!("文件名滚动速度（像素/秒，{}–{}）", MIN_MARQUEE_SPEED, MAX_MARQUEE_SPEED);
+        let input = web_sys::window().and_then(|w| w.prompt_with_message_and_default(&message, &current).ok().flatten());
+        let Some(v) = input.and_then(|v| v.trim().parse::<f64>().ok()).filter(|v| v.is_finite()) else { return };
+        let v = v.clamp(MIN_MARQUEE_SPEED, MAX_MARQUEE_SPEED);
+        set_marquee_speed.set(v);
+        save_setting(MARQUEE_SPEED_SETTING, JsValue::from(v));
+        if !file_path.get_untracked().is_empty() {
+            schedule_auto_scroll("file-path", v);
+        }
+    };
+    let toggle_marquee_pause = move || {
+        let v = !marquee_pause.get_untracked();
+        set_marquee_pause.set(v);
+        save_setting(MARQUEE_PAUSE_SETTING, JsValue::from(v));
+    };
+
+    // 在靠上与居中之间切换匹配行的位置并保存
+    let toggle_match_position = move || {
+        let next = match match_position.get_untracked() {
+            MatchPosition::Top => MatchPosition::Center,
+            MatchPosition::Center => MatchPosition::Top,
+        };
+        set_match_position.set(next);
+        save_setting(MATCH_POSITION_SETTING, JsValue::from_str(next.as_setting()));
+    };
+    // 在 与源文件相同 → LF → CRLF 之间切换复制与导出的换行符并保存
+    let cycle_output_eol = move || {
+        let next = match output_eol.get_untracked() {
+            OutputEol::Source => OutputEol::Lf,
+            OutputEol::Lf => OutputEol::Crlf,
+            OutputEol::Crlf => OutputEol::Source,
+        };
+        set_output_eol.set(next);
+        save_setting(OUTPUT_EOL_SETTING, JsValue::from_str(next.as_setting()));
+    };
+    let toggle_scrollbar_bytes = move || {
+        let v = !scrollbar_bytes.get_untracked();
+        set_scrollbar_bytes.set(v);
+        save_setting(SCROLLBAR_BYTES_SETTING, JsValue::from(v));
+    };
+
+    // 搜索历史下拉：打开时向插件读取本文件与全局的历史
+    let (show_history, set_show_history) = signal(false);
+    let (search_history, set_search_history) = signal(SearchHistory::default());
+    let toggle_history = move |_: leptos::ev::MouseEvent| {
+        if show_history.get_untracked() {
+            set_show_history.set(false);
+            return;
+        }
+        spawn_local(async move {
+            match call_invoke("plugin:large-file-preview|get_search_history", JsValue::NULL).await {
+                Ok(v) => {
+                    set_search_history.set(serde_wasm_bindgen::from_value::<SearchHistory>(v).unwrap_or_default());
+                    set_show_history.set(true);
+                }
+                Err(e) => {
+                    let em = e.as_string().unwrap_or_else(|| format!("{:?}", e));
+                    show_error(&format!("读取搜索历史失败：{}", em)).await;
+                }
+            }
+        });
+    };
+    let pick_history = move |q: String| {
+        set_search_query.set(q);
+        set_show_history.set(false);
+    };
+
+    let toggle_reopen_last = move |_: leptos::ev::MouseEvent| {
+        let enabled = !reopen_last.get_untracked();
+        set_reopen_last.set(enabled);
+        spawn_local(async move {
+            let args = SetReopenLastArgs { enabled };
+            if let Err(e) = call_invoke("plugin:large-file-preview|set_reopen_last", serde_wasm_bindgen::to_value(&args).unwrap()).await {
+                show_error(&format!("保存设置失败：{:?}", e)).await;
+            }
+        });
+    };
+
+    // 打开对比文件：作为后台文件登记（activate=false），不替换当前主视图的文件
+    let open_compare = move |_: leptos::ev::MouseEvent| {
+        spawn_local(async move {
+            let args = js_sys::Object::new();
+            let ex = serde_wasm_bindgen::to_value(OPEN_EXTENSIONS).unwrap();
+            let _ = js_sys::Reflect::set(&args, &wasm_bindgen::JsValue::from_str("extensions"), &ex);
+            let _ = js_sys::Reflect::set(&args, &wasm_bindgen::JsValue::from_str("activate"), &wasm_bindgen::JsValue::FALSE);
+            let res = match call_invoke("plugin:large-file-preview|open_file", wasm_bindgen::JsValue::from(args)).await {
+                Ok(v) => v,
+                Err(e) => {
+                    let em = e.as_string().unwrap_or_else(|| format!("{:?}", e));
+                    show_error(&format!("打开对比文件失败：{}", em)).await;
+                    return;
+                }
+            };
+            match serde_wasm_bindgen::from_value::<OpenResult>(res) {
+                Ok(info) => {
+                    // 替换之前的对比文件
+                    if let Some(old) = compare_handle.get_untracked() {
+                        close_handle(old).await;
+                    }
+                    set_compare_path.set(info.path);
+                    set_diff_line.set(None);
+                    set_diff_info.set(String::new());
+                    set_compare_handle.set(Some(info.handle));
+                }
+                Err(e) => show_error(&format!("打开对比文件失败：无法解析返回值 {}", e)).await,
+            }
+        });
+    };
+
+    let close_compare = move |_: leptos::ev::MouseEvent| {
+        if let Some(h) = compare_handle.get_untracked() {
+            set_compare_handle.set(None);
+            set_compare_path.set(String::new());
+            set_diff_line.set(None);
+            set_diff_info.set(String::new());
+            spawn_local(async move {
+                close_handle(h).await;
+            });
+        }
+    };
+
+    // 对比模式下，右侧内容始终与主视图保持相同的起始行与行数
+    Effect::new(move |_| {
+        let Some(h) = compare_handle.get() else {
+            set_compare_content.set(String::new());
+            return;
+        };
+        let start = visible_start.get();
+        let count = file_content.with(|c| c.lines().count()).max(1);
+        spawn_local(async move {
+            let args = serde_wasm_bindgen::to_value(&ReadLinesArgs { start, count, handle: Some(h), row_per_line: true }).unwrap();
+            match call_invoke("plugin:large-file-preview|read_lines", args).await {
+                Ok(v) => set_compare_content.set(v.as_string().unwrap_or_default()),
+                Err(e) => console::warn_1(&e),
+            }
+        });
+    });
+
+    // 视图（滚轮/滚动条/跳转/翻页/筛选）变化时向宿主发出 `VIEW_CHANGED_EVENT`。
+    // 节流：第一次变化后等待 VIEW_CHANGED_THROTTLE_MS 再发送这段时间内的最后状态
+    let view_latest = StoredValue::new(ViewChanged::default());
+    let view_sent = StoredValue::new(Option::<ViewChanged>::None);
+    let view_pending = StoredValue::new(false);
+    Effect::new(move |_| {
+        if file_path.get().is_empty() {
+            view_sent.set_value(None);
+            return;
+        }
+        let shown = wrapped.with(|w| w.2);
+        let (top_line, bottom_line) = match filter_needle.get() {
+            Some(_) => filter_line_nos.with(|nos| {
+                let page = &nos[..shown.min(nos.len())];
+                (page.first().copied().unwrap_or(0), page.last().copied().unwrap_or(0))
+            }),
+            None => {
+                let top = visible_start.get();
+                (top, top + shown.saturating_sub(1))
+            }
+        };
+        view_latest.set_value(ViewChanged { top_line, bottom_line, total_lines: total_lines.get() });
+        if view_pending.get_value() {
+            return;
+        }
+        let Some(win) = web_sys::window() else { return };
+        view_pending.set_value(true);
+        let flush = Closure::once_into_js(move || {
+            view_pending.set_value(false);
+            let view = view_latest.get_value();
+            if view_sent.get_value() == Some(view) {
+                return;
+            }
+            view_sent.set_value(Some(view));
+            let payload = serde_wasm_bindgen::to_value(&view).unwrap_or(JsValue::NULL);
+            if let Err(e) = event_emit(VIEW_CHANGED_EVENT, payload) {
+                console::warn_1(&e);
+            }
+        });
+        let _ = win.set_timeout_with_callback_and_timeout_and_arguments_0(flush.unchecked_ref(), VIEW_CHANGED_THROTTLE_MS);
+    });
+
+    // 行号区按字节偏移显示时，批量获取当前页各行的起始偏移：(行号列表, 对应偏移)。
+    // 保存行号列表是为了在翻页过程中丢弃已过期的结果
+    let (gutter_mode, set_gutter_mode) = signal(GutterMode::default());
+    let (line_offsets, set_line_offsets) = signal((Vec::<usize>::new(), Vec::<u64>::new()));
+    Effect::new(move |_| {
+        if !gutter_mode.get().shows_offsets() || file_path.get().is_empty() {
+            set_line_offsets.set((Vec::new(), Vec::new()));
+            return;
+        }
+        let start = visible_start.get();
+        let shown = wrapped.with(|w| w.2);
+        let lines: Vec<usize> = match filter_needle.get() {
+            Some(_) => filter_line_nos.get().into_iter().take(shown).collect(),
+            None => (start..start + shown).collect(),
+        };
+        if lines.is_empty() {
+            return;
+        }
+        spawn_local(async move {
+            let args = serde_wasm_bindgen::to_value(&LineOffsetsArgs { lines: lines.clone() }).unwrap();
+            match call_invoke("plugin:large-file-preview|get_line_offsets", args).await {
+                Ok(v) => {
+                    let offsets: Vec<u64> = serde_wasm_bindgen::from_value(v).unwrap_or_default();
+                    set_line_offsets.set((lines, offsets));
+                }
+                Err(e) => console::warn_1(&e),
+            }
+        });
+    });
+
+    // 比较同一文件中的两段行（如同一日志中追加的两次运行），结果并排显示；None 表示对话框关闭
+    let (range_diff, set_range_diff) = signal(Option::<Vec<DiffLine>>::None);
+    let choose_diff_ranges = move || {
+        // 输入 “起始行,行数”（起始行从 1 计）
+        let ask = |message: &str, default: &str| -> Option<(usize, usize)> {
+            let input = web_sys::window().and_then(|w| w.prompt_with_message_and_default(message, default).ok().flatten())?;
+            let (start, count) = input.split_once([',', '，'])?;
+            let start = start.trim().parse::<usize>().ok().filter(|n| *n > 0)?;
+            Some((start - 1, count.trim().parse::<usize>().ok()?))
+        };
+        let default_a = format!("{},100", current_line.get_untracked() + 1);
+        let Some((start_a, count_a)) = ask("第一段：起始行,行数", &default_a) else { return };
+        let default_b = format!("{},{}", start_a + count_a + 1, count_a);
+        let Some((start_b, count_b)) = ask("第二段：起始行,行数", &default_b) else { return };
+        spawn_local(async move {
+            let args = serde_wasm_bindgen::to_value(&DiffRangesArgs { start_a, count_a, start_b, count_b }).unwrap();
+            match call_invoke("plugin:large-file-preview|diff_ranges", args).await {
+                Ok(v) => set_range_diff.set(Some(serde_wasm_bindgen::from_value::<Vec<DiffLine>>(v).unwrap_or_default())),
+                Err(e) => {
+                    let em = e.as_string().unwrap_or_else(|| format!("{:?}", e));
+                    show_error(&format!("比较失败：{}", em)).await;
+                }
+            }
+        });
+    };
+
+    // 校验值对话框：选择算法后对整个活动文件计算哈希，进度来自插件事件
+    let (show_hash_dialog, set_show_hash_dialog) = signal(false);
+    let (hash_algorithm, set_hash_algorithm) = signal("xxh3".to_string());
+    let (hash_progress, set_hash_progress) = signal(String::new());
+    let (hash_result, set_hash_result) = signal(String::new());
+    let (hashing, set_hashing) = signal(false);
+    // 导出匹配行：把当前搜索词（筛选模式下为筛选词）所在的行写入新文件，进度同样来自插件事件
+    let (exporting, set_exporting) = signal(false);
+    let (export_progress, set_export_progress) = signal(String::new());
+    // 查找无效 UTF-8：Some 时显示结果对话框（查找中为空列表），进度同样来自插件事件
+    let (invalid_utf8, set_invalid_utf8) = signal(Option::<Vec<InvalidUtf8>>::None);
+    let (scanning_utf8, set_scanning_utf8) = signal(false);
+    let (utf8_scan_progress, set_utf8_scan_progress) = signal(String::new());
+    // 重复行统计：Some 时显示频率表（统计中为默认值），进度同样来自插件事件
+    let (unique_result, set_unique_result) = signal(Option::<UniqueLines>::None);
+    let (counting_unique, set_counting_unique) = signal(false);
+    let (unique_progress, set_unique_progress) = signal(String::new());
+    {
+        let handler = Closure::wrap(Box::new(move |event: JsValue| {
+            let payload = js_sys::Reflect::get(&event, &JsValue::from_str("payload")).unwrap_or(JsValue::NULL);
+            let field = |name: &str| js_sys::Reflect::get(&payload, &JsValue::from_str(name)).ok().and_then(|v| v.as_f64()).unwrap_or(0.0);
+            let task = js_sys::Reflect::get(&payload, &JsValue::from_str("task")).ok().and_then(|v| v.as_string()).unwrap_or_default();
+            let (done, total) = (field("done"), field("total"));
+            if total > 0.0 {
+                let text = format!("{:.0}%（{} / {}）", done * 100.0 / total, format_bytes(done as u64), format_bytes(total as u64));
+                match task.as_str() {
+                    "hash" => set_hash_progress.set(text),
+                    "export" => set_export_progress.set(text),
+                    "utf8_scan" => set_utf8_scan_progress.set(text),
+                    "unique" => set_unique_progress.set(text),
+                    _ => {}
+                }
+            }
+        }) as Box<dyn Fn(JsValue)>);
+        let _ = event_listen(TASK_PROGRESS_EVENT, &handler);
+        handler.forget();
+    }
+    // 取消正在进行的导出或校验值计算；操作本身随后以 TASK_CANCELLED_MSG 失败返回
+    let cancel_task = move |_: leptos::ev::MouseEvent| {
+        spawn_local(async move {
+            let _ = call_invoke("plugin:large-file-preview|cancel_task", JsValue::NULL).await;
+        });
+    };
+    let compute_hash = move |_: leptos::ev::MouseEvent| {
+        set_hashing.set(true);
+        set_hash_result.set(String::new());
+        set_hash_progress.set(String::new());
+        spawn_local(async move {
+            let args = serde_wasm_bindgen::to_value(&FileHashArgs { algorithm: hash_algorithm.get_untracked() }).unwrap();
+            match call_invoke("plugin:large-file-preview|get_file_hash", args).await {
+                Ok(v) => {
+                    let digest = js_sys::Reflect::get(&v, &JsValue::from_str("digest")).ok().and_then(|d| d.as_string()).unwrap_or_default();
+                    let ms = js_sys::Reflect::get(&v, &JsValue::from_str("duration_ms")).ok().and_then(|d| d.as_f64()).unwrap_or(0.0);
+                    // 压缩文件：显示的是压缩文件本身的校验值，解压后内容的校验值附在说明中
+                    match js_sys::Reflect::get(&v, &JsValue::from_str("decompressed_digest")).ok().and_then(|d| d.as_string()) {
+                        Some(inner) => set_hash_progress.set(format!("压缩文件本身的校验值，用时 {:.0} ms；解压后内容：{}", ms, inner)),
+                        None => set_hash_progress.set(format!("用时 {:.0} ms", ms)),
+                    }
+                    set_hash_result.set(digest);
+                }
+                Err(e) => {
+                    let em = e.as_string().unwrap_or_else(|| format!("{:?}", e));
+                    if em.contains(TASK_CANCELLED_MSG) {
+                        set_hash_progress.set("已取消".to_string());
+                    } else {
+                        show_error(&format!("计算校验值失败：{}", em)).await;
+                    }
+                }
+            }
+            set_hashing.set(false);
+        });
+    };
+    let copy_hash = move |_: leptos::ev::MouseEvent| {
+        let digest = hash_result.get_untracked();
+        spawn_local(async move {
+            let res = match clipboard_write_text(&digest) {
+                Ok(p) => JsFuture::from(p).await,
+                Err(e) => Err(e),
+            };
+            if let Err(e) = res {
+                let em = e.as_string().unwrap_or_else(|| format!("{:?}", e));
+                show_error(&format!("复制失败：{}", em)).await;
+            }
+        });
+    };
+
+    // 跳到下一处差异：若上一处差异仍在当前页内则从其后一行继续，否则从当前视图顶部开始
+    let go_next_diff = move |_: leptos::ev::MouseEvent| {
+        let Some(h) = compare_handle.get_untracked() else { return; };
+        spawn_local(async move {
+            let top = visible_start.get_untracked();
+            let loaded = file_content.with_untracked(|c| c.lines().count());
+            let from = match diff_line.get_untracked() {
+                Some(d) if d >= top && d < top + loaded.max(1) => d + 1,
+                _ => top,
+            };
+            let args = serde_wasm_bindgen::to_value(&NextDiffArgs { from_line: from, other: h }).unwrap();
+            let res = match call_invoke("plugin:large-file-preview|next_diff", args).await {
+                Ok(v) => v,
+                Err(e) => {
+                    let em = e.as_string().unwrap_or_else(|| format!("{:?}", e));
+                    show_error(&format!("查找差异失败：{}", em)).await;
+                    return;
+                }
+            };
+            if res.is_undefined() || res.is_null() {
+                set_diff_info.set(format!("第 {} 行之后没有更多差异", from + 1));
+                return;
+            }
+            let line = js_sys::Reflect::get(&res, &wasm_bindgen::JsValue::from_str("line"))
+                .ok().and_then(|v| v.as_f64()).unwrap_or(0.0) as usize;
+            let ended = |key: &str| js_sys::Reflect::get(&res, &wasm_bindgen::JsValue::from_str(key))
+                .map(|v| v.is_null() || v.is_undefined()).unwrap_or(false);
+            let suffix = if ended("line_a") {
+                "（左侧已结束）"
+            } else if ended("line_b") {
+                "（右侧已结束）"
+            } else {
+                ""
+            };
+            set_diff_line.set(Some(line));
+            set_diff_info.set(format!("差异：第 {} 行{}", line + 1, suffix));
+            let visible = compute_visible_lines("editor-textarea").unwrap_or(DEFAULT_VISIBLE_LINES);
+            let safe = visible.saturating_sub(VISIBLE_SAFETY_MARGIN).max(1);
+            let start = match_view_start(line, safe.min(lines_per_page.get_untracked()));
+            set_visible_start.set(start);
+            set_current_line.set(start);
+            load_content(start, safe.min(lines_per_page.get_untracked()), set_file_content, set_page_loads, None);
+        });
+    };
+
+    // 筛选模式下加载从第 `start_match` 个匹配行开始的一页
+    let load_filtered = move |start_match: usize, with_total: bool| {
+        let Some(needle) = filter_needle.get_untracked() else { return };
+        let visible = compute_visible_lines("editor-textarea").unwrap_or(DEFAULT_VISIBLE_LINES);
+        let count = visible.saturating_sub(VISIBLE_SAFETY_MARGIN).max(1).min(lines_per_page.get_untracked());
+        spawn_local(async move {
+            let args = serde_wasm_bindgen::to_value(&FilteredLinesArgs {
+                needle,
+                ignore_case: SEARCH_IGNORE_CASE,
+                start_match,
+                count,
+                with_total,
+            }).unwrap();
+            let res = match call_invoke("plugin:large-file-preview|read_filtered_lines", args).await {
+                Ok(v) => v,
+                Err(e) => {
+                    let em = e.as_string().unwrap_or_else(|| format!("{:?}", e));
+                    show_error(&format!("筛选失败：{}", em)).await;
+                    return;
+                }
+            };
+            let page: FilteredPage = match serde_wasm_bindgen::from_value(res) {
+                Ok(p) => p,
+                Err(e) => {
+                    show_error(&format!("筛选失败：无法解析返回值 {}", e)).await;
+                    return;
+                }
+            };
+            // 等待期间已退出筛选模式则丢弃结果
+            if filter_needle.get_untracked().is_none() {
+                return;
+            }
+            if let Some(total) = page.total {
+                set_filter_total.set(total);
+            }
+            let mut text = String::new();
+            for l in &page.lines {
+                text.push_str(&l.text);
+                text.push('\n');
+            }
+            set_filter_line_nos.set(page.lines.iter().map(|l| l.line).collect());
+            set_file_content.set(text);
+        });
+    };
+
+    // 把视图顶部移动到 `new`（筛选模式下为匹配行序号），超出范围时钳制到末尾；滚轮与键盘翻页共用
+    // 视图顶部允许的最大行（筛选模式下为匹配行序号）：停在这里时最后一行恰好在页面底部，
+    // 不会再向后多翻出一页空白
+    let max_top_line = move || {
+        let total = if filter_needle.get().is_some() { filter_total.get() } else { total_lines.get() };
+        let visible = compute_visible_lines("editor-textarea").unwrap_or(DEFAULT_VISIBLE_LINES);
+        total.saturating_sub(visible.saturating_sub(VISIBLE_SAFETY_MARGIN).max(1))
+    };
+    // 平滑滚动：跳到匹配等导航时先加载目标页，再让 textarea 以动画滚到目标行（默认关闭，直接跳转）。
+    // 系统开启了“减少动态效果”时始终直接跳转
+    let (smooth_scroll, set_smooth_scroll) = signal(false);
+
+    // 滚动条语义：false 时滑块位置表示视图顶部的行，true 时表示视图中心的行
+    let (slider_center, set_slider_center) = signal(false);
+    // 中心模式下滑块值与视图顶部行之间的偏移（半页）
+    let slider_offset = move || {
+        if slider_center.get() {
+            let visible = compute_visible_lines("editor-textarea").unwrap_or(DEFAULT_VISIBLE_LINES);
+            visible.saturating_sub(VISIBLE_SAFETY_MARGIN).max(1) / 2
+        } else {
+            0
+        }
+    };
+
+    // 字节比例模式只用于普通视图；筛选与倒序视图的滚动条仍按行（匹配行序号）定位
+    let byte_slider = move || scrollbar_bytes.get() && filter_needle.get().is_none() && !reversed.get();
+    // 字节比例模式下视图顶部行的起始偏移：(行号, 偏移)，行号用于丢弃翻页过程中过期的结果
+    let (view_byte_offset, set_view_byte_offset) = signal((0usize, 0u64));
+    Effect::new(move |_| {
+        if !byte_slider() || file_path.get().is_empty() {
+            return;
+        }
+        let line = current_line.get();
+        spawn_local(async move {
+            let args = serde_wasm_bindgen::to_value(&LineOffsetsArgs { lines: vec![line] }).unwrap();
+            match call_invoke("plugin:large-file-preview|get_line_offsets", args).await {
+                Ok(v) => {
+                    let offsets: Vec<u64> = serde_wasm_bindgen::from_value(v).unwrap_or_default();
+                    if let (Some(&offset), true) = (offsets.first(), current_line.get_untracked() == line) {
+                        set_view_byte_offset.set((line, offset));
+                    }
+                }
+                Err(e) => console::warn_1(&e),
+            }
+        });
+    });
+    // 拖动时每次 input 都会请求换算，只采用最后一次请求的结果
+    let byte_seek_seq = StoredValue::new(0u32);
+
+    // 倒序视图的一页：从倒数第 `rev_start` 行起往前读 `count` 行
+    let load_reversed = move |rev_start: usize, count: usize| {
+        let Some(end_line) = total_lines.get_untracked().checked_sub(rev_start + 1) else {
+            set_file_content.set(String::new());
+            return;
+        };
+        set_page_loads.update(|n| *n += 1);
+        spawn_local(async move {
+            let args = serde_wasm_bindgen::to_value(&ReadLinesReverseArgs { end_line, count }).unwrap();
+            match call_invoke("plugin:large-file-preview|read_lines_reverse", args).await {
+                Ok(v) => set_file_content.set(v.as_string().unwrap_or_default()),
+                Err(e) => {
+                    let em = e.as_string().unwrap_or_else(|| format!("{:?}", e));
+                    show_error(&format!("读取文件内容调用失败：{}", em)).await;
+                }
+            }
+            set_page_loads.update(|n| *n = n.saturating_sub(1));
+        });
+    };
+
+    // 跟随末尾（类似 tail -f）：插件检测到文件增长后发出 APPENDED_EVENT。`follow_pinned` 表示视图停在末尾，
+    // 此时新内容到达会自动滚到底部；用户向上滚动时取消，滚回末尾时恢复
+    let (follow_tail, set_follow_tail) = signal(false);
+    let (follow_pinned, set_follow_pinned) = signal(true);
+
+    let scroll_to = move |new: usize| {
+        let filtering = filter_needle.get_untracked().is_some();
+        let max = untrack(max_top_line);
+        let new = new.min(max);
+        if follow_tail.get_untracked() && !filtering && !reversed.get_untracked() {
+            set_follow_pinned.set(new >= max);
+        }
+        set_current_line.set(new);
+        if filtering {
+            load_filtered(new, false);
+            return;
+        }
+        let visible = compute_visible_lines("editor-textarea").unwrap_or(DEFAULT_VISIBLE_LINES);
+        let safe = visible.saturating_sub(VISIBLE_SAFETY_MARGIN).max(1);
+        set_visible_start.set(new);
+        if reversed.get_untracked() {
+            load_reversed(new, safe.min(lines_per_page.get_untracked()));
+            return;
+        }
+        load_content(new, safe.min(lines_per_page.get_untracked()), set_file_content, set_page_loads, None);
+    };
+
+    {
+        let handler = Closure::wrap(Box::new(move |event: JsValue| {
+            let payload = js_sys::Reflect::get(&event, &JsValue::from_str("payload")).unwrap_or(JsValue::NULL);
+            let total = js_sys::Reflect::get(&payload, &JsValue::from_str("total_lines")).ok().and_then(|v| v.as_f64()).unwrap_or(0.0) as usize;
+            if file_path.get_untracked().is_empty() || !follow_tail.get_untracked() {
+                return;
+            }
+            set_total_lines.set(total);
+            if filter_needle.get_untracked().is_some() {
+                return;
+            }
+            if reversed.get_untracked() {
+                // 倒序视图中新内容出现在最上面，停在顶部时直接看到
+                scroll_to(current_line.get_untracked());
+            } else if follow_pinned.get_untracked() {
+                scroll_to(usize::MAX);
+            } else {
+                // 未固定在末尾时保持当前位置，只在视图包含末尾附近的行时刷新
+                scroll_to(current_line.get_untracked());
+            }
+        }) as Box<dyn Fn(JsValue)>);
+        let _ = event_listen(APPENDED_EVENT, &handler);
+        handler.forget();
+    }
+    {
+        // 索引在后台建立完成（包括其他入口触发的）时把估算的“约 N 行”换成精确值；只处理仍是活动文件的句柄
+        let handler = Closure::wrap(Box::new(move |event: JsValue| {
+            let payload = js_sys::Reflect::get(&event, &JsValue::from_str("payload")).unwrap_or(JsValue::NULL);
+            let Some(handle) = js_sys::Reflect::get(&payload, &JsValue::from_str("handle")).ok().and_then(|v| v.as_f64()).map(|v| v as u32) else { return };
+            if active_handle.get_untracked() != Some(handle) {
+                return;
+            }
+            let total = js_sys::Reflect::get(&payload, &JsValue::from_str("total_lines")).ok().and_then(|v| v.as_f64()).unwrap_or(0.0) as usize;
+            let exact = js_sys::Reflect::get(&payload, &JsValue::from_str("exact")).ok().and_then(|v| v.as_bool()).unwrap_or(false);
+            set_total_lines.set(total);
+            set_indexed.set(exact);
+        }) as Box<dyn Fn(JsValue)>);
+        let _ = event_listen(INDEX_COMPLETE_EVENT, &handler);
+        handler.forget();
+    }
+    {
+        // 落点事件与打开结果的先后不确定：文件已是活动文件时直接滚动，否则留给 apply_open_result
+        let handler = Closure::wrap(Box::new(move |event: JsValue| {
+            let payload = js_sys::Reflect::get(&event, &JsValue::from_str("payload")).unwrap_or(JsValue::NULL);
+            let Some(handle) = js_sys::Reflect::get(&payload, &JsValue::from_str("handle")).ok().and_then(|v| v.as_f64()).map(|v| v as u32) else { return };
+            let line = js_sys::Reflect::get(&payload, &JsValue::from_str("line")).ok().and_then(|v| v.as_f64()).unwrap_or(0.0) as usize;
+            if active_handle.get_untracked() != Some(handle) {
+                pending_target.set_value(Some((handle, line)));
+                return;
+            }
+            set_current_line.set(line);
+            scroll_to(line);
+        }) as Box<dyn Fn(JsValue)>);
+        let _ = event_listen(OPEN_TARGET_EVENT, &handler);
+        handler.forget();
+    }
+    let toggle_follow = move || {
+        let enabled = !follow_tail.get_untracked();
+        set_follow_tail.set(enabled);
+        set_follow_pinned.set(true);
+        if enabled {
+            scroll_to(usize::MAX);
+        }
+        spawn_local(async move {
+            let args = serde_wasm_bindgen::to_value(&FollowArgs { enabled }).unwrap();
+            if let Err(e) = call_invoke("plugin:large-file-preview|set_follow", args).await {
+                set_follow_tail.set(false);
+                let em = e.as_string().unwrap_or_else(|| format!("{:?}", e));
+                show_error(&format!("设置跟随末尾失败：{}", em)).await;
+            }
+        });
+    };
+
+    // 监视活动文件所在的文件夹：其中出现更新的匹配文件（如日志轮转后的新文件）时自动打开它。
+    // 插件在关闭活动文件时停止监视；`watching_dir` 为正在监视的文件夹
+    let (watching_dir, set_watching_dir) = signal(Option::<String>::None);
+    let open_watched = move |path: String| {
+        if path == file_path.get_untracked() {
+            return;
+        }
+        spawn_local(async move {
+            set_loading.set(true);
+            set_opening.set(true);
+            // 与 `open_file_with` 相同：先登记为后台文件再激活，原活动文件留在它的标签中
+            let args = serde_wasm_bindgen::to_value(&OpenByPathArgs { path: path.clone(), byte_offset: None, line: None, activate: false }).unwrap();
+            let res = call_invoke("plugin:large-file-preview|open_file_by_path", args).await;
+            set_opening.set(false);
+            let info = match res.map(serde_wasm_bindgen::from_value::<OpenResult>) {
+                Ok(Ok(info)) if !info.path.is_empty() => info,
+                Ok(Ok(_)) => {
+                    show_error(&format!("打开 {} 失败：返回的 path 字段为空", path)).await;
+                    set_loading.set(false);
+                    return;
+                }
+                Ok(Err(e)) => {
+                    show_error(&format!("打开 {} 失败：无法解析返回值 {}", path, e)).await;
+                    set_loading.set(false);
+                    return;
+                }
+                Err(e) => {
+                    let em = e.as_string().unwrap_or_else(|| format!("{:?}", e));
+                    show_error(&format!("打开 {} 失败：{}", path, em)).await;
+                    set_loading.set(false);
+                    return;
+                }
+            };
+            let args = serde_wasm_bindgen::to_value(&HandleArgs { handle: info.handle }).unwrap();
+            if let Err(e) = call_invoke("plugin:large-file-preview|activate_file", args).await {
+                let em = e.as_string().unwrap_or_else(|| format!("{:?}", e));
+                show_error(&format!("打开 {} 失败：{}", path, em)).await;
+                close_handle(info.handle).await;
+                set_loading.set(false);
+                return;
+            }
+            apply_open_result(info);
+            set_loading.set(false);
+        });
+    };
+    {
+        let handler = Closure::wrap(Box::new(move |event: JsValue| {
+            let payload = js_sys::Reflect::get(&event, &JsValue::from_str("payload")).unwrap_or(JsValue::NULL);
+            let Some(path) = js_sys::Reflect::get(&payload, &JsValue::from_str("path")).ok().and_then(|v| v.as_string()) else { return };
+            if watching_dir.get_untracked().is_some() {
+                open_watched(path);
+            }
+        }) as Box<dyn Fn(JsValue)>);
+        let _ = event_listen(DIR_NEWEST_EVENT, &handler);
+        handler.forget();
+    }
+    // 开始时询问要监视的文件名（通配符，或只写扩展名），默认为活动文件的扩展名；正在监视时再次点击则停止
+    let toggle_watch_dir = move || {
+        if watching_dir.get_untracked().is_some() {
+            set_watching_dir.set(None);
+            spawn_local(async move {
+                if let Err(e) = call_invoke("plugin:large-file-preview|stop_watch_dir", JsValue::NULL).await {
+                    console::warn_1(&e);
+                }
+            });
+            return;
+        }
+        let path = file_path.get_untracked();
+        let Some(cut) = path.rfind(['/', '\\']) else { return };
+        let dir = path[..cut].to_string();
+        let default = path[cut + 1..].rsplit_once('.').map(|(_, ext)| format!("*.{}", ext)).unwrap_or_else(|| "*".to_string());
+        let input = web_sys::window().and_then(|w| w.prompt_with_message_and_default("监视所在文件夹中的哪些文件（如 *.log、app-*.txt，或只写扩展名）", &default).ok().flatten());
+        let Some(pattern) = input.map(|v| v.trim().to_string()) else { return };
+        spawn_local(async move {
+            let args = serde_wasm_bindgen::to_value(&WatchDirArgs { dir: dir.clone(), pattern }).unwrap();
+            match call_invoke("plugin:large-file-preview|start_watch_dir", args).await {
+                Ok(v) => {
+                    set_watching_dir.set(Some(dir));
+                    // 开始监视时已经有更新的文件，直接打开
+                    if let Some(newest) = v.as_string() {
+                        open_watched(newest);
+                    }
+                }
+                Err(e) => {
+                    let em = e.as_string().unwrap_or_else(|| format!("{:?}", e));
+                    show_error(&format!("监视文件夹失败：{}", em)).await;
+                }
+            }
+        });
+    };
+
+    // 切换倒序视图，两种视图都从最新的一行开始显示；旧的搜索结果与筛选对应正序的行位置，一并清除
+    let toggle_reversed = move || {
+        let enabled = !reversed.get_untracked();
+        set_filter_needle.set(None);
+        set_filter_line_nos.set(Vec::new());
+        set_matches_list.set(Vec::new());
+        set_matches_lines.set(Vec::new());
+        set_current_match_idx.set(0);
+        set_search_info.set(String::new());
+        set_reversed.set(enabled);
+        scroll_to(if enabled { 0 } else { usize::MAX });
+    };
+    // 开始搜索或筛选、打开或切换文件时回到正序视图
+    Effect::new(move |_| {
+        let searching_or_filtering = filter_needle.with(|f| f.is_some()) || matches_lines.with(|l| !l.is_empty());
+        if searching_or_filtering && reversed.get_untracked() {
+            set_reversed.set(false);
+        }
+    });
+    Effect::new(move |prev: Option<String>| {
+        let path = file_path.get();
+        if prev.is_some_and(|p| p != path) {
+            set_reversed.set(false);
+        }
+        path
+    });
+
+    let open_page_find = move || {
+        if file_path.get_untracked().is_empty() {
+            return;
+        }
+        set_show_page_find.set(true);
+        let Some(win) = web_sys::window() else { return };
+        let focus = Closure::once_into_js(move || {
+            if let Some(el) = web_sys::window().and_then(|w| w.document()).and_then(|d| d.get_element_by_id("page-find-input")) {
+                let _ = el.unchecked_into::<web_sys::HtmlElement>().focus();
+            }
+        });
+        let _ = win.set_timeout_with_callback_and_timeout_and_arguments_0(focus.unchecked_ref(), 0);
+    };
+    // 在当前页的匹配之间循环切换
+    let step_page_find = move |forward: bool| {
+        let n = page_hits.with_untracked(|h| h.len());
+        if n == 0 {
+            return;
+        }
+        set_page_find_idx.update(|i| *i = if forward { (*i + 1) % n } else { (*i + n - 1) % n });
+    };
+
+    // 跳转到行（Ctrl+G）：输入行号时防抖读取该行作为预览，回车后跳转。筛选视图中按匹配行翻页，不提供
+    let (show_goto, set_show_goto) = signal(false);
+    let (goto_input, set_goto_input) = signal(String::new());
+    let (goto_preview, set_goto_preview) = signal(String::new());
+    // 每次输入加一，防抖计时结束或读取返回时已不是最新输入的结果直接丢弃
+    let goto_seq = StoredValue::new(0u32);
+    let open_goto = move || {
+        if file_path.get_untracked().is_empty() || filter_needle.get_untracked().is_some() {
+            return;
+        }
+        set_goto_input.set(String::new());
+        set_goto_preview.set(String::new());
+        set_show_goto.set(true);
+        let Some(win) = web_sys::window() else { return };
+        let focus = Closure::once_into_js(move || {
+            if let Some(el) = web_sys::window().and_then(|w| w.document()).and_then(|d| d.get_element_by_id("goto-line-input")) {
+                let _ = el.unchecked_into::<web_sys::HtmlElement>().focus();
+            }
+        });
+        let _ = win.set_timeout_with_callback_and_timeout_and_arguments_0(focus.unchecked_ref(), 0);
+    };
+    Effect::new(move |_| {
+        let input = goto_input.get();
+        if !show_goto.get_untracked() {
+            return;
+        }
+        goto_seq.update_value(|s| *s = s.wrapping_add(1));
+        let seq = goto_seq.get_value();
+        let Ok(n) = input.trim().parse::<usize>() else {
+            set_goto_preview.set(String::new());
+            return;
+        };
+        if n == 0 || n > total_lines.get_untracked() {
+            set_goto_preview.set("超出范围".to_string());
+            return;
+        }
+        let Some(win) = web_sys::window() else { return };
+        let fetch = Closure::once_into_js(move || {
+            if goto_seq.get_value() != seq {
+                return;
+            }
+            spawn_local(async move {
+                let args = serde_wasm_bindgen::to_value(&LineArgs { line: n - 1 }).unwrap();
+                let text = match call_invoke("plugin:large-file-preview|get_line", args).await {
+                    Ok(v) if v.is_null() => "超出范围".to_string(),
+                    Ok(v) => v.as_string().unwrap_or_default(),
+                    Err(e) => format!("读取失败：{}", e.as_string().unwrap_or_else(|| format!("{:?}", e))),
+                };
+                if goto_seq.get_value() == seq {
+                    set_goto_preview.set(text);
+                }
+            });
+        });
+        let _ = win.set_timeout_with_callback_and_timeout_and_arguments_0(fetch.unchecked_ref(), GOTO_PREVIEW_DEBOUNCE_MS);
+    });
+    // 回车：跳到输入的行（按匹配行的显示位置摆放），超出范围时跳到最后一行
+    let commit_goto = move || {
+        let Ok(n) = goto_input.get_untracked().trim().parse::<usize>() else { return };
+        set_show_goto.set(false);
+        let visible = compute_visible_lines("editor-textarea").unwrap_or(DEFAULT_VISIBLE_LINES);
+        let rows = visible.saturating_sub(VISIBLE_SAFETY_MARGIN).max(1).min(lines_per_page.get_untracked());
+        let line = n.max(1) - 1;
+        if reversed.get_untracked() {
+            // 倒序视图中把目标行放在最上面
+            scroll_to(total_lines.get_untracked().saturating_sub(line + 1));
+        } else {
+            scroll_to(match_view_start(line, rows));
+        }
+    };
+
+    // 按行首时间跳转：输入时间戳或其开头部分（如 “2024-05-01 14:32”），插件二分查找第一个不早于它的行。
+    // 要求日志按时间顺序写入
+    let goto_timestamp = move || {
+        if file_path.get_untracked().is_empty() || filter_needle.get_untracked().is_some() {
+            return;
+        }
+        let Some(prefix) = web_sys::window().and_then(|w| w.prompt_with_message_and_default("跳到行首时间（如 2024-05-01 14:32:00，可只输入开头部分）：", "").ok().flatten()) else { return };
+        let prefix = prefix.trim().to_string();
+        if prefix.is_empty() {
+            return;
+        }
+        spawn_local(async move {
+            let args = serde_wasm_bindgen::to_value(&PrefixArgs { target_prefix: prefix }).unwrap();
+            match call_invoke("plugin:large-file-preview|find_line_by_prefix", args).await {
+                Ok(v) => match v.as_f64() {
+                    Some(line) => {
+                        let visible = compute_visible_lines("editor-textarea").unwrap_or(DEFAULT_VISIBLE_LINES);
+                        let rows = visible.saturating_sub(VISIBLE_SAFETY_MARGIN).max(1).min(lines_per_page.get_untracked());
+                        if reversed.get_untracked() {
+                            scroll_to(total_lines.get_untracked().saturating_sub(line as usize + 1));
+                        } else {
+                            scroll_to(match_view_start(line as usize, rows));
+                        }
+                    }
+                    // 所有行都早于目标时间：停在最后一行（倒序视图中在最上面）
+                    None => scroll_to(if reversed.get_untracked() { 0 } else { usize::MAX }),
+                },
+                Err(e) => {
+                    let em = e.as_string().unwrap_or_else(|| format!("{:?}", e));
+                    show_error(&format!("按时间跳转失败：{}", em)).await;
+                }
+            }
+        });
+    };
+
+    // 标出光标行（未开启当前行高亮时为视图顶部行）所在的代码块，再次点击时取消；
+    // Python、YAML 等按缩进查找，其它文件按花括号配对
+    let toggle_enclosing_block = move || {
+        if block_range.get_untracked().is_some() {
+            set_block_range.set(None);
+            return;
+        }
+        if file_path.get_untracked().is_empty() || filter_needle.get_untracked().is_some() || reversed.get_untracked() {
+            return;
+        }
+        let line = if highlight_current.get_untracked() { cursor_line.get_untracked() } else { current_line.get_untracked() };
+        let path = file_path.get_untracked().to_ascii_lowercase();
+        let by_indent = [".py", ".pyw", ".yaml", ".yml", ".nim", ".coffee"].iter().any(|ext| path.ends_with(ext));
+        let braces = if by_indent { String::new() } else { "{}".to_string() };
+        spawn_local(async move {
+            let args = serde_wasm_bindgen::to_value(&BlockArgs { line, braces }).unwrap();
+            match call_invoke("plugin:large-file-preview|enclosing_block", args).await {
+                Ok(v) if !v.is_null() => {
+                    let field = |name: &str| js_sys::Reflect::get(&v, &JsValue::from_str(name)).ok().and_then(|n| n.as_f64()).unwrap_or(0.0) as usize;
+                    let (start, end) = (field("start"), field("end"));
+                    set_block_range.set(Some((start, end)));
+                    // 块首行不在视图中时滚到块首行
+                    let top = visible_start.get_untracked();
+                    if start < top || start >= top + wrapped.with_untracked(|w| w.2) {
+                        scroll_to(start);
+                    }
+                }
+                Ok(_) => {
+                    let _ = dialog::message("没有找到所在的代码块（括号不配对或超出扫描范围）", dialog::MessageOptions { title: Some("所在代码块"), kind: None }).await;
+                }
+                Err(e) => {
+                    let em = e.as_string().unwrap_or_else(|| format!("{:?}", e));
+                    show_error(&format!("查找代码块失败：{}", em)).await;
+                }
+            }
+        });
+    };
+
+    // 段落式导航（Ctrl+↓ / Ctrl+↑）：把视图顶部移到下一个/上一个空行；blank_ws 为 true 时只含空白字符的行也算空行
+    let (blank_ws, set_blank_ws) = signal(true);
+    let jump_blank_line = move |forward: bool| {
+        if file_path.get_untracked().is_empty() || filter_needle.get_untracked().is_some() {
+            return;
+        }
+        let args = BlankLineArgs { from_line: current_line.get_untracked(), whitespace_is_blank: blank_ws.get_untracked() };
+        let cmd = if forward { "plugin:large-file-preview|find_next_blank_line" } else { "plugin:large-file-preview|find_prev_blank_line" };
+        spawn_local(async move {
+            match call_invoke(cmd, serde_wasm_bindgen::to_value(&args).unwrap()).await {
+                Ok(v) => match v.as_f64() {
+                    Some(line) => scroll_to(line as usize),
+                    // 到达文件边界：停在开头/末尾
+                    None => scroll_to(if forward { usize::MAX } else { 0 }),
+                },
+                Err(e) => {
+                    let em = e.as_string().unwrap_or_else(|| format!("{:?}", e));
+                    show_error(&format!("查找空行失败：{}", em)).await;
+                }
+            }
+        });
+    };
+
+    // 进入/退出筛选模式（以搜索框内容为筛选词）
+    let toggle_filter = move |_: leptos::ev::MouseEvent| {
+        if filter_needle.get_untracked().is_some() {
+            set_filter_needle.set(None);
+            set_filter_line_nos.set(Vec::new());
+            // 回到进入筛选前的位置
+            let start = visible_start.get_untracked();
+            set_current_line.set(start);
+            let visible = compute_visible_lines("editor-textarea").unwrap_or(DEFAULT_VISIBLE_LINES);
+            let safe = visible.saturating_sub(VISIBLE_SAFETY_MARGIN).max(1);
+            load_content(start, safe.min(lines_per_page.get_untracked()), set_file_content, set_page_loads, None);
+            return;
+        }
+        let query = search_query.get_untracked();
+        if query.is_empty() || file_path.get_untracked().is_empty() {
+            return;
+        }
+        set_filter_needle.set(Some(query));
+        set_filter_total.set(0);
+        set_current_line.set(0);
+        load_filtered(0, true);
+    };
+
+    // 读取各候选编码下的文件开头几行，结果非空时显示编码选择对话框
+    async fn load_encoding_previews(set_encoding_previews: WriteSignal<Vec<EncodingPreview>>) {
+        let args = js_sys::Object::new();
+        let _ = js_sys::Reflect::set(&args, &wasm_bindgen::JsValue::from_str("sampleLines"), &wasm_bindgen::JsValue::from(5));
+        match call_invoke("plugin:large-file-preview|preview_encodings", wasm_bindgen::JsValue::from(args)).await {
+            Ok(v) => match serde_wasm_bindgen::from_value::<Vec<EncodingPreview>>(v) {
+                Ok(list) => set_encoding_previews.set(list),
+                Err(e) => show_error(&format!("读取编码预览失败：无法解析返回值 {}", e)).await,
+            },
+            Err(e) => {
+                let em = e.as_string().unwrap_or_else(|| format!("{:?}", e));
+                show_error(&format!("读取编码预览失败：{}", em)).await;
+            }
+        }
+    }
+
+    let open_encoding_dialog = move |_: leptos::ev::MouseEvent| {
+        spawn_local(async move { load_encoding_previews(set_encoding_previews).await; });
+    };
+
+    // 不切换编码，按 encoding 解码当前页显示在对话框中，方便判断文件中间部分是否正常
+    let preview_encoding_at_view = move |encoding: String| {
+        spawn_local(async move {
+            let start = visible_start.get_untracked();
+            let visible = compute_visible_lines("editor-textarea").unwrap_or(DEFAULT_VISIBLE_LINES);
+            let count = visible.saturating_sub(VISIBLE_SAFETY_MARGIN).max(1).min(lines_per_page.get_untracked());
+            let args = serde_wasm_bindgen::to_value(&ReadLinesWithEncodingArgs { start, count, encoding: encoding.clone() }).unwrap();
+            match call_invoke("plugin:large-file-preview|read_lines_with_encoding", args).await {
+                Ok(v) => {
+                    let text = v.as_string().unwrap_or_default();
+                    set_encoding_page_samples.update(|list| {
+                        list.retain(|(e, _, _)| *e != encoding);
+                        list.push((encoding, start, text));
+                    });
+                }
+                Err(e) => {
+                    let em = e.as_string().unwrap_or_else(|| format!("{:?}", e));
+                    show_error(&format!("预览当前页失败：{}", em)).await;
+                }
+            }
+        });
+    };
+
+    // 把行索引导出为独立的索引文件（格式见插件的 `INDEX_FILE_MAGIC`），供其他工具复用
+    let export_index_file = move || {
+        spawn_local(async move {
+            let filters = [dialog::SaveFilter { name: "行索引", extensions: &["lfpidx"] }];
+            let dest_path = match dialog::save(dialog::SaveOptions { filters: Some(&filters) }).await {
+                Ok(Some(p)) => p,
+                Ok(None) => return,
+                Err(e) => {
+                    show_error(&format!("选择保存位置失败：{:?}", e)).await;
+                    return;
+                }
+            };
+            let args = serde_wasm_bindgen::to_value(&ExportIndexArgs { dest_path }).unwrap();
+            match call_invoke("plugin:large-file-preview|export_index", args).await {
+                Ok(v) => {
+                    let num = |key: &str| js_sys::Reflect::get(&v, &JsValue::from_str(key)).ok().and_then(|n| n.as_f64()).unwrap_or(0.0) as u64;
+                    let message = format!("已导出 {} 个索引点（每 {} 行一个，共 {} 行），{}", num("index_len"), num("index_interval"), num("total_lines"), format_bytes(num("bytes")));
+                    let _ = dialog::message(&message, dialog::MessageOptions { title: Some("导出行索引"), kind: None }).await;
+                }
+                Err(e) => {
+                    let em = e.as_string().unwrap_or_else(|| format!("{:?}", e));
+                    show_error(&format!("导出行索引失败：{}", em)).await;
+                }
+            }
+        });
+    };
+
+    let export_matching_lines = move || {
+        let needle = filter_needle.get_untracked().unwrap_or_else(|| search_query.get_untracked());
+        if needle.is_empty() || exporting.get_untracked() {
+            return;
+        }
+        spawn_local(async move {
+            let filters = [dialog::SaveFilter { name: "文本", extensions: &["txt", "log"] }];
+            let dest_path = match dialog::save(dialog::SaveOptions { filters: Some(&filters) }).await {
+                Ok(Some(p)) => p,
+                Ok(None) => return,
+                Err(e) => {
+                    show_error(&format!("选择保存位置失败：{:?}", e)).await;
+                    return;
+                }
+            };
+            set_export_progress.set(String::new());
+            set_exporting.set(true);
+            let args = serde_wasm_bindgen::to_value(&FilterLinesArgs { needle, ignore_case: SEARCH_IGNORE_CASE, invert: false, dest_path }).unwrap();
+            let res = call_invoke("plugin:large-file-preview|filter_lines", args).await;
+            set_exporting.set(false);
+            match res {
+                Ok(v) => {
+                    let num = |key: &str| js_sys::Reflect::get(&v, &JsValue::from_str(key)).ok().and_then(|n| n.as_f64()).unwrap_or(0.0) as u64;
+                    let message = format!("已导出 {} 行，用时 {} ms", num("lines"), num("duration_ms"));
+                    let _ = dialog::message(&message, dialog::MessageOptions { title: Some("导出匹配行"), kind: None }).await;
+                }
+                Err(e) => {
+                    let em = e.as_string().unwrap_or_else(|| format!("{:?}", e));
+                    if !em.contains(TASK_CANCELLED_MSG) {
+                        show_error(&format!("导出匹配行失败：{}", em)).await;
+                    }
+                }
+            }
+        });
     };
-    
-    // UI 状态
-    let (loading, set_loading) = signal(false);
-    // 搜索专用 loading 状态：区分 “打开文件” 与 “正在搜索” 两种不同的 loading 文案
-    let (searching, set_searching) = signal(false);
-    const LINES_PER_PAGE: usize = 30; // 每次加载的行数 (改为以行号为单位)
-
-    // 如果无法测量，可回退到这个值
-    const DEFAULT_VISIBLE_LINES: usize = 20;
-    // 为避免边界处出现竖向滚动条，保留一个安全行数的余量
-    const VISIBLE_SAFETY_MARGIN: usize = 2;
-
-    // 弹窗错误提示的辅助函数
-    async fn show_error(message: &str) {
-        let _ = dialog::message(message, dialog::MessageOptions { title: Some("错误"), kind: None }).await;
-        console::error_1(&wasm_bindgen::JsValue::from_str(message));
-    }
 
-    // 格式化字节为 KB/MB 字符串
-    fn format_bytes(bytes: usize) -> String {
-        const KB: f64 = 1024.0;
-        const MB: f64 = KB * 1024.0;
-        let b = bytes as f64;
-        if b >= MB {
-            format!("{:.2} MB", b / MB)
-        } else if b >= KB {
-            format!("{:.2} KB", b / KB)
-        } else {
-            format!("{} B", bytes)
+    let scan_invalid_utf8 = move || {
+        if file_path.get_untracked().is_empty() || scanning_utf8.get_untracked() {
+            return;
         }
-    }
-
-    // 安全调用 invoke 的辅助函数：返回 Result 而不是直接 panic
-    async fn call_invoke(cmd: &str, args: JsValue) -> Result<wasm_bindgen::JsValue, wasm_bindgen::JsValue> {
-        let p = invoke_promise(cmd, args);
-        match JsFuture::from(p).await {
-            Ok(v) => Ok(v),
-            Err(e) => Err(e),
+        set_utf8_scan_progress.set(String::new());
+        set_invalid_utf8.set(Some(Vec::new()));
+        set_scanning_utf8.set(true);
+        spawn_local(async move {
+            let args = serde_wasm_bindgen::to_value(&InvalidUtf8Args { max_results: INVALID_UTF8_LIMIT }).unwrap();
+            let res = call_invoke("plugin:large-file-preview|scan_invalid_utf8", args).await;
+            set_scanning_utf8.set(false);
+            match res {
+                Ok(v) => set_invalid_utf8.set(Some(serde_wasm_bindgen::from_value(v).unwrap_or_default())),
+                Err(e) => {
+                    set_invalid_utf8.set(None);
+                    let em = e.as_string().unwrap_or_else(|| format!("{:?}", e));
+                    if !em.contains(TASK_CANCELLED_MSG) {
+                        show_error(&format!("查找无效的 UTF-8 失败：{}", em)).await;
+                    }
+                }
+            }
+        });
+    };
+    // 从结果列表跳到某一行：该行按匹配行的位置显示，开启当前行高亮时光标移到该行
+    let jump_to_line = move |line: usize| {
+        if filter_needle.get_untracked().is_some() {
+            return;
         }
-    }
+        if reversed.get_untracked() {
+            scroll_to(total_lines.get_untracked().saturating_sub(line + 1));
+            return;
+        }
+        let visible = compute_visible_lines("editor-textarea").unwrap_or(DEFAULT_VISIBLE_LINES);
+        let rows = visible.saturating_sub(VISIBLE_SAFETY_MARGIN).max(1).min(lines_per_page.get_untracked());
+        scroll_to(match_view_start(line, rows));
+        set_cursor_line.set(line);
+    };
 
-    // 打开文件
-    let open_file = move |ev| {
-        // synchronous debug log to ensure click handler runs
-        // removed perf log
-        let _ = ev; // keep signature compatible
+    let count_unique_lines = move || {
+        if file_path.get_untracked().is_empty() || counting_unique.get_untracked() {
+            return;
+        }
+        set_unique_progress.set(String::new());
+        set_unique_result.set(Some(UniqueLines::default()));
+        set_counting_unique.set(true);
         spawn_local(async move {
-            // removed perf log
-            set_loading.set(true);
-            // pass extension filters to plugin (allow .txt and .log)
-            let args = js_sys::Object::new();
-            let ex = serde_wasm_bindgen::to_value(&vec![".txt", ".log"]).unwrap();
-            let _ = js_sys::Reflect::set(&args, &wasm_bindgen::JsValue::from_str("extensions"), &ex);
-            let res = match call_invoke("plugin:large-file-preview|open_file", wasm_bindgen::JsValue::from(args)).await {
-                Ok(v) => v,
+            let args = serde_wasm_bindgen::to_value(&UniqueLinesArgs { max_distinct: UNIQUE_LINES_LIMIT }).unwrap();
+            let res = call_invoke("plugin:large-file-preview|unique_lines", args).await;
+            set_counting_unique.set(false);
+            match res {
+                Ok(v) => set_unique_result.set(Some(serde_wasm_bindgen::from_value(v).unwrap_or_default())),
                 Err(e) => {
+                    set_unique_result.set(None);
                     let em = e.as_string().unwrap_or_else(|| format!("{:?}", e));
-                    show_error(&format!("打开文件调用失败：{}", em)).await;
-                    set_loading.set(false);
-                    return;
+                    if !em.contains(TASK_CANCELLED_MSG) {
+                        show_error(&format!("统计重复行失败：{}", em)).await;
+                    }
                 }
-            };
-                        // removed perf log
+            }
+        });
+    };
 
-            if let Ok(path_val) = js_sys::Reflect::get(&res, &wasm_bindgen::JsValue::from_str("path")) {
-                if !path_val.is_undefined() && !path_val.is_null() {
-                    if let Some(path) = path_val.as_string() {
-                        set_file_path.set(path);
-                        // 如果 open_file 返回中带有 size 字段，则直接使用它设置 file_size
-                        if let Ok(size_val) = js_sys::Reflect::get(&res, &wasm_bindgen::JsValue::from_str("size")) {
-                            if !size_val.is_undefined() && !size_val.is_null() {
-                                if let Some(n) = size_val.as_f64() {
-                                    set_file_size.set(n as usize);
-                                } else if let Some(s) = size_val.as_string() {
-                                    if let Ok(parsed) = s.parse::<f64>() {
-                                        set_file_size.set(parsed as usize);
-                                    }
-                                }
+    // 文件信息：路径、大小、行数、字符数与插件为该文件占用的内存（行索引越密占用越大）。
+    // 字符数第一次需要读完整个文件，插件会缓存结果
+    let show_file_info = move || {
+        spawn_local(async move {
+            match call_invoke("plugin:large-file-preview|get_memory_stats", JsValue::NULL).await {
+                Ok(v) => {
+                    let num = |key: &str| js_sys::Reflect::get(&v, &JsValue::from_str(key)).ok().and_then(|n| n.as_f64()).unwrap_or(0.0) as u64;
+                    set_loading.set(true);
+                    // 未建立索引时重新抽样估算（文件可能已增长），与精确的行数区分显示
+                    let lines = if indexed.get_untracked() {
+                        total_lines.get_untracked().to_string()
+                    } else {
+                        match call_invoke("plugin:large-file-preview|estimate_total_lines", JsValue::NULL).await {
+                            Ok(t) => {
+                                let n = js_sys::Reflect::get(&t, &JsValue::from_str("total_lines")).ok().and_then(|n| n.as_f64()).unwrap_or(0.0) as usize;
+                                format!("约 {}（抽样估算，建立索引后为精确值）", n)
                             }
+                            Err(_) => format!("约 {}（打开时估算）", total_lines.get_untracked()),
                         }
-                        // 初始化可视起始行为 0
-                        set_visible_start.set(0);
-                        // schedule auto-scroll for filename display after DOM updates
-                        schedule_auto_scroll("file-path");
-                        // removed perf log
-                        
-                        // 获取总行数
-                        let lines_res = match call_invoke("plugin:large-file-preview|get_total_lines", JsValue::NULL).await {
-                            Ok(v) => v,
-                            Err(e) => {
-                                let em = e.as_string().unwrap_or_else(|| format!("{:?}", e));
-                                show_error(&format!("获取总行数失败：{}", em)).await;
-                                set_loading.set(false);
-                                return;
-                            }
-                        };
-                        // removed perf log
+                    };
+                    let chars = match call_invoke("plugin:large-file-preview|get_char_count", JsValue::NULL).await {
+                        Ok(c) => format!("约 {}", c.as_f64().unwrap_or(0.0) as u64),
+                        Err(_) => "无法统计".to_string(),
+                    };
+                    // 最长的几行，便于找出接近截断上限的异常长行
+                    let args = serde_wasm_bindgen::to_value(&LongestLinesArgs { n: 3 }).unwrap();
+                    let longest = match call_invoke("plugin:large-file-preview|get_longest_lines", args).await {
+                        Ok(v) => serde_wasm_bindgen::from_value::<Vec<LongestLine>>(v)
+                            .unwrap_or_default()
+                            .iter()
+                            .map(|l| format!("\n  第 {} 行：{}，{} 个字符{}", l.line + 1, format_bytes(l.bytes), l.chars, if l.truncated { "（超过单行上限，显示时会被截断）" } else { "" }))
+                            .collect::<String>(),
+                        Err(_) => "无法统计".to_string(),
+                    };
+                    set_loading.set(false);
+                    let message = format!(
+                        "{}\n\n大小：{}\n行数：{}\n字符数：{}\n最长的行：{}\n行索引：{}（{} 个索引点，每 {} 行一个）\n缓存的映射窗口：{}",
+                        file_path.get_untracked(),
+                        format_bytes(num("file_len")),
+                        lines,
+                        chars,
+                        longest,
+                        format_bytes(num("index_bytes")),
+                        num("index_len"),
+                        num("index_interval"),
+                        format_bytes(num("cached_window_bytes")),
+                    );
+                    let _ = dialog::message(&message, dialog::MessageOptions { title: Some("文件信息"), kind: None }).await;
+                }
+                Err(e) => {
+                    let em = e.as_string().unwrap_or_else(|| format!("{:?}", e));
+                    show_error(&format!("读取文件信息失败：{}", em)).await;
+                }
+            }
+        });
+    };
 
-                        if lines_res.is_undefined() || lines_res.is_null() {
-                            show_error("获取总行数失败：调用返回空结果").await;
-                        } else if let Some(lines) = lines_res.as_f64() {
-                            set_total_lines.set(lines as usize);
-                            set_current_line.set(0);
-                            
-                                // 在 DOM 更新后测量编辑框可见行数并加载对应行数，避免出现垂直滚动
-                                // 延迟一点时间以等待 textarea 渲染并计算高度
-                                {
-                                    let set_file_content = set_file_content.clone();
-                                    let set_loading = set_loading.clone();
-                                    let _ = web_sys::window().map(|w| {
-                                        let closure = Closure::wrap(Box::new(move || {
-                                            let visible = compute_visible_lines("editor-textarea").unwrap_or(DEFAULT_VISIBLE_LINES);
-                                            // 留出安全边距，避免载入过满导致竖向滚动
-                                            let safe = visible.saturating_sub(VISIBLE_SAFETY_MARGIN).max(1);
-                                            let to_load = safe.min(LINES_PER_PAGE);
-                                            load_content(0, to_load, set_file_content.clone(), set_loading.clone(), None);
-                                        }) as Box<dyn Fn()>);
-                                        let _ = w.set_timeout_with_callback_and_timeout_and_arguments_0(closure.as_ref().unchecked_ref(), 120);
-                                        closure.forget();
-                                    });
-                                }
+    // 尾部模式：询问只显示文件最后多少 MB（留空显示整个文件），插件只扫描这一部分，行号从其第一行起算
+    let choose_tail_window = move || {
+        let current = tail_mb.get_untracked().map(|n| n.to_string()).unwrap_or_else(|| "100".to_string());
+        let input = web_sys::window().and_then(|w| w.prompt_with_message_and_default("只显示文件最后多少 MB？（留空显示整个文件）", &current).ok().flatten());
+        let Some(input) = input else { return };
+        let max_mb = match input.trim() {
+            "" => None,
+            v => match v.parse::<u64>() {
+                Ok(n) if n > 0 => Some(n),
+                _ => return,
+            },
+        };
+        spawn_local(async move {
+            let args = serde_wasm_bindgen::to_value(&TailWindowArgs { max_mb }).unwrap();
+            match call_invoke("plugin:large-file-preview|set_tail_window", args).await {
+                Ok(v) => {
+                    let base = js_sys::Reflect::get(&v, &JsValue::from_str("base_byte")).ok().and_then(|n| n.as_f64()).unwrap_or(0.0);
+                    let total = js_sys::Reflect::get(&v, &JsValue::from_str("total_lines")).ok().and_then(|n| n.as_f64()).unwrap_or(0.0) as usize;
+                    // 窗口不小于文件时插件显示整个文件
+                    set_tail_mb.set(if base > 0.0 { max_mb } else { None });
+                    // 插件为窗口（或整个文件）重新扫描了索引，快速打开的文件此后也有了精确行数
+                    set_indexed.set(true);
+                    set_reversed.set(false);
+                    // 行号改变后旧的搜索结果与筛选都不再对应
+                    set_filter_needle.set(None);
+                    set_filter_line_nos.set(Vec::new());
+                    set_matches_list.set(Vec::new());
+                    set_matches_lines.set(Vec::new());
+                    set_current_match_idx.set(0);
+                    set_search_info.set(String::new());
+                    set_total_lines.set(total);
+                    set_current_line.set(0);
+                    set_visible_start.set(0);
+                    let visible = compute_visible_lines("editor-textarea").unwrap_or(DEFAULT_VISIBLE_LINES);
+                    let safe = visible.saturating_sub(VISIBLE_SAFETY_MARGIN).max(1);
+                    load_content(0, safe.min(lines_per_page.get_untracked()), set_file_content, set_page_loads, None);
+                }
+                Err(e) => {
+                    let em = e.as_string().unwrap_or_else(|| format!("{:?}", e));
+                    show_error(&format!("设置尾部模式失败：{}", em)).await;
+                }
+            }
+        });
+    };
 
-                                // 注册窗口 resize 的防抖处理：在 resize 结束后重新测量并加载可见行数
-                                {
-                                    if let Some(win) = web_sys::window() {
-                                        // 创建防抖 closure（存放在 window.__txt_reader_resize_closure）
-                                        let set_file_content = set_file_content.clone();
-                                        let set_loading = set_loading.clone();
-                                        let resize_closure = Closure::wrap(Box::new(move || {
-                                            // 在 resize 事件被触发后延迟 180ms 再测量
-                                            if let Some(w2) = web_sys::window() {
-                                                let inner = Closure::wrap(Box::new(move || {
-                                                    let visible = compute_visible_lines("editor-textarea").unwrap_or(DEFAULT_VISIBLE_LINES);
-                                                    let safe = visible.saturating_sub(VISIBLE_SAFETY_MARGIN).max(1);
-                                                    let to_load = safe.min(LINES_PER_PAGE);
-                                                    load_content(0, to_load, set_file_content.clone(), set_loading.clone(), None);
-                                                }) as Box<dyn Fn()>);
-                                                let _ = w2.set_timeout_with_callback_and_timeout_and_arguments_0(inner.as_ref().unchecked_ref(), 180);
-                                                inner.forget();
-                                            }
-                                        }) as Box<dyn Fn()>);
-
-                                        // 将该 closure 赋给 window.__txt_reader_resize_closure 以便 later removal
-                                        let _ = js_sys::Reflect::set(&win, &wasm_bindgen::JsValue::from_str("__txt_reader_resize_closure"), resize_closure.as_ref());
-                                        // attach to onresize
-                                        let _ = win.set_onresize(Some(resize_closure.as_ref().unchecked_ref()));
-                                        // leak the closure intentionally (we will remove it reference on close)
-                                        resize_closure.forget();
-                                    }
-                                }
-                        } else {
-                            show_error(&format!("获取总行数失败：无法解析返回值 {:?}", lines_res.as_string())).await;
-                        }
-                    } else {
-                        // removed perf log
-                        show_error("打开文件失败：无法解析文件路径").await;
+    // 为快速打开的文件建立完整索引（扫描整个文件），之后总行数为精确值，滚动条与倒序可用；阅读位置不变
+    let (building_index, set_building_index) = signal(false);
+    let build_index = move || {
+        let Some(handle) = active_handle.get_untracked() else { return };
+        set_building_index.set(true);
+        spawn_local(async move {
+            // 扫描期间先用新的抽样估算（文件可能在打开后增长）显示“约 N 行”，扫描完成后换成精确值
+            if let Ok(t) = call_invoke("plugin:large-file-preview|estimate_total_lines", JsValue::NULL).await {
+                let n = js_sys::Reflect::get(&t, &JsValue::from_str("total_lines")).ok().and_then(|n| n.as_f64()).unwrap_or(0.0) as usize;
+                if active_handle.get_untracked() == Some(handle) && !indexed.get_untracked() && n > 0 {
+                    set_total_lines.set(n);
+                }
+            }
+            let args = serde_wasm_bindgen::to_value(&ReindexArgs { new_interval: INDEX_INTERVAL }).unwrap();
+            let res = match call_invoke("plugin:large-file-preview|reindex", args).await {
+                Ok(_) => call_invoke("plugin:large-file-preview|get_total_lines", JsValue::NULL).await,
+                Err(e) => Err(e),
+            };
+            set_building_index.set(false);
+            match res {
+                Ok(v) if active_handle.get_untracked() == Some(handle) => {
+                    let total = js_sys::Reflect::get(&v, &JsValue::from_str("total_lines")).ok().and_then(|n| n.as_f64()).unwrap_or(0.0) as usize;
+                    let exact = js_sys::Reflect::get(&v, &JsValue::from_str("exact")).ok().and_then(|b| b.as_bool()).unwrap_or(false);
+                    set_total_lines.set(total);
+                    set_indexed.set(exact);
+                    if exact {
+                        set_open_stats.set("已建立完整索引".to_string());
                     }
-                } else {
-                    // removed perf log
-                    show_error("打开文件失败：返回的 path 字段为空").await;
                 }
-            } else {
-                // removed perf log
-                show_error("打开文件失败：未找到 path 字段").await;
+                Ok(_) => {}
+                Err(e) => {
+                    let em = e.as_string().unwrap_or_else(|| format!("{:?}", e));
+                    show_error(&format!("建立索引失败：{}", em)).await;
+                }
+            }
+        });
+    };
+
+    // 格式说明的第一段是编码
+    let set_format_encoding = move |name: &str| {
+        set_file_format.update(|f| {
+            let rest: Vec<&str> = f.split(" · ").skip(1).collect();
+            let mut parts = vec![name.to_uppercase()];
+            parts.extend(rest.into_iter().map(|p| p.to_string()));
+            *f = parts.join(" · ");
+        });
+    };
+
+    // 清除活动文件保存的设置：编码与超长行策略由插件恢复为默认值，折行回到全局默认（不折行），并重新加载当前页
+    let reset_file_settings = move || {
+        spawn_local(async move {
+            match call_invoke("plugin:large-file-preview|reset_file_settings", JsValue::NULL).await {
+                Ok(v) => {
+                    let name = js_sys::Reflect::get(&v, &JsValue::from_str("encoding")).ok().and_then(|e| e.as_string()).unwrap_or_else(|| "utf-8".to_string());
+                    set_format_encoding(&name);
+                    set_wrap_col.set(None);
+                    let visible = compute_visible_lines("editor-textarea").unwrap_or(DEFAULT_VISIBLE_LINES);
+                    let safe = visible.saturating_sub(VISIBLE_SAFETY_MARGIN).max(1);
+                    load_content(visible_start.get_untracked(), safe.min(lines_per_page.get_untracked()), set_file_content, set_page_loads, None);
+                }
+                Err(e) => {
+                    let em = e.as_string().unwrap_or_else(|| format!("{:?}", e));
+                    show_error(&format!("恢复默认设置失败：{}", em)).await;
+                }
+            }
+        });
+    };
+
+    // 应用用户选择的编码，并按新编码重新加载当前页
+    let choose_encoding = move |encoding: String| {
+        spawn_local(async move {
+            let args = js_sys::Object::new();
+            let _ = js_sys::Reflect::set(&args, &wasm_bindgen::JsValue::from_str("encoding"), &wasm_bindgen::JsValue::from_str(&encoding));
+            match call_invoke("plugin:large-file-preview|set_encoding", wasm_bindgen::JsValue::from(args)).await {
+                Ok(v) => {
+                    let name = v.as_string().unwrap_or(encoding);
+                    set_encoding_previews.set(Vec::new());
+                    set_format_encoding(&name);
+                    let start = visible_start.get_untracked();
+                    let visible = compute_visible_lines("editor-textarea").unwrap_or(DEFAULT_VISIBLE_LINES);
+                    let safe = visible.saturating_sub(VISIBLE_SAFETY_MARGIN).max(1);
+                    load_content(start, safe.min(lines_per_page.get_untracked()), set_file_content, set_page_loads, None);
+                }
+                Err(e) => {
+                    let em = e.as_string().unwrap_or_else(|| format!("{:?}", e));
+                    show_error(&format!("设置编码失败：{}", em)).await;
+                }
+            }
+        });
+    };
+
+    // 取消正在进行的打开；插件中止扫描后 open_file 调用会以取消错误返回
+    let cancel_open = move |_: leptos::ev::MouseEvent| {
+        spawn_local(async move {
+            if let Err(e) = call_invoke("plugin:large-file-preview|cancel_open", wasm_bindgen::JsValue::from(js_sys::Object::new())).await {
+                console::warn_1(&e);
             }
-            set_loading.set(false);
         });
     };
 
     // 关闭文件
-    let close_file = move |_| {
+    let close_file = move |_: ()| {
         spawn_local(async move {
+            // 同时关闭对比文件
+            if let Some(h) = compare_handle.get_untracked() {
+                close_handle(h).await;
+                set_compare_handle.set(None);
+                set_compare_path.set(String::new());
+                set_diff_line.set(None);
+                set_diff_info.set(String::new());
+            }
             // removed perf log
-            match call_invoke("plugin:large-file-preview|close_file", JsValue::NULL).await {
+            // 把当前阅读位置交给插件，随会话状态一起写盘，下次打开同一文件时恢复
+            let args = serde_wasm_bindgen::to_value(&CloseFileArgs {
+                last_line: visible_start.get_untracked(),
+            }).unwrap();
+            match call_invoke("plugin:large-file-preview|close_file", args).await {
                 Ok(_res) => {
                     // removed perf log
                     // if res.is_undefined() || res.is_null() {
@@ -284,6 +2803,15 @@ pub fn App() -> impl IntoView {
             }
             set_file_path.set(String::new());
             set_file_size.set(0);
+            set_file_format.set(String::new());
+            set_open_stats.set(String::new());
+            set_tail_mb.set(None);
+            set_indexed.set(true);
+            set_watching_dir.set(None);
+            set_line_ending.set(String::new());
+            set_encoding_previews.set(Vec::new());
+            set_filter_needle.set(None);
+            set_filter_line_nos.set(Vec::new());
             // clear auto-scroll when closing
             clear_auto_scroll("file-path");
             // 尝试移除之前注册的 resize handler
@@ -301,21 +2829,149 @@ pub fn App() -> impl IntoView {
             set_current_line.set(0);
             set_search_query.set(String::new());
             set_search_info.set(String::new());
+            set_matches_list.set(Vec::new());
+            set_matches_lines.set(Vec::new());
+            // 移除该标签，还有其它标签时切换到最后一个
+            if let Some(h) = active_handle.get_untracked() {
+                set_tabs.update(|tabs| tabs.retain(|t| t.handle != h));
+            }
+            set_active_handle.set(None);
+            if let Some(next) = tabs.get_untracked().last().cloned() {
+                let args = serde_wasm_bindgen::to_value(&HandleArgs { handle: next.handle }).unwrap();
+                match call_invoke("plugin:large-file-preview|activate_file", args).await {
+                    Ok(_) => restore_tab(next),
+                    Err(e) => console::warn_1(&e),
+                }
+            }
             // removed perf log
         });
     };
 
+    // 在菜单中移动焦点：`delta` 为 1 / -1 时移到下一个/上一个可用项（首尾循环），为 0 时聚焦第一项
+    fn focus_menu_item(delta: isize) {
+        let Some(doc) = web_sys::window().and_then(|w| w.document()) else { return };
+        let Ok(items) = doc.query_selector_all(".dropdown-menu .menu-item:not(:disabled)") else { return };
+        let n = items.length() as isize;
+        if n == 0 {
+            return;
+        }
+        let active = doc.active_element();
+        let current = (0..n).find(|&i| items.item(i as u32).is_some_and(|node| node.is_same_node(active.as_ref().map(|a| a.unchecked_ref()))));
+        let next = match current {
+            Some(i) if delta != 0 => (i + delta).rem_euclid(n),
+            _ if delta < 0 => n - 1,
+            _ => 0,
+        };
+        if let Some(node) = items.item(next as u32) {
+            let _ = node.unchecked_into::<web_sys::HtmlElement>().focus();
+        }
+    }
+    let close_menu = move || {
+        set_show_dropdown.set(false);
+        if let Some(el) = web_sys::window().and_then(|w| w.document()).and_then(|d| d.get_element_by_id("menu-button")) {
+            let _ = el.unchecked_into::<web_sys::HtmlElement>().focus();
+        }
+    };
+    // 全局快捷键：Alt+M 打开/关闭菜单（打开后可用方向键选择、回车执行、Esc 关闭），Ctrl+O 打开文件，Ctrl+W 关闭文件。
+    // 焦点在可编辑的输入框（搜索框、对话框中的输入框）中时不处理，避免打字时误触
+    let _ = window_event_listener(leptos::ev::keydown, move |ev| {
+        let typing = ev.target().and_then(|t| t.dyn_into::<web_sys::HtmlElement>().ok()).is_some_and(|el| {
+            let tag = el.tag_name();
+            tag == "INPUT" || tag == "SELECT" || (tag == "TEXTAREA" && !el.has_attribute("readonly"))
+        });
+        if typing {
+            return;
+        }
+        if ev.key() == "Escape" && show_dropdown.get_untracked() {
+            close_menu();
+            return;
+        }
+        // 用 code 判断：macOS 上 Option+M 产生的 key 不是 "m"
+        if ev.alt_key() && ev.code() == "KeyM" {
+            ev.prevent_default();
+            let open = !show_dropdown.get_untracked();
+            set_show_dropdown.set(open);
+            if open {
+                if let Some(win) = web_sys::window() {
+                    let focus = Closure::once_into_js(move || focus_menu_item(0));
+                    let _ = win.set_timeout_with_callback_and_timeout_and_arguments_0(focus.unchecked_ref(), 0);
+                }
+            }
+            return;
+        }
+        if !(ev.ctrl_key() || ev.meta_key()) || ev.alt_key() || ev.shift_key() {
+            return;
+        }
+        match ev.code().as_str() {
+            "KeyO" => {
+                ev.prevent_default();
+                set_show_dropdown.set(false);
+                open_file(leptos::ev::MouseEvent::new("click").unwrap());
+            }
+            "KeyW" if !file_path.get_untracked().is_empty() => {
+                ev.prevent_default();
+                set_show_dropdown.set(false);
+                close_file(());
+            }
+            _ => {}
+        }
+    });
+
+    // 关闭标签：活动标签走 close_file（保存阅读位置），后台标签直接按句柄关闭
+    let close_tab = move |h: u32| {
+        if active_handle.get_untracked() == Some(h) {
+            close_file(());
+            return;
+        }
+        set_tabs.update(|tabs| tabs.retain(|t| t.handle != h));
+        spawn_local(async move {
+            close_handle(h).await;
+        });
+    };
+
+    // 点击行号：复制当前已加载内容中对应的整行文本
+    // `rel` 为相对 visible_start 的行序号，`abs` 为绝对行号（0 基准）
+    let copy_line = move |rel: usize, abs: usize| {
+        let text = file_content.get_untracked().lines().nth(rel).unwrap_or("").to_string();
+        spawn_local(async move {
+            let res = match clipboard_write_text(&text) {
+                Ok(p) => JsFuture::from(p).await,
+                Err(e) => Err(e),
+            };
+            match res {
+                Ok(_) => {
+                    set_copied_line.set(Some(abs));
+                    if let Some(win) = web_sys::window() {
+                        let reset = Closure::wrap(Box::new(move || {
+                            if copied_line.get_untracked() == Some(abs) {
+                                set_copied_line.set(None);
+                            }
+                        }) as Box<dyn Fn()>);
+                        let _ = win.set_timeout_with_callback_and_timeout_and_arguments_0(reset.as_ref().unchecked_ref(), 900);
+                        reset.forget();
+                    }
+                }
+                Err(e) => {
+                    let em = e.as_string().unwrap_or_else(|| format!("{:?}", e));
+                    show_error(&format!("复制失败：{}", em)).await;
+                }
+            }
+        });
+    };
+
     // We no longer perform character-offset selection here. Navigation will jump by line number
     // using `matches_lines` and reusing `load_content` to refresh the editor and scrollbar.
 
     // previous/next match handlers
     let go_prev_match = move |_: leptos::ev::MouseEvent| {
         // removed perf log
+        // 跳转到匹配位置需要完整视图，先退出筛选模式
+        set_filter_needle.set(None);
             let matches_list = matches_list.clone();
             let matches_lines = matches_lines.clone();
         let set_idx = set_current_match_idx.clone();
         let set_file_content_clone = set_file_content.clone();
-        let set_loading_clone = set_loading.clone();
+        let set_page_loads_clone = set_page_loads.clone();
         spawn_local(async move {
                 let len = matches_lines.get_untracked().len();
                 if len == 0 {
@@ -330,23 +2986,23 @@ pub fn App() -> impl IntoView {
                 // set visible start and current_line, then load content for that page
                 let visible = compute_visible_lines("editor-textarea").unwrap_or(DEFAULT_VISIBLE_LINES);
                 let safe = visible.saturating_sub(VISIBLE_SAFETY_MARGIN).max(1);
-                let context_before: usize = 3;
-                let start = if target_line >= context_before { target_line - context_before } else { 0 };
+                let start = match_view_start(target_line, safe.min(lines_per_page.get_untracked()));
                 set_visible_start.set(start);
                 set_current_line.set(start);
                 let snapshot = matches_list.get_untracked().clone();
                 let select_cb_opt = make_select_cb(snapshot, idx, start, target_line);
-                load_content(start, safe.min(LINES_PER_PAGE), set_file_content_clone.clone(), set_loading_clone.clone(), select_cb_opt);
+                load_content(start, safe.min(lines_per_page.get_untracked()), set_file_content_clone.clone(), set_page_loads_clone.clone(), select_cb_opt);
         });
     };
 
     let go_next_match = move |_: leptos::ev::MouseEvent| {
         // removed perf log
+        set_filter_needle.set(None);
             let matches_list = matches_list.clone();
             let matches_lines = matches_lines.clone();
         let set_idx = set_current_match_idx.clone();
         let set_file_content_clone = set_file_content.clone();
-        let set_loading_clone = set_loading.clone();
+        let set_page_loads_clone = set_page_loads.clone();
         spawn_local(async move {
                 let len = matches_lines.get_untracked().len();
                 if len == 0 {
@@ -360,13 +3016,156 @@ pub fn App() -> impl IntoView {
                 // removed perf log
                 let visible = compute_visible_lines("editor-textarea").unwrap_or(DEFAULT_VISIBLE_LINES);
                 let safe = visible.saturating_sub(VISIBLE_SAFETY_MARGIN).max(1);
-                let context_before: usize = 3;
-                let start = if target_line >= context_before { target_line - context_before } else { 0 };
+                let start = match_view_start(target_line, safe.min(lines_per_page.get_untracked()));
                 set_visible_start.set(start);
                 set_current_line.set(start);
                 let snapshot = matches_list.get_untracked().clone();
                 let select_cb_opt = make_select_cb(snapshot, idx, start, target_line);
-                load_content(start, safe.min(LINES_PER_PAGE), set_file_content_clone.clone(), set_loading_clone.clone(), select_cb_opt);
+                load_content(start, safe.min(lines_per_page.get_untracked()), set_file_content_clone.clone(), set_page_loads_clone.clone(), select_cb_opt);
+        });
+    };
+
+    // 搜索结果列表：是否展开，以及多选的条目（matches_list 中的下标）
+    let (show_results, set_show_results) = signal(false);
+    let (selected_results, set_selected_results) = signal(Vec::<usize>::new());
+    // 一次最多复制的字节数，超过时提示改用导出到文件
+    const MAX_COPY_RESULT_BYTES: usize = 256 * 1024;
+    // 新的搜索结果到来时清空选择
+    Effect::new(move |_| {
+        matches_list.track();
+        set_selected_results.set(Vec::new());
+    });
+    // 结果列表中的每一项：(matches_list 下标, 行号, 行文本)；搜索未附带行文本时为 None
+    let result_entries = Memo::new(move |_| matches_list.with(|list| list.iter().enumerate().map(|(idx, m)| {
+        let jv = js_sys::JSON::parse(m).unwrap_or(JsValue::NULL);
+        let line = js_sys::Reflect::get(&jv, &JsValue::from_str("line")).ok().and_then(|v| v.as_f64()).unwrap_or(0.0) as usize;
+        let text = js_sys::Reflect::get(&jv, &JsValue::from_str("text")).ok().and_then(|t| t.as_string());
+        (idx, line, text)
+    }).collect::<Vec<_>>()));
+    // 在结果中二次筛选：只在已返回的结果上按子串（不区分大小写）过滤，不重新扫描文件。
+    // 没有附带行文本的结果（如旧标签页中保存的匹配）按需逐行读取，最多 MAX_RESULT_TEXT_FETCHES 行
+    const MAX_RESULT_TEXT_FETCHES: usize = 200;
+    let (results_filter, set_results_filter) = signal(String::new());
+    let (fetched_texts, set_fetched_texts) = signal(HashMap::<usize, String>::new());
+    let (fetching_texts, set_fetching_texts) = signal(false);
+    Effect::new(move |_| {
+        matches_list.track();
+        set_fetched_texts.set(HashMap::new());
+    });
+    let result_text = move |line: usize, text: &Option<String>| -> Option<String> {
+        text.clone().or_else(|| fetched_texts.with(|m| m.get(&line).cloned()))
+    };
+    // 经二次筛选后显示的结果：(下标, 行号, 行文本)
+    let shown_results = Memo::new(move |_| {
+        let needle = results_filter.get().to_lowercase();
+        result_entries.with(|entries| entries.iter().filter_map(|(idx, line, text)| {
+            let text = result_text(*line, text);
+            if needle.is_empty() {
+                return Some((*idx, *line, text.unwrap_or_default()));
+            }
+            let text = text?;
+            text.to_lowercase().contains(&needle).then_some((*idx, *line, text))
+        }).collect::<Vec<_>>())
+    });
+    Effect::new(move |_| {
+        if results_filter.get().is_empty() || fetching_texts.get_untracked() {
+            return;
+        }
+        let mut missing: Vec<usize> = result_entries.with(|entries| fetched_texts.with(|fetched| {
+            entries.iter().filter(|(_, line, text)| text.is_none() && !fetched.contains_key(line)).map(|(_, line, _)| *line).collect()
+        }));
+        missing.dedup();
+        missing.truncate(MAX_RESULT_TEXT_FETCHES.saturating_sub(fetched_texts.with_untracked(|m| m.len())));
+        if missing.is_empty() {
+            return;
+        }
+        set_fetching_texts.set(true);
+        spawn_local(async move {
+            let mut texts = HashMap::new();
+            for line in missing {
+                let args = serde_wasm_bindgen::to_value(&ReadLinesArgs { start: line, count: 1, handle: None, row_per_line: true }).unwrap();
+                match call_invoke("plugin:large-file-preview|read_lines", args).await {
+                    Ok(v) => {
+                        texts.insert(line, v.as_string().unwrap_or_default().trim_end_matches(['\r', '\n']).to_string());
+                    }
+                    Err(e) => {
+                        // 记为空文本，避免反复重试同一行
+                        console::warn_1(&e);
+                        texts.insert(line, String::new());
+                    }
+                }
+            }
+            set_fetched_texts.update(|m| m.extend(texts));
+            set_fetching_texts.set(false);
+        });
+    });
+
+    // 跳转到结果列表中的第 idx 个匹配
+    let jump_to_match = move |idx: usize| {
+        set_filter_needle.set(None);
+        let Some(target_line) = matches_lines.get_untracked().get(idx).cloned() else { return };
+        set_current_match_idx.set(idx);
+        let visible = compute_visible_lines("editor-textarea").unwrap_or(DEFAULT_VISIBLE_LINES);
+        let safe = visible.saturating_sub(VISIBLE_SAFETY_MARGIN).max(1);
+        let start = match_view_start(target_line, safe.min(lines_per_page.get_untracked()));
+        set_visible_start.set(start);
+        set_current_line.set(start);
+        let select_cb_opt = make_select_cb(matches_list.get_untracked(), idx, start, target_line);
+        load_content(start, safe.min(lines_per_page.get_untracked()), set_file_content, set_page_loads, select_cb_opt);
+    };
+
+    // 点击结果：按住 Ctrl/⌘ 时切换该项的选中状态，否则只选中该项并跳转过去
+    let click_result = move |ev: leptos::ev::MouseEvent, idx: usize| {
+        if ev.ctrl_key() || ev.meta_key() {
+            set_selected_results.update(|sel| {
+                if let Some(pos) = sel.iter().position(|&i| i == idx) {
+                    sel.remove(pos);
+                } else {
+                    sel.push(idx);
+                }
+            });
+            return;
+        }
+        set_selected_results.set(vec![idx]);
+        jump_to_match(idx);
+    };
+
+    // 把选中的结果按 “行号: 行文本” 复制到剪贴板；同一行有多处匹配时只复制一次
+    let copy_selected_results = move || {
+        let mut selected = selected_results.get_untracked();
+        selected.sort_unstable();
+        let entries: Vec<(usize, String)> = result_entries.with_untracked(|list| {
+            selected.iter().filter_map(|&i| list.get(i)).map(|(_, line, text)| {
+                (*line, result_text(*line, text).unwrap_or_default())
+            }).collect()
+        });
+        let eol = output_eol.get_untracked().newline(&line_ending.get_untracked());
+        let mut out = String::new();
+        let mut last_line = None;
+        for (line, text) in entries {
+            if last_line == Some(line) {
+                continue;
+            }
+            last_line = Some(line);
+            out.push_str(&format!("{}: {}{}", line + 1, text, eol));
+        }
+        spawn_local(async move {
+            if out.len() > MAX_COPY_RESULT_BYTES {
+                show_error(&format!(
+                    "选中的内容有 {}，超过一次复制的上限 {}。请减少选中的条目，或改用导出匹配行到文件。",
+                    format_bytes(out.len() as u64),
+                    format_bytes(MAX_COPY_RESULT_BYTES as u64)
+                )).await;
+                return;
+            }
+            let res = match clipboard_write_text(&out) {
+                Ok(p) => JsFuture::from(p).await,
+                Err(e) => Err(e),
+            };
+            if let Err(e) = res {
+                let em = e.as_string().unwrap_or_else(|| format!("{:?}", e));
+                show_error(&format!("复制失败：{}", em)).await;
+            }
         });
     };
 
@@ -376,12 +3175,18 @@ pub fn App() -> impl IntoView {
         if query.is_empty() {
             return;
         }
+        set_filter_needle.set(None);
+        let from_line = Some(visible_start.get_untracked()).filter(|l| *l > 0 && !search_whole_file.get_untracked());
 
         spawn_local(async move {
             set_searching.set(true);
             let args = serde_wasm_bindgen::to_value(&SearchArgs {
                 needle: query.clone(),
-                ignore_case: true,
+                ignore_case: SEARCH_IGNORE_CASE,
+                from_line,
+                include_line_text: true,
+                max_samples: 0,
+                anchor: search_anchor.get_untracked(),
             }).unwrap();
 
             let parsed = match call_invoke("plugin:large-file-preview|mmap_search", args).await {
@@ -406,6 +3211,8 @@ pub fn App() -> impl IntoView {
                 .ok().and_then(|d| d.as_f64()).unwrap_or(0.0) as u128;
             let extra_alloc_bytes = js_sys::Reflect::get(&parsed, &wasm_bindgen::JsValue::from_str("extra_alloc_bytes"))
                 .ok().and_then(|a| a.as_f64()).unwrap_or(0.0) as usize;
+            let file_len = js_sys::Reflect::get(&parsed, &wasm_bindgen::JsValue::from_str("file_len"))
+                .ok().and_then(|a| a.as_f64()).unwrap_or(0.0);
 
                 // parse matches array if present
                 // We'll store raw JsValue objects in a Vec<JsValue> via serde_wasm_bindgen::to_value/from_value helpers
@@ -472,42 +3279,126 @@ pub fn App() -> impl IntoView {
                 if let Some(&first_line) = lines_vec.get(0) {
                     let visible = compute_visible_lines("editor-textarea").unwrap_or(DEFAULT_VISIBLE_LINES);
                     let safe = visible.saturating_sub(VISIBLE_SAFETY_MARGIN).max(1);
-                    let context_before: usize = 3;
-                    let start = if first_line >= context_before { first_line - context_before } else { 0 };
+                    let start = match_view_start(first_line, safe.min(lines_per_page.get_untracked()));
                     set_visible_start.set(start);
                     set_current_line.set(start);
                     let snapshot = matches_list.get_untracked().clone();
                     let select_cb_opt = make_select_cb(snapshot, 0usize, start, first_line);
-                    load_content(start, safe.min(LINES_PER_PAGE), set_file_content.clone(), set_loading.clone(), select_cb_opt);
+                    load_content(start, safe.min(lines_per_page.get_untracked()), set_file_content.clone(), set_page_loads.clone(), select_cb_opt);
+                }
+
+                // format duration as seconds with 3 decimals, and extra_alloc in MB with 2 decimals
+                let duration_s = (duration_ms as f64) / 1000.0;
+                let extra_mb = (extra_alloc_bytes as f64) / 1024.0 / 1024.0;
+                // 吞吐量 = 文件大小 / 耗时；耗时不足 1 ms 时无法估算
+                let scanned_gb = file_len / 1024.0 / 1024.0 / 1024.0;
+                let throughput = if duration_ms > 0 {
+                    format!("{:.0} MB/s", file_len / 1024.0 / 1024.0 / duration_s)
+                } else {
+                    "— MB/s".to_string()
+                };
+                let scope = match from_line {
+                    Some(l) => format!("第 {} 行之后", l + 1),
+                    None => String::new(),
+                };
+                set_search_info.set(format!(
+                    "{}{} 个匹配，{:.3} s，扫描 {:.2} GB，{}，额外分配 {:.2} MB",
+                    scope,
+                    count,
+                    duration_s,
+                    scanned_gb,
+                    throughput,
+                    extra_mb
+                ));
+                // removed perf log
+            // removed perf log
+            set_searching.set(false);
+        });
+    };
+
+    // 快速跳转：只查找当前位置之后（`forward` 为 false 时为之前）的第一个匹配，不统计总数。
+    // 记录上一次命中的 (搜索词, 行号)，对同一搜索词再次点击时从命中行的下一行（或上一行）继续
+    let (quick_hit, set_quick_hit) = signal(Option::<(String, usize)>::None);
+    let quick_jump_dir = move |forward: bool| {
+        let query = search_query.get_untracked();
+        if query.is_empty() || file_path.get_untracked().is_empty() {
+            return;
+        }
+        set_filter_needle.set(None);
+        let hit_line = match quick_hit.get_untracked() {
+            Some((q, line)) if q == query => Some(line),
+            _ => None,
+        };
+        // 向后找时 from_line 为第一个要检查的行；向前找时只检查它之前的行
+        let from_line = match hit_line {
+            Some(line) if forward => line + 1,
+            Some(line) => line,
+            None => visible_start.get_untracked(),
+        };
+        spawn_local(async move {
+            set_searching.set(true);
+            let res = if forward {
+                let args = serde_wasm_bindgen::to_value(&FindFirstArgs { needle: query.clone(), ignore_case: SEARCH_IGNORE_CASE, from_line }).unwrap();
+                call_invoke("plugin:large-file-preview|find_first", args).await
+            } else {
+                let args = serde_wasm_bindgen::to_value(&FindPrevArgs { needle: query.clone(), ignore_case: SEARCH_IGNORE_CASE, before_line: from_line }).unwrap();
+                call_invoke("plugin:large-file-preview|find_prev", args).await
+            };
+            let res = match res {
+                Ok(v) => v,
+                Err(e) => {
+                    let em = e.as_string().unwrap_or_else(|| format!("{:?}", e));
+                    show_error(&format!("快速跳转失败：{}", em)).await;
+                    set_searching.set(false);
+                    return;
                 }
-
-                // format duration as seconds with 3 decimals, and extra_alloc in MB with 2 decimals
-                let duration_s = (duration_ms as f64) / 1000.0;
-                let extra_mb = (extra_alloc_bytes as f64) / 1024.0 / 1024.0;
-                set_search_info.set(format!(
-                    "{} 个匹配，{:.3} s，额外分配 {:.2} MB",
-                    count,
-                    duration_s,
-                    extra_mb
-                ));
-                // removed perf log
-            // removed perf log
+            };
             set_searching.set(false);
+            // 快速跳转不产生匹配列表，清空上一次完整搜索的导航状态
+            set_matches_list.set(Vec::new());
+            set_matches_lines.set(Vec::new());
+            if res.is_undefined() || res.is_null() {
+                set_quick_hit.set(None);
+                if forward {
+                    set_search_info.set(format!("第 {} 行之后没有找到 “{}”", from_line + 1, query));
+                } else {
+                    set_search_info.set(format!("第 {} 行之前没有找到 “{}”", from_line + 1, query));
+                }
+                return;
+            }
+            let line = js_sys::Reflect::get(&res, &wasm_bindgen::JsValue::from_str("line"))
+                .ok().and_then(|v| v.as_f64()).unwrap_or(0.0) as usize;
+            set_quick_hit.set(Some((query, line)));
+            let next = if forward { "下一处" } else { "上一处" };
+            set_search_info.set(format!("快速跳转：第 {} 行（再次点击查找{}）", line + 1, next));
+            let visible = compute_visible_lines("editor-textarea").unwrap_or(DEFAULT_VISIBLE_LINES);
+            let safe = visible.saturating_sub(VISIBLE_SAFETY_MARGIN).max(1);
+            let start = match_view_start(line, safe.min(lines_per_page.get_untracked()));
+            set_visible_start.set(start);
+            set_current_line.set(start);
+            let snapshot = vec![js_sys::JSON::stringify(&res).ok().and_then(|j| j.as_string()).unwrap_or_default()];
+            let select_cb_opt = make_select_cb(snapshot, 0usize, start, line);
+            load_content(start, safe.min(lines_per_page.get_untracked()), set_file_content, set_page_loads, select_cb_opt);
         });
     };
+    let quick_jump = move |_: leptos::ev::MouseEvent| quick_jump_dir(true);
+    let quick_jump_prev = move |_: leptos::ev::MouseEvent| quick_jump_dir(false);
 
     // 加载内容的辅助函数
     fn load_content(
         start_line: usize,
         count: usize,
         set_file_content: WriteSignal<String>,
-        set_loading: WriteSignal<bool>,
+        set_page_loads: WriteSignal<u32>,
         on_loaded: Option<wasm_bindgen::prelude::Closure<dyn Fn()>>,
     ) {
+        set_page_loads.update(|n| *n += 1);
         spawn_local(async move {
             let args = serde_wasm_bindgen::to_value(&ReadLinesArgs {
                 start: start_line,
                 count,
+                handle: None,
+                row_per_line: true,
             }).unwrap();
             // removed perf log
 
@@ -516,7 +3407,7 @@ pub fn App() -> impl IntoView {
                 Err(e) => {
                     let em = e.as_string().unwrap_or_else(|| format!("{:?}", e));
                     show_error(&format!("读取文件内容调用失败：{}", em)).await;
-                    set_loading.set(false);
+                    set_page_loads.update(|n| *n = n.saturating_sub(1));
                     return;
                 }
             };
@@ -558,12 +3449,13 @@ pub fn App() -> impl IntoView {
                     }
                 }
             }
-            set_loading.set(false);
+            set_page_loads.update(|n| *n = n.saturating_sub(1));
         });
     }
 
     // 如果文件名宽度超出容器宽度，则为其添加自动滚动（marquee）类并设置滚动距离/时长
-    fn schedule_auto_scroll(element_id: &str) {
+    // `speed` 为滚动速度（px/s）；溢出很少时至少用 6 秒滚完，避免来回抖动
+    fn schedule_auto_scroll(element_id: &str, speed: f64) {
         let id = element_id.to_string();
         let closure = Closure::wrap(Box::new(move || {
             if let Some(window) = web_sys::window() {
@@ -575,13 +3467,23 @@ pub fn App() -> impl IntoView {
                             let scroll_w = html.scroll_width();
                             let client_w = parent.map(|p| p.client_width()).unwrap_or(html.client_width());
                             if scroll_w > client_w {
-                                let distance = (scroll_w - client_w) as f64 + 8.0;
-                                let duration = (distance / 30.0).max(6.0);
-                                let _ = html.style().set_property("--scroll-distance", &format!("{}px", distance));
-                                let _ = html.style().set_property("--scroll-duration", &format!("{}s", duration));
-                                let _ = el.class_list().add_1("auto-scroll");
+                                if prefers_reduced_motion() {
+                                    // 系统要求减少动态效果：不滚动，改为静态省略号 + 悬停提示完整路径
+                                    let _ = el.class_list().remove_1("auto-scroll");
+                                    let _ = el.class_list().add_1("static-ellipsis");
+                                    let _ = el.set_attribute("title", &html.inner_text());
+                                } else {
+                                    let distance = (scroll_w - client_w) as f64 + 8.0;
+                                    let duration = (distance / speed.max(1.0)).max(6.0);
+                                    let _ = html.style().set_property("--scroll-distance", &format!("{}px", distance));
+                                    let _ = html.style().set_property("--scroll-duration", &format!("{}s", duration));
+                                    let _ = el.class_list().remove_1("static-ellipsis");
+                                    let _ = el.class_list().add_1("auto-scroll");
+                                }
                             } else {
                                 let _ = el.class_list().remove_1("auto-scroll");
+                                let _ = el.class_list().remove_1("static-ellipsis");
+                                let _ = el.remove_attribute("title");
                             }
                         }
                     }
@@ -601,6 +3503,8 @@ pub fn App() -> impl IntoView {
             if let Some(document) = window.document() {
                 if let Some(el) = document.get_element_by_id(element_id) {
                     let _ = el.class_list().remove_1("auto-scroll");
+                    let _ = el.class_list().remove_1("static-ellipsis");
+                    let _ = el.remove_attribute("title");
                     if let Some(html) = el.dyn_ref::<web_sys::HtmlElement>() {
                         let _ = html.style().remove_property("--scroll-distance");
                         let _ = html.style().remove_property("--scroll-duration");
@@ -619,18 +3523,201 @@ pub fn App() -> impl IntoView {
                 <div class="menu-container" style="margin-left:auto; position:relative;">
                     <button 
                         class="menu-button" 
+                        id="menu-button"
                         on:click=move |_| set_show_dropdown.set(!show_dropdown.get())
                         aria-label="menu"
+                        aria-keyshortcuts="Alt+M"
+                        aria-haspopup="menu"
+                        aria-expanded=move || if show_dropdown.get() { "true" } else { "false" }
                         title="菜单"
                     >
                         <img src="public/menu.svg" alt="menu" width="20" height="20" style="display:block;"/>
                     </button>
                     <Show when=move || show_dropdown.get()>
-                        <div class="dropdown-menu" style="position:absolute; right:0; top:100%; margin-top:8px; min-width:220px; background:Canvas; color:CanvasText; border:1px solid ButtonText; box-shadow:0 6px 18px rgba(0,0,0,0.12); padding:8px; border-radius:6px; z-index:1000; color-scheme:light dark;">
-                            <button class="menu-item" on:click=move |ev| { open_file(ev); set_show_dropdown.set(false); } style="display:block; width:100%; text-align:left; padding:8px 10px;">
+                        <div class="dropdown-menu" role="menu"
+                            on:keydown=move |ev| {
+                                let delta = match ev.key().as_str() {
+                                    "ArrowDown" => 1,
+                                    "ArrowUp" => -1,
+                                    "Home" => 0,
+                                    "End" => {
+                                        // 从第一项往前循环即为最后一项
+                                        ev.prevent_default();
+                                        focus_menu_item(0);
+                                        focus_menu_item(-1);
+                                        return;
+                                    }
+                                    "Escape" => {
+                                        ev.stop_propagation();
+                                        close_menu();
+                                        return;
+                                    }
+                                    _ => return,
+                                };
+                                ev.prevent_default();
+                                focus_menu_item(delta);
+                            }
+                            style="position:absolute; right:0; top:100%; margin-top:8px; min-width:220px; background:Canvas; color:CanvasText; border:1px solid ButtonText; box-shadow:0 6px 18px rgba(0,0,0,0.12); padding:8px; border-radius:6px; z-index:1000; color-scheme:light dark;">
+                            <button class="menu-item" role="menuitem" on:click=move |ev| { open_file(ev); set_show_dropdown.set(false); } aria-keyshortcuts="Control+O" title="Ctrl+O" style="display:block; width:100%; text-align:left; padding:8px 10px;">
                                 "打开"
                             </button>
-                            <button class="menu-item" on:click=move |ev| { close_file(ev); set_show_dropdown.set(false); } style="display:block; width:100%; text-align:left; padding:8px 10px; margin-top:6px;">
+                            <button class="menu-item" role="menuitem" on:click=move |_| { open_file_with(true); set_show_dropdown.set(false); } style="display:block; width:100%; text-align:left; padding:8px 10px; margin-top:6px;" title="不扫描整个文件、不建立行索引，立即显示超大文件的开头；总行数为估算值，之后可再建立索引">
+                                "快速打开（不建立索引）…"
+                            </button>
+                            <Show when=move || !file_path.get().is_empty() && !indexed.get()>
+                                <button class="menu-item" role="menuitem" on:click=move |_| { build_index(); set_show_dropdown.set(false); } disabled=move || building_index.get() style="display:block; width:100%; text-align:left; padding:8px 10px; margin-top:6px;" title="扫描整个文件建立行索引：得到精确的总行数，并启用滚动条与倒序">
+                                    { move || if building_index.get() { "正在建立索引…" } else { "建立索引" } }
+                                </button>
+                            </Show>
+                            <button class="menu-item" role="menuitem" on:click=move |ev| { open_compare(ev); set_show_dropdown.set(false); } disabled=move || file_path.get().is_empty() style="display:block; width:100%; text-align:left; padding:8px 10px; margin-top:6px;">
+                                "对比…"
+                            </button>
+                            <button class="menu-item" role="menuitem" on:click=move |ev| { open_encoding_dialog(ev); set_show_dropdown.set(false); } disabled=move || file_path.get().is_empty() style="display:block; width:100%; text-align:left; padding:8px 10px; margin-top:6px;">
+                                "编码…"
+                            </button>
+                            <button class="menu-item" role="menuitem" on:click=move |_| { reset_file_settings(); set_show_dropdown.set(false); } disabled=move || file_path.get().is_empty() style="display:block; width:100%; text-align:left; padding:8px 10px; margin-top:6px;" title="清除为这个文件记住的编码、超长行策略与折行设置，恢复为全局默认值">
+                                "恢复本文件的默认设置"
+                            </button>
+                            <button class="menu-item" role="menuitem" on:click=move |_| { if !hashing.get_untracked() { set_hash_result.set(String::new()); set_hash_progress.set(String::new()); } set_show_hash_dialog.set(true); set_show_dropdown.set(false); } disabled=move || file_path.get().is_empty() style="display:block; width:100%; text-align:left; padding:8px 10px; margin-top:6px;">
+                                "校验值…"
+                            </button>
+                            <button class="menu-item" role="menuitem" on:click=move |_| { export_index_file(); set_show_dropdown.set(false); } disabled=move || file_path.get().is_empty() style="display:block; width:100%; text-align:left; padding:8px 10px; margin-top:6px;" title="诊断：导出打开时建立的行索引，供其他工具复用">
+                                "导出行索引…"
+                            </button>
+                            <button class="menu-item" role="menuitem" on:click=move |_| { export_matching_lines(); set_show_dropdown.set(false); } disabled=move || file_path.get().is_empty() || exporting.get() || (filter_needle.get().is_none() && search_query.get().is_empty()) style="display:block; width:100%; text-align:left; padding:8px 10px; margin-top:6px;" title="把包含当前搜索词的行写入新文件">
+                                "导出匹配行…"
+                            </button>
+                            <button class="menu-item" role="menuitem" on:click=move |_| { open_page_find(); set_show_dropdown.set(false); } disabled=move || file_path.get().is_empty() style="display:block; width:100%; text-align:left; padding:8px 10px; margin-top:6px;" title="Ctrl+F：只在已加载的当前页中查找，不扫描文件">
+                                "在当前页查找…"
+                            </button>
+                            <button class="menu-item" role="menuitem" on:click=move |_| { goto_timestamp(); set_show_dropdown.set(false); } disabled=move || file_path.get().is_empty() || filter_needle.get().is_some() style="display:block; width:100%; text-align:left; padding:8px 10px; margin-top:6px;" title="按行首的时间戳二分查找，要求日志按时间顺序写入">
+                                "跳到时间…"
+                            </button>
+                            <button class="menu-item" role="menuitem" on:click=move |_| { scan_invalid_utf8(); set_show_dropdown.set(false); } disabled=move || file_path.get().is_empty() || scanning_utf8.get() style="display:block; width:100%; text-align:left; padding:8px 10px; margin-top:6px;" title="列出按 UTF-8 解码失败（显示为替换字符 �）的位置，点击跳转">
+                                "查找无效的 UTF-8…"
+                            </button>
+                            <button class="menu-item" role="menuitem" on:click=move |_| { count_unique_lines(); set_show_dropdown.set(false); } disabled=move || file_path.get().is_empty() || counting_unique.get() style="display:block; width:100%; text-align:left; padding:8px 10px; margin-top:6px;" title="按出现次数列出重复最多的行（类似 sort | uniq -c），点击跳到第一次出现的位置">
+                                "统计重复行…"
+                            </button>
+                            <button class="menu-item" role="menuitem" on:click=move |_| { toggle_enclosing_block(); set_show_dropdown.set(false); } disabled=move || file_path.get().is_empty() || filter_needle.get().is_some() || reversed.get() style="display:block; width:100%; text-align:left; padding:8px 10px; margin-top:6px;" title="标出光标行所在的代码块：Python、YAML 等按缩进，其它文件按花括号配对">
+                                { move || if block_range.get().is_some() { "✓ 所在代码块" } else { "所在代码块" } }
+                            </button>
+                            <button class="menu-item" role="menuitem" on:click=move |_| { choose_diff_ranges(); set_show_dropdown.set(false); } disabled=move || file_path.get().is_empty() style="display:block; width:100%; text-align:left; padding:8px 10px; margin-top:6px;" title="逐行比较本文件中的两段内容">
+                                "比较两段行…"
+                            </button>
+                            <button class="menu-item" role="menuitem" on:click=move |_| { show_file_info(); set_show_dropdown.set(false); } disabled=move || file_path.get().is_empty() style="display:block; width:100%; text-align:left; padding:8px 10px; margin-top:6px;">
+                                "文件信息…"
+                            </button>
+                            <button class="menu-item" role="menuitem" on:click=move |_| { choose_tail_window(); set_show_dropdown.set(false); } disabled=move || file_path.get().is_empty() style="display:block; width:100%; text-align:left; padding:8px 10px; margin-top:6px;" title="超大日志只看最近的部分：只扫描文件末尾，不为整个文件建立索引">
+                                { move || match tail_mb.get() { Some(n) => format!("✓ 只显示最后 {} MB…", n), None => "只显示最后 N MB…".to_string() } }
+                            </button>
+                            <button class="menu-item" role="menuitem" on:click=move |_| { toggle_reversed(); set_show_dropdown.set(false); } disabled=move || file_path.get().is_empty() || !indexed.get() style="display:block; width:100%; text-align:left; padding:8px 10px; margin-top:6px;" title="从最后一行往前显示，最新的日志在最上面">
+                                { move || if reversed.get() { "✓ 倒序" } else { "倒序" } }
+                            </button>
+                            <button class="menu-item" role="menuitem" on:click=move |_| { toggle_follow(); set_show_dropdown.set(false); } disabled=move || file_path.get().is_empty() style="display:block; width:100%; text-align:left; padding:8px 10px; margin-top:6px;" title="文件追加内容时自动滚到末尾；向上滚动暂停，滚回末尾继续">
+                                { move || match (follow_tail.get(), follow_pinned.get()) {
+                                    (true, true) => "✓ 跟随末尾",
+                                    (true, false) => "✓ 跟随末尾（已暂停）",
+                                    _ => "跟随末尾",
+                                } }
+                            </button>
+                            <button class="menu-item" role="menuitem" on:click=move |_| { toggle_watch_dir(); set_show_dropdown.set(false); } disabled=move || file_path.get().is_empty() && watching_dir.get().is_none() style="display:block; width:100%; text-align:left; padding:8px 10px; margin-top:6px;" title=move || watching_dir.get().unwrap_or_else(|| "所在文件夹中出现更新的匹配文件时自动打开，适合会轮转的日志".to_string())>
+                                { move || if watching_dir.get().is_some() { "✓ 监视所在文件夹" } else { "监视所在文件夹…" } }
+                            </button>
+                            <button class="menu-item" role="menuitem" on:click=move |_| { set_cursor_line.set(visible_start.get_untracked()); set_highlight_current.update(|v| *v = !*v); set_show_dropdown.set(false); } style="display:block; width:100%; text-align:left; padding:8px 10px; margin-top:6px;" title="↑ / ↓ 逐行移动高亮的行">
+                                { move || if highlight_current.get() { "✓ 高亮当前行" } else { "高亮当前行" } }
+                            </button>
+                            <button class="menu-item" role="menuitem" on:click=move |_| { set_trim_trailing.update(|v| *v = !*v); set_show_dropdown.set(false); } style="display:block; width:100%; text-align:left; padding:8px 10px; margin-top:6px;">
+                                { move || if trim_trailing.get() { "✓ 去除行尾空白" } else { "去除行尾空白" } }
+                            </button>
+                            <button class="menu-item" role="menuitem" on:click=move |_| { set_blank_ws.update(|v| *v = !*v); set_show_dropdown.set(false); } style="display:block; width:100%; text-align:left; padding:8px 10px; margin-top:6px;" title="Ctrl+↓ / Ctrl+↑ 在空行之间跳转">
+                                { move || if blank_ws.get() { "✓ 只含空白的行视为空行" } else { "只含空白的行视为空行" } }
+                            </button>
+                            <button class="menu-item" role="menuitem" on:click=move |_| { set_search_whole_file.update(|v| *v = !*v); set_show_dropdown.set(false); } style="display:block; width:100%; text-align:left; padding:8px 10px; margin-top:6px;">
+                                { move || if search_whole_file.get() { "✓ 搜索整个文件" } else { "搜索整个文件" } }
+                            </button>
+                            <button class="menu-item" role="menuitem" on:click=move |_| {
+                                set_search_anchor.update(|a| *a = match *a { "none" => "start", "start" => "end", _ => "none" });
+                                set_show_dropdown.set(false);
+                            } style="display:block; width:100%; text-align:left; padding:8px 10px; margin-top:6px;" title="只找出现在行首或行尾的匹配，不需要正则表达式">
+                                { move || match search_anchor.get() { "start" => "匹配位置：行首", "end" => "匹配位置：行尾", _ => "匹配位置：任意" } }
+                            </button>
+                            <button class="menu-item" role="menuitem" on:click=move |_| { cycle_output_eol(); set_show_dropdown.set(false); } style="display:block; width:100%; text-align:left; padding:8px 10px; margin-top:6px;" title="复制选中的结果与导出匹配行时使用的换行符">
+                                { move || format!("复制/导出换行：{}", output_eol.get().label()) }
+                            </button>
+                            <button class="menu-item" role="menuitem" on:click=move |_| { toggle_match_position(); set_show_dropdown.set(false); } style="display:block; width:100%; text-align:left; padding:8px 10px; margin-top:6px;">
+                                { move || if match_position.get() == MatchPosition::Center { "✓ 匹配行居中显示" } else { "匹配行居中显示" } }
+                            </button>
+                            <button class="menu-item" role="menuitem" on:click=move |_| { choose_marquee_speed(); set_show_dropdown.set(false); } style="display:block; width:100%; text-align:left; padding:8px 10px; margin-top:6px;" title="文件路径过长时的滚动速度；系统要求减少动态效果时不滚动">
+                                { move || format!("文件名滚动速度：{} px/s…", marquee_speed.get()) }
+                            </button>
+                            <button class="menu-item" role="menuitem" on:click=move |_| { toggle_marquee_pause(); set_show_dropdown.set(false); } style="display:block; width:100%; text-align:left; padding:8px 10px; margin-top:6px;">
+                                { move || if marquee_pause.get() { "✓ 悬停时暂停文件名滚动" } else { "悬停时暂停文件名滚动" } }
+                            </button>
+                            <button class="menu-item" role="menuitem" on:click=move |_| { set_log_coloring.update(|v| *v = !*v); set_show_dropdown.set(false); } style="display:block; width:100%; text-align:left; padding:8px 10px; margin-top:6px;">
+                                { move || if log_coloring.get() { "✓ 日志级别着色" } else { "日志级别着色" } }
+                            </button>
+                            <button class="menu-item" role="menuitem" on:click=move |_| {
+                                set_ansi_mode.update(|m| *m = match m {
+                                    AnsiMode::Off => AnsiMode::Strip,
+                                    AnsiMode::Strip => AnsiMode::Color,
+                                    AnsiMode::Color => AnsiMode::Off,
+                                });
+                                set_show_dropdown.set(false);
+                            } style="display:block; width:100%; text-align:left; padding:8px 10px; margin-top:6px;" title="终端输出的日志中的颜色控制码，只影响显示">
+                                { move || match ansi_mode.get() {
+                                    AnsiMode::Off => "去除 ANSI 转义：关",
+                                    AnsiMode::Strip => "去除 ANSI 转义：去除",
+                                    AnsiMode::Color => "去除 ANSI 转义：按颜色着色",
+                                } }
+                            </button>
+                            <button class="menu-item" role="menuitem" on:click=move |_| {
+                                set_gutter_mode.update(|m| *m = match m {
+                                    GutterMode::LineNumber => GutterMode::Relative,
+                                    GutterMode::Relative => GutterMode::Hybrid,
+                                    GutterMode::Hybrid => GutterMode::OffsetHex,
+                                    GutterMode::OffsetHex => GutterMode::OffsetDec,
+                                    GutterMode::OffsetDec => GutterMode::LineNumber,
+                                });
+                                set_show_dropdown.set(false);
+                            } style="display:block; width:100%; text-align:left; padding:8px 10px; margin-top:6px;">
+                                { move || match gutter_mode.get() {
+                                    GutterMode::LineNumber => "行号区：行号",
+                                    GutterMode::Relative => "行号区：相对行号",
+                                    GutterMode::Hybrid => "行号区：相对行号（当前行显示行号）",
+                                    GutterMode::OffsetHex => "行号区：字节偏移（十六进制）",
+                                    GutterMode::OffsetDec => "行号区：字节偏移（十进制）",
+                                } }
+                            </button>
+                            <button class="menu-item" role="menuitem" on:click=move |_| { set_smooth_scroll.update(|v| *v = !*v); set_show_dropdown.set(false); } style="display:block; width:100%; text-align:left; padding:8px 10px; margin-top:6px;">
+                                { move || if smooth_scroll.get() { "✓ 平滑滚动" } else { "平滑滚动" } }
+                            </button>
+                            <button class="menu-item" role="menuitem" on:click=move |_| { set_slider_center.update(|v| *v = !*v); set_show_dropdown.set(false); } style="display:block; width:100%; text-align:left; padding:8px 10px; margin-top:6px;">
+                                { move || if slider_center.get() { "✓ 滚动条表示视图中心" } else { "滚动条表示视图中心" } }
+                            </button>
+                            <button class="menu-item" role="menuitem" on:click=move |_| { toggle_scrollbar_bytes(); set_show_dropdown.set(false); } style="display:block; width:100%; text-align:left; padding:8px 10px; margin-top:6px;" title="滚动条按文件中的字节比例定位，而不是按行号；筛选与倒序视图中仍按行">
+                                { move || if scrollbar_bytes.get() { "✓ 滚动条按字节比例" } else { "滚动条按字节比例" } }
+                            </button>
+                            <button class="menu-item" role="menuitem" on:click=move |_| { toggle_wrap(); set_show_dropdown.set(false); } style="display:block; width:100%; text-align:left; padding:8px 10px; margin-top:6px;">
+                                { move || match wrap_col.get() { Some(n) => format!("✓ 按第 {} 列换行", n), None => "按列换行…".to_string() } }
+                            </button>
+                            <button class="menu-item" role="menuitem" on:click=move |_| { set_align_columns.update(|v| *v = !*v); set_show_dropdown.set(false); } style="display:block; width:100%; text-align:left; padding:8px 10px; margin-top:6px;">
+                                { move || if align_columns.get() { "✓ 按列对齐（CSV/TSV）" } else { "按列对齐（CSV/TSV）" } }
+                            </button>
+                            <button class="menu-item" role="menuitem" on:click=move |_| { choose_delimiter(); set_show_dropdown.set(false); } style="display:block; width:100%; text-align:left; padding:8px 10px; margin-top:6px;">
+                                { move || match column_delimiter.get() {
+                                    Some('\t') => "列分隔符：制表符…".to_string(),
+                                    Some(c) => format!("列分隔符：{}…", c),
+                                    None => "列分隔符：自动…".to_string(),
+                                } }
+                            </button>
+                            <button class="menu-item" role="menuitem" on:click=move |_| { choose_lines_per_page(); set_show_dropdown.set(false); } style="display:block; width:100%; text-align:left; padding:8px 10px; margin-top:6px;">
+                                { move || format!("每页行数：{}…", lines_per_page.get()) }
+                            </button>
+                            <button class="menu-item" role="menuitem" on:click=move |ev| { toggle_reopen_last(ev); set_show_dropdown.set(false); } style="display:block; width:100%; text-align:left; padding:8px 10px; margin-top:6px;">
+                                { move || if reopen_last.get() { "✓ 启动时打开上次的文件" } else { "启动时打开上次的文件" } }
+                            </button>
+                            <button class="menu-item" role="menuitem" on:click=move |_| { close_file(()); set_show_dropdown.set(false); } aria-keyshortcuts="Control+W" title="Ctrl+W" style="display:block; width:100%; text-align:left; padding:8px 10px; margin-top:6px;">
                                 "关闭"
                             </button>
                         </div>
@@ -638,11 +3725,37 @@ pub fn App() -> impl IntoView {
                 </div>
             </header>
 
-            <div class="search-container" style="display:flex; gap:8px; padding:8px;">
+            <Show when=move || !tabs.get().is_empty()>
+                <div class="tab-bar" role="tablist" aria-label="已打开的文件">
+                    <For
+                        each=move || tabs.get()
+                        key=|t| t.handle
+                        children=move |t: TabState| {
+                            let h = t.handle;
+                            let name = t.path.rsplit(['/', '\\']).next().unwrap_or(&t.path).to_string();
+                            view! {
+                                <div class="tab" class:active=move || active_handle.get() == Some(h)>
+                                    <button
+                                        class="tab-title"
+                                        role="tab"
+                                        aria-selected=move || if active_handle.get() == Some(h) { "true" } else { "false" }
+                                        title=t.path.clone()
+                                        on:click=move |_| switch_tab(h)
+                                    >{ name.clone() }</button>
+                                    <button class="tab-close" aria-label=format!("关闭 {}", name) title="关闭" on:click=move |_| close_tab(h)>"×"</button>
+                                </div>
+                            }
+                        }
+                    />
+                </div>
+            </Show>
+
+            <div class="search-container" role="search" style="display:flex; gap:8px; padding:8px; position:relative;">
                 <input
                     type="text"
                     class="search-input"
                     placeholder="输入搜索内容..."
+                    aria-label="搜索内容"
                     prop:value=search_query
                     on:input=move |ev| set_search_query.set(event_target_value(&ev))
                     on:keydown=move |ev| {
@@ -652,6 +3765,33 @@ pub fn App() -> impl IntoView {
                     }
                     style="flex:1; min-width:0;"
                 />
+                <button class="search-button" on:click=toggle_history disabled=move || file_path.get().is_empty() aria-haspopup="listbox" aria-expanded=move || if show_history.get() { "true" } else { "false" } title="搜索历史">
+                    "历史"
+                </button>
+                <Show when=move || show_history.get()>
+                    <div class="search-history" role="listbox" aria-label="搜索历史" style="position:absolute; left:8px; top:100%; min-width:260px; max-height:50vh; overflow-y:auto; background:Canvas; color:CanvasText; border:1px solid ButtonText; box-shadow:0 6px 18px rgba(0,0,0,0.12); padding:8px; border-radius:6px; z-index:1000; color-scheme:light dark;">
+                        { move || {
+                            let history = search_history.get();
+                            let section = move |title: &'static str, items: Vec<String>| view! {
+                                <div style="font-size:12px; opacity:0.65; padding:4px 10px;">{title}</div>
+                                { if items.is_empty() {
+                                    view! { <div style="font-size:12px; opacity:0.5; padding:4px 10px;">"（无）"</div> }.into_any()
+                                } else {
+                                    items.into_iter().map(|q| {
+                                        let picked = q.clone();
+                                        view! {
+                                            <button class="menu-item" role="option" on:click=move |_| pick_history(picked.clone()) style="display:block; width:100%; text-align:left; padding:6px 10px; margin-top:2px;">{q}</button>
+                                        }
+                                    }).collect::<Vec<_>>().into_any()
+                                } }
+                            };
+                            view! {
+                                {section("本文件", history.file)}
+                                {section("全部文件", history.global)}
+                            }
+                        } }
+                    </div>
+                </Show>
                 <button class="search-button" on:click=search disabled=move || loading.get() || searching.get() aria-label="搜索" title="搜索">
                     { move || {
                         // choose icon based on state: loading(opening file) -> loading icon; searching -> loading icon; if matches found -> found icon; otherwise default search icon
@@ -667,6 +3807,15 @@ pub fn App() -> impl IntoView {
                         view! { <img src=src alt="search" width="20" height="20" class=class_str style="display:block;"/> }
                     } }
                 </button>
+                <button class="search-button" on:click=quick_jump_prev disabled=move || loading.get() || searching.get() || file_path.get().is_empty() || search_query.get().is_empty() title="从当前位置跳到上一个匹配，不统计总数">
+                    "上一处"
+                </button>
+                <button class="search-button" on:click=quick_jump disabled=move || loading.get() || searching.get() || file_path.get().is_empty() || search_query.get().is_empty() title="从当前位置跳到下一个匹配，不统计总数">
+                    "快速跳转"
+                </button>
+                <button class="search-button" on:click=toggle_filter disabled=move || file_path.get().is_empty() || (filter_needle.get().is_none() && search_query.get().is_empty()) title="只显示包含搜索内容的行">
+                    { move || if filter_needle.get().is_some() { "退出筛选" } else { "筛选" } }
+                </button>
             </div>
 
             <Show when=move || !search_info.get().is_empty()>
@@ -685,53 +3834,480 @@ pub fn App() -> impl IntoView {
                     <div style="display:flex; gap:6px; align-items:center;">
                         <button class="match-nav" on:click=go_prev_match aria-label="prev" style="background:transparent;border:1px solid transparent;padding:6px 8px;border-radius:4px;cursor:pointer;">{ move || "<" }</button>
                         <button class="match-nav" on:click=go_next_match aria-label="next" style="background:transparent;border:1px solid transparent;padding:6px 8px;border-radius:4px;cursor:pointer;">{ move || ">" }</button>
+                        <button class="match-nav" on:click=move |_| set_show_results.update(|v| *v = !*v) disabled=move || matches_list.get().is_empty() aria-expanded=move || show_results.get().to_string() style="background:transparent;border:1px solid transparent;padding:6px 8px;border-radius:4px;cursor:pointer;">"结果列表"</button>
+                    </div>
+                </div>
+            </Show>
+
+            <Show when=move || show_page_find.get()>
+                <div class="page-find" style="font-size:12px; display:flex; align-items:center; gap:6px; padding:4px 8px;">
+                    <input id="page-find-input" type="search" placeholder="在当前页查找…" aria-label="在当前页查找"
+                        prop:value=move || page_find_query.get()
+                        on:input=move |ev| set_page_find_query.set(event_target_value(&ev))
+                        on:keydown=move |ev| match ev.key().as_str() {
+                            "Enter" => step_page_find(!ev.shift_key()),
+                            "Escape" => set_show_page_find.set(false),
+                            _ => {}
+                        }
+                        style="width:200px; padding:3px 6px;" />
+                    <span style="opacity:0.7;" aria-live="polite">{ move || {
+                        let n = page_hits.with(|h| h.len());
+                        if page_find_query.get().is_empty() {
+                            String::new()
+                        } else if n == 0 {
+                            "当前页没有匹配".to_string()
+                        } else {
+                            format!("第 {} / {} 项", page_find_idx.get() + 1, n)
+                        }
+                    } }</span>
+                    <button class="match-nav" on:click=move |_| step_page_find(false) disabled=move || page_hits.with(|h| h.is_empty()) aria-label="上一个" style="padding:4px 10px;">"<"</button>
+                    <button class="match-nav" on:click=move |_| step_page_find(true) disabled=move || page_hits.with(|h| h.is_empty()) aria-label="下一个" style="padding:4px 10px;">">"</button>
+                    <Show when=move || page_hits.with(|h| h.is_empty()) && !page_find_query.get().is_empty()>
+                        <button class="match-nav" on:click=move |ev| { set_search_query.set(page_find_query.get_untracked()); search(ev); } disabled=move || searching.get() style="padding:4px 10px;" title="用同样的内容搜索整个文件">"搜索整个文件"</button>
+                    </Show>
+                    <span style="flex:1;"></span>
+                    <button class="match-nav" on:click=move |_| set_show_page_find.set(false) aria-label="close" style="background:transparent;border:1px solid transparent;padding:6px 8px;border-radius:4px;cursor:pointer;">"×"</button>
+                </div>
+            </Show>
+
+            <Show when=move || show_results.get() && !matches_list.get().is_empty()>
+                <div class="search-results" style="font-size:12px; padding:4px 8px;">
+                    <div style="display:flex; align-items:center; gap:6px; margin-bottom:4px;">
+                        <input class="results-filter" type="search" placeholder="在结果中筛选…" aria-label="在结果中筛选"
+                            prop:value=move || results_filter.get()
+                            on:input=move |ev| set_results_filter.set(event_target_value(&ev))
+                            style="width:160px; padding:3px 6px;" />
+                        <span style="flex:1; opacity:0.7;">{ move || {
+                            let shown = shown_results.with(|r| r.len());
+                            let total = result_entries.with(|r| r.len());
+                            let scope = if shown == total { String::new() } else { format!("显示 {} / {} 项，", shown, total) };
+                            let pending = if fetching_texts.get() { "正在读取行文本…，" } else { "" };
+                            format!("{}{}已选 {} 项（Ctrl+点击多选）", scope, pending, selected_results.get().len())
+                        } }</span>
+                        <button class="match-nav" on:click=move |_| set_selected_results.set(shown_results.with_untracked(|r| r.iter().map(|(idx, _, _)| *idx).collect())) style="padding:4px 10px;">"全选"</button>
+                        <button class="match-nav" on:click=move |_| copy_selected_results() disabled=move || selected_results.get().is_empty() style="padding:4px 10px;">"复制选中行"</button>
+                    </div>
+                    <ul class="search-results-list" role="listbox" aria-multiselectable="true">
+                        { move || shown_results.get().into_iter().map(|(idx, line, text)| {
+                            let selected = move || selected_results.with(|sel| sel.contains(&idx));
+                            view! {
+                                <li role="option" aria-selected=move || selected().to_string() class:selected=selected class:current=move || current_match_idx.get() == idx on:click=move |ev| click_result(ev, idx)>
+                                    <span class="result-line">{ line + 1 }</span>
+                                    <span class="result-text">{ text }</span>
+                                </li>
+                            }
+                        }).collect::<Vec<_>>() }
+                    </ul>
+                </div>
+            </Show>
+
+            <Show when=move || compare_handle.get().is_some()>
+                <div class="compare-info" style="font-size:12px; opacity:0.7; display:flex; align-items:center; gap:8px; padding:4px 8px;">
+                    <div style="flex:1; min-width:0; overflow:hidden; white-space:nowrap; text-overflow:ellipsis;" title=move || compare_path.get()>
+                        { move || format!("对比：{}  {}", compare_path.get(), diff_info.get()) }
+                    </div>
+                    <div style="display:flex; gap:6px; align-items:center;">
+                        <button class="match-nav" on:click=go_next_diff aria-label="next diff" style="background:transparent;border:1px solid transparent;padding:6px 8px;border-radius:4px;cursor:pointer;">"下一处差异"</button>
+                        <button class="match-nav" on:click=close_compare aria-label="close compare" style="background:transparent;border:1px solid transparent;padding:6px 8px;border-radius:4px;cursor:pointer;">"关闭对比"</button>
+                    </div>
+                </div>
+            </Show>
+
+            <Show when=move || !encoding_previews.get().is_empty()>
+                <div class="encoding-dialog-backdrop" style="position:fixed; inset:0; background:rgba(0,0,0,0.3); display:flex; align-items:center; justify-content:center; z-index:1100;">
+                    <div class="encoding-dialog" role="dialog" aria-label="选择文件编码" style="background:Canvas; color:CanvasText; border:1px solid ButtonText; border-radius:6px; padding:12px; width:min(640px, 90vw); max-height:80vh; overflow:auto; color-scheme:light dark;">
+                        <div style="display:flex; align-items:center; margin-bottom:8px;">
+                            <strong style="flex:1;">"无法确定文件编码，请选择显示正常的一项"</strong>
+                            <button class="match-nav" on:click=move |_| set_encoding_previews.set(Vec::new()) aria-label="close" style="background:transparent;border:1px solid transparent;padding:6px 8px;border-radius:4px;cursor:pointer;">"×"</button>
+                        </div>
+                        <For
+                            each=move || encoding_previews.get()
+                            key=|p| p.encoding.clone()
+                            children=move |p: EncodingPreview| {
+                                let enc = p.encoding.clone();
+                                let enc_view = p.encoding.clone();
+                                let enc_sample = p.encoding.clone();
+                                let utf16 = enc.starts_with("utf-16");
+                                view! {
+                                    <div style="border-top:1px solid rgba(128,128,128,0.3); padding:6px 0;">
+                                        <div style="display:flex; align-items:center; gap:8px;">
+                                            <span style="font-weight:700; flex:1;">{ p.encoding.to_uppercase() }{ if p.had_errors { "（含无法解码的字节）" } else { "" } }</span>
+                                            <button class="match-nav" disabled=utf16 title="按此编码显示当前页，不切换编码" on:click=move |_| preview_encoding_at_view(enc_view.clone()) style="padding:4px 10px;">"预览当前页"</button>
+                                            <button class="match-nav" disabled=utf16 title=if utf16 { "暂不支持以 UTF-16 读取" } else { "" } on:click=move |_| choose_encoding(enc.clone()) style="padding:4px 10px;">"使用此编码"</button>
+                                        </div>
+                                        <pre style="margin:4px 0 0 0; font-size:12px; white-space:pre; overflow-x:auto; opacity:0.85;">{ p.sample_text }</pre>
+                                        { move || encoding_page_samples.with(|list| list.iter().find(|(e, _, _)| *e == enc_sample).map(|(_, start, text)| view! {
+                                            <div style="font-size:12px; margin-top:4px; opacity:0.7;">{ format!("当前页（第 {} 行起）：", start + 1) }</div>
+                                            <pre style="margin:2px 0 0 0; font-size:12px; white-space:pre; overflow-x:auto; max-height:12em; overflow-y:auto;">{ text.clone() }</pre>
+                                        })) }
+                                    </div>
+                                }
+                            }
+                        />
+                    </div>
+                </div>
+            </Show>
+
+            <Show when=move || show_hash_dialog.get()>
+                <div class="encoding-dialog-backdrop" style="position:fixed; inset:0; background:rgba(0,0,0,0.3); display:flex; align-items:center; justify-content:center; z-index:1100;">
+                    <div class="hash-dialog" role="dialog" aria-label="文件校验值" style="background:Canvas; color:CanvasText; border:1px solid ButtonText; border-radius:6px; padding:12px; width:min(560px, 90vw); color-scheme:light dark;">
+                        <div style="display:flex; align-items:center; margin-bottom:8px;">
+                            <strong style="flex:1;">"文件校验值"</strong>
+                            <button class="match-nav" on:click=move |_| set_show_hash_dialog.set(false) aria-label="close" style="background:transparent;border:1px solid transparent;padding:6px 8px;border-radius:4px;cursor:pointer;">"×"</button>
+                        </div>
+                        <div style="display:flex; align-items:center; gap:8px;">
+                            <select
+                                aria-label="哈希算法"
+                                prop:value=move || hash_algorithm.get()
+                                on:change=move |ev| set_hash_algorithm.set(event_target_value(&ev))
+                                disabled=move || hashing.get()
+                            >
+                                <option value="xxh3">"XXH3-64（快速）"</option>
+                                <option value="sha256">"SHA-256"</option>
+                            </select>
+                            <button class="match-nav" on:click=compute_hash disabled=move || hashing.get() style="padding:4px 10px;">
+                                { move || if hashing.get() { "计算中…" } else { "计算" } }
+                            </button>
+                            <Show when=move || hashing.get()>
+                                <button class="match-nav" on:click=cancel_task style="padding:4px 10px;">"取消"</button>
+                            </Show>
+                            <span style="opacity:0.7; font-size:0.85rem;" aria-live="polite">{ move || hash_progress.get() }</span>
+                        </div>
+                        <Show when=move || !hash_result.get().is_empty()>
+                            <div style="display:flex; align-items:center; gap:8px; margin-top:8px;">
+                                <code style="flex:1; word-break:break-all; user-select:all;">{ move || hash_result.get() }</code>
+                                <button class="match-nav" on:click=copy_hash style="padding:4px 10px;">"复制"</button>
+                            </div>
+                        </Show>
+                    </div>
+                </div>
+            </Show>
+
+            <Show when=move || exporting.get()>
+                <div class="encoding-dialog-backdrop" style="position:fixed; inset:0; background:rgba(0,0,0,0.3); display:flex; align-items:center; justify-content:center; z-index:1100;">
+                    <div class="export-dialog" role="dialog" aria-label="导出匹配行" style="background:Canvas; color:CanvasText; border:1px solid ButtonText; border-radius:6px; padding:12px; width:min(420px, 90vw); color-scheme:light dark;">
+                        <div style="margin-bottom:8px;"><strong>"正在导出匹配行…"</strong></div>
+                        <div style="display:flex; align-items:center; gap:8px;">
+                            <span style="flex:1; opacity:0.7; font-size:0.85rem;" aria-live="polite">{ move || export_progress.get() }</span>
+                            <button class="match-nav" on:click=cancel_task style="padding:4px 10px;">"取消"</button>
+                        </div>
+                    </div>
+                </div>
+            </Show>
+
+            <Show when=move || invalid_utf8.get().is_some()>
+                <div class="encoding-dialog-backdrop" style="position:fixed; inset:0; background:rgba(0,0,0,0.3); display:flex; align-items:center; justify-content:center; z-index:1100;">
+                    <div class="invalid-utf8-dialog" role="dialog" aria-label="无效的 UTF-8" style="background:Canvas; color:CanvasText; border:1px solid ButtonText; border-radius:6px; padding:12px; width:min(560px, 90vw); max-height:80vh; display:flex; flex-direction:column; color-scheme:light dark;">
+                        <div style="display:flex; align-items:center; margin-bottom:8px;">
+                            <strong style="flex:1;">{ move || {
+                                let n = invalid_utf8.with(|v| v.as_ref().map_or(0, |v| v.len()));
+                                if scanning_utf8.get() {
+                                    "正在查找无效的 UTF-8…".to_string()
+                                } else if n == 0 {
+                                    "没有发现无效的 UTF-8".to_string()
+                                } else if n >= INVALID_UTF8_LIMIT {
+                                    format!("无效的 UTF-8（只列出前 {} 处）", n)
+                                } else {
+                                    format!("无效的 UTF-8（{} 处）", n)
+                                }
+                            } }</strong>
+                            <button class="match-nav" on:click=move |ev| { if scanning_utf8.get_untracked() { cancel_task(ev); } set_invalid_utf8.set(None); } aria-label="close" style="background:transparent;border:1px solid transparent;padding:6px 8px;border-radius:4px;cursor:pointer;">"×"</button>
+                        </div>
+                        <Show when=move || scanning_utf8.get()>
+                            <div style="display:flex; align-items:center; gap:8px;">
+                                <span style="flex:1; opacity:0.7; font-size:0.85rem;" aria-live="polite">{ move || utf8_scan_progress.get() }</span>
+                                <button class="match-nav" on:click=cancel_task style="padding:4px 10px;">"取消"</button>
+                            </div>
+                        </Show>
+                        <div style="overflow:auto;">
+                            { move || invalid_utf8.get().unwrap_or_default().into_iter().map(|p| {
+                                let line = p.line;
+                                view! {
+                                    <button class="menu-item" on:click=move |_| { set_invalid_utf8.set(None); jump_to_line(line); } style="display:block; width:100%; text-align:left; padding:4px 8px;">
+                                        { format!("第 {} 行，第 {} 列（偏移 {}，字节 0x{:02X}）", p.line + 1, p.column + 1, p.offset, p.byte) }
+                                    </button>
+                                }
+                            }).collect::<Vec<_>>() }
+                        </div>
+                    </div>
+                </div>
+            </Show>
+
+            <Show when=move || unique_result.get().is_some()>
+                <div class="encoding-dialog-backdrop" style="position:fixed; inset:0; background:rgba(0,0,0,0.3); display:flex; align-items:center; justify-content:center; z-index:1100;">
+                    <div class="unique-lines-dialog" role="dialog" aria-label="重复行统计" style="background:Canvas; color:CanvasText; border:1px solid ButtonText; border-radius:6px; padding:12px; width:min(900px, 95vw); max-height:85vh; display:flex; flex-direction:column; color-scheme:light dark;">
+                        <div style="display:flex; align-items:center; margin-bottom:8px;">
+                            <strong style="flex:1;">{ move || {
+                                if counting_unique.get() {
+                                    return "正在统计重复行…".to_string();
+                                }
+                                let r = unique_result.get().unwrap_or_default();
+                                let mut title = format!("共 {} 行，{} 种不同的行", r.total_lines, r.distinct);
+                                if r.truncated {
+                                    title.push_str(&format!("（种数过多，之后新出现的 {} 行未计入）", r.untracked_lines));
+                                }
+                                title
+                            } }</strong>
+                            <button class="match-nav" on:click=move |ev| { if counting_unique.get_untracked() { cancel_task(ev); } set_unique_result.set(None); } aria-label="close" style="background:transparent;border:1px solid transparent;padding:6px 8px;border-radius:4px;cursor:pointer;">"×"</button>
+                        </div>
+                        <Show when=move || counting_unique.get()>
+                            <div style="display:flex; align-items:center; gap:8px;">
+                                <span style="flex:1; opacity:0.7; font-size:0.85rem;" aria-live="polite">{ move || unique_progress.get() }</span>
+                                <button class="match-nav" on:click=cancel_task style="padding:4px 10px;">"取消"</button>
+                            </div>
+                        </Show>
+                        <div style="overflow:auto;">
+                            <table class="unique-lines">
+                                <tbody>
+                                    { move || unique_result.get().unwrap_or_default().lines.into_iter().map(|l| {
+                                        let line = l.first_line;
+                                        view! {
+                                            <tr on:click=move |_| { set_unique_result.set(None); jump_to_line(line); } title=format!("第一次出现在第 {} 行", line + 1)>
+                                                <td class="unique-count">{l.count}</td>
+                                                <td class="unique-text">{l.text}</td>
+                                            </tr>
+                                        }
+                                    }).collect::<Vec<_>>() }
+                                </tbody>
+                            </table>
+                        </div>
+                    </div>
+                </div>
+            </Show>
+
+            <Show when=move || range_diff.get().is_some()>
+                <div class="encoding-dialog-backdrop" style="position:fixed; inset:0; background:rgba(0,0,0,0.3); display:flex; align-items:center; justify-content:center; z-index:1100;">
+                    <div class="range-diff-dialog" role="dialog" aria-label="比较两段行" style="background:Canvas; color:CanvasText; border:1px solid ButtonText; border-radius:6px; padding:12px; width:min(1100px, 95vw); max-height:85vh; overflow:auto; color-scheme:light dark;">
+                        <div style="display:flex; align-items:center; margin-bottom:8px;">
+                            <strong style="flex:1;">{ move || range_diff.with(|d| {
+                                let d = d.as_deref().unwrap_or(&[]);
+                                let removed = d.iter().filter(|l| l.tag == "delete").count();
+                                let added = d.iter().filter(|l| l.tag == "insert").count();
+                                if removed == 0 && added == 0 { "两段内容相同".to_string() } else { format!("比较两段行：−{} +{}", removed, added) }
+                            }) }</strong>
+                            <button class="match-nav" on:click=move |_| set_range_diff.set(None) aria-label="close" style="background:transparent;border:1px solid transparent;padding:6px 8px;border-radius:4px;cursor:pointer;">"×"</button>
+                        </div>
+                        <table class="range-diff">
+                            <tbody>
+                                { move || range_diff.get().unwrap_or_default().into_iter().map(|d| {
+                                    let left = d.line_a.map(|l| (l + 1).to_string()).unwrap_or_default();
+                                    let right = d.line_b.map(|l| (l + 1).to_string()).unwrap_or_default();
+                                    let (mark_a, mark_b) = match d.tag.as_str() {
+                                        "delete" => ("−", ""),
+                                        "insert" => ("", "+"),
+                                        _ => ("", ""),
+                                    };
+                                    let text_a = if d.line_a.is_some() { d.text.clone() } else { String::new() };
+                                    let text_b = if d.line_b.is_some() { d.text } else { String::new() };
+                                    view! {
+                                        <tr class=d.tag>
+                                            <td class="diff-line">{left}</td>
+                                            <td class="diff-mark">{mark_a}</td>
+                                            <td class="diff-text">{text_a}</td>
+                                            <td class="diff-line">{right}</td>
+                                            <td class="diff-mark">{mark_b}</td>
+                                            <td class="diff-text">{text_b}</td>
+                                        </tr>
+                                    }
+                                }).collect::<Vec<_>>() }
+                            </tbody>
+                        </table>
+                    </div>
+                </div>
+            </Show>
+
+            <Show when=move || show_goto.get()>
+                <div class="encoding-dialog-backdrop" style="position:fixed; inset:0; background:rgba(0,0,0,0.3); display:flex; align-items:center; justify-content:center; z-index:1100;">
+                    <div class="goto-dialog" role="dialog" aria-label="跳转到行" style="background:Canvas; color:CanvasText; border:1px solid ButtonText; border-radius:6px; padding:12px; width:min(560px, 90vw); color-scheme:light dark;">
+                        <div style="display:flex; align-items:center; margin-bottom:8px;">
+                            <strong style="flex:1;">{ move || format!("跳转到行（1–{}）", total_lines.get()) }</strong>
+                            <button class="match-nav" on:click=move |_| set_show_goto.set(false) aria-label="close" style="background:transparent;border:1px solid transparent;padding:6px 8px;border-radius:4px;cursor:pointer;">"×"</button>
+                        </div>
+                        <input
+                            id="goto-line-input"
+                            type="text"
+                            inputmode="numeric"
+                            aria-label="行号"
+                            prop:value=move || goto_input.get()
+                            on:input=move |ev| set_goto_input.set(event_target_value(&ev))
+                            on:keydown=move |ev| match ev.key().as_str() {
+                                "Enter" => commit_goto(),
+                                "Escape" => set_show_goto.set(false),
+                                _ => {}
+                            }
+                            style="width:100%; box-sizing:border-box;"
+                        />
+                        <pre aria-live="polite" style="margin:8px 0 0 0; min-height:1.4em; font-size:12px; white-space:pre; overflow-x:auto; opacity:0.85;">{ move || goto_preview.get() }</pre>
                     </div>
                 </div>
             </Show>
 
-            
+            // 屏幕阅读器播报：翻页/跳转后当前显示的行范围
+            <div class="sr-only" role="status" aria-live="polite">{ move || {
+                if file_path.get().is_empty() {
+                    return String::new();
+                }
+                if filter_needle.get().is_some() {
+                    let shown = filter_line_nos.get().len();
+                    let first = current_line.get();
+                    return format!("筛选视图，显示第 {} 到 {} 个匹配行，共 {} 个", first + 1, first + shown, filter_total.get());
+                }
+                let (start, end) = view_range.get();
+                let total = if indexed.get() { format!("共 {} 行", total_lines.get()) } else { format!("约 {} 行", total_lines.get()) };
+                format!("显示第 {} 到 {} 行，{}", start + 1, end, total)
+            } }</div>
 
             <main class="main-content" style="flex:1; display:flex; overflow:hidden;">
                 <div class="content-area" style="flex:1; display:flex; flex-direction:column; overflow:hidden;">
                         <div class="file-info">
                             <div style="display:flex; align-items:center; gap:12px; min-width:0;">
                                     <div style="flex:1; min-width:0; overflow:hidden;">
-                                        <span id="file-path" style="display:inline-block; white-space:nowrap;">{ move || if file_path.get().is_empty() { "请使用顶部菜单打开一个文本文件".to_string() } else { file_path.get() } }</span>
+                                        <span id="file-path" class:pause-on-hover=move || marquee_pause.get() style="display:inline-block; white-space:nowrap;">{ move || if file_path.get().is_empty() { "请使用顶部菜单打开一个文本文件".to_string() } else { file_path.get() } }</span>
                                     </div>
+                                    <Show when=move || opening.get()>
+                                        <button class="match-nav" on:click=cancel_open style="flex:0 0 auto; padding:4px 10px;">"取消打开"</button>
+                                    </Show>
+                                    <Show when=move || !file_path.get().is_empty()>
+                                        <span style="opacity:0.65; flex:0 0 auto; margin-left:6px;">{ move || {
+                                            let (start, end) = view_range.get();
+                                            if filter_needle.get().is_some() {
+                                                let shown = filter_line_nos.get().len();
+                                                let first = current_line.get();
+                                                if shown > 0 {
+                                                    format!("匹配行 {}–{} / 共 {}", first + 1, first + shown, filter_total.get())
+                                                } else {
+                                                    format!("共 {} 个匹配行", filter_total.get())
+                                                }
+                                            } else {
+                                                // 未建立索引时总行数只是估算
+                                                let total = if indexed.get() { format!("总 {}", total_lines.get()) } else { format!("约 {}", total_lines.get()) };
+                                                if end > start {
+                                                    format!("行 {}–{} / {}", start + 1, end, total)
+                                                } else {
+                                                    format!("{} 行", total)
+                                                }
+                                            }
+                                        } }</span>
+                                    </Show>
+                                    <Show when=move || !file_format.get().is_empty()>
+                                        <span style="opacity:0.65; flex:0 0 auto; margin-left:6px;">{ move || file_format.get() }</span>
+                                    </Show>
+                                    <Show when=move || tail_mb.get().is_some()>
+                                        <span style="opacity:0.65; flex:0 0 auto; margin-left:6px;" title="行号从显示部分的第一行起算">{ move || format!("显示最后 {} MB", tail_mb.get().unwrap_or(0)) }</span>
+                                    </Show>
+                                    <Show when=move || detected_format.get() == "csv" && !align_columns.get()>
+                                        <button class="match-nav" title="看起来是 CSV 文件" on:click=move |_| set_align_columns.set(true) style="flex:0 0 auto; padding:4px 10px;">"按列对齐"</button>
+                                    </Show>
+                                    <Show when=move || detected_format.get() == "log" && !log_coloring.get()>
+                                        <button class="match-nav" title="看起来是日志文件" on:click=move |_| set_log_coloring.set(true) style="flex:0 0 auto; padding:4px 10px;">"启用日志级别着色"</button>
+                                    </Show>
                                     <Show when=move || file_size.get() != 0>
-                                        <span style="font-weight:700; opacity:0.65; flex:0 0 auto; margin-left:6px;">{ move || format_bytes(file_size.get()) }</span>
+                                        <span style="font-weight:700; opacity:0.65; flex:0 0 auto; margin-left:6px;" title=move || open_stats.get()>{ move || format_bytes(file_size.get()) }</span>
                                     </Show>
                                 </div>
                         </div>
                             <div style="flex:1; display:flex; align-items:stretch; overflow:hidden;">
-                                    <div class="line-numbers" aria-hidden="true">
+                                    <div class="line-numbers" id="line-numbers" class:offsets=move || gutter_mode.get().shows_offsets() aria-hidden="true">
                                         <pre class="line-numbers-pre">{ move || {
-                                            // 根据 visible_start 与当前文件内容行数生成行号
+                                            // 根据 visible_start 与当前文件内容行数生成逐行的行号元素
                                             let start = visible_start.get();
-                                            let content = file_content.get();
-                                            let mut out = String::new();
                                             if file_path.get().is_empty() {
                                                 // 未打开文件时显示空白行号区域，行数为可见行数的估计
                                                 let visible = compute_visible_lines("editor-textarea").unwrap_or(DEFAULT_VISIBLE_LINES);
-                                                for _ in 0..visible {
-                                                    out.push_str("\n");
-                                                }
+                                                (0..visible).map(|_| view! { <span class="line-number"></span> }.into_any()).collect::<Vec<_>>()
                                             } else {
-                                                let lines = content.lines().count().max(1);
-                                                for i in 0..lines {
-                                                    out.push_str(&format!("{}\n", start + i + 1));
-                                                }
+                                                // 每个显示行一个元素；硬换行产生的续行留空。行号与 textarea 的内容来自同一个 wrapped，
+                                                // 行数必须一致，否则之后的行号会整体错位
+                                                let rows = wrapped.with(|w| {
+                                                    debug_assert_eq!(w.1.len(), w.0.lines().count(), "gutter rows must match displayed rows");
+                                                    debug_assert!(w.1.first().map_or(true, |r| *r == Some(0)), "first gutter row must be visible_start");
+                                                    w.1.clone()
+                                                });
+                                                // 筛选模式下显示每行的原始行号
+                                                let filtered = filter_needle.get().map(|_| filter_line_nos.get());
+                                                let mode = gutter_mode.get();
+                                                let offsets = line_offsets.get();
+                                                // 相对行号按显示的逻辑行计算（筛选/倒序视图中即屏幕上相距的行数），随光标行移动更新
+                                                let cur = if matches!(mode, GutterMode::Relative | GutterMode::Hybrid) { cursor_rel.get().unwrap_or(0) } else { 0 };
+                                                rows.into_iter().map(|row| {
+                                                    let Some(i) = row else {
+                                                        return view! { <span class="line-number"></span> }.into_any();
+                                                    };
+                                                    let abs = match &filtered {
+                                                        Some(nos) => nos.get(i).copied().unwrap_or(start + i),
+                                                        // 倒序视图中行号从上往下递减
+                                                        None if reversed.get() => total_lines.get().saturating_sub(start + i + 1),
+                                                        None => start + i,
+                                                    };
+                                                    view! {
+                                                        <span
+                                                            class="line-number clickable"
+                                                            class:copied=move || copied_line.get() == Some(abs)
+                                                            title="点击复制该行"
+                                                            on:click=move |_| copy_line(i, abs)
+                                                        >{
+                                                            // 偏移尚未取回时先留空
+                                                            let offset = offsets.0.iter().position(|l| *l == abs).and_then(|p| offsets.1.get(p).copied());
+                                                            match (mode, offset) {
+                                                                (GutterMode::LineNumber, _) => (abs + 1).to_string(),
+                                                                (GutterMode::Hybrid, _) if i == cur => (abs + 1).to_string(),
+                                                                (GutterMode::Relative | GutterMode::Hybrid, _) => i.abs_diff(cur).to_string(),
+                                                                (GutterMode::OffsetHex, Some(o)) => format!("{:X}", o),
+                                                                (GutterMode::OffsetDec, Some(o)) => o.to_string(),
+                                                                (_, None) => String::new(),
+                                                            }
+                                                        }</span>
+                                                    }.into_any()
+                                                }).collect::<Vec<_>>()
                                             }
-                                            out
                                         } }</pre>
                                     </div>
 
+                                <div class="editor-stack" style="flex:1; position:relative; display:flex; min-width:0;">
+                                // 翻页读取中的提示条；CSS 延迟显示，几毫秒内完成的读取不会闪烁
+                                <div class="page-loading-bar" class:active=move || page_loads.get() > 0 aria-hidden="true"></div>
+                                <Show when=move || file_empty.get()>
+                                    <div class="empty-file-note" role="status">"文件为空"</div>
+                                </Show>
+                                <pre class="editor-backdrop" class:aligned-columns=move || align_columns.get() id="editor-backdrop" aria-hidden="true">{ move || {
+                                    // 背景层中的文本透明，只用于让匹配高亮与 textarea 中的文字对齐
+                                    let colors = line_colors.get();
+                                    let cursor = cursor_rel.get();
+                                    let top = visible_start.get();
+                                    let block = block_range.get();
+                                    // 每个显示行所属的逻辑行，换行产生的续行与所属行一起高亮
+                                    let mut rel = 0usize;
+                                    let row_rels = wrapped.with(|w| w.1.iter().map(|r| { if let Some(i) = r { rel = *i; } rel }).collect::<Vec<_>>());
+                                    row_highlights.get().into_iter().enumerate().map(|(k, segments)| {
+                                        let style = colors.get(k).cloned().flatten().map(|c| format!("background-color:{}", c)).unwrap_or_default();
+                                        let current = cursor.is_some() && cursor == row_rels.get(k).copied();
+                                        let in_block = block.is_some_and(|(a, b)| row_rels.get(k).is_some_and(|&r| (a..=b).contains(&(top + r))));
+                                        view! {
+                                            <span class="backdrop-row" class:current-row=current class:block-row=in_block style=style>{
+                                                segments.into_iter().map(|(t, hit, active, color)| if hit {
+                                                    view! { <mark class="search-hit" class:active=active>{t}</mark> }.into_any()
+                                                } else if let Some(c) = color {
+                                                    view! { <span class="ansi-color" style=format!("background-color:{}", c)>{t}</span> }.into_any()
+                                                } else {
+                                                    view! { <span>{t}</span> }.into_any()
+                                                }).collect::<Vec<_>>()
+                                            }</span>
+                                        }
+                                    }).collect::<Vec<_>>()
+                                } }</pre>
                                 <textarea
                                     class="content-textarea"
+                                    class:smooth-scroll=move || smooth_scroll.get() && !prefers_reduced_motion()
+                                    class:aligned-columns=move || align_columns.get()
+                                    class:page-loading=move || page_loads.get() > 0
                                     id="editor-textarea"
                                     readonly=true
                                     wrap="off"
-                                    prop:value=file_content
+                                    prop:value=display_content
+                                    on:scroll=move |_| {
+                                        // 背景层与行号区跟随 textarea 滚动（横向滚动时匹配高亮也要对齐）
+                                        sync_editor_layers();
+                                    }
                                     on:wheel=move |ev| {
                                         ev.prevent_default();
                                         let dy = ev.delta_y();
@@ -739,49 +4315,169 @@ pub fn App() -> impl IntoView {
                                         let lines = (dy / px_per_line).round() as isize;
                                         if lines != 0 {
                                             let cur = current_line.get();
-                                            let mut new = if lines > 0 {
+                                            let new = if lines > 0 {
                                                 cur.saturating_add(lines as usize)
                                             } else {
                                                 cur.saturating_sub((-lines) as usize)
                                             };
-                                            let max_start = total_lines.get();
-                                            if new > max_start { new = max_start; }
-                                            set_current_line.set(new);
-                                            let visible = compute_visible_lines("editor-textarea").unwrap_or(DEFAULT_VISIBLE_LINES);
-                                            let safe = visible.saturating_sub(VISIBLE_SAFETY_MARGIN).max(1);
-                                            set_visible_start.set(new);
-                                            load_content(new, safe.min(LINES_PER_PAGE), set_file_content.clone(), set_loading.clone(), None);
+                                            scroll_to(new);
                                         }
                                     }
+                                    on:keydown=move |ev| {
+                                        // 不依赖鼠标的翻页：PageUp/PageDown 翻一页，Ctrl+Home/Ctrl+End 到开头/末尾，
+                                        // Ctrl+↓/Ctrl+↑ 到下一个/上一个空行
+                                        if file_path.get_untracked().is_empty() {
+                                            return;
+                                        }
+                                        if ev.ctrl_key() && (ev.key() == "ArrowDown" || ev.key() == "ArrowUp") {
+                                            ev.prevent_default();
+                                            jump_blank_line(ev.key() == "ArrowDown");
+                                            return;
+                                        }
+                                        if highlight_current.get_untracked() && filter_needle.get_untracked().is_none() && !reversed.get_untracked() && !ev.ctrl_key() && !ev.alt_key() && !ev.meta_key()
+                                            && (ev.key() == "ArrowDown" || ev.key() == "ArrowUp")
+                                        {
+                                            ev.prevent_default();
+                                            let total = total_lines.get_untracked();
+                                            let cur = cursor_line.get_untracked();
+                                            let next = if ev.key() == "ArrowDown" { (cur + 1).min(total.saturating_sub(1)) } else { cur.saturating_sub(1) };
+                                            let top = visible_start.get_untracked();
+                                            let shown = wrapped.with_untracked(|w| w.2).max(1);
+                                            set_cursor_line.set(next);
+                                            if next < top {
+                                                scroll_to(next);
+                                            } else if next >= top + shown {
+                                                scroll_to(next + 1 - shown);
+                                            }
+                                            return;
+                                        }
+                                        if ev.key() == "Home" && !ev.ctrl_key() && !ev.alt_key() && !ev.meta_key() && !ev.shift_key() {
+                                            if let Some(textarea) = ev.target().and_then(|t| t.dyn_into::<web_sys::HtmlTextAreaElement>().ok()) {
+                                                ev.prevent_default();
+                                                smart_home(&textarea);
+                                            }
+                                            return;
+                                        }
+                                        if ev.ctrl_key() && ev.key().eq_ignore_ascii_case("g") {
+                                            ev.prevent_default();
+                                            open_goto();
+                                            return;
+                                        }
+                                        if ev.ctrl_key() && ev.key().eq_ignore_ascii_case("f") {
+                                            ev.prevent_default();
+                                            open_page_find();
+                                            return;
+                                        }
+                                        let visible = compute_visible_lines("editor-textarea").unwrap_or(DEFAULT_VISIBLE_LINES);
+                                        let mut page = visible.saturating_sub(VISIBLE_SAFETY_MARGIN).max(1).min(lines_per_page.get_untracked());
+                                        // 硬换行时一页实际显示的逻辑行更少，按实际显示的行数翻页以免跳过内容
+                                        if wrap_col.get_untracked().is_some() {
+                                            page = wrapped.with_untracked(|w| w.2).max(1);
+                                        }
+                                        let cur = current_line.get_untracked();
+                                        let target = match ev.key().as_str() {
+                                            "PageDown" => Some(cur.saturating_add(page)),
+                                            "PageUp" => Some(cur.saturating_sub(page)),
+                                            "Home" if ev.ctrl_key() => Some(0),
+                                            "End" if ev.ctrl_key() => Some(usize::MAX),
+                                            _ => None,
+                                        };
+                                        if let Some(t) = target {
+                                            ev.prevent_default();
+                                            scroll_to(t);
+                                        }
+                                    }
+                                    aria-label="文件内容（只读）。PageUp/PageDown 翻页，Home 到行首的第一个非空白字符（再按一次到行首），Ctrl+Home/Ctrl+End 跳到开头/末尾，Ctrl+↓/Ctrl+↑ 跳到下一个/上一个空行，Ctrl+G 跳转到行，Ctrl+F 在当前页查找"
+                                    aria-readonly="true"
                                     style="flex:1; width:100%; resize:none; white-space:pre; overflow:auto;"
                                 ></textarea>
+                                </div>
+
+                                <Show when=move || compare_handle.get().is_some()>
+                                    <textarea
+                                        class="content-textarea compare-textarea"
+                                        readonly=true
+                                        wrap="off"
+                                        prop:value=display_compare_content
+                                        style="flex:1; width:100%; resize:none; white-space:pre; overflow:auto;"
+                                    ></textarea>
+                                </Show>
 
                                 <div class="editor-scrollbar" style="width:40px; display:flex; align-items:stretch; justify-content:center; padding:4px;">
                                     <input
                                         type="range"
                                         class="scrollbar"
                                         min=0
-                                        max=move || total_lines.get() as i32
-                                        // Slider maps directly: 0 (top) -> first line, max -> last line
-                                        prop:value=move || current_line.get() as i32
-                                        disabled=move || file_path.get().is_empty() || total_lines.get() == 0
+                                        max=move || if byte_slider() { BYTE_SLIDER_STEPS as i32 } else { (max_top_line() + slider_offset()) as i32 }
+                                        // 顶部模式：滑块值即视图顶部行；中心模式：滑块值为视图中心行（顶部行 + 半页）。
+                                        // 最大值对应最后一页，此时最后一行可见（筛选模式下为匹配行序号）。
+                                        // 字节比例模式：滑块值为视图顶部行的起始偏移占文件大小的比例
+                                        prop:value=move || {
+                                            if byte_slider() {
+                                                let size = file_size.get().max(1);
+                                                (view_byte_offset.get().1.min(size) * BYTE_SLIDER_STEPS / size) as i32
+                                            } else {
+                                                (current_line.get() + slider_offset()) as i32
+                                            }
+                                        }
+                                        // 未建立索引时行数只是估算，任意跳转也要从文件开头顺序读起，直到建立索引前不可拖动
+                                        disabled=move || file_path.get().is_empty() || total_lines.get() == 0 || !indexed.get()
                                         on:input=move |ev| {
+                                            if untrack(byte_slider) {
+                                                // 把字节比例换算成偏移，经行索引解析为所在的行后加载
+                                                let Ok(v) = event_target_value(&ev).parse::<u64>() else { return };
+                                                let offset = (file_size.get_untracked() as u128 * v.min(BYTE_SLIDER_STEPS) as u128 / BYTE_SLIDER_STEPS as u128) as u64;
+                                                byte_seek_seq.update_value(|s| *s = s.wrapping_add(1));
+                                                let seq = byte_seek_seq.get_value();
+                                                spawn_local(async move {
+                                                    let args = serde_wasm_bindgen::to_value(&ContextAtOffsetArgs { byte_offset: offset, before: 0, after: 0 }).unwrap();
+                                                    let Ok(res) = call_invoke("plugin:large-file-preview|context_at_offset", args).await else { return };
+                                                    if byte_seek_seq.get_value() != seq {
+                                                        return;
+                                                    }
+                                                    let line = js_sys::Reflect::get(&res, &JsValue::from_str("line")).ok().and_then(|l| l.as_f64()).unwrap_or(0.0) as usize;
+                                                    let line = line.min(untrack(max_top_line));
+                                                    set_current_line.set(line);
+                                                    set_visible_start.set(line);
+                                                    let visible = compute_visible_lines("editor-textarea").unwrap_or(DEFAULT_VISIBLE_LINES);
+                                                    let safe = visible.saturating_sub(VISIBLE_SAFETY_MARGIN).max(1);
+                                                    load_content(line, safe.min(lines_per_page.get_untracked()), set_file_content, set_page_loads, None);
+                                                });
+                                                return;
+                                            }
                                             if let Ok(v) = event_target_value(&ev).parse::<f64>() {
                                                 let mut raw = v as isize;
                                                 if raw < 0 { raw = 0; }
-                                                let raw = raw as usize;
-                                                // raw is the new current_line (page top)
-                                                let line = raw.min(total_lines.get());
+                                                // 换算为新的视图顶部行
+                                                let raw = (raw as usize).saturating_sub(untrack(slider_offset)).min(untrack(max_top_line));
+                                                if filter_needle.get_untracked().is_some() {
+                                                    let line = raw;
+                                                    set_current_line.set(line);
+                                                    load_filtered(line, false);
+                                                    return;
+                                                }
+                                                let line = raw;
                                                 set_current_line.set(line);
                                                 // 计算当前编辑器可见行数，并加载以 line 为顶部的内容
                                                 let visible = compute_visible_lines("editor-textarea").unwrap_or(DEFAULT_VISIBLE_LINES);
                                                 let safe = visible.saturating_sub(VISIBLE_SAFETY_MARGIN).max(1);
                                                 let start = line;
                                                 set_visible_start.set(start);
-                                                load_content(start, safe.min(LINES_PER_PAGE), set_file_content.clone(), set_loading.clone(), None);
+                                                load_content(start, safe.min(lines_per_page.get_untracked()), set_file_content.clone(), set_page_loads.clone(), None);
                                             }
                                         }
                                         aria-orientation="vertical"
+                                        aria-label="阅读位置（行）"
+                                        aria-valuetext=move || {
+                                            if byte_slider() {
+                                                let size = file_size.get().max(1);
+                                                format!("文件的 {:.1}% 处，第 {} 行，共 {} 行", view_byte_offset.get().1 as f64 * 100.0 / size as f64, current_line.get() + 1, total_lines.get())
+                                            } else if filter_needle.get().is_some() {
+                                                format!("第 {} 个匹配行，共 {} 个", current_line.get() + 1, filter_total.get())
+                                            } else {
+                                                format!("第 {} 行，共 {} 行", current_line.get() + 1, total_lines.get())
+                                            }
+                                        }
                                         style="writing-mode:vertical-rl; -webkit-appearance: slider-vertical; -webkit-transform-origin:center; transform-origin:center;"
                                     />
                                 </div>
@@ -820,16 +4516,10 @@ pub fn App() -> impl IntoView {
                                     }
                                 }
 
-                                if let Ok(line_height_val) = style.get_property_value("line-height") {
-                                    // line-height 可能为 "20px" 或 "normal"
-                                    if line_height_val.ends_with("px") {
-                                        if let Ok(v) = line_height_val[..line_height_val.len()-2].trim().parse::<f64>() {
-                                            if v > 0.0 {
-                                                let count = (height / v).floor() as usize;
-                                                return Some(count.max(1));
-                                            }
-                                        }
-                                    }
+                                // line-height 可能为 "20px" 或 "normal"
+                                if let Some(v) = resolve_line_height(&style) {
+                                    let count = (height / v).floor() as usize;
+                                    return Some(count.max(1));
                                 }
                             }
                         }
@@ -853,12 +4543,8 @@ pub fn App() -> impl IntoView {
                         let mut line_px = 18.0f64; // 默认估计
                         if let Ok(style) = window.get_computed_style(&el) {
                             if let Some(style) = style {
-                                if let Ok(line_height_val) = style.get_property_value("line-height") {
-                                    if line_height_val.ends_with("px") {
-                                        if let Ok(v) = line_height_val[..line_height_val.len()-2].trim().parse::<f64>() {
-                                            if v > 0.0 { line_px = v; }
-                                        }
-                                    }
+                                if let Some(v) = resolve_line_height(&style) {
+                                    line_px = v;
                                 }
                             }
                         }
@@ -869,3 +4555,295 @@ pub fn App() -> impl IntoView {
         }
         None
     }
+
+    // 行号区与背景层都以 textarea 的滚动位置为准：textarea 在一页内滚动（跳到匹配、平滑滚动、
+    // 内容变短时浏览器钳制滚动位置）后，把同样的偏移应用到另外两层，保证行号与内容逐行对齐
+    fn sync_editor_layers() {
+        let Some(doc) = web_sys::window().and_then(|w| w.document()) else { return };
+        let Some(ta) = doc.get_element_by_id("editor-textarea") else { return };
+        if let Some(bd) = doc.get_element_by_id("editor-backdrop") {
+            bd.set_scroll_top(ta.scroll_top());
+            bd.set_scroll_left(ta.scroll_left());
+        }
+        if let Some(gutter) = doc.get_element_by_id("line-numbers") {
+            gutter.set_scroll_top(ta.scroll_top());
+        }
+    }
+
+    // 检测系统是否开启了 “减少动态效果”（prefers-reduced-motion: reduce）
+    fn prefers_reduced_motion() -> bool {
+        web_sys::window()
+            .and_then(|w| w.match_media("(prefers-reduced-motion: reduce)").ok().flatten())
+            .map(|mql| mql.matches())
+            .unwrap_or(false)
+    }
+
+    // 解析一行中的 ANSI 转义序列并去除：CSI（ESC [ … 终止字节，SGR 以 m 结尾）、OSC（ESC ] … BEL 或 ESC \）
+    // 与其它双字符转义。返回 (去除后的文本, 原文每个字符位置对应的去除后字符位置（比字符数多一项，末项为文本长度）,
+    // 按 SGR 前景色着色的区间 (起始字符, 结束字符, CSS 颜色))
+    fn parse_ansi_line(line: &str) -> (String, Vec<usize>, Vec<(usize, usize, String)>) {
+        let chars: Vec<char> = line.chars().collect();
+        let mut text = String::with_capacity(line.len());
+        let mut map = Vec::with_capacity(chars.len() + 1);
+        let mut spans = Vec::new();
+        let mut color: Option<String> = None;
+        let mut color_start = 0usize;
+        let mut out = 0usize;
+        let mut i = 0usize;
+        while i < chars.len() {
+            if chars[i] != '\x1b' {
+                map.push(out);
+                text.push(chars[i]);
+                out += 1;
+                i += 1;
+                continue;
+            }
+            let start = i;
+            match chars.get(i + 1) {
+                Some('[') => {
+                    let params_start = i + 2;
+                    i = params_start;
+                    while i < chars.len() && !('\x40'..='\x7e').contains(&chars[i]) {
+                        i += 1;
+                    }
+                    if chars.get(i) == Some(&'m') {
+                        let params: String = chars[params_start..i].iter().collect();
+                        let next = apply_sgr(&params, color.clone());
+                        if next != color {
+                            if let Some(c) = color.take() {
+                                if out > color_start {
+                                    spans.push((color_start, out, c));
+                                }
+                            }
+                            color = next;
+                            color_start = out;
+                        }
+                    }
+                    i += 1;
+                }
+                Some(']') => {
+                    i += 2;
+                    while i < chars.len() && chars[i] != '\x07' && !(chars[i] == '\x1b' && chars.get(i + 1) == Some(&'\\')) {
+                        i += 1;
+                    }
+                    i += if chars.get(i) == Some(&'\x1b') { 2 } else { 1 };
+                }
+                Some(_) => i += 2,
+                None => i += 1,
+            }
+            i = i.min(chars.len());
+            map.extend(std::iter::repeat(out).take(i - start));
+        }
+        map.push(out);
+        if let Some(c) = color {
+            if out > color_start {
+                spans.push((color_start, out, c));
+            }
+        }
+        (text, map, spans)
+    }
+
+    // 按 SGR 参数（如 "1;31"、"38;5;208"）更新当前前景色；只关心前景色，其它属性忽略
+    fn apply_sgr(params: &str, current: Option<String>) -> Option<String> {
+        let codes: Vec<u32> = params.split(';').map(|p| p.parse().unwrap_or(0)).collect();
+        let mut color = current;
+        let mut k = 0usize;
+        while k < codes.len() {
+            match codes[k] {
+                0 | 39 => color = None,
+                c @ 30..=37 => color = Some(ansi_color_css(c - 30)),
+                c @ 90..=97 => color = Some(ansi_color_css(c - 90 + 8)),
+                38 => match codes.get(k + 1) {
+                    Some(5) => {
+                        if let Some(&n) = codes.get(k + 2) {
+                            color = Some(ansi_color_css(n.min(255)));
+                        }
+                        k += 2;
+                    }
+                    Some(2) => {
+                        if let (Some(&r), Some(&g), Some(&b)) = (codes.get(k + 2), codes.get(k + 3), codes.get(k + 4)) {
+                            color = Some(format!("rgba({}, {}, {}, 0.35)", r.min(255), g.min(255), b.min(255)));
+                        }
+                        k += 4;
+                    }
+                    _ => {}
+                },
+                _ => {}
+            }
+            k += 1;
+        }
+        color
+    }
+
+    // xterm 256 色中第 `n` 色的 CSS 颜色（半透明，作为背景层中文字的底色）
+    fn ansi_color_css(n: u32) -> String {
+        const BASIC: [(u8, u8, u8); 16] = [
+            (0, 0, 0), (205, 49, 49), (13, 188, 121), (229, 229, 16), (36, 114, 200), (188, 63, 188), (17, 168, 205), (229, 229, 229),
+            (102, 102, 102), (241, 76, 76), (35, 209, 139), (245, 245, 67), (59, 142, 234), (214, 112, 214), (41, 184, 219), (255, 255, 255),
+        ];
+        let (r, g, b) = match n {
+            0..=15 => BASIC[n as usize],
+            16..=231 => {
+                let level = |v: u32| if v == 0 { 0 } else { (55 + v * 40) as u8 };
+                let i = n - 16;
+                (level(i / 36), level(i / 6 % 6), level(i % 6))
+            }
+            _ => {
+                let v = (8 + (n - 232) * 10) as u8;
+                (v, v, v)
+            }
+        };
+        format!("rgba({}, {}, {}, 0.35)", r, g, b)
+    }
+
+    // 去除每行末尾的空格与制表符，行数保持不变
+    fn trim_trailing_ws(content: &str) -> String {
+        let mut out = String::with_capacity(content.len());
+        for l in content.lines() {
+            out.push_str(l.trim_end_matches([' ', '\t']));
+            out.push('\n');
+        }
+        out
+    }
+
+    // 自动识别列分隔符：第一个非空行中制表符不少于逗号时按 TSV，否则按 CSV
+    fn guess_delimiter(content: &str) -> char {
+        let first = content.lines().find(|l| !l.trim().is_empty()).unwrap_or("");
+        let tabs = first.matches('\t').count();
+        if tabs > 0 && tabs >= first.matches(',').count() { '\t' } else { ',' }
+    }
+
+    // 按分隔符拆分一行；双引号内的分隔符不拆分，字段外层的引号去掉，`""` 还原为 `"`
+    fn split_delimited(line: &str, delimiter: char) -> Vec<String> {
+        let mut fields = Vec::new();
+        let mut field = String::new();
+        let mut in_quotes = false;
+        let mut chars = line.chars().peekable();
+        while let Some(c) = chars.next() {
+            if in_quotes {
+                if c == '"' {
+                    if chars.peek() == Some(&'"') {
+                        field.push('"');
+                        chars.next();
+                    } else {
+                        in_quotes = false;
+                    }
+                } else {
+                    field.push(c);
+                }
+            } else if c == '"' && field.is_empty() {
+                in_quotes = true;
+            } else if c == delimiter {
+                fields.push(std::mem::take(&mut field));
+            } else {
+                field.push(c);
+            }
+        }
+        fields.push(field);
+        fields
+    }
+
+    // 把每行按列补齐到该列在本页内的最大宽度（按字符数，单列最多补到 ALIGN_MAX_WIDTH），
+    // 列之间用两个空格分隔；行数保持不变，空行原样保留
+    fn align_delimited(content: &str, delimiter: char) -> String {
+        const ALIGN_MAX_WIDTH: usize = 48;
+        let rows: Vec<Option<Vec<String>>> = content
+            .lines()
+            .map(|l| if l.is_empty() { None } else { Some(split_delimited(l, delimiter)) })
+            .collect();
+        let mut widths: Vec<usize> = Vec::new();
+        for fields in rows.iter().flatten() {
+            for (i, f) in fields.iter().enumerate() {
+                let w = f.chars().count().min(ALIGN_MAX_WIDTH);
+                match widths.get_mut(i) {
+                    Some(max) => *max = (*max).max(w),
+                    None => widths.push(w),
+                }
+            }
+        }
+        let mut out = String::with_capacity(content.len() * 2);
+        for fields in &rows {
+            if let Some(fields) = fields {
+                for (i, f) in fields.iter().enumerate() {
+                    out.push_str(f);
+                    if i + 1 < fields.len() {
+                        let pad = widths[i].saturating_sub(f.chars().count());
+                        out.push_str(&" ".repeat(pad + 2));
+                    }
+                }
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    // 按行首附近（前 64 字节内的前几个单词）出现的级别关键字分类，跳过时间戳等前缀。
+    // 每页加载都会对所有行调用，因此只做字节级比较，不分配内存
+    fn classify_log_line<'a>(line: &str, rules: &'a [LogLevelRule]) -> Option<&'a LogLevelRule> {
+        const HEAD_BYTES: usize = 64;
+        const HEAD_WORDS: usize = 6;
+        let head = &line.as_bytes()[..line.len().min(HEAD_BYTES)];
+        head.split(|b| !b.is_ascii_alphanumeric())
+            .filter(|w| !w.is_empty())
+            .take(HEAD_WORDS)
+            .find_map(|w| rules.iter().find(|r| w.eq_ignore_ascii_case(r.token.as_bytes())))
+    }
+
+    // 在第 `col` 个字符处折行，最多输出 `max_rows` 个显示行（至少完整输出第一行）。
+    // 返回 (文本, 每个显示行对应的逻辑行序号（续行为 None）, 输出的逻辑行数)
+    fn wrap_at_column(content: &str, col: usize, max_rows: usize) -> (String, Vec<Option<usize>>, usize) {
+        let col = col.max(1);
+        let mut out = String::with_capacity(content.len());
+        let mut rows = Vec::new();
+        let mut shown = 0usize;
+        for (i, line) in content.lines().enumerate() {
+            let chars: Vec<char> = line.chars().collect();
+            let chunks: Vec<&[char]> = if chars.is_empty() { vec![&[][..]] } else { chars.chunks(col).collect() };
+            if shown > 0 && rows.len() + chunks.len() > max_rows {
+                break;
+            }
+            for (k, chunk) in chunks.iter().enumerate() {
+                out.extend(chunk.iter());
+                out.push('\n');
+                rows.push(if k == 0 { Some(i) } else { None });
+            }
+            shown += 1;
+        }
+        (out, rows, shown)
+    }
+
+    // 从计算样式得到实际行高（px）。`line-height: normal` 时其值取决于字体，
+    // 用一个同字体的隐藏单行元素测量 offsetHeight；测量失败时按 font-size × 1.2 估算
+    fn resolve_line_height(style: &web_sys::CssStyleDeclaration) -> Option<f64> {
+        fn parse_px(v: &str) -> Option<f64> {
+            v.strip_suffix("px")?.trim().parse::<f64>().ok().filter(|v| *v > 0.0)
+        }
+        let line_height = style.get_property_value("line-height").ok()?;
+        if let Some(v) = parse_px(&line_height) {
+            return Some(v);
+        }
+        if line_height.trim() != "normal" {
+            return None;
+        }
+        let font_size = style.get_property_value("font-size").ok().and_then(|v| parse_px(&v));
+        let font_family = style.get_property_value("font-family").unwrap_or_default();
+        let measured = (|| {
+            let document = web_sys::window()?.document()?;
+            let body = document.body()?;
+            let probe = document.create_element("span").ok()?;
+            let _ = probe.set_attribute(
+                "style",
+                &format!(
+                    "position:absolute; visibility:hidden; white-space:pre; line-height:normal; font-family:{}; font-size:{}px;",
+                    font_family,
+                    font_size.unwrap_or(16.0)
+                ),
+            );
+            probe.set_text_content(Some("Mg"));
+            body.append_child(&probe).ok()?;
+            let h = probe.dyn_ref::<web_sys::HtmlElement>().map(|p| p.offset_height() as f64);
+            probe.remove();
+            h.filter(|h| *h > 0.0)
+        })();
+        measured.or_else(|| font_size.map(|f| f * 1.2))
+    }